@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Threshold {
+    Above(i32),
+    Below(i32),
+}
+
+impl Threshold {
+    pub fn holds(&self, population: i32) -> bool {
+        match *self {
+            Threshold::Above(limit) => population > limit,
+            Threshold::Below(limit) => population < limit,
+        }
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Threshold::Above(limit) => write!(f, "exceeds {}", limit),
+            Threshold::Below(limit) => write!(f, "drops below {}", limit),
+        }
+    }
+}
+
+/// A population threshold that notifies (and optionally pauses) when it is crossed.
+///
+/// Alerts only fire on the generation where the threshold starts to hold, so a population that
+/// stays above the limit does not produce a notification every generation.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Alert {
+    pub threshold: Threshold,
+    pub pause: bool,
+    triggered: bool,
+}
+
+impl Alert {
+    pub fn new(threshold: Threshold, pause: bool) -> Alert {
+        Alert {
+            threshold,
+            pause,
+            triggered: false,
+        }
+    }
+
+    /// Check the population against the threshold, returning whether the alert fired.
+    pub fn check(&mut self, population: i32) -> bool {
+        let holds = self.threshold.holds(population);
+        let fired = holds && !self.triggered;
+        self.triggered = holds;
+        fired
+    }
+}
+
+impl FromStr for Alert {
+    type Err = anyhow::Error;
+
+    /// Parse alerts of the form `above 10000 pause` or `< 10`.
+    fn from_str(s: &str) -> Result<Alert> {
+        let usage = || anyhow!("usage: alert above|below <population> [pause]");
+        let mut words = s.split_whitespace();
+        let direction = words.next().ok_or_else(usage)?;
+        let limit = words.next().ok_or_else(usage)?.parse()?;
+        let threshold = match direction {
+            "above" | ">" => Threshold::Above(limit),
+            "below" | "<" => Threshold::Below(limit),
+            _ => return Err(usage()),
+        };
+        let pause = match words.next() {
+            None => false,
+            Some("pause") => true,
+            Some(_) => return Err(usage()),
+        };
+        Ok(Alert::new(threshold, pause))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::simulation::State;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn parses_alerts() -> Result<()> {
+        assert_eq!(
+            "above 10000 pause".parse::<Alert>()?,
+            Alert::new(Threshold::Above(10000), true)
+        );
+        assert_eq!(
+            "< 10".parse::<Alert>()?,
+            Alert::new(Threshold::Below(10), false)
+        );
+        assert!("sideways 10".parse::<Alert>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn fires_once_per_crossing() {
+        let mut alert = Alert::new(Threshold::Above(10), false);
+        assert!(!alert.check(5));
+        assert!(alert.check(11));
+        assert!(!alert.check(12));
+        assert!(!alert.check(9));
+        assert!(alert.check(20));
+    }
+
+    #[test]
+    fn pausing_alerts_stop_jobs() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "r-pentomino", "--build"], 40, 20)?;
+        harness
+            .type_keys(":alert above 10 pause\n:ff 1000\n")?
+            .ticks(2)?;
+        assert!(harness.program.job.is_none());
+        assert_eq!(
+            harness.status(),
+            "alert: population 12 exceeds 10 (generation 6)"
+        );
+        assert_eq!(harness.generations(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn shows_and_dismisses_errors() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider", "--build"], 40, 20)?;
+        harness.type_keys(":merge /nonexistent/glider.rle\n")?;
+        let frame = harness.program.screen.frame(&harness.program, None);
+        let lines = frame.error().unwrap();
+        // Long lines wrap to the width of the screen.
+        assert_eq!(
+            lines[1..3],
+            ["  failed to read /nonexistent/glider.r", "le"]
+        );
+        assert!(lines[3].starts_with("  caused by: No such file"));
+        // Esc dismisses the panel rather than quitting.
+        harness.type_keys("q")?.press(KeyCode::Esc)?;
+        assert!(harness.program.error.is_none());
+        assert_eq!(harness.state(), State::Paused);
+        assert!(harness.status().starts_with("alive: 5"));
+        Ok(())
+    }
+}
@@ -0,0 +1,394 @@
+use crate::life::{CellState, LifeWorld};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// What a pattern eventually settles into.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Classification {
+    /// Every cell died.
+    Extinct,
+    /// The pattern stopped changing.
+    StillLife,
+    /// The pattern repeats in place with the given period.
+    Oscillator(usize),
+    /// The pattern repeats with the given period, displaced by `(dx, dy)`.
+    Spaceship(usize, (i32, i32)),
+    /// The pattern did not settle within the generation limit.
+    Unresolved,
+}
+
+impl Classification {
+    pub fn period(&self) -> Option<usize> {
+        match *self {
+            Classification::StillLife => Some(1),
+            Classification::Oscillator(period) | Classification::Spaceship(period, _) => {
+                Some(period)
+            }
+            Classification::Extinct | Classification::Unresolved => None,
+        }
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Classification::Extinct => write!(f, "extinct"),
+            Classification::StillLife => write!(f, "still life"),
+            Classification::Oscillator(_) => write!(f, "oscillator"),
+            Classification::Spaceship(_, _) => write!(f, "spaceship"),
+            Classification::Unresolved => write!(f, "unresolved"),
+        }
+    }
+}
+
+/// How fast a pattern's population grows in the long run.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Growth {
+    /// The population stays within some bound, as for anything that settles.
+    Bounded,
+    /// The population grows in proportion to the generation, as for guns.
+    Linear,
+    /// The population grows with the square of the generation, as for breeders.
+    Quadratic,
+}
+
+impl fmt::Display for Growth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Growth::Bounded => write!(f, "bounded"),
+            Growth::Linear => write!(f, "linear"),
+            Growth::Quadratic => write!(f, "quadratic"),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Outcome {
+    pub classification: Classification,
+    /// The number of generations before the pattern settled (or the limit, if it did not).
+    pub lifespan: usize,
+    pub final_population: i32,
+    /// Estimated from the population history for unresolved patterns, or `None` if they ran for
+    /// too few generations to tell.
+    pub growth: Option<Growth>,
+}
+
+/// Evolve a world until it dies out, starts repeating itself, or reaches `max_generations`, and
+/// report what happened.
+///
+/// Repetition is detected by hashing the living cells relative to their bounding box, so moving
+/// patterns are recognized as well as stationary ones.
+pub fn classify<S: CellState>(world: &mut LifeWorld<S>, max_generations: usize) -> Outcome {
+    let start = world.generations;
+    let mut seen: FxHashMap<u64, (usize, (i32, i32))> = FxHashMap::default();
+    let mut populations = Vec::new();
+    loop {
+        let age = world.generations - start;
+        let Some((hash, origin)) = fingerprint(world) else {
+            return Outcome {
+                classification: Classification::Extinct,
+                lifespan: age,
+                final_population: 0,
+                growth: Some(Growth::Bounded),
+            };
+        };
+        populations.push(world.num_alive());
+        if let Some(&(first, first_origin)) = seen.get(&hash) {
+            let period = age - first;
+            let offset = (origin.0 - first_origin.0, origin.1 - first_origin.1);
+            let classification = match (period, offset) {
+                (1, (0, 0)) => Classification::StillLife,
+                (_, (0, 0)) => Classification::Oscillator(period),
+                _ => Classification::Spaceship(period, offset),
+            };
+            return Outcome {
+                classification,
+                lifespan: first,
+                final_population: world.num_alive(),
+                growth: Some(Growth::Bounded),
+            };
+        }
+        if age >= max_generations {
+            return Outcome {
+                classification: Classification::Unresolved,
+                lifespan: age,
+                final_population: world.num_alive(),
+                growth: growth(&populations),
+            };
+        }
+        seen.insert(hash, (age, origin));
+        world.evolve();
+    }
+}
+
+/// The fewest generations of population history [`growth`] fits a curve to.
+const MIN_GROWTH_SAMPLES: usize = 32;
+
+/// Estimate how the population grows from its history, one entry per generation, by fitting
+/// `population = a * generation^k` to the later half of it (the early generations are mostly
+/// the pattern getting going) and rounding the exponent `k`.
+fn growth(populations: &[i32]) -> Option<Growth> {
+    let n = populations.len();
+    if n < MIN_GROWTH_SAMPLES {
+        return None;
+    }
+    let points: Vec<(f64, f64)> = (n / 2..n)
+        .map(|i| (((i + 1) as f64).ln(), f64::from(populations[i].max(1)).ln()))
+        .collect();
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(c, v), (x, y)| {
+        (
+            c + (x - mean_x) * (y - mean_y),
+            v + (x - mean_x) * (x - mean_x),
+        )
+    });
+    let exponent = covariance / variance;
+    Some(if exponent < 0.5 {
+        Growth::Bounded
+    } else if exponent < 1.5 {
+        Growth::Linear
+    } else {
+        Growth::Quadratic
+    })
+}
+
+/// The longest population cycle that counts as stable in [`longevity`]. This covers the common
+/// oscillators and, since escaping gliders keep their population, soups that have emitted them.
+const MAX_POPULATION_PERIOD: usize = 30;
+/// How many times the population cycle must repeat before the pattern counts as stable.
+const STABLE_REPEATS: usize = 8;
+
+/// How long a pattern stays active and how large it gets, as measured by [`longevity`].
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Longevity {
+    /// The number of generations before the population became periodic (or the limit, if it
+    /// did not).
+    pub lifespan: usize,
+    pub peak_population: i32,
+    pub stabilized: bool,
+}
+
+/// Evolve a world until its population becomes periodic or `max_generations` is reached.
+///
+/// Unlike [`classify`], this only looks at the population, so a soup that has settled into
+/// still lifes and oscillators counts as stable even while gliders it emitted fly away.
+pub fn longevity<S: CellState>(world: &mut LifeWorld<S>, max_generations: usize) -> Longevity {
+    let mut history = vec![world.num_alive()];
+    loop {
+        let peak_population = history.iter().copied().max().unwrap_or(0);
+        if let Some(lifespan) = stable_since(&history) {
+            return Longevity {
+                lifespan,
+                peak_population,
+                stabilized: true,
+            };
+        }
+        if history.len() > max_generations {
+            return Longevity {
+                lifespan: max_generations,
+                peak_population,
+                stabilized: false,
+            };
+        }
+        world.evolve();
+        history.push(world.num_alive());
+    }
+}
+
+/// The generation from which the population history has been periodic, if the most recent
+/// generations repeat often enough to call it stable.
+fn stable_since(history: &[i32]) -> Option<usize> {
+    let n = history.len();
+    for period in 1..=MAX_POPULATION_PERIOD {
+        let window = period * STABLE_REPEATS;
+        if n < window + period {
+            break;
+        }
+        if (n - window..n).all(|i| history[i] == history[i - period]) {
+            let mut since = n - window - period;
+            while since > 0 && history[since - 1] == history[since - 1 + period] {
+                since -= 1;
+            }
+            return Some(since);
+        }
+    }
+    None
+}
+
+/// A group of living cells that touch each other, orthogonally or diagonally.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Cluster {
+    /// The cells in the cluster, sorted by position.
+    pub cells: Vec<(i32, i32)>,
+    /// The inclusive `(min, max)` corners of the cluster.
+    pub bounding_box: ((i32, i32), (i32, i32)),
+}
+
+impl Cluster {
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+/// Partition the living cells into 8-connected clusters, largest first (ties are broken by
+/// position so the order is stable).
+pub fn clusters<S: CellState>(world: &LifeWorld<S>) -> Vec<Cluster> {
+    let mut visited: FxHashSet<(i32, i32)> = FxHashSet::default();
+    let mut clusters = Vec::new();
+    for start in world.cells() {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut cells = Vec::new();
+        let mut stack = vec![start];
+        let (mut min, mut max) = (start, start);
+        while let Some((x, y)) = stack.pop() {
+            cells.push((x, y));
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let neighbor = (x + dx, y + dy);
+                    if world.alive(neighbor.0, neighbor.1) && visited.insert(neighbor) {
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+        cells.sort_unstable();
+        clusters.push(Cluster {
+            cells,
+            bounding_box: (min, max),
+        });
+    }
+    clusters.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cells[0].cmp(&b.cells[0])));
+    clusters
+}
+
+/// Hash the shape of the living cells, independent of where they are, along with the corner of
+/// their bounding box.
+fn fingerprint<S: CellState>(world: &LifeWorld<S>) -> Option<(u64, (i32, i32))> {
+    let ((x0, y0), _) = world.bounding_box()?;
+    let mut cells = world
+        .cells()
+        .map(|(x, y)| (x - x0, y - y0))
+        .collect::<Vec<_>>();
+    cells.sort_unstable();
+    let mut hasher = rustc_hash::FxHasher::default();
+    cells.hash(&mut hasher);
+    Some((hasher.finish(), (x0, y0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::LifePattern;
+
+    #[test]
+    fn classifies_extinction() {
+        let mut world = LifeWorld::new();
+        world.raise(0, 0);
+        let outcome = classify(&mut world, 100);
+        assert_eq!(outcome.classification, Classification::Extinct);
+        assert_eq!(outcome.lifespan, 1);
+    }
+
+    #[test]
+    fn classifies_still_life() {
+        let mut world = LifeWorld::new();
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            world.raise(x, y);
+        }
+        let outcome = classify(&mut world, 100);
+        assert_eq!(outcome.classification, Classification::StillLife);
+        assert_eq!(outcome.final_population, 4);
+    }
+
+    #[test]
+    fn classifies_oscillator() {
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        let outcome = classify(&mut world, 100);
+        assert_eq!(outcome.classification, Classification::Oscillator(2));
+        assert_eq!(outcome.lifespan, 0);
+    }
+
+    #[test]
+    fn classifies_spaceship() {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        let outcome = classify(&mut world, 100);
+        assert_eq!(
+            outcome.classification,
+            Classification::Spaceship(4, (1, -1))
+        );
+    }
+
+    #[test]
+    fn finds_clusters() {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        // A blinker off to the side, and a diagonal pair touching only at a corner.
+        for (x, y) in [(10, 0), (10, 1), (10, 2), (-5, -5), (-4, -4)] {
+            world.raise(x, y);
+        }
+        let clusters = clusters(&world);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[0].len(), 5);
+        assert_eq!(clusters[0].bounding_box, ((0, 0), (2, 2)));
+        assert_eq!(clusters[1].cells, vec![(10, 0), (10, 1), (10, 2)]);
+        assert_eq!(clusters[2].cells, vec![(-5, -5), (-4, -4)]);
+        assert_eq!(clusters[2].bounding_box, ((-5, -5), (-4, -4)));
+    }
+
+    #[test]
+    fn finds_no_clusters_in_empty_world() {
+        assert!(clusters(&LifeWorld::new()).is_empty());
+    }
+
+    #[test]
+    fn measures_longevity() {
+        let mut world = LifeWorld::new();
+        for (x, y) in [(1, 0), (0, 1), (1, 1), (1, 2), (2, 2)] {
+            world.raise(x, y);
+        }
+        let r_pentomino = longevity(&mut world, 2000);
+        assert!(r_pentomino.stabilized);
+        assert_eq!(r_pentomino.lifespan, 1103);
+        assert_eq!(r_pentomino.peak_population, 319);
+
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        assert_eq!(longevity(&mut world, 100).lifespan, 0);
+    }
+
+    #[test]
+    fn gives_up_at_limit() {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        let outcome = classify(&mut world, 2);
+        assert_eq!(outcome.classification, Classification::Unresolved);
+        assert_eq!(outcome.lifespan, 2);
+        assert_eq!(outcome.growth, None);
+    }
+
+    #[test]
+    fn estimates_growth() -> anyhow::Result<()> {
+        let mut world = LifeWorld::from(&LifePattern::GosperGun);
+        let gun = classify(&mut world, 600);
+        assert_eq!(gun.classification, Classification::Unresolved);
+        assert_eq!(gun.growth, Some(Growth::Linear));
+
+        // Every cell touching a live one is born, so a single cell grows into a square.
+        let mut world = LifeWorld::new();
+        world.set_rule(crate::rule::parse("B12345678/S012345678")?);
+        world.raise(0, 0);
+        assert_eq!(classify(&mut world, 100).growth, Some(Growth::Quadratic));
+
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        assert_eq!(classify(&mut world, 100).growth, Some(Growth::Bounded));
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use crate::cmdline;
+use crate::raster::{Palette, Raster, Region, Rgb};
+use crate::{formats, LifePattern, LifeWorld};
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use rand::random;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct GifArgs {
+    #[arg(short = 'p', long = "pattern", value_enum, default_value_t = LifePattern::Glider)]
+    pattern: LifePattern,
+    /// Start from a pattern file instead.
+    #[arg(short = 'f', long = "file", conflicts_with = "pattern")]
+    file: Option<PathBuf>,
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+    /// Number of generations to run.
+    #[arg(short = 'n', long = "generations", default_value = "100")]
+    generations: usize,
+    /// Draw a frame every N generations.
+    #[arg(long = "every", default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    every: u64,
+    /// Time each frame is shown, in milliseconds (GIFs count in hundredths of a second).
+    #[arg(long = "delay", default_value = "100")]
+    delay: u32,
+    /// Size of each cell in pixels.
+    #[arg(long = "scale", default_value = "4", value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+    /// Region to draw, `x0,y0:x1,y1`. Defaults to everything the pattern reaches during the run.
+    #[arg(long = "region", value_parser = cmdline::parse_region)]
+    region: Option<Region>,
+    #[arg(long = "live-color", default_value = "#ffffff")]
+    live_color: Rgb,
+    #[arg(long = "dead-color", default_value = "#000000")]
+    dead_color: Rgb,
+    #[arg(short = 'o', long = "output", default_value = "life.gif")]
+    output: PathBuf,
+}
+
+/// Evolve a pattern and write the run as an animated GIF that loops forever.
+pub fn run(args: &GifArgs) -> Result<()> {
+    let world = match (&args.file, &args.pattern) {
+        (Some(path), _) => formats::load(path)?,
+        (None, LifePattern::Random(size)) => {
+            LifeWorld::random(*size, args.seed.unwrap_or_else(random))
+        }
+        (None, pattern) => LifeWorld::from(pattern),
+    };
+    // Finding the region takes a run of its own, so frames don't need to be kept in memory.
+    let region = args
+        .region
+        .unwrap_or_else(|| reach(world.clone(), args.generations));
+    let palette = Palette {
+        live: args.live_color,
+        dead: args.dead_color,
+    };
+    let file = File::create(&args.output)
+        .with_context(|| format!("failed to create {}", args.output.display()))?;
+    let frames = write_gif(BufWriter::new(file), world, args, region, &palette)?;
+    eprintln!("wrote {} frames to {}", frames, args.output.display());
+    Ok(())
+}
+
+/// The bounding box of every cell that is alive at some point in the first `generations`
+/// generations.
+fn reach(mut world: LifeWorld, generations: usize) -> Region {
+    let mut region: Option<Region> = None;
+    for generation in 0..=generations {
+        if let Some(((x0, y0), (x1, y1))) = world.bounding_box() {
+            region = Some(match region {
+                Some(((u0, v0), (u1, v1))) => ((u0.min(x0), v0.min(y0)), (u1.max(x1), v1.max(y1))),
+                None => ((x0, y0), (x1, y1)),
+            });
+        }
+        if generation < generations {
+            world.evolve();
+        }
+    }
+    region.unwrap_or(((0, 0), (0, 0)))
+}
+
+/// Encode the run as a GIF, returning the number of frames.
+fn write_gif<W: Write>(
+    writer: W,
+    mut world: LifeWorld,
+    args: &GifArgs,
+    region: Region,
+    palette: &Palette,
+) -> Result<usize> {
+    let first = Raster::render(&world, region, args.scale)?;
+    let (Ok(width), Ok(height)) = (u16::try_from(first.width), u16::try_from(first.height)) else {
+        return Err(anyhow!(
+            "{}x{} pixels is too large for a GIF (try a smaller --scale or --region)",
+            first.width,
+            first.height
+        ));
+    };
+    let Rgb(r0, g0, b0) = palette.dead;
+    let Rgb(r1, g1, b1) = palette.live;
+    let mut encoder = gif::Encoder::new(writer, width, height, &[r0, g0, b0, r1, g1, b1])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    let delay = u16::try_from(args.delay / 10).unwrap_or(u16::MAX);
+    let mut frames = 0;
+    for generation in 0..=args.generations {
+        if (generation as u64).is_multiple_of(args.every) {
+            let raster = Raster::render(&world, region, args.scale)?;
+            let indices: Vec<u8> = raster
+                .pixels()
+                .iter()
+                .map(|&alive| u8::from(alive))
+                .collect();
+            encoder.write_frame(&gif::Frame {
+                width,
+                height,
+                delay,
+                buffer: Cow::Owned(indices),
+                ..gif::Frame::default()
+            })?;
+            frames += 1;
+        }
+        if generation < args.generations {
+            world.evolve();
+        }
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Command {
+        #[command(flatten)]
+        args: GifArgs,
+    }
+
+    #[test]
+    fn writes_a_frame_per_step() -> Result<()> {
+        let args = ["gif", "-n", "8", "--every", "2", "--scale", "2"];
+        let args = Command::try_parse_from(args)?.args;
+        let world = LifeWorld::from(&LifePattern::Glider);
+        let region = reach(world.clone(), args.generations);
+        // The glider moves two cells diagonally in eight generations.
+        assert_eq!(region, ((0, -2), (4, 2)));
+        let mut buf = Vec::new();
+        let frames = write_gif(&mut buf, world, &args, region, &Palette::default())?;
+        assert_eq!(frames, 5);
+        assert_eq!(&buf[..6], b"GIF89a");
+        assert_eq!(u16::from_le_bytes([buf[6], buf[7]]), 10);
+        Ok(())
+    }
+}
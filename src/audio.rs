@@ -0,0 +1,109 @@
+use crate::Changes;
+use anyhow::{anyhow, Result};
+use rodio::source::{SineWave, Source};
+use rodio::{OutputStream, OutputStreamHandle, Sink};
+use std::fmt;
+use std::time::Duration;
+
+/// Semitone offsets of the major pentatonic scale, so that any sequence of notes sounds
+/// consonant.
+const PENTATONIC: [u32; 5] = [0, 2, 4, 7, 9];
+/// The pitch of an empty world (A2).
+const BASE_FREQUENCY: f32 = 110.;
+/// The range of pitches, in octaves above the base.
+const OCTAVES: u32 = 4;
+/// Notes last one timestep, but no longer than this so slow runs don't drone.
+const MAX_NOTE: Duration = Duration::from_millis(250);
+const FADE: Duration = Duration::from_millis(5);
+
+/// Plays one short chord per generation: the pitch rises with the population, births sound at
+/// that pitch and deaths an octave below, each as loud as the share of cells that changed.
+pub struct Sonifier {
+    // The stream must be kept alive for the sink to play.
+    _stream: OutputStream,
+    _handle: OutputStreamHandle,
+    sink: Sink,
+    note: Duration,
+}
+
+impl Sonifier {
+    pub fn new(timestep: Duration) -> Result<Sonifier> {
+        let (stream, handle) = OutputStream::try_default()
+            .map_err(|e| anyhow!("failed to open audio output: {}", e))?;
+        let sink = Sink::try_new(&handle).map_err(|e| anyhow!("failed to start audio: {}", e))?;
+        Ok(Sonifier {
+            _stream: stream,
+            _handle: handle,
+            sink,
+            note: timestep.min(MAX_NOTE),
+        })
+    }
+
+    pub fn play(&self, population: i32, changes: Changes) {
+        // Skip notes rather than fall behind the simulation.
+        if self.sink.len() > 1 {
+            return;
+        }
+        let frequency = pitch(population);
+        let births = loudness(population, changes.births);
+        let deaths = loudness(population, changes.deaths);
+        if births == 0. && deaths == 0. {
+            return;
+        }
+        let tone = |frequency: f32, volume: f32| {
+            SineWave::new(frequency)
+                .take_duration(self.note)
+                .fade_in(FADE)
+                .amplify(volume)
+        };
+        self.sink
+            .append(tone(frequency, births).mix(tone(frequency / 2., deaths)));
+    }
+}
+
+impl fmt::Debug for Sonifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sonifier")
+            .field("note", &self.note)
+            .finish()
+    }
+}
+
+/// The frequency for a population: one pentatonic step per half doubling, so that the pitch
+/// tracks the order of magnitude rather than the exact count.
+fn pitch(population: i32) -> f32 {
+    let steps = PENTATONIC.len() as u32 * OCTAVES;
+    let step = ((population.max(0) as f32 + 1.).log2() * 2.) as u32;
+    let step = step.min(steps - 1);
+    let semitones = 12 * (step / PENTATONIC.len() as u32) + PENTATONIC[step as usize % 5];
+    BASE_FREQUENCY * 2f32.powf(semitones as f32 / 12.)
+}
+
+/// The volume for a number of changed cells, relative to the population.
+fn loudness(population: i32, changed: usize) -> f32 {
+    if changed == 0 {
+        return 0.;
+    }
+    let share = changed as f32 / population.max(1) as f32;
+    0.05 + 0.25 * share.min(1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pitch_rises_with_population() {
+        assert_eq!(pitch(0), BASE_FREQUENCY);
+        assert!(pitch(10) > pitch(5));
+        assert!(pitch(1000) > pitch(10));
+        assert_eq!(pitch(i32::MAX), pitch(1 << 20));
+    }
+
+    #[test]
+    fn silent_without_changes() {
+        assert_eq!(loudness(100, 0), 0.);
+        assert!(loudness(100, 50) > loudness(100, 5));
+        assert!(loudness(0, 10) <= 0.3);
+    }
+}
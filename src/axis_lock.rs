@@ -0,0 +1,104 @@
+use crate::Position;
+use clap::ValueEnum;
+use std::fmt;
+
+/// A line that single-cell moves of the cursor and camera are held to, for walking along a
+/// glider lane or lining up components at 45 degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum AxisLock {
+    #[default]
+    Off,
+    Horizontal,
+    Vertical,
+    /// Up and to the right, like `/`.
+    Diagonal,
+    /// Down and to the right, like `\`.
+    Antidiagonal,
+}
+
+impl AxisLock {
+    /// The lock after this one, going round from off.
+    pub fn next(self) -> AxisLock {
+        match self {
+            AxisLock::Off => AxisLock::Horizontal,
+            AxisLock::Horizontal => AxisLock::Vertical,
+            AxisLock::Vertical => AxisLock::Diagonal,
+            AxisLock::Diagonal => AxisLock::Antidiagonal,
+            AxisLock::Antidiagonal => AxisLock::Off,
+        }
+    }
+
+    /// A step held to the line: moves across it are dropped, and along a diagonal a step on
+    /// either axis goes one cell along both, so `w`/`d` and `s`/`a` go either way along it.
+    pub fn constrain(self, (dx, dy): Position) -> Position {
+        match self {
+            AxisLock::Off => (dx, dy),
+            AxisLock::Horizontal => (dx, 0),
+            AxisLock::Vertical => (0, dy),
+            AxisLock::Diagonal => ((dx + dy).signum(), (dx + dy).signum()),
+            AxisLock::Antidiagonal => ((dx - dy).signum(), (dy - dx).signum()),
+        }
+    }
+}
+
+impl fmt::Display for AxisLock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AxisLock::Off => write!(f, "off"),
+            AxisLock::Horizontal => write!(f, "horizontal"),
+            AxisLock::Vertical => write!(f, "vertical"),
+            AxisLock::Diagonal => write!(f, "diagonal (/)"),
+            AxisLock::Antidiagonal => write!(f, "antidiagonal (\\)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use anyhow::Result;
+
+    #[test]
+    fn holds_steps_to_the_line() {
+        let steps = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+        let constrain = |lock: AxisLock| steps.map(|step| lock.constrain(step));
+        assert_eq!(constrain(AxisLock::Off), steps);
+        assert_eq!(
+            constrain(AxisLock::Horizontal),
+            [(0, 0), (1, 0), (0, 0), (-1, 0)]
+        );
+        assert_eq!(
+            constrain(AxisLock::Vertical),
+            [(0, 1), (0, 0), (0, -1), (0, 0)]
+        );
+        assert_eq!(
+            constrain(AxisLock::Diagonal),
+            [(1, 1), (1, 1), (-1, -1), (-1, -1)]
+        );
+        assert_eq!(
+            constrain(AxisLock::Antidiagonal),
+            [(-1, 1), (1, -1), (1, -1), (-1, 1)]
+        );
+    }
+
+    #[test]
+    fn locks_moves_to_an_axis() -> Result<()> {
+        let mut harness = Harness::new(&["--build", "--axis-lock", "vertical"], 40, 20)?;
+        harness.type_keys("dwwa")?;
+        assert_eq!(harness.program.cursor, (0, 2));
+        harness.type_keys("/")?;
+        assert_eq!(harness.status(), "axis lock diagonal (/)");
+        let camera = |harness: &Harness| {
+            let camera = &harness.program.screen.camera;
+            (camera.x, camera.y)
+        };
+        let (x, y) = camera(&harness);
+        harness.type_keys("wwdsl")?;
+        assert_eq!(harness.program.cursor, (2, 4));
+        assert_eq!(camera(&harness), (x + 1, y + 1));
+        harness.type_keys("//")?;
+        assert_eq!(harness.status(), "axis lock off");
+        Ok(())
+    }
+}
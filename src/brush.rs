@@ -0,0 +1,276 @@
+use crate::rule::Rule;
+use crate::LifeWorld;
+use crate::Position;
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+/// The largest spray radius, in cells.
+pub const MAX_SPRAY_RADIUS: i32 = 50;
+/// The largest brush radius, in cells.
+pub const MAX_BRUSH_RADIUS: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shape {
+    Square,
+    Circle,
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shape::Square => write!(f, "square"),
+            Shape::Circle => write!(f, "circle"),
+        }
+    }
+}
+
+/// The cells `e` fills or erases around the cursor. A radius of 1 is just the cell under the
+/// cursor, and each step up adds a ring around it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Brush {
+    pub shape: Shape,
+    pub radius: i32,
+    /// The state the brush fills with under a multi-state rule, numbered as in multi-state RLE:
+    /// 1 for alive and 2 onwards for the dying states, such as WireWorld's wire.
+    pub state: u8,
+}
+
+impl Brush {
+    /// Whether the brush covers `pos` when centered on `center`.
+    pub fn covers(&self, center: Position, pos: Position) -> bool {
+        let (dx, dy) = (pos.0 - center.0, pos.1 - center.1);
+        match self.shape {
+            Shape::Square => dx.abs() < self.radius && dy.abs() < self.radius,
+            // Cells within half a cell of the outer ring, which looks rounder than a plain
+            // distance cutoff.
+            Shape::Circle => 4 * (dx * dx + dy * dy) <= (2 * self.radius - 1).pow(2),
+        }
+    }
+
+    /// The cells the brush covers when centered on `center`.
+    pub fn footprint(&self, center: Position) -> impl Iterator<Item = Position> + '_ {
+        let (cx, cy) = center;
+        let r = self.radius - 1;
+        (cy - r..=cy + r)
+            .flat_map(move |y| (cx - r..=cx + r).map(move |x| (x, y)))
+            .filter(move |&pos| self.covers(center, pos))
+    }
+
+    /// Fill the footprint around `center`, or erase it if the center cell is already in the
+    /// brush's state, so that a radius of 1 toggles the center cell. A state the rule doesn't
+    /// have, as after switching rules, fills with live cells.
+    pub fn apply(&self, world: &mut LifeWorld, center: Position) {
+        let decay = if self.state < world.rule().states() {
+            self.state - 1
+        } else {
+            0
+        };
+        let erase = match decay {
+            0 => world.alive(center.0, center.1),
+            _ => world.decay(center.0, center.1) == decay,
+        };
+        for (x, y) in self.footprint(center) {
+            if erase {
+                world.lower(x, y);
+            } else if decay == 0 {
+                world.raise(x, y);
+            } else {
+                world.set_dying(x, y, decay);
+            }
+        }
+    }
+
+    /// Switch to painting the next state of `rule` after the current one, wrapping around to
+    /// alive after the last.
+    pub fn cycle_state(&mut self, rule: &dyn Rule) {
+        self.state = self.state % rule.states().saturating_sub(1).max(1) + 1;
+    }
+}
+
+impl fmt::Display for Brush {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} brush, radius {}", self.shape, self.radius)
+    }
+}
+
+/// The cells within `radius` of `center`.
+pub fn disc(center: Position, radius: i32) -> impl Iterator<Item = Position> {
+    let (cx, cy) = center;
+    (-radius..=radius).flat_map(move |dy| {
+        (-radius..=radius)
+            .filter(move |dx| dx * dx + dy * dy <= radius * radius)
+            .map(move |dx| (cx + dx, cy + dy))
+    })
+}
+
+/// Parse a density between 0 and 1, e.g. `0.25`, or a percentage, e.g. `25%`.
+pub fn parse_density(s: &str) -> Result<f64> {
+    let density = match s.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|percent| percent / 100.),
+        None => s.parse(),
+    };
+    density
+        .ok()
+        .filter(|density| (0. ..=1.).contains(density))
+        .ok_or_else(|| anyhow!("invalid density: {} (expected 0 to 1, or 0% to 100%)", s))
+}
+
+/// Raises cells at random around the cursor as it moves, for seeding a region organically
+/// rather than placing exact shapes.
+#[derive(Debug)]
+pub struct Spray {
+    pub radius: i32,
+    /// The chance of raising each cell under the brush.
+    pub density: f64,
+    rng: StdRng,
+}
+
+impl Spray {
+    pub fn new(radius: i32, density: f64, seed: u64) -> Spray {
+        Spray {
+            radius,
+            density,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Raise cells within the radius of `center` with probability `density`, returning how many
+    /// were born.
+    pub fn apply(&mut self, world: &mut LifeWorld, center: Position) -> usize {
+        let mut born = 0;
+        for (x, y) in disc(center, self.radius) {
+            if self.rng.gen_bool(self.density) && !world.alive(x, y) {
+                world.raise(x, y);
+                born += 1;
+            }
+        }
+        born
+    }
+}
+
+impl fmt::Display for Spray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "radius {}, density {:.0}%",
+            self.radius,
+            100. * self.density
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn fills_and_erases_the_footprint() {
+        let circle = |radius| Brush {
+            shape: Shape::Circle,
+            radius,
+            state: 1,
+        };
+        let sizes: Vec<usize> = (1..=4)
+            .map(|radius| circle(radius).footprint((5, 5)).count())
+            .collect();
+        assert_eq!(sizes, [1, 9, 21, 37]);
+        let square = Brush {
+            shape: Shape::Square,
+            radius: 3,
+            state: 1,
+        };
+        assert_eq!(square.footprint((0, 0)).count(), 25);
+        let mut world = LifeWorld::new();
+        square.apply(&mut world, (0, 0));
+        assert_eq!(world.num_alive(), 25);
+        circle(2).apply(&mut world, (2, 2));
+        assert_eq!(world.num_alive(), 25 - 4);
+        // A radius of 1 toggles a single cell.
+        circle(1).apply(&mut world, (2, 2));
+        circle(1).apply(&mut world, (9, 9));
+        assert_eq!(world.num_alive(), 25 - 4 + 2);
+    }
+
+    #[test]
+    fn paints_dying_states() -> Result<()> {
+        let mut world = LifeWorld::new();
+        world.set_rule(crate::rule::parse("WireWorld")?);
+        let mut brush = Brush {
+            shape: Shape::Square,
+            radius: 2,
+            state: 1,
+        };
+        brush.cycle_state(&**world.rule());
+        brush.cycle_state(&**world.rule());
+        assert_eq!(brush.state, 3);
+        brush.apply(&mut world, (0, 0));
+        assert_eq!(world.dying().count(), 9);
+        assert_eq!(world.decay(1, 1), 2);
+        brush.apply(&mut world, (1, 1));
+        assert_eq!(world.dying().count(), 5);
+        brush.cycle_state(&**world.rule());
+        assert_eq!(brush.state, 1);
+        // States beyond the rule's paint live cells.
+        brush.state = 3;
+        world.set_rule(crate::rule::life());
+        brush.apply(&mut world, (5, 5));
+        assert_eq!(world.num_alive(), 9);
+        Ok(())
+    }
+
+    #[test]
+    fn sprays_within_the_radius() -> Result<()> {
+        assert_eq!(disc((0, 0), 1).count(), 5);
+        assert_eq!(disc((0, 0), 3).count(), 29);
+        let mut world = LifeWorld::new();
+        let mut spray = Spray::new(3, 0.5, 1);
+        let born = spray.apply(&mut world, (10, 10));
+        assert!(born > 0 && born < 29, "{}", born);
+        assert!(world
+            .cells()
+            .all(|(x, y)| (x - 10).pow(2) + (y - 10).pow(2) <= 9));
+        assert_eq!(Spray::new(3, 1., 1).apply(&mut world, (10, 10)), 29 - born);
+        assert_eq!(parse_density("25%")?, 0.25);
+        assert!(parse_density("1.5").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn fills_and_erases_with_the_brush() -> Result<()> {
+        let args = ["--build", "--brush-radius", "2", "--brush-shape", "square"];
+        let mut harness = Harness::new(&args, 40, 20)?;
+        harness.type_keys("e")?;
+        assert_eq!(harness.cells().len(), 9);
+        harness.type_keys("]")?;
+        assert_eq!(harness.status(), "square brush, radius 3");
+        harness.type_keys("e")?;
+        assert_eq!(harness.cells(), vec![]);
+        harness.type_keys(":brush 1 circle\ne")?;
+        assert_eq!(harness.cells(), vec![(0, 0)]);
+        harness.type_keys(":brush 11\n")?;
+        assert!(harness.status().starts_with("error: invalid radius: 11"));
+        Ok(())
+    }
+
+    #[test]
+    fn sprays_cells_as_the_cursor_moves() -> Result<()> {
+        let args = ["--build", "--spray-radius", "1", "--spray-density", "100%"];
+        let mut harness = Harness::new(&args, 40, 20)?;
+        harness.type_keys("b")?;
+        assert_eq!(harness.status(), "spraying (radius 1, density 100%)");
+        assert_eq!(harness.cells().len(), 5);
+        harness.type_keys("d")?;
+        assert_eq!(harness.cells().len(), 8);
+        harness.type_keys("bd")?;
+        assert_eq!(harness.cells().len(), 8);
+        harness.type_keys(":spray 2 0.5\n")?;
+        assert_eq!(harness.status(), "spray: radius 2, density 50%");
+        harness.type_keys(":spray 0 0.5\n")?;
+        assert!(harness.status().starts_with("error: invalid radius: 0"));
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Records everything drawn to the terminal, with timing, as an asciinema v2 cast file, so a
+/// session can be replayed with `asciinema play` or embedded on the web.
+pub struct Cast {
+    writer: Box<dyn Write + Send>,
+    start: Instant,
+}
+
+impl Cast {
+    /// Start a recording of a `width` by `height` terminal.
+    pub fn create(path: &Path, width: u16, height: u16) -> Result<Cast> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create cast {}", path.display()))?;
+        Cast::new(Box::new(BufWriter::new(file)), width, height)
+    }
+
+    fn new(mut writer: Box<dyn Write + Send>, width: u16, height: u16) -> Result<Cast> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        writeln!(
+            writer,
+            r#"{{"version":2,"width":{},"height":{},"timestamp":{}}}"#,
+            width, height, timestamp
+        )?;
+        Ok(Cast {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Add output written to the terminal now.
+    pub fn record(&mut self, output: &[u8]) -> Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        let text = String::from_utf8_lossy(output);
+        writeln!(self.writer, r#"[{:.6},"o",{}]"#, time, json_string(&text))?;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.writer.flush().context("failed to write cast")
+    }
+}
+
+impl std::fmt::Debug for Cast {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Cast").finish_non_exhaustive()
+    }
+}
+
+/// Quote a string for JSON, escaping the control characters that make up most of terminal
+/// output.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer whose contents can be read back after it has been handed over.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_asciicast_v2() -> Result<()> {
+        let buf = Shared::default();
+        let mut cast = Cast::new(Box::new(buf.clone()), 80, 24)?;
+        cast.record("\x1b[1;1H█ \"hi\"\n".as_bytes())?;
+        cast.finish()?;
+        let text = String::from_utf8(buf.0.lock().unwrap().clone())?;
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with(r#"{"version":2,"width":80,"height":24,"timestamp":"#));
+        assert!(lines[1].starts_with('['));
+        assert!(lines[1].ends_with(r#","o","\u001b[1;1H█ \"hi\"\n"]"#));
+        Ok(())
+    }
+}
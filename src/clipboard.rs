@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use std::fmt::{self, Debug};
+
+/// The system clipboard, opened on first use and then kept open: on Linux, text we copy is only
+/// available to other programs while we still hold the clipboard.
+#[derive(Default)]
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    fn open(&mut self) -> Result<&mut arboard::Clipboard> {
+        if self.inner.is_none() {
+            self.inner = Some(arboard::Clipboard::new().context("failed to open the clipboard")?);
+        }
+        Ok(self.inner.as_mut().expect("the clipboard was just opened"))
+    }
+
+    pub fn get_text(&mut self) -> Result<String> {
+        self.open()?
+            .get_text()
+            .context("failed to read the clipboard")
+    }
+
+    pub fn set_text(&mut self, text: String) -> Result<()> {
+        self.open()?
+            .set_text(text)
+            .context("failed to write to the clipboard")
+    }
+}
+
+impl Debug for Clipboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Clipboard({})",
+            if self.inner.is_some() {
+                "open"
+            } else {
+                "closed"
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn pastes_patterns_at_the_cursor() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        harness.type_keys("ddw")?;
+        let rle = "#N Blinker\r\n#C from LifeWiki\r\nx = 3, y = 1, rule = B3/S23\r\n3o!\r\n";
+        harness.program.paste_text(rle)?;
+        assert_eq!(harness.cells(), vec![(2, 1), (3, 1), (4, 1)]);
+        assert_eq!(harness.status(), "merged 3 cells from the clipboard at 2,1");
+        harness.program.paste_text("\n!Name: Block\nOO\nOO\n")?;
+        assert_eq!(harness.cells().len(), 5);
+        assert!(harness.program.paste_text("").is_err());
+        assert!(harness
+            .program
+            .paste_text("https://conwaylife.com")
+            .is_err());
+        Ok(())
+    }
+}
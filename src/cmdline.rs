@@ -0,0 +1,441 @@
+use crate::alert::Alert;
+use crate::brush;
+use crate::condition::Condition;
+use crate::life::MAX_SCALE;
+use crate::raster::Region;
+use crate::rule::{self, Rule};
+use crate::Position;
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The longest accepted timestep; anything longer is almost certainly a typo.
+pub const MAX_DURATION: Duration = Duration::from_secs(3600);
+
+/// The names of the commands, without their one-letter aliases, offered when completing.
+pub const COMMANDS: [&str; 23] = [
+    "alert", "apgcode", "brush", "export", "ff", "forward", "gen", "goto", "history", "label",
+    "load", "ls", "merge", "patterns", "quit", "rule", "run", "save", "snap", "spray", "store",
+    "unlabel", "view",
+];
+
+/// The commands that take a path, completed as one.
+pub const PATH_COMMANDS: [&str; 6] = ["save", "w", "load", "e", "export", "merge"];
+
+/// A command entered on the `:` command line.
+#[derive(PartialEq, Clone, Debug)]
+pub enum ExCommand {
+    Label(Option<Position>, String),
+    Unlabel(Option<Position>),
+    Save(String),
+    Load(String),
+    Export(String),
+    /// Save the selection to the pattern library under a name, with tags.
+    Store(String, Vec<String>),
+    /// Set the radius of the brush, and optionally its shape.
+    Brush(i32, Option<brush::Shape>),
+    /// Set the radius and density of the spray brush.
+    Spray(i32, f64),
+    /// Show the apgcode of the selection.
+    Apgcode,
+    /// List the available patterns, optionally only those with a tag.
+    Patterns(Option<String>),
+    /// Load a pattern file, scale it up by a factor, and merge it into the world at a position
+    /// (defaults to the cursor).
+    Merge(String, Option<Position>, u32),
+    /// Snap patterns placed at the cursor to a lattice with this spacing, or stop snapping.
+    Snap(Option<i32>),
+    FastForward(usize),
+    /// Advance to an absolute generation number.
+    Goto(usize),
+    RunUntil(Condition),
+    Alert(Alert),
+    ClearAlerts,
+    /// Name the current camera position.
+    SaveView(String),
+    /// Move the camera to a named position, or list the names.
+    View(Option<String>),
+    /// Forget the history except for the current cells.
+    ResetHistory,
+    /// Switch the world to a rule, or show the current one.
+    Rule(Option<Arc<dyn Rule>>),
+    Quit,
+}
+
+impl FromStr for ExCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<ExCommand> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match name {
+            "label" => {
+                let (pos, text) = split_position(rest)?;
+                if text.is_empty() {
+                    return Err(anyhow!("usage: label [x,y] <text>"));
+                }
+                Ok(ExCommand::Label(pos, text.to_string()))
+            }
+            "unlabel" => {
+                let (pos, text) = split_position(rest)?;
+                if !text.is_empty() {
+                    return Err(anyhow!("usage: unlabel [x,y]"));
+                }
+                Ok(ExCommand::Unlabel(pos))
+            }
+            "save" | "w" => Ok(ExCommand::Save(required(rest, "save <path>")?)),
+            "load" | "e" => Ok(ExCommand::Load(required(rest, "load <path>")?)),
+            "export" => Ok(ExCommand::Export(required(rest, "export <path>")?)),
+            "store" => {
+                let mut words = rest.split_whitespace().map(str::to_string);
+                let name = words
+                    .next()
+                    .ok_or_else(|| anyhow!("usage: store <name> [tag...]"))?;
+                Ok(ExCommand::Store(name, words.collect()))
+            }
+            "brush" => {
+                let usage = || anyhow!("usage: brush <radius> [square|circle]");
+                let mut words = rest.split_whitespace();
+                let radius = words.next().ok_or_else(usage)?;
+                let radius = radius
+                    .parse()
+                    .ok()
+                    .filter(|radius| (1..=brush::MAX_BRUSH_RADIUS).contains(radius))
+                    .ok_or_else(|| anyhow!("invalid radius: {} (1 to 10)", radius))?;
+                let shape = words
+                    .next()
+                    .map(|shape| brush::Shape::from_str(shape, true).map_err(|e| anyhow!(e)))
+                    .transpose()?;
+                if words.next().is_some() {
+                    return Err(usage());
+                }
+                Ok(ExCommand::Brush(radius, shape))
+            }
+            "spray" => {
+                let usage = || anyhow!("usage: spray <radius> <density>");
+                let (radius, density) = rest.split_once(' ').ok_or_else(usage)?;
+                let radius = radius
+                    .parse()
+                    .ok()
+                    .filter(|radius| (1..=brush::MAX_SPRAY_RADIUS).contains(radius))
+                    .ok_or_else(|| anyhow!("invalid radius: {}", radius))?;
+                Ok(ExCommand::Spray(
+                    radius,
+                    brush::parse_density(density.trim())?,
+                ))
+            }
+            "apgcode" if rest.is_empty() => Ok(ExCommand::Apgcode),
+            "patterns" | "ls" if rest.is_empty() => Ok(ExCommand::Patterns(None)),
+            "patterns" | "ls" => Ok(ExCommand::Patterns(Some(rest.to_string()))),
+            "merge" => {
+                // Options are trailing `@x,y` and `*N` words, so paths may contain spaces.
+                let (mut path, mut pos, mut scale) = (rest, None, 1);
+                loop {
+                    let (head, word) = path.rsplit_once(' ').unwrap_or(("", path));
+                    if let Some(xy) = word.strip_prefix('@') {
+                        pos = Some(parse_position(xy)?);
+                    } else if let Some(factor) = word.strip_prefix('*') {
+                        scale = factor
+                            .parse()
+                            .ok()
+                            .filter(|factor| (1..=MAX_SCALE).contains(factor))
+                            .ok_or_else(|| {
+                                anyhow!("invalid scale: {} (1 to {})", word, MAX_SCALE)
+                            })?;
+                    } else {
+                        break;
+                    }
+                    path = head.trim_end();
+                }
+                Ok(ExCommand::Merge(
+                    required(path, "merge <path> [@x,y] [*N]")?,
+                    pos,
+                    scale,
+                ))
+            }
+            "snap" if rest == "off" => Ok(ExCommand::Snap(None)),
+            "snap" => {
+                let spacing = required(rest, "snap <spacing>|off")?;
+                let spacing = spacing
+                    .parse()
+                    .ok()
+                    .filter(|&spacing| spacing > 0)
+                    .ok_or_else(|| anyhow!("invalid spacing: {}", spacing))?;
+                Ok(ExCommand::Snap(Some(spacing)))
+            }
+            "ff" | "forward" => {
+                let generations = required(rest, "ff <generations>")?
+                    .parse()
+                    .map_err(|_| anyhow!("usage: ff <generations>"))?;
+                Ok(ExCommand::FastForward(generations))
+            }
+            "gen" | "goto" => {
+                let generation = required(rest, "gen <generation>")?
+                    .parse()
+                    .map_err(|_| anyhow!("usage: gen <generation>"))?;
+                Ok(ExCommand::Goto(generation))
+            }
+            "run" => match rest.strip_prefix("until ") {
+                Some(condition) => Ok(ExCommand::RunUntil(condition.parse()?)),
+                None => Err(anyhow!("usage: run until <statistic> <op> <value>")),
+            },
+            "alert" if rest == "clear" => Ok(ExCommand::ClearAlerts),
+            "alert" => Ok(ExCommand::Alert(rest.parse()?)),
+            "history" if rest == "reset" => Ok(ExCommand::ResetHistory),
+            "view" => match rest.split_once(' ') {
+                Some(("save", name)) => Ok(ExCommand::SaveView(name.trim().to_string())),
+                None if rest == "save" => Err(anyhow!("usage: view save <name>")),
+                None if rest.is_empty() => Ok(ExCommand::View(None)),
+                None => Ok(ExCommand::View(Some(rest.to_string()))),
+                Some(_) => Err(anyhow!("usage: view [save] <name>")),
+            },
+            "history" => Err(anyhow!("usage: history reset")),
+            "rule" if rest.is_empty() => Ok(ExCommand::Rule(None)),
+            "rule" => Ok(ExCommand::Rule(Some(rule::parse(rest)?))),
+            "quit" | "q" => Ok(ExCommand::Quit),
+            "" => Err(anyhow!("empty command")),
+            _ => Err(anyhow!("unknown command: {}", name)),
+        }
+    }
+}
+
+/// Parse an `x,y` pair.
+pub fn parse_position(s: &str) -> Result<Position> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow!("invalid position: {}", s))?;
+    Ok((x.trim().parse()?, y.trim().parse()?))
+}
+
+/// Parse a duration such as `50ms`, `2.5ms` or `0.25s`. Bare numbers are taken as milliseconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1e-3)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.)
+    } else {
+        (s, 1e-3)
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid duration: {} (expected e.g. 50ms or 0.25s)", s))?;
+    if !value.is_finite() || value < 0. {
+        return Err(anyhow!("invalid duration: {}", s));
+    }
+    let duration = Duration::try_from_secs_f64(value * scale)?;
+    if duration.is_zero() {
+        return Err(anyhow!("duration must be greater than zero"));
+    }
+    if duration > MAX_DURATION {
+        return Err(anyhow!(
+            "duration must be at most {}s",
+            MAX_DURATION.as_secs()
+        ));
+    }
+    Ok(duration)
+}
+
+/// Parse a region given as two corners, `x0,y0:x1,y1`.
+pub fn parse_region(s: &str) -> Result<Region> {
+    let (min, max) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid region: {}", s))?;
+    let (x0, y0) = parse_position(min)?;
+    let (x1, y1) = parse_position(max)?;
+    Ok(((x0.min(x1), y0.min(y1)), (x0.max(x1), y0.max(y1))))
+}
+
+/// Split an optional leading `x,y` position off of the arguments.
+fn split_position(args: &str) -> Result<(Option<Position>, &str)> {
+    let (first, rest) = args.split_once(' ').unwrap_or((args, ""));
+    if first.contains(',') {
+        Ok((Some(parse_position(first)?), rest.trim()))
+    } else {
+        Ok((None, args))
+    }
+}
+
+fn required(arg: &str, usage: &str) -> Result<String> {
+    if arg.is_empty() {
+        Err(anyhow!("usage: {}", usage))
+    } else {
+        Ok(arg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_label_at_cursor() -> Result<()> {
+        let cmd: ExCommand = "label gun A".parse()?;
+        assert_eq!(cmd, ExCommand::Label(None, String::from("gun A")));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_label_at_position() -> Result<()> {
+        let cmd: ExCommand = "label -3,12 eater 2".parse()?;
+        assert_eq!(
+            cmd,
+            ExCommand::Label(Some((-3, 12)), String::from("eater 2"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_unlabel() -> Result<()> {
+        assert_eq!("unlabel".parse::<ExCommand>()?, ExCommand::Unlabel(None));
+        assert_eq!(
+            "unlabel 4,5".parse::<ExCommand>()?,
+            ExCommand::Unlabel(Some((4, 5)))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_durations() -> Result<()> {
+        assert_eq!(parse_duration("100")?, Duration::from_millis(100));
+        assert_eq!(parse_duration("50ms")?, Duration::from_millis(50));
+        assert_eq!(parse_duration("2.5ms")?, Duration::from_micros(2500));
+        assert_eq!(parse_duration("0.25s")?, Duration::from_millis(250));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        for s in ["0", "0ms", "-5ms", "fast", "2h", "7200s", "NaN"] {
+            assert!(parse_duration(s).is_err(), "{}", s);
+        }
+        assert_eq!(
+            parse_duration("0s").unwrap_err().to_string(),
+            "duration must be greater than zero"
+        );
+    }
+
+    #[test]
+    fn parses_regions() -> Result<()> {
+        assert_eq!(parse_region("-4,5:10,-2")?, ((-4, -2), (10, 5)));
+        assert!(parse_region("1,2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_merge() -> Result<()> {
+        assert_eq!(
+            "merge salvo.pbm @-10,4".parse::<ExCommand>()?,
+            ExCommand::Merge(String::from("salvo.pbm"), Some((-10, 4)), 1)
+        );
+        assert_eq!(
+            "merge my target.pbm *3".parse::<ExCommand>()?,
+            ExCommand::Merge(String::from("my target.pbm"), None, 3)
+        );
+        assert!("merge @1,2".parse::<ExCommand>().is_err());
+        assert!("merge a.pbm *0".parse::<ExCommand>().is_err());
+        assert!("merge a.pbm *256".parse::<ExCommand>().is_ok());
+        assert!("merge a.pbm *4294967295".parse::<ExCommand>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_snap() -> Result<()> {
+        assert_eq!("snap 8".parse::<ExCommand>()?, ExCommand::Snap(Some(8)));
+        assert_eq!("snap off".parse::<ExCommand>()?, ExCommand::Snap(None));
+        assert!("snap".parse::<ExCommand>().is_err());
+        assert!("snap 0".parse::<ExCommand>().is_err());
+        assert!("snap -8".parse::<ExCommand>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_library_commands() -> Result<()> {
+        assert_eq!(
+            "store eater-pair still-life eater".parse::<ExCommand>()?,
+            ExCommand::Store(
+                String::from("eater-pair"),
+                vec![String::from("still-life"), String::from("eater")]
+            )
+        );
+        assert!("store".parse::<ExCommand>().is_err());
+        assert_eq!("patterns".parse::<ExCommand>()?, ExCommand::Patterns(None));
+        assert_eq!(
+            "ls gun".parse::<ExCommand>()?,
+            ExCommand::Patterns(Some(String::from("gun")))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_views() -> Result<()> {
+        assert_eq!(
+            "view save overview".parse::<ExCommand>()?,
+            ExCommand::SaveView(String::from("overview"))
+        );
+        assert_eq!(
+            "view overview".parse::<ExCommand>()?,
+            ExCommand::View(Some(String::from("overview")))
+        );
+        assert_eq!("view".parse::<ExCommand>()?, ExCommand::View(None));
+        assert!("view save".parse::<ExCommand>().is_err());
+        assert!("view two words".parse::<ExCommand>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_rules() -> Result<()> {
+        assert_eq!(
+            "rule b36/s23".parse::<ExCommand>()?,
+            ExCommand::Rule(Some(rule::parse("B36/S23")?))
+        );
+        assert_eq!("rule".parse::<ExCommand>()?, ExCommand::Rule(None));
+        assert_eq!(
+            "rule highlife".parse::<ExCommand>()?,
+            ExCommand::Rule(Some(rule::parse("B36/S23")?))
+        );
+        assert!("rule B3".parse::<ExCommand>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_run_until() -> Result<()> {
+        assert_eq!(
+            "run until population < 50".parse::<ExCommand>()?,
+            ExCommand::RunUntil("population < 50".parse()?)
+        );
+        assert!("run bbox > 200".parse::<ExCommand>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_fast_forward() -> Result<()> {
+        assert_eq!(
+            "ff 1000".parse::<ExCommand>()?,
+            ExCommand::FastForward(1000)
+        );
+        assert!("ff".parse::<ExCommand>().is_err());
+        assert_eq!("gen 5000".parse::<ExCommand>()?, ExCommand::Goto(5000));
+        assert!("gen -1".parse::<ExCommand>().is_err());
+        assert!("forward -5".parse::<ExCommand>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        let err = "frobnicate".parse::<ExCommand>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown command: frobnicate");
+        for name in COMMANDS {
+            let err = name.parse::<ExCommand>().err().map(|err| err.to_string());
+            assert!(
+                err.is_none_or(|err| !err.starts_with("unknown")),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::cmdline::{COMMANDS, PATH_COMMANDS};
+use std::fs;
+use std::path::Path;
+
+/// Complete the word being typed at the end of a `:` command line: a command name in the first
+/// word, and after it a path for commands that take one, or for `merge`, also the name of a
+/// pattern in `patterns`. The word is completed as far as all of its completions agree, and if
+/// more than one is left they are returned as well, to be listed.
+pub fn complete(line: &str, patterns: &[String]) -> (String, Vec<String>) {
+    let (head, word, mut candidates) = match line.split_once(' ') {
+        None => (
+            String::new(),
+            line,
+            COMMANDS.iter().map(|name| name.to_string()).collect(),
+        ),
+        Some((name, rest)) if PATH_COMMANDS.contains(&name) => {
+            let word = rest.trim_start();
+            let mut candidates = paths(word);
+            if name == "merge" {
+                candidates.extend(patterns.iter().cloned());
+            }
+            (
+                line[..line.len() - word.len()].to_string(),
+                word,
+                candidates,
+            )
+        }
+        Some(_) => return (line.to_string(), Vec::new()),
+    };
+    candidates.retain(|candidate| candidate.starts_with(word));
+    candidates.sort();
+    candidates.dedup();
+    match candidates.as_slice() {
+        [] => (line.to_string(), Vec::new()),
+        // A finished command name is followed by its arguments, and a directory by its files.
+        [only] if head.is_empty() => (format!("{} ", only), Vec::new()),
+        [only] => (head + only, Vec::new()),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.len(), |common, candidate| {
+                first
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(common.min(candidate.len()), |((i, _), _)| common.min(i))
+            });
+            (head + &first[..common], candidates)
+        }
+    }
+}
+
+/// The files and directories (ending in `/`) in the directory `word` is in, as the words that
+/// would name them. Hidden files are only included if the name being typed starts with a dot.
+fn paths(word: &str) -> Vec<String> {
+    let (dir, name) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    }) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let file_name = entry.file_name().into_string().ok()?;
+            if file_name.starts_with('.') && !name.starts_with('.') {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, file_name, slash))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn completes_commands_paths_and_patterns() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-complete-{}", std::process::id()));
+        fs::create_dir_all(dir.join("patterns"))?;
+        fs::write(dir.join("glider.rle"), "")?;
+        fs::write(dir.join("gun.rle"), "")?;
+        fs::write(dir.join(".hidden"), "")?;
+        let dir = dir.to_str().unwrap();
+        let patterns = [String::from("glider"), String::from("gosper-gun")];
+        let complete = |line: &str| complete(line, &patterns);
+        let in_dir = |line: &str| complete(&line.replace("DIR", dir));
+        let completions = [
+            complete("sn"),
+            complete("s"),
+            complete("rule B3"),
+            in_dir("load DIR/p"),
+            in_dir("load DIR/g"),
+            in_dir("load DIR/"),
+            complete("merge g"),
+            complete("merge go"),
+        ];
+        fs::remove_dir_all(dir)?;
+
+        let lines: Vec<String> = completions
+            .iter()
+            .map(|(line, _)| line.replace(dir, "DIR"))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "snap ",
+                "s",
+                "rule B3",
+                "load DIR/patterns/",
+                "load DIR/g",
+                "load DIR/",
+                "merge g",
+                "merge gosper-gun",
+            ]
+        );
+        assert_eq!(completions[1].1, ["save", "snap", "spray", "store"]);
+        assert_eq!(completions[4].1.len(), 2);
+        assert_eq!(completions[5].1.len(), 3);
+        assert_eq!(completions[6].1, ["glider", "gosper-gun"]);
+        Ok(())
+    }
+
+    #[test]
+    fn completes_and_recalls_commands() -> anyhow::Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        harness.type_keys(":s")?.press(KeyCode::Tab)?;
+        assert_eq!(harness.status(), ":s  (save snap spray store)");
+        harness
+            .type_keys("n")?
+            .press(KeyCode::Tab)?
+            .type_keys("4\n")?;
+        assert!(harness
+            .status()
+            .starts_with("snapping placed patterns to 4x4"));
+        harness
+            .type_keys(":merge gos")?
+            .press(KeyCode::Tab)?
+            .type_keys(" @1,1\n")?;
+        assert_eq!(harness.cells().len(), 36);
+        harness
+            .type_keys(":")?
+            .press(KeyCode::Up)?
+            .press(KeyCode::Up)?;
+        assert_eq!(harness.status(), ":snap 4");
+        harness.press(KeyCode::Down)?.press(KeyCode::Down)?;
+        assert_eq!(harness.status(), ":");
+        Ok(())
+    }
+}
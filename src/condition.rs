@@ -0,0 +1,197 @@
+use crate::LifeWorld;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+const USAGE: &str = "usage: run until <population|generation|bbox|width|height> <op> <value>";
+
+/// A statistic of the world that conditions can test.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Stat {
+    Population,
+    Generation,
+    /// The larger side of the bounding box.
+    Bbox,
+    Width,
+    Height,
+}
+
+impl Stat {
+    fn name(&self) -> &'static str {
+        match self {
+            Stat::Population => "population",
+            Stat::Generation => "generation",
+            Stat::Bbox => "bbox",
+            Stat::Width => "width",
+            Stat::Height => "height",
+        }
+    }
+
+    fn measure(&self, world: &LifeWorld) -> i64 {
+        // Only scan for the bounding box if the statistic needs it.
+        let size = || match world.bounding_box() {
+            Some(((x0, y0), (x1, y1))) => (i64::from(x1 - x0) + 1, i64::from(y1 - y0) + 1),
+            None => (0, 0),
+        };
+        match self {
+            Stat::Population => i64::from(world.num_alive()),
+            Stat::Generation => world.generations as i64,
+            Stat::Bbox => {
+                let (width, height) = size();
+                width.max(height)
+            }
+            Stat::Width => size().0,
+            Stat::Height => size().1,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        }
+    }
+}
+
+/// A predicate over the world's statistics, such as `bbox > 200` or `population < 50`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Condition {
+    pub stat: Stat,
+    pub comparison: Comparison,
+    pub value: i64,
+}
+
+impl Condition {
+    pub fn holds(&self, world: &LifeWorld) -> bool {
+        let measured = self.stat.measure(world);
+        match self.comparison {
+            Comparison::Less => measured < self.value,
+            Comparison::LessOrEqual => measured <= self.value,
+            Comparison::Greater => measured > self.value,
+            Comparison::GreaterOrEqual => measured >= self.value,
+            Comparison::Equal => measured == self.value,
+            Comparison::NotEqual => measured != self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.stat.name(),
+            self.comparison.symbol(),
+            self.value
+        )
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Condition> {
+        let mut words = s.split_whitespace();
+        let (Some(stat), Some(comparison), Some(value), None) =
+            (words.next(), words.next(), words.next(), words.next())
+        else {
+            return Err(anyhow!(USAGE));
+        };
+        let stat = match stat {
+            "population" | "pop" | "alive" => Stat::Population,
+            "generation" | "generations" | "gen" => Stat::Generation,
+            "bbox" => Stat::Bbox,
+            "width" => Stat::Width,
+            "height" => Stat::Height,
+            _ => return Err(anyhow!("unknown statistic: {}", stat)),
+        };
+        let comparison = match comparison {
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            "==" | "=" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            _ => return Err(anyhow!("unknown comparison: {}", comparison)),
+        };
+        let value = value
+            .parse()
+            .map_err(|_| anyhow!("invalid value: {}", value))?;
+        Ok(Condition {
+            stat,
+            comparison,
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::simulation::State;
+    use crate::LifePattern;
+
+    #[test]
+    fn parses_conditions() -> Result<()> {
+        let condition: Condition = "bbox > 200".parse()?;
+        assert_eq!(
+            condition,
+            Condition {
+                stat: Stat::Bbox,
+                comparison: Comparison::Greater,
+                value: 200,
+            }
+        );
+        assert_eq!(condition.to_string(), "bbox > 200");
+        assert_eq!(
+            "pop = 5".parse::<Condition>()?.to_string(),
+            "population == 5"
+        );
+        assert!("bbox >".parse::<Condition>().is_err());
+        assert!("area > 5".parse::<Condition>().is_err());
+        assert!("bbox ~ 5".parse::<Condition>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tests_world_stats() -> Result<()> {
+        let world = LifeWorld::from(&LifePattern::Blinker);
+        assert!("population == 3".parse::<Condition>()?.holds(&world));
+        assert!("bbox >= 3".parse::<Condition>()?.holds(&world));
+        assert!("width < 2".parse::<Condition>()?.holds(&world));
+        assert!(!"generation > 0".parse::<Condition>()?.holds(&world));
+        assert!("bbox == 0".parse::<Condition>()?.holds(&LifeWorld::new()));
+        Ok(())
+    }
+
+    #[test]
+    fn runs_until_a_condition_holds() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider"], 40, 20)?;
+        harness.type_keys(":run until generation >= 20\n")?;
+        while harness.program.job.is_some() {
+            harness.tick()?;
+        }
+        assert_eq!(harness.state(), State::Paused);
+        assert!(harness
+            .status()
+            .starts_with("generation >= 20 after 20 generations"));
+        Ok(())
+    }
+}
@@ -0,0 +1,432 @@
+use crate::cmdline;
+use crate::raster::Rgb;
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::KeyCode;
+use crossterm::style::Color;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often [`Watcher`] checks the config file for changes.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The characters the background grid and the separator above the status line are drawn with.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Glyphs {
+    pub origin: char,
+    /// Where grid lines cross.
+    pub cross: char,
+    pub vertical: char,
+    pub horizontal: char,
+    /// The separator where a vertical grid line meets it.
+    pub separator_tick: char,
+    pub separator: char,
+}
+
+impl Glyphs {
+    pub const UNICODE: Glyphs = Glyphs {
+        origin: '●',
+        cross: '┼',
+        vertical: '│',
+        horizontal: '─',
+        separator_tick: '┷',
+        separator: '━',
+    };
+
+    /// For fonts that draw box-drawing characters poorly or double-width.
+    pub const ASCII: Glyphs = Glyphs {
+        origin: 'o',
+        cross: '+',
+        vertical: '|',
+        horizontal: '-',
+        separator_tick: '+',
+        separator: '=',
+    };
+
+    fn preset(name: &str) -> Result<Glyphs> {
+        match name {
+            "unicode" => Ok(Glyphs::UNICODE),
+            "ascii" => Ok(Glyphs::ASCII),
+            _ => Err(anyhow!(
+                "unknown glyph preset: {} (expected unicode or ascii)",
+                name
+            )),
+        }
+    }
+
+    fn set(&mut self, name: &str, glyph: char) -> Result<()> {
+        let slot = match name {
+            "origin" => &mut self.origin,
+            "cross" => &mut self.cross,
+            "vertical" => &mut self.vertical,
+            "horizontal" => &mut self.horizontal,
+            "separator-tick" => &mut self.separator_tick,
+            "separator" => &mut self.separator,
+            _ => return Err(anyhow!("unknown glyph: {}", name)),
+        };
+        *slot = glyph;
+        Ok(())
+    }
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs::UNICODE
+    }
+}
+
+/// The colors the grid is drawn with, apart from the cells themselves.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Theme {
+    pub cursor: Color,
+    /// Marks set for measuring.
+    pub mark: Color,
+    /// The dead cells the brush would fill.
+    pub brush: Color,
+    /// Cells that have been alive but no longer are, with history on.
+    pub history: Color,
+    /// The space outside a bounded plane or a surface other than a torus.
+    pub outside: Color,
+    /// The lines between panes.
+    pub divider: Color,
+    pub rulers: Color,
+    pub labels: Color,
+}
+
+impl Theme {
+    fn set(&mut self, name: &str, color: Color) -> Result<()> {
+        let slot = match name {
+            "cursor" => &mut self.cursor,
+            "mark" => &mut self.mark,
+            "brush" => &mut self.brush,
+            "history" => &mut self.history,
+            "outside" => &mut self.outside,
+            "divider" => &mut self.divider,
+            "rulers" => &mut self.rulers,
+            "labels" => &mut self.labels,
+            _ => return Err(anyhow!("unknown color: {}", name)),
+        };
+        *slot = color;
+        Ok(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            cursor: Color::Green,
+            mark: Color::Magenta,
+            brush: Color::DarkGreen,
+            history: Color::DarkBlue,
+            outside: Color::DarkGrey,
+            divider: Color::DarkGrey,
+            rulers: Color::DarkGrey,
+            labels: Color::Yellow,
+        }
+    }
+}
+
+/// A terminal color by name, as crossterm spells them (`dark_grey`), or as `#rrggbb`.
+fn parse_color(s: &str) -> Result<Color> {
+    if s.starts_with('#') {
+        let Rgb(r, g, b) = s.parse()?;
+        return Ok(Color::Rgb { r, g, b });
+    }
+    Color::try_from(s).map_err(|_| anyhow!("invalid color: {} (expected a name or #rrggbb)", s))
+}
+
+/// A key as written in a `map` setting: a single character, or the name of a special key.
+fn parse_key(s: &str) -> Result<KeyCode> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    match s {
+        "space" => Ok(KeyCode::Char(' ')),
+        "tab" => Ok(KeyCode::Tab),
+        "enter" => Ok(KeyCode::Enter),
+        "esc" => Ok(KeyCode::Esc),
+        "backspace" => Ok(KeyCode::Backspace),
+        "up" => Ok(KeyCode::Up),
+        "down" => Ok(KeyCode::Down),
+        "left" => Ok(KeyCode::Left),
+        "right" => Ok(KeyCode::Right),
+        "home" => Ok(KeyCode::Home),
+        "end" => Ok(KeyCode::End),
+        "pageup" => Ok(KeyCode::PageUp),
+        "pagedown" => Ok(KeyCode::PageDown),
+        _ => s
+            .strip_prefix('f')
+            .and_then(|n| n.parse().ok())
+            .filter(|n| (1..=12).contains(n))
+            .map(KeyCode::F)
+            .ok_or_else(|| anyhow!("unknown key: {}", s)),
+    }
+}
+
+fn parse_switch(s: &str) -> Result<bool> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(anyhow!("expected on or off, not {}", s)),
+    }
+}
+
+/// Settings read from the config file.
+///
+/// The file is plain text with one setting per line; blank lines and lines starting with `#` are
+/// ignored:
+///
+/// ```text
+/// glyphs ascii
+/// glyph origin *
+/// color cursor #ff8800
+/// map n j
+/// timestep 50ms
+/// rulers on
+/// ```
+///
+/// `map` makes a key act as another one, and `timestep` and `rulers` are defaults for the options
+/// of the same names, used unless they are given on the command line.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Config {
+    pub glyphs: Glyphs,
+    pub theme: Theme,
+    /// Keys to treat as other keys, as `(pressed, acts as)`.
+    pub keymap: Vec<(KeyCode, KeyCode)>,
+    pub timestep: Option<Duration>,
+    pub rulers: Option<bool>,
+}
+
+impl Config {
+    /// The key `code` acts as, after the `map` settings.
+    pub fn key(&self, code: KeyCode) -> KeyCode {
+        self.keymap
+            .iter()
+            .find(|&&(pressed, _)| pressed == code)
+            .map_or(code, |&(_, key)| key)
+    }
+
+    /// `$XDG_CONFIG_HOME/rust-life`, or `~/.config/rust-life`.
+    pub fn dir() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("rust-life"))
+    }
+
+    /// `$XDG_DATA_HOME/rust-life`, or `~/.local/share/rust-life`, for files the program keeps
+    /// for itself rather than settings.
+    pub fn data_dir() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?)
+                .join(".local")
+                .join("share"),
+        };
+        Some(dir.join("rust-life"))
+    }
+
+    /// `$XDG_CONFIG_HOME/rust-life/config`, or `~/.config/rust-life/config`.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::dir()?.join("config"))
+    }
+
+    /// Load the config from `path`, or from the default location if no path is given. A missing
+    /// file at the default location just means the defaults are used.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        match Config::locate(path) {
+            Some((path, required)) => Config::read(&path, required),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// The file to load the config from, and whether it has to exist.
+    fn locate(path: Option<&Path>) -> Option<(PathBuf, bool)> {
+        match path {
+            Some(path) => Some((path.to_path_buf(), true)),
+            None => Some((Config::default_path()?, false)),
+        }
+    }
+
+    fn read(path: &Path, required: bool) -> Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Config::parse(&contents)
+                .with_context(|| format!("invalid config {}", path.display())),
+            Err(e) if !required && e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).with_context(|| format!("failed to read config {}", path.display())),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Config> {
+        let mut config = Config::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            config
+                .parse_setting(line)
+                .with_context(|| format!("invalid setting on line {}", i + 1))?;
+        }
+        Ok(config)
+    }
+
+    fn parse_setting(&mut self, line: &str) -> Result<()> {
+        let mut fields = line.split_whitespace();
+        let key = fields.next().unwrap_or_default();
+        let mut next = || fields.next().ok_or_else(|| anyhow!("missing field"));
+        match key {
+            "glyphs" => self.glyphs = Glyphs::preset(next()?)?,
+            "glyph" => {
+                let name = next()?;
+                let mut chars = next()?.chars();
+                let (Some(glyph), None) = (chars.next(), chars.next()) else {
+                    return Err(anyhow!("a glyph must be a single character"));
+                };
+                self.glyphs.set(name, glyph)?;
+            }
+            "color" => {
+                let name = next()?;
+                self.theme.set(name, parse_color(next()?)?)?;
+            }
+            "map" => {
+                let pressed = parse_key(next()?)?;
+                let key = parse_key(next()?)?;
+                self.keymap.retain(|&(other, _)| other != pressed);
+                self.keymap.push((pressed, key));
+            }
+            "timestep" => self.timestep = Some(cmdline::parse_duration(next()?)?),
+            "rulers" => self.rulers = Some(parse_switch(next()?)?),
+            _ => return Err(anyhow!("unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+}
+
+/// Notices when the config file changes, by checking its modification time and length every
+/// [`CHECK_INTERVAL`], so edits can be applied without a restart.
+#[derive(Debug)]
+pub struct Watcher {
+    file: Option<(PathBuf, bool)>,
+    /// The modification time and length as of the last check, or `None` if it didn't exist.
+    stamp: Option<(SystemTime, u64)>,
+    checked: Instant,
+}
+
+impl Watcher {
+    /// Watch the file [`Config::load`] would read for `path`.
+    pub fn new(path: Option<&Path>) -> Watcher {
+        let file = Config::locate(path);
+        let stamp = file.as_ref().and_then(|(path, _)| Watcher::stamp(path));
+        Watcher {
+            file,
+            stamp,
+            checked: Instant::now(),
+        }
+    }
+
+    /// The config loaded again, if the file has been changed, created or removed since the last
+    /// check and that was at least [`CHECK_INTERVAL`] ago.
+    pub fn poll(&mut self, now: Instant) -> Option<Result<Config>> {
+        if now.duration_since(self.checked) < CHECK_INTERVAL {
+            return None;
+        }
+        self.checked = now;
+        let (path, required) = self.file.as_ref()?;
+        let stamp = Watcher::stamp(path);
+        if stamp == self.stamp {
+            return None;
+        }
+        self.stamp = stamp;
+        Some(Config::read(path, *required))
+    }
+
+    fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_glyphs() -> Result<()> {
+        let config = Config::parse("# comment\n\nglyphs ascii\nglyph origin *\n")?;
+        assert_eq!(
+            config.glyphs,
+            Glyphs {
+                origin: '*',
+                ..Glyphs::ASCII
+            }
+        );
+        assert_eq!(Config::parse("")?, Config::default());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_themes_keys_and_defaults() -> Result<()> {
+        let config = Config::parse(
+            "color cursor #ff8800\ncolor labels dark_cyan\nmap n j\nmap f1 ?\nmap n down\n\
+             timestep 50ms\nrulers on\n",
+        )?;
+        assert_eq!(
+            config.theme.cursor,
+            Color::Rgb {
+                r: 255,
+                g: 136,
+                b: 0
+            }
+        );
+        assert_eq!(config.theme.labels, Color::DarkCyan);
+        assert_eq!(config.theme.mark, Theme::default().mark);
+        // A key mapped twice keeps the last mapping.
+        assert_eq!(config.key(KeyCode::Char('n')), KeyCode::Down);
+        assert_eq!(config.key(KeyCode::F(1)), KeyCode::Char('?'));
+        assert_eq!(config.key(KeyCode::Char('j')), KeyCode::Char('j'));
+        assert_eq!(config.timestep, Some(Duration::from_millis(50)));
+        assert_eq!(config.rulers, Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn reloads_changed_files() -> Result<()> {
+        let path = env::temp_dir().join(format!("rust-life-config-{}", std::process::id()));
+        fs::write(&path, "glyphs ascii\n")?;
+        let mut watcher = Watcher::new(Some(&path));
+        let start = Instant::now();
+        let unchanged = watcher.poll(start + CHECK_INTERVAL).is_none();
+        fs::write(&path, "glyphs unicode\n")?;
+        let too_soon = watcher.poll(start + CHECK_INTERVAL).is_none();
+        let reloaded = watcher.poll(start + 2 * CHECK_INTERVAL).transpose()?;
+        fs::write(&path, "glyphs fancy\n")?;
+        let invalid = watcher.poll(start + 3 * CHECK_INTERVAL);
+        fs::remove_file(&path)?;
+        let removed = watcher.poll(start + 4 * CHECK_INTERVAL);
+
+        assert!(unchanged && too_soon);
+        assert_eq!(reloaded.map(|config| config.glyphs), Some(Glyphs::UNICODE));
+        assert!(matches!(invalid, Some(Err(_))));
+        // The file was given explicitly, so it going missing is an error too.
+        assert!(matches!(removed, Some(Err(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!(Config::parse("glyphs fancy").is_err());
+        assert!(Config::parse("glyph cross ++").is_err());
+        assert!(Config::parse("glyph corner +").is_err());
+        assert!(Config::parse("colour red").is_err());
+        assert!(Config::parse("color cursor mauve").is_err());
+        assert!(Config::parse("color grid red").is_err());
+        assert!(Config::parse("map f13 j").is_err());
+        assert!(Config::parse("map n").is_err());
+        assert!(Config::parse("rulers yes").is_err());
+    }
+}
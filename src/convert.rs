@@ -0,0 +1,88 @@
+use crate::export;
+use crate::formats::{self, Pattern};
+use crate::life::MAX_SCALE;
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// Pattern file to read (format chosen by extension: .rle, .cells, .lif or .pbm), or `-` for
+    /// stdin (format guessed from the contents).
+    input: PathBuf,
+    /// File to write, in the format given by its extension: .rle, .cells, .lif (Life 1.06),
+    /// .pbm, .xbm or .svg.
+    output: PathBuf,
+    /// Scale the pattern up, turning each cell into an N×N block (up to 256).
+    #[arg(
+        long = "scale",
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..=i64::from(MAX_SCALE))
+    )]
+    scale: u32,
+}
+
+/// Read a pattern in one format and write it in another, without running it.
+pub fn run(args: &ConvertArgs) -> Result<()> {
+    let pattern = read(&args.input)?;
+    if let Some(warning) = pattern.rule_warning(pattern.world.rule().as_ref()) {
+        eprintln!("{}", warning);
+    }
+    let world = pattern.world.scaled(args.scale).ok_or_else(|| {
+        anyhow!(
+            "{} is too large to scale by {}",
+            args.input.display(),
+            args.scale
+        )
+    })?;
+    export::write_world(&world, &args.output, None)?;
+    eprintln!(
+        "wrote {} cells to {}",
+        world.num_alive(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Pattern> {
+    if path != Path::new("-") {
+        return formats::load_pattern(path);
+    }
+    let mut data = Vec::new();
+    io::stdin()
+        .read_to_end(&mut data)
+        .context("failed to read stdin")?;
+    formats::read_pattern(&data).context("failed to load stdin")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn converts_between_formats() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-convert-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let input = dir.join("glider.rle");
+        fs::write(&input, "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n")?;
+        let convert = |output: &str, scale| -> Result<String> {
+            run(&ConvertArgs {
+                input: input.clone(),
+                output: dir.join(output),
+                scale,
+            })?;
+            Ok(fs::read_to_string(dir.join(output))?)
+        };
+        let cells = convert("glider.cells", 1);
+        let big = convert("big.cells", 2);
+        let unknown = convert("glider.txt", 1);
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(cells?, ".O\n..O\nOOO\n");
+        assert_eq!(big?, "..OO\n..OO\n....OO\n....OO\nOOOOOO\nOOOOOO\n");
+        assert!(unknown.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use crate::config::Config;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How many commands the history keeps.
+const MAX_LINES: usize = 200;
+
+/// The commands entered on the `:` command line, oldest first, kept across sessions in a file in
+/// the data directory with one command per line.
+#[derive(Debug, Default)]
+pub struct ExHistory {
+    lines: Vec<String>,
+    /// Where the history is saved after each command, if anywhere.
+    path: Option<PathBuf>,
+    /// While stepping through the history, the index of the command shown and the line that was
+    /// typed before, which only commands starting with it are stepped through.
+    browsing: Option<(usize, String)>,
+}
+
+impl ExHistory {
+    /// `$XDG_DATA_HOME/rust-life/ex-history`, or `~/.local/share/rust-life/ex-history`.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::data_dir()?.join("ex-history"))
+    }
+
+    /// Read the history kept at `path`, with a missing file being an empty history.
+    pub fn load(path: &Path) -> Result<ExHistory> {
+        let lines = match fs::read_to_string(path) {
+            Ok(contents) => contents.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        Ok(ExHistory {
+            lines,
+            path: Some(path.to_path_buf()),
+            browsing: None,
+        })
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let contents: String = self
+            .lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Add an entered command, unless it repeats the last one, and save the history. Stops
+    /// stepping through the history.
+    pub fn add(&mut self, line: &str) -> Result<()> {
+        self.browsing = None;
+        let line = line.trim();
+        if line.is_empty() || self.lines.last().is_some_and(|last| last == line) {
+            return Ok(());
+        }
+        self.lines.push(line.to_string());
+        if self.lines.len() > MAX_LINES {
+            self.lines.drain(..self.lines.len() - MAX_LINES);
+        }
+        match &self.path {
+            Some(path) => self.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Stop stepping through the history, as when the command line is closed.
+    pub fn stop(&mut self) {
+        self.browsing = None;
+    }
+
+    /// The command before the one shown that starts with what was typed, which is `current` if
+    /// this is the first step back.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let (index, typed) = self
+            .browsing
+            .get_or_insert_with(|| (self.lines.len(), current.to_string()));
+        let matches = |line: &String| line.starts_with(typed.as_str());
+        let found = self.lines[..*index].iter().rposition(matches)?;
+        *index = found;
+        Some(&self.lines[found])
+    }
+
+    /// The command after the one shown that starts with what was typed, or once past the newest,
+    /// what was typed.
+    pub fn next(&mut self) -> Option<String> {
+        let (index, typed) = self.browsing.as_mut()?;
+        let matches = |line: &String| line.starts_with(typed.as_str());
+        match self.lines[*index + 1..].iter().position(matches) {
+            Some(offset) => {
+                *index += 1 + offset;
+                Some(self.lines[*index].clone())
+            }
+            None => self.browsing.take().map(|(_, typed)| typed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_through_matching_commands() -> Result<()> {
+        let path = std::env::temp_dir()
+            .join(format!("rust-life-ex-history-{}", std::process::id()))
+            .join("ex-history");
+        let mut history = ExHistory::load(&path)?;
+        for line in ["rule B36/S23", "ff 100", "rule B3/S23", "rule B3/S23", "  "] {
+            history.add(line)?;
+        }
+        let reloaded = ExHistory::load(&path);
+        fs::remove_dir_all(path.parent().unwrap())?;
+
+        assert_eq!(reloaded?.lines, ["rule B36/S23", "ff 100", "rule B3/S23"]);
+        assert_eq!(history.previous("ru"), Some("rule B3/S23"));
+        assert_eq!(history.previous("rule B3/S23"), Some("rule B36/S23"));
+        assert_eq!(history.previous("rule B36/S23"), None);
+        assert_eq!(history.next().as_deref(), Some("rule B3/S23"));
+        assert_eq!(history.next().as_deref(), Some("ru"));
+        assert_eq!(history.next(), None);
+        assert_eq!(history.previous(""), Some("rule B3/S23"));
+        assert_eq!(history.previous("rule B3/S23"), Some("ff 100"));
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::raster::{Palette, Raster, Region};
+use crate::{formats, LifeWorld};
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Write the world to an RLE file named after the current generation.
+pub fn write_snapshot(world: &LifeWorld, dir: &Path, seed: Option<u64>) -> Result<PathBuf> {
+    let path = dir.join(format!("gen-{:08}.rle", world.generations));
+    let contents = format!(
+        "#C generation {}\n{}{}",
+        world.generations,
+        seed_comment(seed),
+        world.to_rle()
+    );
+    fs::write(&path, contents)
+        .with_context(|| format!("failed to write snapshot {}", path.display()))?;
+    Ok(path)
+}
+
+/// Write a region of the world to a PNG file numbered by `index`, so the frames form a
+/// contiguous sequence for tools like ffmpeg (`-i frame-%06d.png`).
+pub fn write_frame(
+    world: &LifeWorld,
+    dir: &Path,
+    index: usize,
+    region: Region,
+    style: &ImageStyle,
+) -> Result<PathBuf> {
+    let path = dir.join(format!("frame-{:06}.png", index));
+    write_png(world, &path, region, style)?;
+    Ok(path)
+}
+
+/// Write a region of the world to a PNG screenshot named after the current generation.
+pub fn write_screenshot(
+    world: &LifeWorld,
+    dir: &Path,
+    region: Region,
+    style: &ImageStyle,
+) -> Result<PathBuf> {
+    let path = dir.join(format!("gen-{:08}.png", world.generations));
+    write_png(world, &path, region, style)?;
+    Ok(path)
+}
+
+/// How the world is drawn in PNG frames and screenshots: the size of each cell in pixels and the
+/// colors.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct ImageStyle {
+    pub scale: u32,
+    pub palette: Palette,
+}
+
+/// Write a region of the world to a PNG file.
+pub fn write_png(world: &LifeWorld, path: &Path, region: Region, style: &ImageStyle) -> Result<()> {
+    let raster = Raster::render(world, region, style.scale)?;
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    raster.write_png(BufWriter::new(file), &style.palette)
+}
+
+/// Write a picture of the live cells (their bounding box) to `path`: an SVG if the extension is
+/// `svg`, with grid lines if `grid` is set, and otherwise a PNG drawn in `style`.
+pub fn write_picture(world: &LifeWorld, path: &Path, style: &ImageStyle, grid: bool) -> Result<()> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    if extension.eq_ignore_ascii_case("svg") {
+        fs::write(path, world.to_svg(grid))
+            .with_context(|| format!("failed to write {}", path.display()))
+    } else {
+        write_png(world, path, bounding_region(world), style)
+    }
+}
+
+/// The bounding box of the live cells, or just the origin if there are none.
+pub fn bounding_region(world: &LifeWorld) -> Region {
+    world.bounding_box().unwrap_or(((0, 0), (0, 0)))
+}
+
+/// Write the world to `path` in the format given by its extension: `rle`, `cells`, `lif` (Life
+/// 1.06), `pbm`, `xbm` or `svg`.
+pub fn write_world(world: &LifeWorld, path: &Path, seed: Option<u64>) -> Result<()> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let contents = match extension.to_ascii_lowercase().as_str() {
+        "rle" => format!("{}{}", seed_comment(seed), world.to_rle()).into_bytes(),
+        "cells" => formats::to_cells(world).into_bytes(),
+        "lif" | "life" => formats::to_life106(world).into_bytes(),
+        "pbm" => formats::to_pbm(world),
+        "xbm" => formats::to_xbm(world, &xbm_name(path)).into_bytes(),
+        "svg" => world.to_svg(false).into_bytes(),
+        _ => {
+            return Err(anyhow!(
+                "unsupported export format: {} (expected .rle, .cells, .lif, .pbm, .xbm or .svg)",
+                path.display()
+            ))
+        }
+    };
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// An RLE comment recording the seed of a random soup, so the run can be reproduced.
+fn seed_comment(seed: Option<u64>) -> String {
+    seed.map(|seed| format!("#C seed {}\n", seed))
+        .unwrap_or_default()
+}
+
+/// A C identifier for an XBM file's declarations, based on the file name.
+fn xbm_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("");
+    let mut name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert_str(0, "life_");
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn names_xbm_declarations_after_file() {
+        assert_eq!(xbm_name(Path::new("out/glider-gun.xbm")), "glider_gun");
+        assert_eq!(xbm_name(Path::new("2024.xbm")), "life_2024");
+    }
+
+    #[test]
+    fn saves_screenshots_of_the_view() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-shot-{}", std::process::id()));
+        let snapshots = dir.to_str().unwrap();
+        let args = [
+            "--build",
+            "--snapshot-dir",
+            snapshots,
+            "--screenshot-scale",
+            "2",
+        ];
+        let mut harness = Harness::new(&args, 40, 20)?;
+        harness.type_keys("I")?;
+        let path = dir.join("gen-00000000.png");
+        assert_eq!(harness.status(), format!("saved {}", path.display()));
+        let decoder = png::Decoder::new(std::fs::File::open(&path)?);
+        let info = decoder.read_info()?.info().clone();
+        std::fs::remove_dir_all(&dir)?;
+        let (columns, rows) = harness.program.screen.view_size();
+        assert_eq!(
+            (info.width, info.height),
+            (2 * columns as u32, 4 * rows as u32)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1216 @@
+use crate::analysis::{self, Classification};
+use crate::life::{CellState, LifePattern, LifeWorld};
+use crate::rule::{self, Rule};
+use crate::topology::Topology;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Maximum length of a line of RLE data, as recommended by the format description.
+const RLE_LINE_LENGTH: usize = 70;
+
+/// Encode the living cells of a world in the RLE format used by Golly and LifeWiki, along with
+/// the dying ones under a multi-state rule, which are written in Golly's multi-state notation.
+///
+/// The world's y axis points up while RLE rows run top to bottom, so the first row of the output
+/// is the highest row of the bounding box.
+pub fn to_rle<S: CellState>(world: &LifeWorld<S>) -> String {
+    // The topology follows the rule as in Golly, e.g. `B3/S23:T200,100`.
+    let rule = match world.topology() {
+        Topology::Plane => world.rule().to_string(),
+        topology => format!("{}:{}", world.rule(), topology),
+    };
+    let Some(((x0, y0), (x1, y1))) = world.extent() else {
+        return format!("x = 0, y = 0, rule = {}\n!\n", rule);
+    };
+    let header = format!(
+        "x = {}, y = {}, rule = {}\n",
+        x1 - x0 + 1,
+        y1 - y0 + 1,
+        rule
+    );
+    let multi_state = world.rule().states() > 2;
+    let dead = if multi_state { "." } else { "b" };
+
+    let mut tokens = Vec::new();
+    let mut pending_rows = 0;
+    for y in (y0..=y1).rev() {
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        for x in x0..=x1 {
+            let tag = match (world.alive(x, y), world.decay(x, y)) {
+                (true, _) if multi_state => state_tag(1),
+                (true, _) => String::from("o"),
+                (false, decay) if decay > 0 && multi_state => state_tag(usize::from(decay) + 1),
+                _ => String::from(dead),
+            };
+            match runs.last_mut() {
+                Some((count, last)) if *last == tag => *count += 1,
+                _ => runs.push((1, tag)),
+            }
+        }
+        if runs.last().is_some_and(|(_, tag)| tag == dead) {
+            runs.pop();
+        }
+        if runs.is_empty() {
+            pending_rows += 1;
+            continue;
+        }
+        if !tokens.is_empty() {
+            tokens.push(run(pending_rows + 1, "$"));
+        }
+        pending_rows = 0;
+        tokens.extend(runs.iter().map(|(count, tag)| run(*count, tag)));
+    }
+    tokens.push(String::from("!"));
+
+    let mut out = header;
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length + token.len() > RLE_LINE_LENGTH {
+            out.push('\n');
+            line_length = 0;
+        }
+        line_length += token.len();
+        out += &token;
+    }
+    out.push('\n');
+    out
+}
+
+/// Encode the living cells of a world in the plaintext `.cells` format used by LifeWiki: a grid
+/// of `.` and `O` covering the bounding box, with the highest row first and trailing dead cells
+/// left off.
+pub fn to_cells<S: CellState>(world: &LifeWorld<S>) -> String {
+    let Some(((x0, y0), (x1, y1))) = world.bounding_box() else {
+        return String::new();
+    };
+    let mut out = String::new();
+    for y in (y0..=y1).rev() {
+        let row: String = (x0..=x1)
+            .map(|x| if world.alive(x, y) { 'O' } else { '.' })
+            .collect();
+        out += row.trim_end_matches('.');
+        out.push('\n');
+    }
+    out
+}
+
+/// Encode the living cells of a world in the Life 1.06 format: a header line followed by one
+/// `x y` line per cell. Life 1.06 has y pointing down, so rows are flipped but coordinates are
+/// otherwise kept, so the pattern loads back where it was.
+pub fn to_life106<S: CellState>(world: &LifeWorld<S>) -> String {
+    let mut cells: Vec<(i32, i32)> = world.cells().map(|(x, y)| (x, -y)).collect();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    let mut out = String::from("#Life 1.06\n");
+    for (x, y) in cells {
+        out += &format!("{} {}\n", x, y);
+    }
+    out
+}
+
+/// Encode the bounding box of a world as a binary PBM (P4) bitmap, one bit per cell with live
+/// cells set. The first row is the highest row of the bounding box.
+pub fn to_pbm<S: CellState>(world: &LifeWorld<S>) -> Vec<u8> {
+    let Some(((x0, y0), (x1, y1))) = world.bounding_box() else {
+        return b"P4\n0 0\n".to_vec();
+    };
+    let mut out = format!("P4\n{} {}\n", x1 - x0 + 1, y1 - y0 + 1).into_bytes();
+    for y in (y0..=y1).rev() {
+        out.extend(pack_row(world, x0, x1, y, |bit| 0x80 >> bit));
+    }
+    out
+}
+
+/// Encode the bounding box of a world as an XBM bitmap, a C source fragment declaring
+/// `<name>_width`, `<name>_height` and `<name>_bits`.
+pub fn to_xbm<S: CellState>(world: &LifeWorld<S>, name: &str) -> String {
+    let (width, height, bytes) = match world.bounding_box() {
+        Some(((x0, y0), (x1, y1))) => {
+            let bytes: Vec<u8> = (y0..=y1)
+                .rev()
+                .flat_map(|y| pack_row(world, x0, x1, y, |bit| 1 << bit))
+                .collect();
+            (x1 - x0 + 1, y1 - y0 + 1, bytes)
+        }
+        None => (0, 0, Vec::new()),
+    };
+    let mut out = format!(
+        "#define {name}_width {}\n#define {name}_height {}\nstatic unsigned char {name}_bits[] = {{",
+        width, height
+    );
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out += if i % XBM_BYTES_PER_LINE == 0 {
+            "\n   "
+        } else {
+            " "
+        };
+        out += &format!("0x{:02x}", byte);
+    }
+    out += "};\n";
+    out
+}
+
+/// Encode the bounding box of a world as an SVG image with one square per live cell, for
+/// print-quality figures. Each cell is one unit of the view box and is drawn
+/// [`SVG_CELL_SIZE`] pixels across by default; `grid` adds lines between the cells.
+pub fn to_svg<S: CellState>(world: &LifeWorld<S>, grid: bool) -> String {
+    let ((x0, y0), (x1, y1)) = world.bounding_box().unwrap_or(((0, 0), (-1, -1)));
+    let (columns, rows) = (x1 - x0 + 1, y1 - y0 + 1);
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {} {}\">\n",
+        columns * SVG_CELL_SIZE,
+        rows * SVG_CELL_SIZE,
+        columns,
+        rows
+    );
+    out += "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n<g fill=\"black\">\n";
+    let mut cells: Vec<(i32, i32)> = world.cells().map(|(x, y)| (x - x0, y1 - y)).collect();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    for (x, y) in cells {
+        out += &format!("<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\"/>\n", x, y);
+    }
+    out += "</g>\n";
+    if grid {
+        out += "<g stroke=\"#c0c0c0\" stroke-width=\"0.05\">\n";
+        for x in 0..=columns {
+            out += &format!("<line x1=\"{x}\" y1=\"0\" x2=\"{x}\" y2=\"{}\"/>\n", rows);
+        }
+        for y in 0..=rows {
+            out += &format!(
+                "<line x1=\"0\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\"/>\n",
+                columns
+            );
+        }
+        out += "</g>\n";
+    }
+    out += "</svg>\n";
+    out
+}
+
+/// The size of a cell in SVG output, in pixels.
+const SVG_CELL_SIZE: i32 = 10;
+
+/// Number of bytes written per line of XBM data, as in files written by X11's `bitmap` tool.
+const XBM_BYTES_PER_LINE: usize = 12;
+
+/// Pack one row of cells into bytes, padding the last byte with dead cells. `mask` gives the
+/// bit for a column within its byte, since PBM is most significant bit first and XBM least.
+fn pack_row<S: CellState>(
+    world: &LifeWorld<S>,
+    x0: i32,
+    x1: i32,
+    y: i32,
+    mask: impl Fn(usize) -> u8,
+) -> Vec<u8> {
+    let width = (x1 - x0 + 1) as usize;
+    let mut bytes = vec![0u8; width.div_ceil(8)];
+    for (i, x) in (x0..=x1).enumerate() {
+        if world.alive(x, y) {
+            bytes[i / 8] |= mask(i % 8);
+        }
+    }
+    bytes
+}
+
+/// The digits of the extended Wechsler format. Digits up to `v` are columns of five cells, with
+/// the top cell in the lowest bit; the rest only appear after `y`, counting runs of empty
+/// columns.
+const WECHSLER_DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+/// The widest and tallest object Catagolue gives an apgcode to.
+const APGCODE_MAX_SIZE: i32 = 40;
+/// How many generations [`to_apgcode`] runs a pattern looking for its period.
+const APGCODE_MAX_PERIOD: usize = 1000;
+
+/// Name a still life, oscillator or spaceship by its apgcode, as used by Catagolue: `xs` and the
+/// population, `xp` and the period, or `xq` and the period, then `_` and the extended Wechsler
+/// encoding of the phase and orientation with the shortest (and then alphabetically first)
+/// encoding. Fails unless the pattern is already repeating and fits in 40 by 40 cells.
+pub fn to_apgcode<S: CellState>(world: &LifeWorld<S>) -> Result<String> {
+    let mut world = world.clone();
+    let outcome = analysis::classify(&mut world.clone(), APGCODE_MAX_PERIOD);
+    if outcome.lifespan > 0 {
+        return Err(anyhow!(
+            "the pattern has not stabilized (it settles after {} generations)",
+            outcome.lifespan
+        ));
+    }
+    let (prefix, period) = match outcome.classification {
+        Classification::StillLife => (format!("xs{}", world.num_alive()), 1),
+        Classification::Oscillator(period) => (format!("xp{}", period), period),
+        Classification::Spaceship(period, _) => (format!("xq{}", period), period),
+        Classification::Extinct => return Err(anyhow!("the pattern is empty")),
+        Classification::Unresolved => {
+            return Err(anyhow!("not a still life, oscillator or spaceship"));
+        }
+    };
+    let mut best: Option<String> = None;
+    for _ in 0..period {
+        let ((x0, y0), (x1, y1)) = world.bounding_box().expect("the pattern is not empty");
+        let (width, height) = (x1 - x0 + 1, y1 - y0 + 1);
+        if width > APGCODE_MAX_SIZE || height > APGCODE_MAX_SIZE {
+            return Err(anyhow!("{}x{} is too large for an apgcode", width, height));
+        }
+        // Columns and rows from the top left corner, as in the format.
+        let cells: Vec<(i32, i32)> = world.cells().map(|(x, y)| (x - x0, y1 - y)).collect();
+        for orientation in 0..8 {
+            let transpose = orientation >= 4;
+            let (w, h) = if transpose {
+                (height, width)
+            } else {
+                (width, height)
+            };
+            let oriented: Vec<(i32, i32)> = cells
+                .iter()
+                .map(|&(c, r)| if transpose { (r, c) } else { (c, r) })
+                .map(|(c, r)| {
+                    if orientation & 1 != 0 {
+                        (w - 1 - c, r)
+                    } else {
+                        (c, r)
+                    }
+                })
+                .map(|(c, r)| {
+                    if orientation & 2 != 0 {
+                        (c, h - 1 - r)
+                    } else {
+                        (c, r)
+                    }
+                })
+                .collect();
+            let code = wechsler(&oriented, w, h);
+            let shorter = |best: &String| (code.len(), &code) < (best.len(), best);
+            if best.as_ref().is_none_or(shorter) {
+                best = Some(code);
+            }
+        }
+        world.evolve();
+    }
+    Ok(format!("{}_{}", prefix, best.unwrap_or_default()))
+}
+
+/// Encode cells given as columns and rows from the top left corner in extended Wechsler format:
+/// strips of five rows separated by `z`, each a digit per column, with runs of empty columns
+/// shortened (`w` for two, `x` for three, `y` and a digit for four or more) and left off at the
+/// end of a strip.
+fn wechsler(cells: &[(i32, i32)], width: i32, height: i32) -> String {
+    let strips = (height + 4) / 5;
+    let mut columns = vec![0usize; (width * strips) as usize];
+    for &(c, r) in cells {
+        columns[((r / 5) * width + c) as usize] |= 1 << (r % 5);
+    }
+    let mut out = String::new();
+    for (strip, columns) in columns.chunks(width as usize).enumerate() {
+        if strip > 0 {
+            out.push('z');
+        }
+        let mut zeros = 0;
+        for &column in columns {
+            if column == 0 {
+                zeros += 1;
+                continue;
+            }
+            match zeros {
+                0 => (),
+                1 => out.push('0'),
+                2 => out.push('w'),
+                3 => out.push('x'),
+                n => {
+                    out.push('y');
+                    out.push(WECHSLER_DIGITS[n - 4] as char);
+                }
+            }
+            zeros = 0;
+            out.push(WECHSLER_DIGITS[column] as char);
+        }
+    }
+    out
+}
+
+/// Whether a string looks like an apgcode [`parse_apgcode`] can decode.
+pub fn is_apgcode(code: &str) -> bool {
+    apgcode_body(code).is_some()
+}
+
+/// The encoded cells of an `xs`, `xp` or `xq` apgcode.
+fn apgcode_body(code: &str) -> Option<&str> {
+    let (prefix, body) = code.split_once('_')?;
+    let count = prefix
+        .strip_prefix("xs")
+        .or_else(|| prefix.strip_prefix("xp"))
+        .or_else(|| prefix.strip_prefix("xq"))?;
+    (!count.is_empty() && count.bytes().all(|b| b.is_ascii_digit())).then_some(body)
+}
+
+/// Decode an `xs`, `xp` or `xq` apgcode, with the bottom left corner of the pattern's bounding
+/// box at the origin.
+pub fn parse_apgcode(code: &str) -> Result<LifeWorld> {
+    let body =
+        apgcode_body(code).ok_or_else(|| anyhow!("not an xs, xp or xq apgcode: {}", code))?;
+    let digit = |c: char| {
+        WECHSLER_DIGITS
+            .iter()
+            .position(|&d| d as char == c)
+            .ok_or_else(|| anyhow!("invalid apgcode character: {:?}", c))
+    };
+    let mut cells = Vec::new();
+    let (mut strip, mut column) = (0usize, 0usize);
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            'z' => {
+                strip += 1;
+                column = 0;
+            }
+            'w' => column += 2,
+            'x' => column += 3,
+            'y' => {
+                let run = chars
+                    .next()
+                    .ok_or_else(|| anyhow!("apgcode ends after y"))?;
+                column += 4 + digit(run)?;
+            }
+            c => {
+                let bits = digit(c)?;
+                if bits >= 32 {
+                    return Err(anyhow!("invalid apgcode character: {:?}", c));
+                }
+                let rows = (0..5).filter(|bit| bits & 1 << bit != 0);
+                cells.extend(rows.map(|bit| (column, strip * 5 + bit)));
+                column += 1;
+            }
+        }
+    }
+    // Rows run top to bottom, so flip them to put the lowest row at y = 0.
+    let bottom = cells.iter().map(|&(_, row)| row).max().unwrap_or(0);
+    let mut world = LifeWorld::new();
+    for (column, row) in cells {
+        world.raise(column as i32, (bottom - row) as i32);
+    }
+    Ok(world)
+}
+
+/// A pattern read from a file, with the rule the file declares, if any.
+#[derive(Debug)]
+pub struct Pattern {
+    pub world: LifeWorld,
+    pub rule: Option<String>,
+}
+
+impl Pattern {
+    /// A pattern whose world is set to evolve by the declared rule, if it is one the engine
+    /// supports, and in the declared topology, e.g. the torus of `B3/S23:T200,100`.
+    fn new(mut world: LifeWorld, mut rule: Option<String>) -> Pattern {
+        let declared = rule.as_deref().and_then(|rule| rule.split_once(':'));
+        if let Some((name, topology)) = declared {
+            // An unsupported topology stays in the rule, so the pattern is warned about.
+            if let Ok(topology) = topology.parse() {
+                world.set_topology(topology);
+                rule = Some(name.to_string());
+            }
+        }
+        if let Some(rule) = rule.as_deref().and_then(|rule| rule::parse(rule).ok()) {
+            world.set_rule(rule);
+        }
+        Pattern { world, rule }
+    }
+
+    /// A warning if the file declares a rule other than `running`, the one the pattern will
+    /// actually run as, so such patterns don't silently behave differently than in their home
+    /// rule.
+    pub fn rule_warning(&self, running: &dyn Rule) -> Option<String> {
+        let rule = self.rule.as_ref()?;
+        if rule::parse(rule).is_ok_and(|rule| *rule == *running) {
+            return None;
+        }
+        Some(format!(
+            "warning: the pattern is for rule {}, but it will run as {}",
+            rule, running
+        ))
+    }
+}
+
+impl From<LifeWorld> for Pattern {
+    fn from(world: LifeWorld) -> Self {
+        Pattern { world, rule: None }
+    }
+}
+
+/// Built-in patterns declare the rule they are meant for, except for soups, which aren't meant
+/// for any rule in particular.
+impl From<&LifePattern> for Pattern {
+    fn from(pattern: &LifePattern) -> Self {
+        let rule = match pattern {
+            LifePattern::Blank | LifePattern::Random(_) => None,
+            _ => Some(pattern.info().rule.to_string()),
+        };
+        Pattern {
+            world: LifeWorld::from(pattern),
+            rule,
+        }
+    }
+}
+
+/// Load a pattern file, choosing the format by extension. Patterns are placed with the bottom
+/// left corner of their bounding box at the origin, except for Life 1.06 files, which give
+/// absolute coordinates.
+pub fn load(path: &Path) -> Result<LifeWorld> {
+    Ok(load_pattern(path)?.world)
+}
+
+/// Load a pattern file like [`load`], along with the rule it declares.
+pub fn load_pattern(path: &Path) -> Result<Pattern> {
+    let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    parse_as(&extension.to_ascii_lowercase(), &data)
+        .with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Decode a pattern without a file name to go by, such as one piped to stdin, guessing the
+/// format from its contents.
+pub fn read_pattern(data: &[u8]) -> Result<Pattern> {
+    parse_as(sniff(data), data)
+}
+
+/// Decode a pattern in the format with the given (lowercase) file extension.
+fn parse_as(extension: &str, data: &[u8]) -> Result<Pattern> {
+    match extension {
+        "rle" => text(data).and_then(|text| {
+            let mut world = parse_rle(text)?;
+            world.generations = rle_generation(text).unwrap_or(0);
+            Ok(Pattern::new(world, rle_rule(text)))
+        }),
+        "lif" | "life" => {
+            text(data).and_then(|text| Ok(Pattern::new(parse_life(text)?, life105_rule(text))))
+        }
+        "cells" => text(data).and_then(parse_cells).map(Pattern::from),
+        "pbm" => parse_pbm(data).map(Pattern::from),
+        _ => Err(anyhow!(
+            "unsupported pattern format (expected .rle, .cells, .lif or .pbm)"
+        )),
+    }
+}
+
+/// The extension of the format `data` appears to be in. Anything that isn't recognisably
+/// another format is taken to be RLE, which needn't have a header.
+fn sniff(data: &[u8]) -> &'static str {
+    let start = data.trim_ascii_start();
+    if start.starts_with(b"P1") || start.starts_with(b"P4") {
+        "pbm"
+    } else if start.starts_with(b"#Life") {
+        "life"
+    } else if start.starts_with(b"!")
+        || start.split(|&b| b == b'\n').all(|line| {
+            line.trim_ascii()
+                .iter()
+                .all(|b| matches!(b, b'.' | b'O' | b'*'))
+        })
+    {
+        "cells"
+    } else {
+        "rle"
+    }
+}
+
+fn text(data: &[u8]) -> Result<&str> {
+    std::str::from_utf8(data).map_err(|_| anyhow!("pattern file is not valid UTF-8"))
+}
+
+/// The rule in an RLE header line, e.g. `B36/S23` from `x = 3, y = 3, rule = B36/S23`.
+pub fn rle_rule(data: &str) -> Option<String> {
+    let header = data
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.starts_with('#'))
+        .filter(|line| line.starts_with('x') && line.contains('='))?;
+    // The rule comes last and may hold commas itself, e.g. `R5,C0,M1,S34..58,B34..45,NM`.
+    let (key, value) = header[header.find("rule")?..].split_once('=')?;
+    (key.trim() == "rule").then(|| value.trim().to_string())
+}
+
+/// The generation an RLE file was saved at, from a `#C generation N` comment as written by
+/// snapshots and the pattern library, or the `Gen=N` field of a Golly `#CXRLE` line.
+pub fn rle_generation(data: &str) -> Option<usize> {
+    let mut comments = data
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.starts_with('#'));
+    comments.find_map(|line| {
+        if let Some(generation) = line.strip_prefix("#C generation ") {
+            return generation.trim().parse().ok();
+        }
+        let fields = line.strip_prefix("#CXRLE")?;
+        fields
+            .split_whitespace()
+            .find_map(|field| field.strip_prefix("Gen="))
+            .and_then(|generation| generation.parse().ok())
+    })
+}
+
+/// The rule in a Life 1.05 `#R` line, in S/B notation. `#N` (or no line) means Life.
+fn life105_rule(data: &str) -> Option<String> {
+    data.lines()
+        .find_map(|line| line.trim().strip_prefix("#R"))
+        .map(|rule| rule.trim().to_string())
+}
+
+/// Decode a pattern in the RLE format used by Golly and LifeWiki. `#` lines and the
+/// `x = .., y = ..` header are skipped, as the size is implied by the cells. Under a multi-state
+/// rule declared in the header, states from 2 on are dying cells; otherwise any state other than
+/// dead (`b` or `.`) is taken as alive.
+pub fn parse_rle(data: &str) -> Result<LifeWorld> {
+    let states = rle_rule(data)
+        .and_then(|rule| rule::parse(rule.split(':').next()?).ok())
+        .map_or(2, |rule| usize::from(rule.states()));
+    let mut world = LifeWorld::new();
+    for ((x, y), state) in rle_states(data)? {
+        if (2..states).contains(&state) {
+            world.set_dying(x, y, (state - 1) as u8);
+        } else {
+            world.raise(x, y);
+        }
+    }
+    Ok(world)
+}
+
+/// The cells of an RLE pattern other than dead ones, with the bottom left corner of its bounding
+/// box at the origin, and their states: 1 for `o` and the letters of two-state patterns, and the
+/// state numbered by Golly's multi-state notation for `A` to `X`, which may follow a letter from
+/// `p` to `y` for states above 24.
+pub(crate) fn rle_states(data: &str) -> Result<Vec<((i32, i32), usize)>> {
+    let mut cells = Vec::new();
+    let (mut row, mut column) = (0usize, 0usize);
+    let mut count: Option<usize> = None;
+    let mut prefix: Option<usize> = None;
+    let body = data
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter(|line| !(line.starts_with('x') && line.contains('=')));
+    'lines: for line in body {
+        for c in line.chars() {
+            match c {
+                _ if prefix.is_some() && !('A'..='X').contains(&c) => {
+                    return Err(anyhow!("invalid RLE state: {:?} after a state prefix", c));
+                }
+                '0'..='9' => {
+                    let digit = c as usize - '0' as usize;
+                    count = count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(digit))
+                        .filter(|&n| n <= MAX_RLE_RUN)
+                        .map(Some)
+                        .ok_or_else(|| anyhow!("RLE run too long"))?;
+                }
+                'b' | '.' => column += count.take().unwrap_or(1),
+                '$' => {
+                    row += count.take().unwrap_or(1);
+                    column = 0;
+                }
+                '!' => break 'lines,
+                'p'..='y' => prefix = Some(24 * (c as usize - 'p' as usize + 1)),
+                c if c.is_ascii_alphabetic() => {
+                    let state = match c {
+                        'A'..='X' => prefix.take().unwrap_or(0) + c as usize - 'A' as usize + 1,
+                        _ => 1,
+                    };
+                    let run = count.take().unwrap_or(1);
+                    cells.extend((column..column + run).map(|x| ((x, row), state)));
+                    column += run;
+                }
+                c if c.is_whitespace() => (),
+                _ => return Err(anyhow!("invalid RLE character: {:?}", c)),
+            }
+        }
+    }
+    // Rows run top to bottom, so flip them to put the lowest row at y = 0.
+    let bottom = cells.iter().map(|&((_, y), _)| y).max().unwrap_or(0);
+    let coordinate = |c: usize| {
+        i32::try_from(c)
+            .ok()
+            .filter(|&c| c <= MAX_COORDINATE)
+            .ok_or_else(|| anyhow!("RLE pattern too large"))
+    };
+    cells
+        .into_iter()
+        .map(|((x, y), state)| Ok(((coordinate(x)?, coordinate(bottom - y)?), state)))
+        .collect()
+}
+
+/// Decode a pattern in the plaintext `.cells` format. Lines starting with `!` are comments; `O`
+/// or `*` is a live cell and any other character a dead one.
+pub fn parse_cells(data: &str) -> Result<LifeWorld> {
+    let rows: Vec<&str> = data
+        .lines()
+        .filter(|line| !line.starts_with('!'))
+        .map(str::trim_end)
+        .collect();
+    // Rows run top to bottom, so flip them to put the lowest row at y = 0.
+    let bottom = rows
+        .iter()
+        .rposition(|row| row.contains(['O', '*']))
+        .unwrap_or(0);
+    let mut world = LifeWorld::new();
+    for (row, line) in rows.iter().enumerate().take(bottom + 1) {
+        for (column, c) in line.chars().enumerate() {
+            if c == 'O' || c == '*' {
+                world.raise(column as i32, (bottom - row) as i32);
+            }
+        }
+    }
+    Ok(world)
+}
+
+/// Decode a `.lif` file, choosing the version from its header line.
+pub fn parse_life(data: &str) -> Result<LifeWorld> {
+    match data.lines().next().map(str::trim) {
+        Some("#Life 1.05") => parse_life105(data),
+        Some("#Life 1.06") => parse_life106(data),
+        Some(header) if header.starts_with("#Life") => {
+            Err(anyhow!("unsupported Life file version: {}", header))
+        }
+        _ => Err(anyhow!("missing #Life header")),
+    }
+}
+
+/// Decode a Life 1.05 file: blocks of `.` and `*` rows, each starting with a `#P x y` line
+/// giving the position of its top left cell, with y pointing down. Other `#` lines (descriptions
+/// and rules) are skipped.
+pub fn parse_life105(data: &str) -> Result<LifeWorld> {
+    let mut world = LifeWorld::new();
+    // The position of the next row, if a block has started.
+    let mut origin: Option<(i32, i32)> = None;
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if let Some(offset) = line.strip_prefix("#P") {
+            let mut fields = offset.split_whitespace().map(str::parse::<i32>);
+            let (Some(Ok(x)), Some(Ok(y)), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(anyhow!(
+                    "invalid block position on line {}: {}",
+                    i + 1,
+                    line
+                ));
+            };
+            origin = Some((x, y));
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((x0, y)) = origin.as_mut() else {
+            return Err(anyhow!("cells before the first #P line on line {}", i + 1));
+        };
+        let out_of_range = || anyhow!("cell out of range on line {}: {}", i + 1, line);
+        // Rows stay in range, so moving down to the next one can't overflow.
+        if !in_range(*y) {
+            return Err(out_of_range());
+        }
+        for (column, c) in line.chars().enumerate() {
+            match c {
+                '*' => {
+                    let x = i32::try_from(column)
+                        .ok()
+                        .and_then(|column| x0.checked_add(column))
+                        .filter(|&x| in_range(x))
+                        .ok_or_else(out_of_range)?;
+                    world.raise(x, -*y);
+                }
+                '.' => (),
+                _ => return Err(anyhow!("invalid cell on line {}: {:?}", i + 1, c)),
+            }
+        }
+        *y += 1;
+    }
+    Ok(world)
+}
+
+/// Decode a Life 1.06 file: a `#Life 1.06` header followed by one `x y` line per live cell, with
+/// y pointing down.
+pub fn parse_life106(data: &str) -> Result<LifeWorld> {
+    let mut world = LifeWorld::new();
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace().map(str::parse::<i32>);
+        let (Some(Ok(x)), Some(Ok(y)), None) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(anyhow!("invalid cell on line {}: {}", i + 1, line));
+        };
+        if !in_range(x) || !in_range(y) {
+            return Err(anyhow!("cell out of range on line {}: {}", i + 1, line));
+        }
+        world.raise(x, -y);
+    }
+    Ok(world)
+}
+
+/// The longest run accepted in RLE input, so a corrupt count can't exhaust memory.
+const MAX_RLE_RUN: usize = 1 << 20;
+
+/// The furthest from the origin a cell read from a file may be, leaving room for the widest
+/// neighborhood around it within the range of coordinates.
+const MAX_COORDINATE: i32 = i32::MAX - rule::MAX_RADIUS as i32 - 1;
+
+/// Whether a coordinate read from a file is within [`MAX_COORDINATE`] of the origin.
+fn in_range(c: i32) -> bool {
+    (-MAX_COORDINATE..=MAX_COORDINATE).contains(&c)
+}
+
+/// Decode a plain (P1) or binary (P4) PBM bitmap, with each set pixel becoming a live cell.
+pub fn parse_pbm(data: &[u8]) -> Result<LifeWorld> {
+    let mut header = PbmHeader { data, pos: 0 };
+    let magic = header.token()?;
+    let width: usize = header.number()?;
+    let height: usize = header.number()?;
+    if width.max(height) > MAX_COORDINATE as usize {
+        return Err(anyhow!("PBM image too large: {}x{}", width, height));
+    }
+    let mut world = LifeWorld::new();
+    let mut place = |row: usize, column: usize| {
+        world.raise(column as i32, (height - 1 - row) as i32);
+    };
+    match magic {
+        b"P1" => {
+            let mut bits = data[header.pos..]
+                .iter()
+                .filter(|b| !b.is_ascii_whitespace());
+            for row in 0..height {
+                for column in 0..width {
+                    match bits.next() {
+                        Some(b'1') => place(row, column),
+                        Some(b'0') => (),
+                        Some(&b) => return Err(anyhow!("invalid PBM pixel: {:?}", b as char)),
+                        None => return Err(anyhow!("PBM data ends early")),
+                    }
+                }
+            }
+        }
+        b"P4" => {
+            // Exactly one whitespace character separates the header from the raster.
+            let raster = data.get(header.pos + 1..).unwrap_or_default();
+            let stride = width.div_ceil(8);
+            let size = stride
+                .checked_mul(height)
+                .ok_or_else(|| anyhow!("PBM image too large: {}x{}", width, height))?;
+            if raster.len() < size {
+                return Err(anyhow!("PBM data ends early"));
+            }
+            for row in 0..height {
+                for column in 0..width {
+                    if raster[row * stride + column / 8] & (0x80 >> (column % 8)) != 0 {
+                        place(row, column);
+                    }
+                }
+            }
+        }
+        _ => return Err(anyhow!("not a PBM file")),
+    }
+    Ok(world)
+}
+
+/// Reads the whitespace separated header fields of a PBM file, skipping `#` comments.
+struct PbmHeader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PbmHeader<'a> {
+    fn token(&mut self) -> Result<&'a [u8]> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err(anyhow!("PBM header ends early")),
+            }
+        }
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        Ok(&self.data[start..self.pos])
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let token = self.token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow!("invalid PBM size: {}", String::from_utf8_lossy(token)))
+    }
+}
+
+/// The tag for a state of a multi-state rule in RLE, as Golly writes them: `A` to `X` for states
+/// 1 to 24, then a letter from `p` for each further 24 followed by one of `A` to `X`.
+fn state_tag(state: usize) -> String {
+    let letter = |from: u8, offset: usize| char::from(from + offset as u8);
+    match state {
+        ..=24 => letter(b'A', state - 1).to_string(),
+        _ => format!(
+            "{}{}",
+            letter(b'p', (state - 25) / 24),
+            letter(b'A', (state - 25) % 24)
+        ),
+    }
+}
+
+fn run(count: usize, tag: &str) -> String {
+    if count == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", count, tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::LifePattern;
+
+    #[test]
+    fn reads_patterns_without_an_extension() -> Result<()> {
+        let glider = LifeWorld::from(&LifePattern::Glider);
+        let formats: [&[u8]; 5] = [
+            b"#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n",
+            b"bo$2bo$3o!",
+            b"!Name: Glider\n.O.\n..O\nOOO\n",
+            b"#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n",
+            b"P1\n3 3\n0 1 0\n0 0 1\n1 1 1\n",
+        ];
+        for data in formats {
+            assert_eq!(to_rle(&read_pattern(data)?.world), to_rle(&glider));
+        }
+        let highlife = read_pattern(b"x = 1, y = 1, rule = B36/S23\no!")?;
+        assert_eq!(highlife.rule.as_deref(), Some("B36/S23"));
+        Ok(())
+    }
+
+    #[test]
+    fn encodes_apgcodes() -> Result<()> {
+        let code = |pattern| to_apgcode(&LifeWorld::from(&pattern));
+        assert_eq!(code(LifePattern::Blinker)?, "xp2_7");
+        assert_eq!(code(LifePattern::Glider)?, "xq4_153");
+        assert_eq!(code(LifePattern::Lwss)?, "xq4_6frc");
+        assert_eq!(code(LifePattern::Pentadecathlon)?, "xp15_4r4z4r4");
+        assert_eq!(
+            code(LifePattern::Pulsar)?,
+            "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401"
+        );
+        let mut beehive = parse_cells(".OO.\nO..O\n.OO.\n")?;
+        assert_eq!(to_apgcode(&beehive)?, "xs6_696");
+        beehive.raise(10, 10);
+        assert!(to_apgcode(&beehive).is_err());
+        assert!(code(LifePattern::RPentomino).is_err());
+        assert!(code(LifePattern::Blank).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_apgcodes() -> Result<()> {
+        let cells = |world: &LifeWorld| {
+            let mut cells: Vec<_> = world.cells().collect();
+            cells.sort();
+            cells
+        };
+        assert_eq!(
+            cells(&parse_apgcode("xp2_7")?),
+            vec![(0, 0), (0, 1), (0, 2)]
+        );
+        let pulsar = "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401";
+        assert_eq!(to_apgcode(&parse_apgcode(pulsar)?)?, pulsar);
+        // Two blocks with two empty columns between them.
+        let blocks = parse_apgcode("xs8_33w33")?;
+        assert_eq!(blocks.num_alive(), 8);
+        assert_eq!(blocks.bounding_box(), Some(((0, 0), (5, 1))));
+        assert_eq!(
+            parse_apgcode("xs8_33y033")?.bounding_box(),
+            Some(((0, 0), (7, 1)))
+        );
+        assert!(parse_apgcode("xs4_3w").is_ok());
+        assert!(parse_apgcode("yl144_1_16_afb5f3db909e60548f086e22ee3353ac").is_err());
+        assert!(parse_apgcode("xs4_3?").is_err());
+        assert!(parse_apgcode("xs4_3y").is_err());
+        assert!(is_apgcode("xq4_153"));
+        assert!(!is_apgcode("glider"));
+        Ok(())
+    }
+
+    #[test]
+    fn encodes_svg() {
+        let world = LifeWorld::from(&LifePattern::Blinker);
+        let svg = to_svg(&world, false);
+        assert!(svg.contains("width=\"10\" height=\"30\" viewBox=\"0 0 1 3\""));
+        assert_eq!(svg.matches("width=\"1\"").count(), 3);
+        assert!(svg.contains("<rect x=\"0\" y=\"2\" width=\"1\" height=\"1\"/>"));
+        // Two vertical and four horizontal grid lines.
+        assert_eq!(to_svg(&world, true).matches("<line").count(), 6);
+        assert!(to_svg(&LifeWorld::new(), true).contains("viewBox=\"0 0 0 0\""));
+    }
+
+    #[test]
+    fn encodes_glider() {
+        let world = LifeWorld::from(&LifePattern::Glider);
+        assert_eq!(to_rle(&world), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+    }
+
+    #[test]
+    fn encodes_empty_rows() {
+        let mut world = LifeWorld::new();
+        world.raise(0, 0);
+        world.raise(2, 3);
+        assert_eq!(to_rle(&world), "x = 3, y = 4, rule = B3/S23\n2bo3$o!\n");
+    }
+
+    #[test]
+    fn encodes_empty_world() {
+        let world = LifeWorld::new();
+        assert_eq!(to_rle(&world), "x = 0, y = 0, rule = B3/S23\n!\n");
+    }
+
+    #[test]
+    fn encodes_pbm() {
+        let world = LifeWorld::from(&LifePattern::Glider);
+        assert_eq!(to_pbm(&world), b"P4\n3 3\n\x40\x20\xe0".to_vec());
+        assert_eq!(to_pbm(&LifeWorld::new()), b"P4\n0 0\n".to_vec());
+    }
+
+    #[test]
+    fn encodes_xbm() {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        assert_eq!(
+            to_xbm(&world, "glider"),
+            "#define glider_width 3\n#define glider_height 3\n\
+             static unsigned char glider_bits[] = {\n   0x02, 0x04, 0x07};\n"
+        );
+        // Rows wider than a byte are padded separately.
+        world.raise(9, 0);
+        let xbm = to_xbm(&world, "glider");
+        assert!(
+            xbm.contains("{\n   0x02, 0x00, 0x04, 0x00, 0x07, 0x02};"),
+            "{}",
+            xbm
+        );
+    }
+
+    #[test]
+    fn decodes_pbm() -> Result<()> {
+        let glider = LifeWorld::from(&LifePattern::Glider);
+        let cells = |world: &LifeWorld| {
+            let mut cells: Vec<_> = world.cells().collect();
+            cells.sort();
+            cells
+        };
+        assert_eq!(cells(&parse_pbm(&to_pbm(&glider))?), cells(&glider));
+        let plain = b"P1\n# a glider\n3 3\n0 1 0\n001\n1 1 1\n";
+        assert_eq!(cells(&parse_pbm(plain)?), cells(&glider));
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_rle() -> Result<()> {
+        let cells = |world: &LifeWorld| {
+            let mut cells: Vec<_> = world.cells().collect();
+            cells.sort();
+            cells
+        };
+        let glider = LifeWorld::from(&LifePattern::Glider);
+        assert_eq!(cells(&parse_rle(&to_rle(&glider))?), cells(&glider));
+        let commented = "#N Glider
+#C a comment
+x = 3, y = 3, rule = B3/S23
+.o$
+2bo$3o!
+";
+        assert_eq!(cells(&parse_rle(commented)?), cells(&glider));
+        let mut gaps = LifeWorld::new();
+        gaps.raise(0, 0);
+        gaps.raise(2, 3);
+        assert_eq!(cells(&parse_rle("2bo3$o!")?), cells(&gaps));
+        assert!(parse_rle("3o?!").is_err());
+        assert!(parse_rle("99999999999o!").is_err());
+        // Runs that together reach past the range of coordinates are rejected too.
+        let wide = format!("{}o!", "1048576b".repeat(2048));
+        assert!(parse_rle(&wide).is_err());
+        let tall = format!("o{}o!", "1048576$".repeat(2048));
+        assert!(parse_rle(&tall).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_multi_state_rle() -> Result<()> {
+        let mut wires = LifeWorld::new();
+        wires.set_rule(rule::parse("WireWorld")?);
+        for x in 0..4 {
+            wires.set_dying(x, 1, 2);
+        }
+        wires.set_dying(0, 1, 1);
+        wires.raise(1, 1);
+        wires.set_dying(3, 0, 2);
+        let rle = to_rle(&wires);
+        assert_eq!(rle, "x = 4, y = 2, rule = WireWorld\nBA2C$3.C!\n");
+        let pattern = read_pattern(rle.as_bytes())?;
+        assert_eq!(pattern.world.to_rle(), rle);
+        assert_eq!(pattern.world.decay(3, 0), 2);
+        // Without a multi-state rule, every state is alive.
+        assert_eq!(parse_rle("BA2C!")?.num_alive(), 4);
+        assert_eq!(rle_states("pAyO!")?, [((0, 0), 25), ((1, 0), 255)]);
+        assert_eq!(
+            (state_tag(25), state_tag(255)),
+            (String::from("pA"), String::from("yO"))
+        );
+        assert!(parse_rle("p$A!").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_cells() -> Result<()> {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        world.raise(0, 4);
+        let cells = to_cells(&world);
+        assert_eq!(cells, "O\n\n.O\n..O\nOOO\n");
+        let parsed = parse_cells(&format!("!Name: test\n!\n{}....\n", cells))?;
+        let mut expected: Vec<_> = world.cells().collect();
+        let mut actual: Vec<_> = parsed.cells().collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+        assert_eq!(to_cells(&LifeWorld::new()), "");
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_life106() -> Result<()> {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        world.raise(-5, 7);
+        let life = to_life106(&world);
+        assert!(life.starts_with("#Life 1.06\n-5 -7\n1 -2\n"), "{}", life);
+        let mut expected: Vec<_> = world.cells().collect();
+        let mut actual: Vec<_> = parse_life(&life)?.cells().collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+        assert!(parse_life("#Life 1.06\n1 2 3\n").is_err());
+        assert!(parse_life("#Life 1.06\n1 x\n").is_err());
+        assert!(parse_life("#Life 1.06\n0 -2147483648\n").is_err());
+        assert!(parse_life("#Life 1.06\n2147483647 0\n").is_err());
+        assert!(parse_life("1 2\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reads_declared_rules() {
+        assert_eq!(
+            rle_rule("#N x\nx = 3, y = 1, rule = B36/S23\n3o!"),
+            Some("B36/S23".into())
+        );
+        assert_eq!(rle_rule("x = 3, y = 1\n3o!"), None);
+        assert_eq!(
+            rle_rule("x = 1, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\no!"),
+            Some("R5,C0,M1,S34..58,B34..45,NM".into())
+        );
+        assert_eq!(
+            life105_rule("#Life 1.05\n#R 23/36\n#P 0 0\n*"),
+            Some("23/36".into())
+        );
+        let pattern = |rule: &str| Pattern {
+            world: LifeWorld::new(),
+            rule: Some(rule.to_string()),
+        };
+        assert_eq!(pattern("B3/S23").rule_warning(&*rule::life()), None);
+        assert_eq!(pattern("23/3").rule_warning(&*rule::life()), None);
+        assert!(pattern("B36/S23").rule_warning(&*rule::life()).is_some());
+        assert!(pattern("LifeHistory")
+            .rule_warning(&*rule::life())
+            .is_some());
+    }
+
+    #[test]
+    fn reads_saved_generations() -> Result<()> {
+        assert_eq!(
+            rle_generation("#C generation 42\n#C seed 7\nx = 1, y = 1\no!"),
+            Some(42)
+        );
+        assert_eq!(
+            rle_generation("#CXRLE Pos=-1,-1 Gen=1234\nx = 1, y = 1\no!"),
+            Some(1234)
+        );
+        assert_eq!(rle_generation("#C generation soon\nx = 1, y = 1\no!"), None);
+        assert_eq!(rle_generation("x = 1, y = 1\no!\n#C generation 3"), None);
+        let pattern = read_pattern(b"#C generation 42\nx = 3, y = 1\n3o!")?;
+        assert_eq!(pattern.world.generations, 42);
+        Ok(())
+    }
+
+    #[test]
+    fn runs_patterns_by_their_declared_rule() -> Result<()> {
+        let highlife = read_pattern(b"x = 3, y = 1, rule = B36/S23\n3o!")?;
+        assert_eq!(highlife.world.rule().to_string(), "B36/S23");
+        assert_eq!(highlife.rule_warning(&**highlife.world.rule()), None);
+        assert!(to_rle(&highlife.world).starts_with("x = 3, y = 1, rule = B36/S23\n"));
+        let unknown = read_pattern(b"x = 3, y = 1, rule = LifeHistory\n3A!")?;
+        assert!(**unknown.world.rule() == *rule::life());
+        assert!(unknown.rule_warning(&**unknown.world.rule()).is_some());
+        let torus = read_pattern(b"x = 3, y = 1, rule = B3/S23:T20,10\n3o!")?;
+        assert_eq!(torus.world.topology(), Topology::torus("20x10")?);
+        assert_eq!(torus.rule_warning(&**torus.world.rule()), None);
+        assert!(to_rle(&torus.world).starts_with("x = 3, y = 1, rule = B3/S23:T20,10\n"));
+        let bounded = read_pattern(b"x = 3, y = 1, rule = B3/S23:P20,10\n3o!")?;
+        assert_eq!(bounded.world.topology(), Topology::bounded("20x10")?);
+        // Tori whose edges are joined with a shift aren't supported.
+        let shifted = read_pattern(b"x = 3, y = 1, rule = B3/S23:T20+1,10\n3o!")?;
+        assert_eq!(shifted.world.topology(), Topology::Plane);
+        assert!(shifted.rule_warning(&**shifted.world.rule()).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_life105() -> Result<()> {
+        let life = "#Life 1.05\n#D Two gliders\n#N\n#P -1 -1\n.*\n..*\n***\n#P 10 0\n*\n";
+        let mut actual: Vec<_> = parse_life(life)?.cells().collect();
+        actual.sort();
+        assert_eq!(
+            actual,
+            vec![(-1, -1), (0, -1), (0, 1), (1, -1), (1, 0), (10, 0)]
+        );
+        assert!(parse_life("#Life 1.05\n.*\n").is_err());
+        assert!(parse_life("#Life 1.05\n#P 0 0\n.o\n").is_err());
+        assert!(parse_life("#Life 1.05\n#P 0\n").is_err());
+        assert!(parse_life("#Life 1.05\n#P 2147483647 0\n.*\n").is_err());
+        assert!(parse_life("#Life 1.05\n#P 0 -2147483648\n*\n").is_err());
+        assert!(parse_life("#Life 1.05\n#P 0 2147483647\n.\n.\n*\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bad_pbm() {
+        assert!(parse_pbm(b"P2\n1 1\n1\n").is_err());
+        assert!(parse_pbm(b"P4\n2147483647 1\n\x80").is_err());
+        assert!(parse_pbm(b"P4\n16 4611686018427387904\n\x80").is_err());
+        assert!(parse_pbm(b"P1\n2 2\n1 0 1\n").is_err());
+        assert!(parse_pbm(b"P4\n16 2\n\xff").is_err());
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        let mut world = LifeWorld::new();
+        for x in 0..100 {
+            world.raise(2 * x, 0);
+        }
+        let rle = to_rle(&world);
+        assert!(rle.lines().all(|line| line.len() <= RLE_LINE_LENGTH));
+    }
+}
@@ -0,0 +1,277 @@
+use rustc_hash::FxHashMap;
+
+/// log2 of the side length of a chunk.
+const CHUNK_BITS: u32 = 5;
+/// The side length of a chunk, in cells.
+pub const CHUNK_SIZE: i32 = 1 << CHUNK_BITS;
+const CHUNK_MASK: i32 = CHUNK_SIZE - 1;
+const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// A square block of cells stored densely, so looking up a cell within it does not need hashing.
+/// A bitmap of the filled slots lets iteration skip over empty space.
+#[derive(Clone, Debug)]
+struct Chunk<T> {
+    slots: Box<[Option<T>]>,
+    occupied: [u64; CHUNK_AREA / 64],
+    len: usize,
+}
+
+impl<T> Chunk<T> {
+    fn new() -> Chunk<T> {
+        Chunk {
+            slots: (0..CHUNK_AREA).map(|_| None).collect(),
+            occupied: [0; CHUNK_AREA / 64],
+            len: 0,
+        }
+    }
+
+    /// Update the bookkeeping after the slot at `index` may have been filled or emptied.
+    fn update(&mut self, index: usize, was_some: bool) {
+        match (was_some, self.slots[index].is_some()) {
+            (false, true) => {
+                self.occupied[index / 64] |= 1 << (index % 64);
+                self.len += 1;
+            }
+            (true, false) => {
+                self.occupied[index / 64] &= !(1 << (index % 64));
+                self.len -= 1;
+            }
+            _ => (),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.occupied
+            .iter()
+            .enumerate()
+            .flat_map(|(word, &bits)| {
+                let mut bits = bits;
+                std::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let bit = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    Some(word * 64 + bit)
+                })
+            })
+            .filter_map(|index| Some((index, self.slots[index].as_ref()?)))
+    }
+}
+
+/// A sparse map from positions to values, organized as a hashmap of 32x32 chunks.
+///
+/// Cells that are close together live in the same chunk, so neighbor lookups and region queries
+/// only hash once per chunk rather than once per cell, and iterating over a region only touches
+/// the chunks that overlap it. Empty chunks are dropped.
+#[derive(Clone, Debug)]
+pub struct Grid<T> {
+    chunks: FxHashMap<(i32, i32), Chunk<T>>,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Grid {
+            chunks: FxHashMap::default(),
+        }
+    }
+}
+
+/// Split a position into the key of its chunk and its index within the chunk.
+fn locate((x, y): (i32, i32)) -> ((i32, i32), usize) {
+    let key = (x >> CHUNK_BITS, y >> CHUNK_BITS);
+    let index = ((y & CHUNK_MASK) * CHUNK_SIZE + (x & CHUNK_MASK)) as usize;
+    (key, index)
+}
+
+fn position((cx, cy): (i32, i32), index: usize) -> (i32, i32) {
+    let index = index as i32;
+    (
+        (cx << CHUNK_BITS) + (index & CHUNK_MASK),
+        (cy << CHUNK_BITS) + (index >> CHUNK_BITS),
+    )
+}
+
+impl<T> Grid<T> {
+    pub fn get(&self, pos: (i32, i32)) -> Option<&T> {
+        let (key, index) = locate(pos);
+        self.chunks.get(&key)?.slots[index].as_ref()
+    }
+
+    pub fn get_mut(&mut self, pos: (i32, i32)) -> Option<&mut T> {
+        let (key, index) = locate(pos);
+        self.chunks.get_mut(&key)?.slots[index].as_mut()
+    }
+
+    pub fn get_or_insert_with(&mut self, pos: (i32, i32), f: impl FnOnce() -> T) -> &mut T {
+        let (key, index) = locate(pos);
+        let chunk = self.chunks.entry(key).or_insert_with(Chunk::new);
+        let slot = &mut chunk.slots[index];
+        if slot.is_none() {
+            chunk.occupied[index / 64] |= 1 << (index % 64);
+            chunk.len += 1;
+        }
+        slot.get_or_insert_with(f)
+    }
+
+    pub fn remove(&mut self, pos: (i32, i32)) -> Option<T> {
+        let (key, index) = locate(pos);
+        let chunk = self.chunks.get_mut(&key)?;
+        let value = chunk.slots[index].take()?;
+        chunk.update(index, true);
+        if chunk.len == 0 {
+            self.chunks.remove(&key);
+        }
+        Some(value)
+    }
+
+    /// Call `f` with the slot of every position in the square block reaching `radius` cells
+    /// around `center` (3x3 for a radius of 1), looking up each chunk the block touches only once.
+    /// Slots may be filled or emptied by `f`. When `create` is false, positions in chunks that do
+    /// not exist yet are skipped rather than allocated.
+    pub fn update_block(
+        &mut self,
+        center: (i32, i32),
+        radius: i32,
+        create: bool,
+        mut f: impl FnMut((i32, i32), &mut Option<T>),
+    ) {
+        let (x, y) = center;
+        let (min, max) = ((x - radius, y - radius), (x + radius, y + radius));
+        let ((cx0, cy0), _) = locate(min);
+        let ((cx1, cy1), _) = locate(max);
+        // Usually the whole block is inside one chunk.
+        for cy in cy0..=cy1 {
+            for cx in cx0..=cx1 {
+                let key = (cx, cy);
+                let chunk = if create {
+                    self.chunks.entry(key).or_insert_with(Chunk::new)
+                } else {
+                    match self.chunks.get_mut(&key) {
+                        Some(chunk) => chunk,
+                        None => continue,
+                    }
+                };
+                // The part of the block inside this chunk.
+                let (x0, y0) = (cx << CHUNK_BITS, cy << CHUNK_BITS);
+                for py in min.1.max(y0)..=max.1.min(y0 + CHUNK_MASK) {
+                    for px in min.0.max(x0)..=max.0.min(x0 + CHUNK_MASK) {
+                        let index = ((py & CHUNK_MASK) * CHUNK_SIZE + (px & CHUNK_MASK)) as usize;
+                        let was_some = chunk.slots[index].is_some();
+                        f((px, py), &mut chunk.slots[index]);
+                        chunk.update(index, was_some);
+                    }
+                }
+                if chunk.len == 0 {
+                    self.chunks.remove(&key);
+                }
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), &T)> {
+        self.chunks.iter().flat_map(|(&key, chunk)| {
+            chunk
+                .iter()
+                .map(move |(index, value)| (position(key, index), value))
+        })
+    }
+
+    /// Iterate over the values in row `y`, visiting only the chunks that overlap it.
+    pub fn iter_row(&self, y: i32) -> impl Iterator<Item = ((i32, i32), &T)> {
+        let (_, start) = locate((0, y));
+        let cy = y >> CHUNK_BITS;
+        self.chunks
+            .iter()
+            .filter(move |&(&(_, key_y), _)| key_y == cy)
+            .flat_map(move |(&key, chunk)| {
+                (start..start + CHUNK_SIZE as usize).filter_map(move |index| {
+                    Some((position(key, index), chunk.slots[index].as_ref()?))
+                })
+            })
+    }
+
+    /// Iterate over the values in the inclusive region between `min` and `max`, visiting only the
+    /// chunks that overlap it.
+    pub fn iter_region(
+        &self,
+        min: (i32, i32),
+        max: (i32, i32),
+    ) -> impl Iterator<Item = ((i32, i32), &T)> {
+        let (x0, y0) = min;
+        let (x1, y1) = max;
+        let ((cx0, cy0), _) = locate(min);
+        let ((cx1, cy1), _) = locate(max);
+        (cy0..=cy1)
+            .flat_map(move |cy| (cx0..=cx1).map(move |cx| (cx, cy)))
+            .filter_map(|key| Some((key, self.chunks.get(&key)?)))
+            .flat_map(|(key, chunk)| {
+                chunk
+                    .iter()
+                    .map(move |(index, value)| (position(key, index), value))
+            })
+            .filter(move |&((x, y), _)| x >= x0 && x <= x1 && y >= y0 && y <= y1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_negative_positions() {
+        for pos in [(0, 0), (-1, -1), (31, 32), (-33, 64), (1000, -1000)] {
+            let (key, index) = locate(pos);
+            assert_eq!(position(key, index), pos);
+        }
+        assert_eq!(locate((-1, 0)).0, (-1, 0));
+        assert_eq!(locate((32, -32)).0, (1, -1));
+    }
+
+    #[test]
+    fn removes_empty_chunks() {
+        let mut grid = Grid::default();
+        *grid.get_or_insert_with((5, -5), || 0) += 1;
+        assert_eq!(grid.get((5, -5)), Some(&1));
+        assert_eq!(grid.chunks.len(), 1);
+        assert_eq!(grid.remove((5, -5)), Some(1));
+        assert!(grid.chunks.is_empty());
+    }
+
+    #[test]
+    fn updates_blocks_across_chunks() {
+        let mut grid = Grid::default();
+        grid.update_block((0, 0), 1, true, |_, slot| *slot = Some(1));
+        assert_eq!(grid.iter().count(), 9);
+        assert_eq!(grid.chunks.len(), 4);
+        grid.update_block((-1, -1), 1, false, |_, slot| *slot = None);
+        assert_eq!(grid.iter().count(), 5);
+        grid.update_block((0, 0), 1, false, |_, slot| *slot = None);
+        assert!(grid.chunks.is_empty());
+        grid.update_block((10, 10), 5, true, |_, slot| *slot = Some(1));
+        assert_eq!(grid.iter().count(), 121);
+        assert_eq!(grid.chunks.len(), 1);
+        grid.update_block((30, 10), 3, true, |_, slot| *slot = Some(1));
+        assert_eq!(grid.iter().count(), 121 + 49);
+        assert_eq!(grid.iter_region((27, 7), (33, 13)).count(), 49);
+        assert_eq!(grid.chunks.len(), 2);
+    }
+
+    #[test]
+    fn iterates_regions() {
+        let mut grid = Grid::default();
+        for pos in [(0, 0), (40, 0), (-40, 3), (10, 10)] {
+            grid.get_or_insert_with(pos, || ());
+        }
+        let mut found = grid
+            .iter_region((-40, 0), (10, 5))
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, vec![(-40, 3), (0, 0)]);
+        assert_eq!(grid.iter().count(), 4);
+        let mut row = grid.iter_row(0).map(|(pos, _)| pos).collect::<Vec<_>>();
+        row.sort();
+        assert_eq!(row, vec![(0, 0), (40, 0)]);
+    }
+}
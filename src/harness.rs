@@ -0,0 +1,133 @@
+pub use crate::simulation::State;
+use crate::ui::Screen;
+use crate::{Args, Position, Program};
+use anyhow::Result;
+use clap::Parser;
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use std::time::Instant;
+
+/// Drives a [`Program`] without a terminal. Synthetic key and mouse events go through the same
+/// handlers as real input, against a fake screen of a fixed size, so keybindings and state
+/// transitions can be tested end to end. Outside this crate's own tests it needs the
+/// `test-harness` feature.
+pub struct Harness {
+    pub(crate) program: Program,
+}
+
+impl Harness {
+    /// Start a program as if run with `args` (not including the program name) in a terminal of
+    /// `width` by `height` characters.
+    pub fn new(args: &[&str], width: u16, height: u16) -> Result<Harness> {
+        let args = Args::try_parse_from(std::iter::once("rust-life").chain(args.iter().copied()))?;
+        let mut program = Program::build(args, Screen::fake(width, height), None)?;
+        program.start()?;
+        Ok(Harness { program })
+    }
+
+    pub fn press(&mut self, code: KeyCode) -> Result<&mut Harness> {
+        self.program.handle_key(code, KeyModifiers::NONE)?;
+        Ok(self)
+    }
+
+    /// Press a key with Ctrl held.
+    pub fn press_ctrl(&mut self, c: char) -> Result<&mut Harness> {
+        self.program
+            .handle_key(KeyCode::Char(c), KeyModifiers::CONTROL)?;
+        Ok(self)
+    }
+
+    /// Press a key for each character of `keys`, with `\n` as Enter.
+    pub fn type_keys(&mut self, keys: &str) -> Result<&mut Harness> {
+        for c in keys.chars() {
+            self.press(if c == '\n' {
+                KeyCode::Enter
+            } else {
+                KeyCode::Char(c)
+            })?;
+        }
+        Ok(self)
+    }
+
+    pub fn click(&mut self, button: MouseButton, column: u16, row: u16) -> Result<&mut Harness> {
+        self.program.handle_mouse(MouseEvent {
+            kind: MouseEventKind::Down(button),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+        Ok(self)
+    }
+
+    /// Do what the main loop does once the next generation is due: run a slice of the current
+    /// job if there is one, otherwise advance a generation if running.
+    pub fn tick(&mut self) -> Result<&mut Harness> {
+        let program = &mut self.program;
+        if program.job.is_some() {
+            program.run_job()?;
+        } else if program.sim.state() == State::Running {
+            program.sim.step();
+            program.after_step()?;
+        }
+        Ok(self)
+    }
+
+    pub fn ticks(&mut self, count: usize) -> Result<&mut Harness> {
+        for _ in 0..count {
+            self.tick()?;
+        }
+        Ok(self)
+    }
+
+    pub fn state(&self) -> State {
+        self.program.sim.state()
+    }
+
+    pub fn generations(&self) -> usize {
+        self.program.sim.world.generations
+    }
+
+    /// The live cells, sorted.
+    pub fn cells(&self) -> Vec<Position> {
+        let mut cells: Vec<Position> = self.program.sim.world.cells().collect();
+        cells.sort();
+        cells
+    }
+
+    /// The status line as it would be drawn now.
+    pub fn status(&mut self) -> String {
+        self.program.refresh_status(Instant::now());
+        let frame = self.program.screen.frame(&self.program, None);
+        frame.status().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_and_runs_from_build_mode() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        assert_eq!(harness.state(), State::Paused);
+        harness.type_keys("dwe")?.tick()?;
+        assert_eq!(harness.cells(), vec![(1, 1)]);
+        assert!(harness.status().starts_with("alive: 1, generations: 0"));
+        harness.press(KeyCode::Char(' '))?.tick()?;
+        assert_eq!(harness.state(), State::Running);
+        assert_eq!(harness.cells(), vec![]);
+        harness.press(KeyCode::Esc)?;
+        assert_eq!(harness.state(), State::Done);
+        Ok(())
+    }
+
+    #[test]
+    fn clicks_map_to_cells() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        let target = harness.program.screen.cell_at(20, 9);
+        harness.click(MouseButton::Left, 20, 9)?;
+        assert_eq!(Some(harness.program.cursor), target);
+        harness.click(MouseButton::Right, 0, 0)?;
+        assert_eq!(harness.cells(), target.into_iter().collect::<Vec<_>>());
+        Ok(())
+    }
+}
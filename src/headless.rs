@@ -0,0 +1,192 @@
+use crate::export;
+use crate::interrupt;
+use crate::osc::OscSender;
+use crate::raster::Region;
+use crate::simulation::Simulation;
+use crate::stats::StatsStream;
+use crate::ui;
+use crate::Args;
+use crate::{Changes, LifeWorld};
+use anyhow::Result;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Cells of padding around the initial pattern when no frame region is given.
+const FRAME_MARGIN: i32 = 16;
+/// How often progress is reported during runs with `--max-generations`.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Run the simulation without a terminal UI, writing snapshots and frames as configured.
+pub fn run(args: &Args) -> Result<()> {
+    let (world, seed) = match &args.session {
+        Some(path) => {
+            let session = args.load_session(path)?;
+            (session.world(), session.seed)
+        }
+        None => {
+            let (pattern, seed) = args.world()?;
+            if let Some(warning) = pattern.rule_warning(pattern.world.rule().as_ref()) {
+                eprintln!("{}", warning);
+            }
+            (pattern.world, seed)
+        }
+    };
+    let frames_every = args.frames_every.filter(|&n| n > 0);
+    if frames_every.is_some() {
+        fs::create_dir_all(&args.frames_dir)?;
+    }
+    let region = args.frame_region.unwrap_or_else(|| default_region(&world));
+    let frame_style = args.image_style(args.frame_scale);
+    let mut sim = Simulation::new(world, args.timestep());
+    sim.seed = seed;
+    sim.set_history(args.history);
+    sim.set_snapshots(args.snapshot_every, &args.snapshot_dir)?;
+    if let Some(path) = &args.stats_stream {
+        sim.set_stats(StatsStream::open(path, args.stats_format(path))?);
+    }
+    if let Some(target) = &args.osc {
+        sim.set_osc(OscSender::connect(target, args.osc_cells)?)?;
+    }
+
+    // Headless runs go as fast as they can, so generations are stepped directly rather than
+    // paced to the timestep.
+    let mut frames = 0;
+    let mut progress = args
+        .max_generations
+        .map(|max| Progress::new(sim.world.generations, max, Instant::now()));
+    sim.record(Changes::default());
+    loop {
+        if let Some(e) = sim.take_error() {
+            return Err(e);
+        }
+        let generation = sim.world.generations;
+        if let Some(every) = frames_every
+            && generation.is_multiple_of(every)
+        {
+            export::write_frame(&sim.world, &args.frames_dir, frames, region, &frame_style)?;
+            frames += 1;
+        }
+        if args.max_generations.is_some_and(|max| generation >= max) {
+            break;
+        }
+        if interrupt::requested() {
+            // Keep the state the run got to, even if snapshots were not requested.
+            let path = sim.save_snapshot()?;
+            eprintln!("interrupted, wrote {}", path.display());
+            break;
+        }
+        if let Some(report) = progress
+            .as_mut()
+            .and_then(|progress| progress.update(generation, Instant::now()))
+        {
+            eprintln!("{}", report);
+        }
+        sim.step();
+    }
+    if let Some(path) = &args.screenshot {
+        let style = args.image_style(args.screenshot_scale);
+        export::write_picture(&sim.world, path, &style, args.svg_grid)?;
+    }
+    let seed = seed
+        .map(|seed| format!(", seed: {}", seed))
+        .unwrap_or_default();
+    let envelope = sim
+        .history()
+        .map(|history| format!(", envelope: {}", ui::envelope(history)))
+        .unwrap_or_default();
+    eprintln!(
+        "generations: {}, alive: {}, frames: {}{}{}",
+        sim.world.generations,
+        sim.world.num_alive(),
+        frames,
+        seed,
+        envelope
+    );
+    Ok(())
+}
+
+/// Reports how far a run with a generation limit has got, how fast it is going, and when it
+/// should finish.
+struct Progress {
+    total: usize,
+    last: (usize, Instant),
+}
+
+impl Progress {
+    fn new(generation: usize, total: usize, now: Instant) -> Progress {
+        Progress {
+            total,
+            last: (generation, now),
+        }
+    }
+
+    /// A progress report, if one is due. The rate is measured since the previous report, so the
+    /// estimate follows the run as it speeds up or slows down.
+    fn update(&mut self, generation: usize, now: Instant) -> Option<String> {
+        let (last_generation, last_time) = self.last;
+        let elapsed = now.duration_since(last_time);
+        if elapsed < PROGRESS_INTERVAL {
+            return None;
+        }
+        self.last = (generation, now);
+        let rate = (generation - last_generation) as f64 / elapsed.as_secs_f64();
+        let remaining = self.total.saturating_sub(generation);
+        let eta = if rate > 0. {
+            format_eta(Duration::from_secs_f64(remaining as f64 / rate))
+        } else {
+            String::from("-")
+        };
+        Some(format!(
+            "generation {}/{} ({:.0}%), {:.0} generations/s, ETA {}",
+            generation,
+            self.total,
+            100. * generation as f64 / self.total.max(1) as f64,
+            rate,
+            eta
+        ))
+    }
+}
+
+/// Format a duration as `m:ss`, or `h:mm:ss` from an hour up.
+fn format_eta(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn default_region(world: &LifeWorld) -> Region {
+    let ((x0, y0), (x1, y1)) = export::bounding_region(world);
+    (
+        (x0 - FRAME_MARGIN, y0 - FRAME_MARGIN),
+        (x1 + FRAME_MARGIN, y1 + FRAME_MARGIN),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_progress_periodically() {
+        let start = Instant::now();
+        let mut progress = Progress::new(0, 10000, start);
+        assert_eq!(progress.update(100, start + Duration::from_secs(1)), None);
+        assert_eq!(
+            progress
+                .update(2000, start + Duration::from_secs(2))
+                .as_deref(),
+            Some("generation 2000/10000 (20%), 1000 generations/s, ETA 0:08")
+        );
+        assert_eq!(progress.update(2500, start + Duration::from_secs(3)), None);
+    }
+
+    #[test]
+    fn formats_eta() {
+        assert_eq!(format_eta(Duration::from_secs(75)), "1:15");
+        assert_eq!(format_eta(Duration::from_secs(3 * 3600 + 65)), "3:01:05");
+    }
+}
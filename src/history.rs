@@ -0,0 +1,128 @@
+use crate::grid::Grid;
+use crate::life::{LifeWorld, ObserverId, WorldEvent};
+use crate::raster::Region;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Every cell that has been alive in a world since recording started, like the extra states of
+/// Golly's LifeHistory rule, so the envelope a pattern has touched stays visible after the cells
+/// themselves have moved on.
+///
+/// Cells are recorded from the world's births and deaths. Cells raised by editing are only
+/// recorded once they die, so callers should treat live cells as part of the history.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    envelope: Arc<Mutex<Envelope>>,
+}
+
+#[derive(Debug, Default)]
+struct Envelope {
+    cells: Grid<()>,
+    len: usize,
+    bounding_box: Option<Region>,
+}
+
+impl Envelope {
+    fn add(&mut self, (x, y): (i32, i32)) {
+        if self.cells.get((x, y)).is_some() {
+            return;
+        }
+        self.cells.get_or_insert_with((x, y), || ());
+        self.len += 1;
+        self.bounding_box = Some(match self.bounding_box {
+            Some(((x0, y0), (x1, y1))) => ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y))),
+            None => ((x, y), (x, y)),
+        });
+    }
+}
+
+impl History {
+    /// Start recording the history of `world` from its current cells. The observer this
+    /// registers is returned so recording can be stopped with [`LifeWorld::remove_observer`].
+    pub fn record(world: &mut LifeWorld) -> (History, ObserverId) {
+        let history = History::default();
+        history.reset(world);
+        let sink = history.clone();
+        let id = world.on_event(move |event| {
+            if let WorldEvent::Birth(x, y) | WorldEvent::Death(x, y) = *event {
+                sink.lock().add((x, y));
+            }
+        });
+        (history, id)
+    }
+
+    /// Forget everything but the current cells of `world`.
+    pub fn reset(&self, world: &LifeWorld) {
+        let mut envelope = self.lock();
+        *envelope = Envelope::default();
+        for pos in world.cells() {
+            envelope.add(pos);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Envelope> {
+        // A panic while recording can only leave a cell unrecorded, so a poisoned lock is fine.
+        self.envelope.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn was_alive(&self, x: i32, y: i32) -> bool {
+        self.lock().cells.get((x, y)).is_some()
+    }
+
+    /// The recorded cells in the inclusive region between `min` and `max`.
+    pub fn cells_in(&self, min: (i32, i32), max: (i32, i32)) -> Vec<(i32, i32)> {
+        self.lock()
+            .cells
+            .iter_region(min, max)
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    /// The number of cells in the envelope.
+    pub fn len(&self) -> usize {
+        self.lock().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The inclusive bounding box of the envelope, or `None` if it is empty.
+    pub fn bounding_box(&self) -> Option<Region> {
+        self.lock().bounding_box
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::LifePattern;
+
+    #[test]
+    fn records_every_cell_ever_alive() {
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        let (history, id) = History::record(&mut world);
+        world.evolve();
+        // The blinker covers a plus shape between its two phases.
+        let mut cells = history.cells_in((-5, -5), (5, 5));
+        cells.sort();
+        assert_eq!(cells, [(-1, 1), (0, 0), (0, 1), (0, 2), (1, 1)]);
+        assert!(history.was_alive(0, 0));
+        assert!(!world.alive(0, 0));
+        assert_eq!(history.len(), 5);
+        assert_eq!(history.bounding_box(), Some(((-1, 0), (1, 2))));
+
+        // Edited cells show up once they die.
+        world.raise(4, 4);
+        world.evolve();
+        assert!(history.was_alive(4, 4));
+        assert_eq!(history.bounding_box(), Some(((-1, 0), (4, 4))));
+
+        history.reset(&world);
+        assert_eq!(history.len(), 3);
+
+        assert!(world.remove_observer(id));
+        world.raise(-4, -4);
+        world.evolve();
+        assert!(!history.was_alive(-4, -4));
+    }
+}
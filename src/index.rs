@@ -0,0 +1,267 @@
+use crate::formats;
+use crate::library;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::hash::Hasher;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+/// The file the index is kept in, in the library directory it covers.
+const INDEX_FILE: &str = ".index";
+
+/// The inclusive corners of a pattern's bounding box.
+pub type Bounds = ((i32, i32), (i32, i32));
+
+/// What the index records about a pattern file.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Summary {
+    /// The file's modification time, in nanoseconds since the epoch, and length when it was
+    /// summarized, which tell when it has changed since.
+    modified: u128,
+    len: u64,
+    /// A hash of the contents, so a file that was touched but not changed isn't parsed again.
+    checksum: u64,
+    /// `None` for empty patterns and files that don't parse.
+    pub bounds: Option<Bounds>,
+    pub tags: Vec<String>,
+}
+
+impl Summary {
+    /// Read and summarize a file, reusing `previous` if the contents haven't changed.
+    fn read(path: &Path, previous: Option<&Summary>) -> Result<Summary> {
+        let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let (modified, len) = stamp(&fs::metadata(path)?);
+        let checksum = checksum(&data);
+        if let Some(previous) = previous.filter(|previous| previous.checksum == checksum) {
+            return Ok(Summary {
+                modified,
+                len,
+                ..previous.clone()
+            });
+        }
+        Ok(Summary {
+            modified,
+            len,
+            checksum,
+            bounds: formats::read_pattern(&data)
+                .ok()
+                .and_then(|p| p.world.bounding_box()),
+            tags: library::read_tags(&String::from_utf8_lossy(&data)),
+        })
+    }
+
+    fn is_current(&self, metadata: &Metadata) -> bool {
+        stamp(metadata) == (self.modified, self.len)
+    }
+}
+
+/// Summaries of the pattern files in a library directory, by file name, so that listing the
+/// library doesn't mean reading and parsing every file in it.
+///
+/// The index is kept in a `.index` file in the directory with a tab-separated line per pattern
+/// file. It is only a cache: a missing or unreadable index is rebuilt, and failing to save one
+/// is ignored.
+#[derive(PartialEq, Default, Debug)]
+pub struct Index {
+    summaries: BTreeMap<String, Summary>,
+}
+
+impl Index {
+    /// The index of `dir`, brought up to date as far as can be done quickly: files new since it
+    /// was saved are summarized now and removed files dropped, but files that have changed keep
+    /// their old summaries and are returned by name to be refreshed later.
+    pub fn update(dir: &Path) -> Result<(Index, Vec<String>)> {
+        let files = match fs::read_dir(dir) {
+            Ok(files) => files,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+        };
+        let saved = Index::load(dir);
+        let mut index = Index::default();
+        let mut stale = Vec::new();
+        for file in files {
+            let file = file?;
+            let path = file.path();
+            let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !matches!(extension, "rle" | "cells") {
+                continue;
+            }
+            let summary = match saved.summaries.get(name) {
+                Some(summary) => {
+                    if !summary.is_current(&file.metadata()?) {
+                        stale.push(name.to_string());
+                    }
+                    summary.clone()
+                }
+                None => Summary::read(&path, None)?,
+            };
+            index.summaries.insert(name.to_string(), summary);
+        }
+        if index != saved {
+            let _ = index.save(dir);
+        }
+        Ok((index, stale))
+    }
+
+    /// The summarized files, by file name.
+    pub fn summaries(&self) -> impl Iterator<Item = (&str, &Summary)> {
+        self.summaries
+            .iter()
+            .map(|(name, summary)| (name.as_str(), summary))
+    }
+
+    /// Summarize the `stale` files again and save the index.
+    pub fn refresh(&mut self, dir: &Path, stale: &[String]) -> Result<()> {
+        for name in stale {
+            let summary = Summary::read(&dir.join(name), self.summaries.get(name))?;
+            self.summaries.insert(name.clone(), summary);
+        }
+        self.save(dir)
+    }
+
+    /// [`refresh`](Index::refresh) on another thread, so the library can be listed from the
+    /// old summaries straight away.
+    pub fn refresh_in_background(mut self, dir: PathBuf, stale: Vec<String>) {
+        thread::spawn(move || {
+            let _ = self.refresh(&dir, &stale);
+        });
+    }
+
+    fn load(dir: &Path) -> Index {
+        fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|contents| Index::parse(&contents))
+            .unwrap_or_default()
+    }
+
+    fn parse(contents: &str) -> Option<Index> {
+        let mut summaries = BTreeMap::new();
+        for line in contents.lines() {
+            let mut fields = line.split('\t');
+            let mut next = || fields.next();
+            let name = next()?.to_string();
+            let modified = next()?.parse().ok()?;
+            let len = next()?.parse().ok()?;
+            let checksum = u64::from_str_radix(next()?, 16).ok()?;
+            let bounds = match next()? {
+                "-" => None,
+                bounds => {
+                    let numbers: Vec<i32> = bounds
+                        .split(' ')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .ok()?;
+                    let &[x0, y0, x1, y1] = numbers.as_slice() else {
+                        return None;
+                    };
+                    Some(((x0, y0), (x1, y1)))
+                }
+            };
+            let tags = next()?
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string);
+            let summary = Summary {
+                modified,
+                len,
+                checksum,
+                bounds,
+                tags: tags.collect(),
+            };
+            summaries.insert(name, summary);
+        }
+        Some(Index { summaries })
+    }
+
+    /// Write the index, to a temporary file first so a reader never sees half of it.
+    fn save(&self, dir: &Path) -> Result<()> {
+        let mut contents = String::new();
+        for (name, summary) in &self.summaries {
+            let bounds = match summary.bounds {
+                Some(((x0, y0), (x1, y1))) => format!("{} {} {} {}", x0, y0, x1, y1),
+                None => String::from("-"),
+            };
+            contents += &format!(
+                "{}\t{}\t{}\t{:x}\t{}\t{}\n",
+                name,
+                summary.modified,
+                summary.len,
+                summary.checksum,
+                bounds,
+                summary.tags.join(",")
+            );
+        }
+        let path = dir.join(INDEX_FILE);
+        let temporary = dir.join(format!("{}.{}", INDEX_FILE, std::process::id()));
+        fs::write(&temporary, contents)
+            .with_context(|| format!("failed to write {}", temporary.display()))?;
+        fs::rename(&temporary, &path).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// A file's modification time and length.
+fn stamp(metadata: &Metadata) -> (u128, u64) {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos());
+    (modified, metadata.len())
+}
+
+fn checksum(data: &[u8]) -> u64 {
+    let mut hasher = rustc_hash::FxHasher::default();
+    hasher.write(data);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refreshes_stale_summaries() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-index-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("domino.cells"), "!tags: test\nOO\n")?;
+        fs::write(dir.join("notes.txt"), "not a pattern")?;
+        let (first, stale) = Index::update(&dir)?;
+        let saved = Index::load(&dir);
+        fs::write(dir.join("domino.cells"), "!tags: still-life\nOO\nOO\n")?;
+        fs::write(dir.join("blinker.rle"), "x = 3, y = 1\n3o!\n")?;
+        let (mut second, changed) = Index::update(&dir)?;
+        let listed: Vec<_> = second
+            .summaries()
+            .map(|(name, s)| (name.to_string(), s.tags.clone()))
+            .collect();
+        second.refresh(&dir, &changed)?;
+        let refreshed = Index::load(&dir);
+        fs::remove_dir_all(&dir)?;
+
+        assert!(stale.is_empty());
+        assert_eq!(saved, first);
+        let domino = &first.summaries["domino.cells"];
+        assert_eq!(domino.bounds, Some(((0, 0), (1, 0))));
+        assert_eq!(domino.tags, ["test"]);
+        // The changed file is listed as it was until it is refreshed, but new files straight away.
+        assert_eq!(changed, ["domino.cells"]);
+        assert_eq!(
+            listed,
+            [
+                (String::from("blinker.rle"), Vec::new()),
+                (String::from("domino.cells"), vec![String::from("test")])
+            ]
+        );
+        assert_eq!(refreshed, second);
+        let domino = &refreshed.summaries["domino.cells"];
+        assert_eq!(domino.bounds, Some(((0, 0), (1, 1))));
+        assert_eq!(domino.tags, ["still-life"]);
+        Ok(())
+    }
+}
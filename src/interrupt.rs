@@ -0,0 +1,21 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catch Ctrl+C in headless and batch modes, so a run can stop at the next convenient point and
+/// write out what it has instead of being killed halfway. A second Ctrl+C exits immediately.
+pub fn install() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        eprintln!("Received Ctrl-C, finishing up (press again to exit immediately)...");
+    })?;
+    Ok(())
+}
+
+/// Whether Ctrl+C has been pressed since `install`.
+pub fn requested() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,99 @@
+use crate::condition::Condition;
+use crate::session::Session;
+use crate::LifeWorld;
+use std::fmt;
+use std::time::Duration;
+
+/// How long a job runs before the UI checks for input and redraws.
+pub const SLICE: Duration = Duration::from_millis(30);
+
+/// A long operation that runs a slice at a time between input checks, so the UI stays
+/// responsive and the operation can be canceled with Esc.
+#[derive(Debug)]
+pub enum Job {
+    /// Advance the world `total` generations as fast as possible.
+    FastForward { done: usize, total: usize },
+    /// Advance the world as fast as possible until a condition holds.
+    RunUntil { condition: Condition, done: usize },
+    /// Build the world for a loaded session, a batch of cells at a time. The session and world are
+    /// boxed to keep the other jobs small.
+    Load {
+        path: String,
+        session: Box<Session>,
+        world: Box<LifeWorld>,
+        loaded: usize,
+    },
+}
+
+impl Job {
+    pub fn load(path: String, session: Session) -> Job {
+        Job::Load {
+            path,
+            world: Box::new(session.empty_world()),
+            session: Box::new(session),
+            loaded: 0,
+        }
+    }
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Job::FastForward { done, total } => {
+                write!(f, "fast-forward: {}/{} generations", done, total)
+            }
+            Job::RunUntil { condition, done } => {
+                write!(f, "running until {}: {} generations", condition, done)
+            }
+            Job::Load {
+                path,
+                session,
+                loaded,
+                ..
+            } => write!(
+                f,
+                "loading {}: {}/{} cells",
+                path,
+                loaded,
+                session.cells.len()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::harness::Harness;
+    use crate::simulation::State;
+    use anyhow::Result;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn runs_commands_and_cancels_jobs() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider", "--build"], 40, 20)?;
+        harness.type_keys(":ff 8\n")?;
+        assert!(harness.status().starts_with("fast-forward: 0/8"));
+        harness.ticks(2)?;
+        assert_eq!(harness.status(), "fast-forwarded 8 generations");
+        assert_eq!(harness.program.sim.world.generations, 8);
+
+        harness.type_keys(":ff 100000000\n")?.press(KeyCode::Esc)?;
+        assert!(harness.program.job.is_none());
+        assert_eq!(harness.state(), State::Paused);
+        harness.type_keys(":bogus\n")?;
+        assert!(harness.status().starts_with("error:"));
+        Ok(())
+    }
+
+    #[test]
+    fn goes_to_a_generation() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider", "--build"], 40, 20)?;
+        harness.type_keys("..:gen 10\n")?.ticks(2)?;
+        assert_eq!(harness.program.sim.world.generations, 10);
+        harness.type_keys(":gen 10\n")?;
+        assert_eq!(harness.status(), "already at generation 10");
+        harness.type_keys(":gen 3\n")?;
+        assert!(harness.status().starts_with("error: can't go back"));
+        Ok(())
+    }
+}
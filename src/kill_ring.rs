@@ -0,0 +1,95 @@
+use crate::LifeWorld;
+use std::collections::VecDeque;
+
+/// How many patterns the kill ring keeps.
+const CAPACITY: usize = 10;
+
+/// The patterns most recently cut, deleted or copied, newest first, so that one whose place on
+/// the clipboard was taken can still be pasted.
+#[derive(Debug, Default)]
+pub struct KillRing {
+    patterns: VecDeque<LifeWorld>,
+}
+
+impl KillRing {
+    /// Add a pattern, moved so that its bounding box starts at the origin (as a pasted pattern
+    /// does), dropping the oldest if the ring is full. Empty patterns are not kept.
+    pub fn push(&mut self, pattern: &LifeWorld) {
+        let Some(((x0, y0), _)) = pattern.extent() else {
+            return;
+        };
+        let mut moved = LifeWorld::new();
+        moved.set_rule(pattern.rule().clone());
+        moved.merge(pattern, (-x0, -y0));
+        if self.patterns.len() == CAPACITY {
+            self.patterns.pop_back();
+        }
+        self.patterns.push_front(moved);
+    }
+
+    /// The pattern `index` kills ago, with 0 the most recent.
+    pub fn get(&self, index: usize) -> Option<&LifeWorld> {
+        self.patterns.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use anyhow::Result;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn keeps_the_latest_patterns() {
+        let mut ring = KillRing::default();
+        ring.push(&LifeWorld::new());
+        assert_eq!(ring.len(), 0);
+        for x in 0..CAPACITY as i32 + 2 {
+            let mut pattern = LifeWorld::new();
+            pattern.raise(x, -3);
+            pattern.raise(x + 1, -2);
+            ring.push(&pattern);
+        }
+        assert_eq!(ring.len(), CAPACITY);
+        assert!(ring.get(CAPACITY).is_none());
+        let newest = ring.get(0).unwrap();
+        assert!(newest.alive(0, 0) && newest.alive(1, 1));
+        assert_eq!(newest.num_alive(), 2);
+    }
+
+    #[test]
+    fn cycles_through_the_kill_ring() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        assert!(harness
+            .type_keys("Y")?
+            .status()
+            .starts_with("error: the kill ring is empty"));
+        harness.press(KeyCode::Esc)?;
+        // A block, selected, and a domino beside it.
+        harness.type_keys("medeweaedm")?.type_keys("dddddese")?;
+        harness.type_keys("D")?;
+        assert_eq!(harness.status(), "deleted 4 cells (Y to paste)");
+        assert_eq!(harness.cells(), vec![(6, 0), (6, 1)]);
+        harness.type_keys("mmwD")?;
+        assert!(harness.cells().is_empty());
+        harness.type_keys("dddY")?;
+        assert_eq!(harness.cells(), vec![(9, 1), (9, 2)]);
+        assert_eq!(
+            harness.status(),
+            "pasted kill 1 of 2 at 9,1 (Y again for an older one)"
+        );
+        harness.type_keys("Y")?;
+        assert_eq!(harness.cells(), vec![(9, 1), (9, 2), (10, 1), (10, 2)]);
+        harness.type_keys("Y")?;
+        assert_eq!(harness.cells(), vec![(9, 1), (9, 2)]);
+        // After anything else, pasting starts again from the newest.
+        harness.type_keys("dY")?;
+        assert_eq!(harness.cells(), vec![(9, 1), (9, 2), (10, 1), (10, 2)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use crate::analysis::{self, Outcome};
+use crate::interrupt;
+use crate::{LifePattern, LifeWorld};
+use anyhow::{Context, Result};
+use clap::Args;
+use rand::random;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+const CSV_HEADER: &str = "pattern,seed,rule,lifespan,final_population,period,classification,growth";
+
+#[derive(Args, Debug)]
+pub struct LabArgs {
+    #[arg(short = 'p', long = "pattern", value_enum, default_value_t = LifePattern::Random(10000))]
+    pattern: LifePattern,
+    /// Number of cells in random soups.
+    #[arg(long = "size")]
+    size: Option<usize>,
+    /// Seed for the first random soup; later runs use consecutive seeds.
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+    #[arg(short = 'n', long = "runs", default_value = "1")]
+    runs: u64,
+    #[arg(long = "max-generations", default_value = "10000")]
+    max_generations: usize,
+    /// CSV file that results are appended to.
+    #[arg(short = 'o', long = "output", default_value = "lab.csv")]
+    output: PathBuf,
+}
+
+/// Run a batch of experiments, appending one CSV row per run.
+pub fn run(args: &LabArgs) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&args.output)
+        .with_context(|| format!("failed to open {}", args.output.display()))?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", CSV_HEADER)?;
+    }
+
+    let first_seed = args.seed.unwrap_or_else(random);
+    for run in 0..args.runs {
+        if interrupt::requested() {
+            eprintln!("interrupted after {} of {} runs", run, args.runs);
+            break;
+        }
+        let (mut world, seed) = match args.pattern {
+            LifePattern::Random(default_size) => {
+                let seed = first_seed.wrapping_add(run);
+                let size = args.size.unwrap_or(default_size);
+                (LifeWorld::random(size, seed), Some(seed))
+            }
+            _ => (LifeWorld::from(&args.pattern), None),
+        };
+        let outcome = analysis::classify(&mut world, args.max_generations);
+        let row = csv_row(&args.pattern, seed, &outcome);
+        writeln!(file, "{}", row)?;
+        eprintln!("{}", row);
+    }
+    Ok(())
+}
+
+fn csv_row(pattern: &LifePattern, seed: Option<u64>, outcome: &Outcome) -> String {
+    let name = clap::ValueEnum::to_possible_value(pattern)
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+    format!(
+        "{},{},{},{},{},{},{},{}",
+        name,
+        seed.map(|seed| seed.to_string()).unwrap_or_default(),
+        "B3/S23",
+        outcome.lifespan,
+        outcome.final_population,
+        outcome
+            .classification
+            .period()
+            .map(|period| period.to_string())
+            .unwrap_or_default(),
+        outcome.classification,
+        outcome
+            .growth
+            .map(|growth| growth.to_string())
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{Classification, Growth};
+
+    #[test]
+    fn formats_rows() {
+        let outcome = Outcome {
+            classification: Classification::Oscillator(2),
+            lifespan: 120,
+            final_population: 36,
+            growth: Some(Growth::Bounded),
+        };
+        assert_eq!(
+            csv_row(&LifePattern::Random(100), Some(7), &outcome),
+            "random,7,B3/S23,120,36,2,oscillator,bounded"
+        );
+        assert_eq!(
+            csv_row(&LifePattern::Glider, None, &outcome),
+            "glider,,B3/S23,120,36,2,oscillator,bounded"
+        );
+    }
+}
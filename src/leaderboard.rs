@@ -0,0 +1,225 @@
+use crate::analysis;
+use crate::interrupt;
+use crate::LifeWorld;
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use rand::random;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const CSV_HEADER: &str = "seed,size,lifespan,peak_population,stabilized";
+
+#[derive(Args, Debug)]
+pub struct LeaderboardArgs {
+    /// Number of cells in each soup.
+    #[arg(long = "size", default_value = "10000")]
+    size: usize,
+    /// Seed for the first soup; later soups use consecutive seeds.
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+    #[arg(short = 'n', long = "runs", default_value = "100")]
+    runs: u64,
+    #[arg(long = "max-generations", default_value = "10000")]
+    max_generations: usize,
+    /// Number of seeds kept on each board.
+    #[arg(long = "top", default_value = "10")]
+    top: usize,
+    /// CSV file the leaderboard is kept in between runs.
+    #[arg(short = 'o', long = "output", default_value = "leaderboard.csv")]
+    output: PathBuf,
+}
+
+/// One soup's result.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Entry {
+    pub seed: u64,
+    pub size: usize,
+    pub lifespan: usize,
+    pub peak_population: i32,
+    pub stabilized: bool,
+}
+
+/// The longest-lived and highest-peak soups seen so far. A soup is kept while it is on either
+/// board.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Leaderboard {
+    top: usize,
+    entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    pub fn new(top: usize) -> Leaderboard {
+        Leaderboard {
+            top,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Load a leaderboard, or start an empty one if the file does not exist yet.
+    pub fn load(path: &Path, top: usize) -> Result<Leaderboard> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Leaderboard::new(top)),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        let mut board = Leaderboard::new(top);
+        for (n, line) in contents.lines().enumerate().skip(1) {
+            let entry = parse_entry(line)
+                .with_context(|| format!("{}:{}: invalid entry", path.display(), n + 1))?;
+            board.insert(entry);
+        }
+        Ok(board)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut out = format!("{}\n", CSV_HEADER);
+        for entry in &self.entries {
+            out += &format!(
+                "{},{},{},{},{}\n",
+                entry.seed, entry.size, entry.lifespan, entry.peak_population, entry.stabilized
+            );
+        }
+        fs::write(path, out).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Add a result, returning whether it made either board.
+    pub fn insert(&mut self, entry: Entry) -> bool {
+        if self
+            .entries
+            .iter()
+            .any(|e| e.seed == entry.seed && e.size == entry.size)
+        {
+            return false;
+        }
+        self.entries.push(entry.clone());
+        let keep: Vec<Entry> = self
+            .entries
+            .iter()
+            .filter(|e| self.longest().contains(e) || self.highest().contains(e))
+            .cloned()
+            .collect();
+        self.entries = keep;
+        self.entries.contains(&entry)
+    }
+
+    /// The soups that took longest to stabilize, longest first. Soups that never stabilized are
+    /// left out, since their lifespan is just the generation limit.
+    pub fn longest(&self) -> Vec<&Entry> {
+        let mut entries: Vec<&Entry> = self.entries.iter().filter(|e| e.stabilized).collect();
+        entries.sort_by_key(|e| (std::cmp::Reverse(e.lifespan), e.seed));
+        entries.truncate(self.top);
+        entries
+    }
+
+    /// The soups that reached the highest population, highest first.
+    pub fn highest(&self) -> Vec<&Entry> {
+        let mut entries: Vec<&Entry> = self.entries.iter().collect();
+        entries.sort_by_key(|e| (std::cmp::Reverse(e.peak_population), e.seed));
+        entries.truncate(self.top);
+        entries
+    }
+}
+
+fn parse_entry(line: &str) -> Result<Entry> {
+    let fields: Vec<&str> = line.split(',').collect();
+    let [seed, size, lifespan, peak_population, stabilized] = fields[..] else {
+        return Err(anyhow!("expected {} fields", CSV_HEADER.split(',').count()));
+    };
+    Ok(Entry {
+        seed: seed.parse()?,
+        size: size.parse()?,
+        lifespan: lifespan.parse()?,
+        peak_population: peak_population.parse()?,
+        stabilized: stabilized.parse()?,
+    })
+}
+
+/// Run a batch of soups and update the leaderboard with any that make it.
+pub fn run(args: &LeaderboardArgs) -> Result<()> {
+    let mut board = Leaderboard::load(&args.output, args.top)?;
+    let first_seed = args.seed.unwrap_or_else(random);
+    for run in 0..args.runs {
+        if interrupt::requested() {
+            eprintln!("interrupted after {} of {} soups", run, args.runs);
+            break;
+        }
+        let seed = first_seed.wrapping_add(run);
+        let mut world: LifeWorld = LifeWorld::random(args.size, seed);
+        let longevity = analysis::longevity(&mut world, args.max_generations);
+        let entry = Entry {
+            seed,
+            size: args.size,
+            lifespan: longevity.lifespan,
+            peak_population: longevity.peak_population,
+            stabilized: longevity.stabilized,
+        };
+        if board.insert(entry) {
+            eprintln!(
+                "seed {}: lifespan {}, peak population {}",
+                seed, longevity.lifespan, longevity.peak_population
+            );
+            board.save(&args.output)?;
+        }
+    }
+    board.save(&args.output)?;
+
+    println!("Longest-lived (generations before stabilizing):");
+    for entry in board.longest() {
+        println!(
+            "  {:>8}  seed {} (size {})",
+            entry.lifespan, entry.seed, entry.size
+        );
+    }
+    println!("Highest peak population:");
+    for entry in board.highest() {
+        println!(
+            "  {:>8}  seed {} (size {})",
+            entry.peak_population, entry.seed, entry.size
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(seed: u64, lifespan: usize, peak_population: i32) -> Entry {
+        Entry {
+            seed,
+            size: 100,
+            lifespan,
+            peak_population,
+            stabilized: true,
+        }
+    }
+
+    #[test]
+    fn keeps_top_entries_of_either_board() {
+        let mut board = Leaderboard::new(2);
+        assert!(board.insert(entry(1, 100, 50)));
+        assert!(board.insert(entry(2, 300, 10)));
+        assert!(board.insert(entry(3, 200, 20)));
+        // Seed 1 is off the lifespan board but still has the highest peak.
+        assert_eq!(
+            board.longest(),
+            vec![&entry(2, 300, 10), &entry(3, 200, 20)]
+        );
+        assert_eq!(
+            board.highest(),
+            vec![&entry(1, 100, 50), &entry(3, 200, 20)]
+        );
+        assert!(!board.insert(entry(4, 10, 5)));
+        assert!(!board.insert(entry(2, 300, 10)));
+        assert_eq!(board.entries.len(), 3);
+    }
+
+    #[test]
+    fn parses_saved_entries() -> Result<()> {
+        let e = entry(42, 1103, 319);
+        assert_eq!(parse_entry("42,100,1103,319,true")?, e);
+        assert!(parse_entry("42,100,1103").is_err());
+        Ok(())
+    }
+}
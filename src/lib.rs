@@ -0,0 +1,2129 @@
+use crate::formats::Pattern;
+use crate::raster::{Palette, Region, Rgb};
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::turmite::Ant;
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use rand::random;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+mod alert;
+pub mod analysis;
+mod animation;
+#[cfg(feature = "audio")]
+mod audio;
+mod axis_lock;
+mod brush;
+mod cast;
+mod clipboard;
+mod cmdline;
+mod completion;
+mod condition;
+mod config;
+mod convert;
+mod ex_history;
+mod export;
+pub mod formats;
+mod grid;
+#[cfg(any(test, feature = "test-harness"))]
+pub mod harness;
+mod headless;
+pub mod history;
+mod index;
+mod interrupt;
+mod job;
+mod kill_ring;
+mod lab;
+mod leaderboard;
+mod library;
+pub mod life;
+mod measure;
+mod osc;
+mod pacing;
+mod picker;
+pub mod raster;
+mod recent;
+mod render;
+pub mod rule;
+mod rule_editor;
+mod search;
+mod session;
+mod session_lock;
+mod simulation;
+mod stats;
+mod tickrate;
+mod toast;
+pub mod topology;
+pub mod turmite;
+mod ui;
+#[cfg(feature = "ratatui")]
+pub mod widget;
+use crate::alert::Alert;
+use crate::axis_lock::AxisLock;
+use crate::brush::{Brush, Spray};
+use crate::cast::Cast;
+use crate::clipboard::Clipboard;
+use crate::cmdline::ExCommand;
+use crate::config::Config;
+use crate::ex_history::ExHistory;
+use crate::export::ImageStyle;
+use crate::job::Job;
+use crate::kill_ring::KillRing;
+use crate::library::{Library, PatternName};
+use crate::measure::Measurement;
+use crate::osc::OscSender;
+use crate::picker::{Picker, Previews};
+use crate::recent::Recent;
+use crate::render::{FrameSkip, Renderer};
+use crate::rule_editor::RuleEditor;
+use crate::session::Session;
+use crate::session_lock::{Busy, SessionLock};
+use crate::simulation::{Command, Simulation, State};
+use crate::stats::{StatsFormat, StatsStream};
+use crate::toast::Toast;
+use crate::ui::Screen;
+
+pub use crate::life::{
+    CellState, Changes, LifeCell, LifePattern, LifeWorld, Neighbors, ObserverId, PatternInfo,
+    WorldEvent, WorldStats,
+};
+
+/// How close to the edge of the view the live cells may get before auto-pan recenters.
+const AUTO_PAN_MARGIN: i32 = 2;
+/// The timestep unless one is given with `--timestep` or in the config.
+const DEFAULT_TIMESTEP: Duration = Duration::from_millis(100);
+/// The shortest timestep the `+` key speeds up to.
+const MIN_TIMESTEP: Duration = Duration::from_micros(100);
+/// How often the status line is remade while running.
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Run the program with the command line arguments it was started with.
+pub fn run() -> Result<()> {
+    let mut args = Args::parse();
+    if let Some(choice) = args.recent {
+        let list = Recent::default_path().ok_or_else(|| anyhow!("no data directory"))?;
+        let recent = Recent::load(&list)?;
+        let Some(n) = choice else {
+            for (i, item) in recent.items.iter().enumerate() {
+                println!("{:>2}  {}", i + 1, item);
+            }
+            return Ok(());
+        };
+        let item = n
+            .checked_sub(1)
+            .and_then(|i| recent.items.get(i))
+            .ok_or_else(|| anyhow!("no recent file {} (see --recent)", n))?;
+        match item.kind {
+            recent::Kind::Pattern => args.file = Some(item.path.clone()),
+            recent::Kind::Session => args.session = Some(item.path.clone()),
+        }
+    }
+    if let Some(tag) = &args.list_patterns {
+        let library = Library::open(args.library.clone());
+        let tag = Some(tag.as_str()).filter(|tag| !tag.is_empty());
+        for entry in library::catalog(library.as_ref(), tag)? {
+            println!("{}", entry);
+        }
+        return Ok(());
+    }
+    if let Some(mode) = &args.mode {
+        interrupt::install()?;
+        return match mode {
+            Mode::Lab(lab_args) => lab::run(lab_args),
+            Mode::Leaderboard(board_args) => leaderboard::run(board_args),
+            Mode::EvolveSearch(search_args) => search::run(search_args),
+            Mode::ExportGif(gif_args) => animation::run(gif_args),
+            Mode::Convert(convert_args) => convert::run(convert_args),
+        };
+    }
+    if args.headless {
+        interrupt::install()?;
+        return headless::run(&args);
+    }
+    if let Some(path) = &args.screenshot {
+        let (pattern, _) = args.world()?;
+        let style = args.image_style(args.screenshot_scale);
+        return export::write_picture(&pattern.world, path, &style, args.svg_grid);
+    }
+    let mut program = Program::new(args)?;
+    program.run()?;
+    Ok(())
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Time between generations, e.g. `100` (milliseconds), `50ms`, `2.5ms` or `0.25s`.
+    /// Defaults to the config's `timestep`, or else 100ms.
+    #[arg(short = 't', long = "timestep", value_parser = cmdline::parse_duration)]
+    timestep: Option<Duration>,
+    /// A built-in pattern (see `--list-patterns`), an apgcode such as `xp2_7`, or the name of a
+    /// pattern in the pattern library.
+    #[arg(short = 'p', long = "pattern", default_value = "blank")]
+    pattern: PatternName,
+    /// Load the starting pattern from a file instead (format chosen by extension: .rle, .cells,
+    /// .lif or .pbm), or from stdin if it is `-` (format guessed from the contents).
+    #[arg(short = 'f', long = "file", conflicts_with = "pattern")]
+    file: Option<PathBuf>,
+    /// The rule to run, in B/S notation, e.g. `B36/S23` for HighLife or `B2/S` for Seeds, a
+    /// turmite such as `RL` for Langton's Ant, or an elementary rule such as `W110`.
+    /// Defaults to the rule declared by the session, or else Conway's Life; a pattern file that
+    /// declares another rule asks whether to switch to it (headless runs switch without asking).
+    #[arg(long = "rule", value_parser = rule::parse)]
+    rule: Option<Arc<dyn Rule>>,
+    /// Run a preset rule: life, highlife, day-and-night, seeds, life-without-death, maze or 2x2.
+    #[arg(long = "rule-name", value_parser = rule::preset, conflicts_with = "rule")]
+    rule_name: Option<Arc<dyn Rule>>,
+    /// Run on a torus of this size, e.g. `200x100`, centered on the origin, so that patterns
+    /// leaving one edge come back on the opposite one. Defaults to the topology declared by the
+    /// pattern file or session, or else the unbounded plane.
+    #[arg(long = "wrap", value_name = "WxH", value_parser = Topology::torus)]
+    wrap: Option<Topology>,
+    /// Run on a bounded plane of this size, e.g. `200x100`, centered on the origin, outside of
+    /// which cells are always dead.
+    #[arg(
+        long = "bounded",
+        value_name = "WxH",
+        value_parser = Topology::bounded,
+        conflicts_with = "wrap"
+    )]
+    bounded: Option<Topology>,
+    /// Run on a topology given in Golly's notation: `T200,100` for a torus, `P200,100` for a
+    /// bounded plane, `K200*,100` or `K200,100*` for a Klein bottle with its top and bottom or
+    /// left and right edges twisted, `C200,100` for a cross-surface or `S200` for a sphere.
+    #[arg(long = "topology", conflicts_with_all = ["wrap", "bounded"])]
+    topology: Option<Topology>,
+    /// Number the generations from N, e.g. to continue a run exported from elsewhere. Defaults to
+    /// the generation recorded in the RLE file or session, or 0.
+    #[arg(long = "start-generation", value_name = "N")]
+    start_generation: Option<usize>,
+    /// Seed for `--pattern random`. Without one a seed is picked at random; either way it is
+    /// recorded in saved sessions, snapshots, exports and the stats stream.
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+    #[arg(short = 'b', long = "build", default_value_t = false)]
+    build: bool,
+    #[arg(long = "session")]
+    session: Option<PathBuf>,
+    /// List the recently loaded pattern files and sessions and exit, or with a number, start from
+    /// that one (1 is the most recent).
+    #[arg(
+        long = "recent",
+        value_name = "N",
+        num_args = 0..=1,
+        conflicts_with_all = ["file", "pattern", "session"]
+    )]
+    recent: Option<Option<usize>>,
+    /// Config file to use instead of `~/.config/rust-life/config`.
+    #[arg(long = "config")]
+    config: Option<PathBuf>,
+    /// Pattern library directory to use instead of `~/.config/rust-life/patterns`.
+    #[arg(long = "library")]
+    library: Option<PathBuf>,
+    /// List the built-in and library patterns, only those tagged TAG if one is given, and exit.
+    #[arg(long = "list-patterns", value_name = "TAG", num_args = 0..=1, default_missing_value = "")]
+    list_patterns: Option<String>,
+    #[arg(short = 'r', long = "rulers", default_value_t = false)]
+    rulers: bool,
+    /// Start with history on, recording every cell that is ever alive (see `H`). The size and
+    /// bounding box of this envelope are added to the stats stream and headless summary.
+    #[arg(long = "history", default_value_t = false)]
+    history: bool,
+    #[arg(long = "snapshot-every")]
+    snapshot_every: Option<usize>,
+    #[arg(long = "snapshot-dir", default_value = ".")]
+    snapshot_dir: PathBuf,
+    #[arg(long = "headless", default_value_t = false)]
+    headless: bool,
+    #[arg(long = "max-generations")]
+    max_generations: Option<usize>,
+    #[arg(long = "frames-every")]
+    frames_every: Option<usize>,
+    #[arg(long = "frames-dir", default_value = ".")]
+    frames_dir: PathBuf,
+    #[arg(long = "frame-scale", default_value = "4", value_parser = clap::value_parser!(u32).range(1..))]
+    frame_scale: u32,
+    #[arg(long = "frame-region", value_parser = cmdline::parse_region)]
+    frame_region: Option<Region>,
+    /// Write a PNG (or an SVG, if the file name ends in `.svg`) of the live cells to this file
+    /// and exit (with `--headless`, once the run ends) instead of opening the TUI.
+    #[arg(long = "screenshot")]
+    screenshot: Option<PathBuf>,
+    /// Draw grid lines between the cells in SVG screenshots.
+    #[arg(long = "svg-grid", default_value_t = false)]
+    svg_grid: bool,
+    /// The size of each cell in screenshots, in pixels.
+    #[arg(long = "screenshot-scale", default_value = "4", value_parser = clap::value_parser!(u32).range(1..))]
+    screenshot_scale: u32,
+    /// The color of live cells in screenshots and frames (`#rrggbb`).
+    #[arg(long = "live-color", default_value = "#ffffff")]
+    live_color: Rgb,
+    /// The color of dead cells in screenshots and frames (`#rrggbb`).
+    #[arg(long = "dead-color", default_value = "#000000")]
+    dead_color: Rgb,
+    /// Write the generation, population, births, deaths and bounding box of every generation
+    /// to this file (or stdout, with `-` in headless mode).
+    #[arg(long = "stats-stream")]
+    stats_stream: Option<PathBuf>,
+    /// The format of the stats stream: JSON lines, or CSV with a header row. Defaults to CSV
+    /// for `.csv` files and JSON otherwise.
+    #[arg(long = "stats-format", value_enum, requires = "stats_stream")]
+    stats_format: Option<StatsFormat>,
+    /// Record every frame drawn, with timing, to an asciinema (v2) cast file.
+    #[arg(long = "record-cast", value_name = "FILE", conflicts_with = "headless")]
+    record_cast: Option<PathBuf>,
+    /// Send per-generation stats as OSC messages to this UDP address (`host:port`).
+    #[arg(long = "osc")]
+    osc: Option<String>,
+    /// Also send an OSC message for every birth and death.
+    #[arg(long = "osc-cells", default_value_t = false, requires = "osc")]
+    osc_cells: bool,
+    /// The radius of the brush `e` fills or erases with, in cells (1 is a single cell).
+    #[arg(
+        long = "brush-radius",
+        default_value = "1",
+        value_parser = clap::value_parser!(i32).range(1..=brush::MAX_BRUSH_RADIUS as i64)
+    )]
+    brush_radius: i32,
+    #[arg(long = "brush-shape", value_enum, default_value = "circle")]
+    brush_shape: brush::Shape,
+    /// Snap patterns placed at the cursor to a lattice with this spacing, in cells, so that
+    /// their origins land on multiples of it.
+    #[arg(long = "snap", value_parser = clap::value_parser!(i32).range(1..))]
+    snap: Option<i32>,
+    /// Hold single-cell moves of the cursor and camera to a line (see `/`).
+    #[arg(long = "axis-lock", value_enum, default_value = "off")]
+    axis_lock: AxisLock,
+    /// The radius of the spray brush (see `b`), in cells.
+    #[arg(
+        long = "spray-radius",
+        default_value = "4",
+        value_parser = clap::value_parser!(i32).range(1..=brush::MAX_SPRAY_RADIUS as i64)
+    )]
+    spray_radius: i32,
+    /// The chance of the spray brush raising each cell under it, from 0 to 1 (or 0% to 100%).
+    #[arg(long = "spray-density", default_value = "0.25", value_parser = brush::parse_density)]
+    spray_density: f64,
+    /// Recount clusters every N generations (0 disables the count).
+    #[arg(long = "cluster-interval", default_value = "10")]
+    cluster_interval: usize,
+    /// Play each generation as a tone: pitch follows the population, loudness the births and
+    /// deaths.
+    #[cfg(feature = "audio")]
+    #[arg(long = "audio", default_value_t = false)]
+    audio: bool,
+    #[command(subcommand)]
+    mode: Option<Mode>,
+}
+
+impl Args {
+    /// The world to start from: the `--file` pattern if one was given, otherwise `--pattern`,
+    /// with `--rule` and `--start-generation` applied. Random soups also return the seed they
+    /// were generated from.
+    fn world(&self) -> Result<(Pattern, Option<u64>)> {
+        let (mut pattern, seed) = self.pattern()?;
+        if let Some(rule) = self.chosen_rule() {
+            pattern.world.set_rule(Arc::clone(rule));
+        }
+        if let Some(topology) = self.topology() {
+            pattern.world.set_topology(topology);
+        }
+        if let Some(generation) = self.start_generation {
+            pattern.world.generations = generation;
+        }
+        Ok((pattern, seed))
+    }
+
+    fn pattern(&self) -> Result<(Pattern, Option<u64>)> {
+        match (&self.file, &self.pattern) {
+            (Some(path), _) if path == Path::new("-") => {
+                let mut data = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut data)
+                    .context("failed to read stdin")?;
+                let pattern = formats::read_pattern(&data).context("failed to load stdin")?;
+                Ok((pattern, None))
+            }
+            (Some(path), _) => Ok((formats::load_pattern(path)?, None)),
+            (None, PatternName::BuiltIn(LifePattern::Random(size))) => {
+                let seed = self.seed.unwrap_or_else(random);
+                Ok((LifeWorld::random(*size, seed).into(), Some(seed)))
+            }
+            (None, PatternName::BuiltIn(pattern)) => Ok((Pattern::from(pattern), None)),
+            (None, PatternName::Apgcode(code)) => Ok((formats::parse_apgcode(code)?.into(), None)),
+            (None, PatternName::Library(name)) => {
+                let library = Library::open(self.library.clone());
+                Ok((library::find(library.as_ref(), name)?.load()?, None))
+            }
+        }
+    }
+
+    fn timestep(&self) -> Duration {
+        self.timestep.unwrap_or(DEFAULT_TIMESTEP)
+    }
+
+    /// The rule given with `--rule` or `--rule-name`, if any.
+    fn chosen_rule(&self) -> Option<&Arc<dyn Rule>> {
+        self.rule.as_ref().or(self.rule_name.as_ref())
+    }
+
+    /// The topology given with `--wrap`, `--bounded` or `--topology`, if any.
+    fn topology(&self) -> Option<Topology> {
+        self.wrap.or(self.bounded).or(self.topology)
+    }
+
+    /// Load a session, overriding its rule, topology and generation with `--rule`, `--wrap` (or
+    /// `--bounded` or `--topology`) and `--start-generation` if they were given.
+    fn load_session(&self, path: &Path) -> Result<Session> {
+        let mut session = Session::load(path)?;
+        session.rule = self.chosen_rule().cloned().unwrap_or(session.rule);
+        session.topology = self.topology().unwrap_or(session.topology);
+        session.generations = self.start_generation.unwrap_or(session.generations);
+        Ok(session)
+    }
+
+    /// The format of a stats stream written to `path`.
+    fn stats_format(&self, path: &Path) -> StatsFormat {
+        self.stats_format
+            .unwrap_or_else(|| StatsFormat::for_path(path))
+    }
+
+    /// How PNG images are drawn, with cells `scale` pixels across.
+    fn image_style(&self, scale: u32) -> ImageStyle {
+        ImageStyle {
+            scale,
+            palette: Palette {
+                live: self.live_color,
+                dead: self.dead_color,
+            },
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Mode {
+    /// Run a pattern or batch of random soups, classify the outcomes, and append them to a CSV
+    /// file.
+    Lab(lab::LabArgs),
+    /// Run many random soups and keep a persistent leaderboard of the longest-lived and
+    /// highest-peak seeds.
+    Leaderboard(leaderboard::LeaderboardArgs),
+    /// Evolve a pattern by mutating it and keeping improvements, writing the best to an RLE
+    /// file.
+    EvolveSearch(search::SearchArgs),
+    /// Run a pattern for a number of generations and write the run as an animated GIF.
+    ExportGif(animation::GifArgs),
+    /// Convert a pattern file to another format, e.g. `convert glider.rle glider.cells`.
+    Convert(convert::ConvertArgs),
+}
+
+#[derive(Debug)]
+struct Program {
+    pub sim: Simulation,
+    pub cursor: Position,
+    /// Declared before `screen` so the render thread is stopped before the terminal is released.
+    /// There is none when driven by the test harness.
+    pub renderer: Option<Renderer>,
+    pub screen: Screen,
+    pub frame_skip: FrameSkip,
+    /// Whether the profiling overlay is shown.
+    pub profile: bool,
+    pub build_mode: bool,
+    pub labels: BTreeMap<Position, String>,
+    /// Named camera positions, kept in sessions.
+    pub views: BTreeMap<String, Position>,
+    pub cmdline: Option<String>,
+    /// The commands entered on the command line, stepped through with the up and down arrows.
+    pub ex_history: ExHistory,
+    /// The completions of the word being typed on the command line, while more than one is left.
+    pub completions: Vec<String>,
+    pub message: Toast,
+    /// The config last loaded, for its key map and to tell which defaults a reload changes.
+    config: Config,
+    /// Watches the config file the program was started with, if it was started with one.
+    config_watcher: Option<config::Watcher>,
+    /// A recoverable failure, shown in a panel over the grid until dismissed.
+    pub error: Option<anyhow::Error>,
+    pub marks: Vec<Position>,
+    pub library: Option<Library>,
+    pub picker: Option<Picker>,
+    /// The previews shown in the picker, kept so each pattern is only previewed once.
+    pub previews: Previews,
+    /// The rule editor, while it is open.
+    pub rule_editor: Option<RuleEditor>,
+    /// A rule declared by a loaded pattern, waiting for a yes or no before it is switched to.
+    rule_prompt: Option<RulePrompt>,
+    /// The file the recently loaded files are listed in, if they are being tracked.
+    pub recent: Option<PathBuf>,
+    /// The lock on the session file last loaded or saved, unless another instance has it open.
+    session_lock: Option<SessionLock>,
+    /// A long operation in progress, run a slice at a time from the main loop.
+    pub job: Option<Job>,
+    pub rulers: bool,
+    /// Whether the camera follows the live cells when they are about to leave the view.
+    pub auto_pan: bool,
+    /// Whether the key help overlay is shown.
+    pub help: bool,
+    /// How screenshots of the view are drawn.
+    pub image_style: ImageStyle,
+    pub clipboard: Clipboard,
+    pub kill_ring: KillRing,
+    /// The last paste from the kill ring, if nothing has happened since, so pasting again can
+    /// swap it for an older pattern.
+    kill_paste: Option<KillPaste>,
+    pub brush: Brush,
+    pub spray: Spray,
+    /// Whether the spray brush raises cells wherever the cursor moves.
+    pub spraying: bool,
+    pub axis_lock: AxisLock,
+    /// The spacing of the lattice patterns placed at the cursor snap to.
+    pub snap: Option<i32>,
+    pub alerts: Vec<Alert>,
+    pub cluster_interval: usize,
+    /// The world's statistics as of the last count of its clusters.
+    pub sampled_stats: Option<WorldStats>,
+    /// The last world status line (see `Screen::world_status`) and when it was made.
+    pub status: Option<(String, Instant)>,
+}
+
+impl Program {
+    fn new(mut args: Args) -> Result<Self> {
+        // Watching starts first so that an edit made while loading is picked up.
+        let config_watcher = config::Watcher::new(args.config.as_deref());
+        let config = Config::load(args.config.as_deref())?;
+        args.timestep = args.timestep.or(config.timestep);
+        args.rulers |= config.rulers == Some(true);
+        let mut screen = Screen::new()?;
+        screen.glyphs = config.glyphs;
+        screen.theme = config.theme;
+        // Since we are using raw mode, Ctrl+C will not send a SIGINT but catch the signal just in
+        // case the SIGINT gets sent by an external process.
+        ctrlc::set_handler(|| {
+            if let Err(e) = Screen::release_terminal() {
+                eprintln!("Failed to release terminal: {:?}", e);
+            }
+            println!("Received Ctrl-C, exiting...");
+            std::process::exit(0);
+        })?;
+        let patterns = args
+            .file
+            .iter()
+            .filter(|path| *path != Path::new("-"))
+            .map(|path| (recent::Kind::Pattern, path.clone()));
+        let sessions = args
+            .session
+            .iter()
+            .map(|path| (recent::Kind::Session, path.clone()));
+        let loaded: Vec<_> = patterns.chain(sessions).collect();
+        let cast = match &args.record_cast {
+            Some(path) => Some(Cast::create(path, screen.width, screen.height)?),
+            None => None,
+        };
+        let mut program = Program::build(args, screen, Some(Renderer::spawn(cast)?))?;
+        program.recent = Recent::default_path();
+        program.config_watcher = Some(config_watcher);
+        program.config = config;
+        // Like the recent files list, the history is only a convenience, so it is not an error
+        // if it can't be read.
+        let ex_history = ExHistory::default_path().and_then(|path| ExHistory::load(&path).ok());
+        program.ex_history = ex_history.unwrap_or_default();
+        for (kind, path) in loaded {
+            program.remember(kind, &path);
+        }
+        Ok(program)
+    }
+
+    /// Add a loaded file to the recent files list. Failures are ignored, as the list is only a
+    /// convenience.
+    fn remember(&self, kind: recent::Kind, path: &Path) {
+        if let Some(list) = &self.recent {
+            let _ = recent::remember(list, kind, path);
+        }
+    }
+
+    /// Set up a program on the given screen, drawing frames with `renderer` if there is one.
+    fn build(args: Args, screen: Screen, renderer: Option<Renderer>) -> Result<Self> {
+        let (mut pattern, seed) = args.world()?;
+        let warning = pattern.rule_warning(pattern.world.rule().as_ref());
+        // A file that declares another rule starts out under Life, and the declared rule is only
+        // switched to once the user agrees.
+        let rule_prompt = match &args.file {
+            Some(path) if args.chosen_rule().is_none() && *pattern.world.rule() != rule::life() => {
+                let rule = Arc::clone(pattern.world.rule());
+                pattern.world.set_rule(rule::life());
+                Some(RulePrompt {
+                    rule,
+                    context: format!("loaded {}", path.display()),
+                    resume: !args.build,
+                })
+            }
+            _ => None,
+        };
+        let mut program = Self {
+            sim: Simulation::new(pattern.world, args.timestep()),
+            renderer,
+            screen,
+            frame_skip: FrameSkip::new(),
+            profile: false,
+            cursor: (0, 0),
+            build_mode: args.build,
+            labels: BTreeMap::new(),
+            views: BTreeMap::new(),
+            cmdline: None,
+            ex_history: ExHistory::default(),
+            completions: Vec::new(),
+            message: Toast::default(),
+            config: Config::default(),
+            config_watcher: None,
+            error: None,
+            marks: Vec::new(),
+            library: Library::open(args.library.clone()),
+            picker: None,
+            previews: Previews::new(),
+            rule_editor: None,
+            rule_prompt,
+            recent: None,
+            session_lock: None,
+            job: None,
+            rulers: args.rulers,
+            auto_pan: false,
+            help: false,
+            image_style: args.image_style(args.screenshot_scale),
+            clipboard: Clipboard::default(),
+            kill_ring: KillRing::default(),
+            kill_paste: None,
+            brush: Brush {
+                shape: args.brush_shape,
+                radius: args.brush_radius,
+                state: 1,
+            },
+            spray: Spray::new(args.spray_radius, args.spray_density, random()),
+            spraying: false,
+            axis_lock: args.axis_lock,
+            snap: args.snap,
+            alerts: Vec::new(),
+            cluster_interval: args.cluster_interval,
+            sampled_stats: None,
+            status: None,
+        };
+        if let Some(warning) = warning {
+            program.message.show(warning);
+        }
+        program.sim.seed = seed;
+        program.sim.set_history(args.history);
+        program
+            .sim
+            .set_snapshots(args.snapshot_every, &args.snapshot_dir)?;
+        if let Some(path) = &args.session {
+            program.restore(args.load_session(path)?);
+            program.open_session(path);
+        }
+        program.count_clusters();
+        if let Some(path) = &args.stats_stream {
+            if path == Path::new("-") {
+                return Err(anyhow!(
+                    "streaming stats to stdout is only supported in headless mode"
+                ));
+            }
+            program
+                .sim
+                .set_stats(StatsStream::open(path, args.stats_format(path))?);
+        }
+        if let Some(target) = &args.osc {
+            program
+                .sim
+                .set_osc(OscSender::connect(target, args.osc_cells)?)?;
+        }
+        #[cfg(feature = "audio")]
+        if args.audio {
+            program
+                .sim
+                .set_audio(audio::Sonifier::new(args.timestep())?);
+        }
+        program.sim.record(Changes::default());
+        if let Some(e) = program.sim.take_error() {
+            return Err(e);
+        }
+        Ok(program)
+    }
+
+    /// The measurement between the marked points, using the cursor as the second point until it
+    /// has been marked.
+    fn measurement(&self) -> Option<Measurement> {
+        match self.marks[..] {
+            [from] => Some(Measurement::new(from, self.cursor)),
+            [from, to] => Some(Measurement::new(from, to)),
+            _ => None,
+        }
+    }
+
+    /// The part of the world inside the rectangle between the marked points (using the cursor as
+    /// the second corner until it has been marked), or all of it if nothing is marked.
+    fn selection(&self) -> LifeWorld {
+        let ((x0, y0), (x1, y1)) = match self.marks[..] {
+            [from] => (from, self.cursor),
+            [from, to] => (from, to),
+            _ => return self.sim.world.clone(),
+        };
+        let (min, max) = ((x0.min(x1), y0.min(y1)), (x0.max(x1), y0.max(y1)));
+        let mut selection = LifeWorld::new();
+        selection.set_rule(Arc::clone(self.sim.world.rule()));
+        for (x, y) in self.sim.world.cells_in(min, max) {
+            selection.raise(x, y);
+        }
+        for ((x, y), decay) in self.sim.world.dying_in(min, max) {
+            selection.set_dying(x, y, decay);
+        }
+        selection.generations = self.sim.world.generations;
+        selection
+    }
+
+    fn session(&self) -> Session {
+        let mut cells: Vec<Position> = self.sim.world.cells().collect();
+        cells.sort();
+        let mut dying: Vec<(Position, u8)> = self.sim.world.dying().collect();
+        dying.sort();
+        Session {
+            generations: self.sim.world.generations,
+            rule: Arc::clone(self.sim.world.rule()),
+            topology: self.sim.world.topology(),
+            seed: self.sim.seed,
+            camera: (self.screen.camera.x, self.screen.camera.y),
+            cursor: self.cursor,
+            labels: self.labels.clone(),
+            views: self.views.clone(),
+            cells,
+            dying,
+            ants: self.sim.world.ants().to_vec(),
+        }
+    }
+
+    fn restore(&mut self, session: Session) {
+        let world = session.world();
+        self.restore_world(world, session);
+    }
+
+    /// Take the lock on the session at `path`, releasing the one on the session open before.
+    /// Returns who has it instead if another instance does, leaving the old lock in place.
+    fn lock_session(&mut self, path: &Path) -> Option<Busy> {
+        if self
+            .session_lock
+            .as_ref()
+            .is_some_and(|lock| lock.covers(path))
+        {
+            return None;
+        }
+        match SessionLock::acquire(path) {
+            Ok(Ok(lock)) => self.session_lock = Some(lock),
+            Ok(Err(busy)) => return Some(busy),
+            // The lock is only a safeguard, so a session where it can't be taken, such as in a
+            // read-only directory, is used without one.
+            Err(_) => self.session_lock = None,
+        }
+        None
+    }
+
+    /// Lock a session that has been loaded, warning if another instance has it open.
+    fn open_session(&mut self, path: &Path) {
+        if let Some(busy) = self.lock_session(path) {
+            self.session_lock = None;
+            self.message
+                .show(format!("warning: {}, so saving over it is refused", busy));
+        }
+    }
+
+    /// Restore a session whose world has already been built.
+    fn restore_world(&mut self, world: LifeWorld, session: Session) {
+        if let Err(e) = self.sim.set_world(world) {
+            self.report(e);
+        }
+        self.sim.seed = session.seed;
+        self.count_clusters();
+        self.screen.camera.x = session.camera.0;
+        self.screen.camera.y = session.camera.1;
+        self.cursor = session.cursor;
+        self.labels = session.labels;
+        self.views = session.views;
+    }
+
+    /// Follow up on a generation the simulation has advanced: report output errors, check
+    /// alerts, and recount clusters. Returns whether an alert paused the simulation.
+    fn after_step(&mut self) -> Result<bool> {
+        if let Some(e) = self.sim.take_error() {
+            self.report(e);
+        }
+        let population = self.sim.world.num_alive();
+        let mut paused = false;
+        for alert in &mut self.alerts {
+            if alert.check(population) {
+                self.message.show(format!(
+                    "alert: population {} {} (generation {})",
+                    population, alert.threshold, self.sim.world.generations
+                ));
+                if alert.pause {
+                    self.sim.handle(Command::Pause)?;
+                    paused = true;
+                }
+            }
+        }
+        if self.cluster_interval > 0
+            && self
+                .sim
+                .world
+                .generations
+                .is_multiple_of(self.cluster_interval)
+        {
+            self.count_clusters();
+        }
+        if self.auto_pan {
+            self.follow_activity();
+        }
+        if self.sim.world.rule().elementary().is_some() {
+            self.follow_row();
+        }
+        Ok(paused)
+    }
+
+    /// Scroll the view down once the newest row of an elementary rule reaches the bottom, so the
+    /// rows run up the screen like a printout.
+    fn follow_row(&mut self) {
+        let Some(row) = self.sim.world.elementary_row() else {
+            return;
+        };
+        let (min, _) = self.screen.inner_region(AUTO_PAN_MARGIN);
+        if row < min.1 {
+            // The camera moves in terminal rows of two cells.
+            self.screen.camera.y -= (min.1 - row + 1) / 2;
+        }
+    }
+
+    /// Recenter the camera on the live cells once none are left well inside the view, so escaping
+    /// gliders or growing patterns do not vanish off screen.
+    fn follow_activity(&mut self) {
+        let (min, max) = self.screen.inner_region(AUTO_PAN_MARGIN);
+        if self.sim.world.cells_in(min, max).next().is_some() {
+            return;
+        }
+        if let Some(((x0, y0), (x1, y1))) = self.sim.world.bounding_box() {
+            self.screen
+                .center_on((x0 + (x1 - x0) / 2, y0 + (y1 - y0) / 2));
+        }
+    }
+
+    /// Recount the clusters shown in the status line by sampling the world's statistics. Counting
+    /// them scans the whole world, so it is only done every `cluster_interval` generations and
+    /// after edits.
+    fn count_clusters(&mut self) {
+        if self.cluster_interval == 0 {
+            return;
+        }
+        self.sampled_stats = Some(self.sim.world.stats());
+    }
+
+    fn execute(&mut self, command: ExCommand) -> Result<()> {
+        match command {
+            ExCommand::Label(pos, text) => {
+                self.labels.insert(pos.unwrap_or(self.cursor), text);
+            }
+            ExCommand::Unlabel(pos) => {
+                self.labels.remove(&pos.unwrap_or(self.cursor));
+            }
+            ExCommand::Save(path) => {
+                if let Some(busy) = self.lock_session(Path::new(&path)) {
+                    return Err(anyhow!("{}, so it was not saved over", busy));
+                }
+                self.session().save(Path::new(&path))?;
+                self.message.show(format!("saved session to {}", path));
+            }
+            ExCommand::Store(name, tags) => {
+                let library = self
+                    .library
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("no pattern library directory (use --library)"))?;
+                let selection = self.selection();
+                let mut comments = vec![format!("generation {}", selection.generations)];
+                comments.extend(self.sim.seed.map(|seed| format!("seed {}", seed)));
+                let path = library.save(&name, &selection, &tags, &comments)?;
+                self.message.show(format!(
+                    "stored {} cells as {} in {}",
+                    selection.num_alive(),
+                    name,
+                    path.display()
+                ));
+            }
+            ExCommand::Brush(radius, shape) => {
+                self.brush.radius = radius;
+                self.brush.shape = shape.unwrap_or(self.brush.shape);
+                self.message.show(self.brush.to_string());
+            }
+            ExCommand::Spray(radius, density) => {
+                self.spray.radius = radius;
+                self.spray.density = density;
+                self.message.show(format!("spray: {}", self.spray));
+            }
+            ExCommand::Apgcode => {
+                let code = formats::to_apgcode(&self.selection())?;
+                self.message.show(format!("apgcode: {}", code));
+            }
+            ExCommand::Patterns(tag) => {
+                let entries = library::catalog(self.library.as_ref(), tag.as_deref())?;
+                let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+                self.message.show(match tag {
+                    Some(tag) => format!("patterns tagged {}: {}", tag, names.join(", ")),
+                    None => format!("patterns: {}", names.join(", ")),
+                });
+            }
+            ExCommand::Load(path) => {
+                let session = Session::load(Path::new(&path))?;
+                self.job = Some(Job::load(path, session));
+            }
+            ExCommand::Export(path) => {
+                export::write_world(&self.sim.world, Path::new(&path), self.sim.seed)?;
+                self.message.show(format!("exported world to {}", path));
+            }
+            // A bare name that isn't a file is looked up among the patterns, as with `-p`.
+            ExCommand::Merge(name, pos, scale)
+                if !name.contains(['/', '.']) && !Path::new(&name).exists() =>
+            {
+                let entry = library::find(self.library.as_ref(), &name)?;
+                self.place(entry.load()?, scale, pos.unwrap_or(self.placement()), &name)?;
+            }
+            ExCommand::Merge(path, pos, scale) => {
+                let loaded = formats::load_pattern(Path::new(&path))?;
+                self.place(loaded, scale, pos.unwrap_or(self.placement()), &path)?;
+                self.remember(recent::Kind::Pattern, Path::new(&path));
+            }
+            ExCommand::Snap(spacing) => {
+                self.snap = spacing;
+                self.message.show(match spacing {
+                    Some(spacing) => format!("snapping placed patterns to {0}x{0}", spacing),
+                    None => String::from("snapping off"),
+                });
+            }
+            ExCommand::FastForward(generations) => {
+                self.job = Some(Job::FastForward {
+                    done: 0,
+                    total: generations,
+                });
+            }
+            ExCommand::Goto(generation) => {
+                // Only the current generation is kept, so the world can only be moved forward.
+                let current = self.sim.world.generations;
+                match generation.checked_sub(current) {
+                    Some(0) => self
+                        .message
+                        .show(format!("already at generation {}", current)),
+                    Some(generations) => {
+                        self.job = Some(Job::FastForward {
+                            done: 0,
+                            total: generations,
+                        });
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "can't go back to generation {} from {} (no history is kept)",
+                            generation,
+                            current
+                        ))
+                    }
+                }
+            }
+            ExCommand::RunUntil(condition) => {
+                self.job = Some(Job::RunUntil { condition, done: 0 });
+            }
+            ExCommand::Alert(alert) => {
+                self.message
+                    .show(format!("added alert: population {}", alert.threshold));
+                self.alerts.push(alert);
+            }
+            ExCommand::ClearAlerts => {
+                self.alerts.clear();
+            }
+            ExCommand::SaveView(name) => {
+                let camera = (self.screen.camera.x, self.screen.camera.y);
+                self.views.insert(name.clone(), camera);
+                self.message.show(format!("saved view {}", name));
+            }
+            ExCommand::View(Some(name)) => {
+                let &(x, y) = self
+                    .views
+                    .get(&name)
+                    .ok_or_else(|| anyhow!("no view named {} (see :view)", name))?;
+                self.screen.camera.x = x;
+                self.screen.camera.y = y;
+            }
+            ExCommand::View(None) => {
+                let names: Vec<&str> = self.views.keys().map(String::as_str).collect();
+                self.message.show(if names.is_empty() {
+                    String::from("no views (save one with :view save <name>)")
+                } else {
+                    format!("views: {}", names.join(", "))
+                });
+            }
+            ExCommand::Rule(Some(rule)) => {
+                self.message.show(format!("rule: {}", rule));
+                self.sim.world.set_rule(rule);
+            }
+            ExCommand::Rule(None) => {
+                self.message
+                    .show(format!("rule: {}", self.sim.world.rule()));
+            }
+            ExCommand::ResetHistory => {
+                let history = self
+                    .sim
+                    .history()
+                    .ok_or_else(|| anyhow!("history is off (press H to turn it on)"))?;
+                history.reset(&self.sim.world);
+                self.message.show("reset the history");
+            }
+            ExCommand::Quit => {
+                self.sim.handle(Command::Quit)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the camera to the view after the current one, in order of name, so a few distant
+    /// regions can be watched in turn.
+    fn next_view(&mut self) {
+        let camera = (self.screen.camera.x, self.screen.camera.y);
+        // Start after the view the camera is at, if it is at one.
+        let current = self
+            .views
+            .iter()
+            .find(|&(_, &pos)| pos == camera)
+            .map(|(name, _)| name);
+        let next = match current {
+            Some(current) => self
+                .views
+                .range::<String, _>((Bound::Excluded(current), Bound::Unbounded))
+                .next()
+                .or_else(|| self.views.iter().next()),
+            None => self.views.iter().next(),
+        };
+        match next {
+            Some((name, &(x, y))) => {
+                self.screen.camera.x = x;
+                self.screen.camera.y = y;
+                self.message.show(format!("view {}", name));
+            }
+            None => self
+                .message
+                .show("no views (save one with :view save <name>)"),
+        }
+    }
+
+    /// Show a failure in the error panel rather than ending the session.
+    fn report(&mut self, error: anyhow::Error) {
+        self.message.clear();
+        self.error = Some(error);
+    }
+
+    /// Merge a loaded pattern into the world, scaled up by `scale`, with its origin at `pos`.
+    fn place(&mut self, loaded: Pattern, scale: u32, (x, y): Position, name: &str) -> Result<()> {
+        let pattern = loaded
+            .world
+            .scaled(scale)
+            .ok_or_else(|| anyhow!("{} is too large to scale by {}", name, scale))?;
+        self.sim.world.merge(&pattern, (x, y));
+        self.count_clusters();
+        let merged = format!(
+            "merged {} cells from {} at {},{}",
+            pattern.num_alive(),
+            name,
+            x,
+            y
+        );
+        // A declared rule the engine supports is switched to if the user agrees; others are only
+        // warned about.
+        let running = self.sim.world.rule();
+        match loaded.rule_warning(running.as_ref()) {
+            Some(_) if loaded.world.rule() != running => {
+                let rule = Arc::clone(loaded.world.rule());
+                self.ask_rule(rule, merged)?;
+            }
+            Some(warning) => self.message.show(warning),
+            None => self.message.show(merged),
+        }
+        Ok(())
+    }
+
+    /// Ask whether to switch to a rule declared by a loaded pattern, pausing until the answer.
+    fn ask_rule(&mut self, rule: Arc<dyn Rule>, context: String) -> Result<()> {
+        let resume = self.sim.state() == State::Running;
+        if resume {
+            self.sim.handle(Command::Pause)?;
+        }
+        self.rule_prompt = Some(RulePrompt {
+            rule,
+            context,
+            resume,
+        });
+        Ok(())
+    }
+
+    /// Switch to the prompted rule on `y` and keep the running one on `n` or Esc, then carry on
+    /// running if the prompt paused the simulation. Other keys are ignored until then.
+    fn handle_rule_prompt_key(&mut self, code: KeyCode) -> Result<()> {
+        let Some(prompt) = self.rule_prompt.take() else {
+            return Ok(());
+        };
+        match code {
+            KeyCode::Char('y') => {
+                self.message.show(format!("rule: {}", prompt.rule));
+                self.sim.world.set_rule(prompt.rule);
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.message
+                    .show(format!("kept rule {}", self.sim.world.rule()));
+            }
+            _ => {
+                self.rule_prompt = Some(prompt);
+                return Ok(());
+            }
+        }
+        if prompt.resume {
+            self.sim.handle(Command::Resume)?;
+        }
+        Ok(())
+    }
+
+    /// Stamp the pattern on the system clipboard at the cursor.
+    fn paste(&mut self) -> Result<()> {
+        let text = self.clipboard.get_text()?;
+        self.paste_text(&text)
+    }
+
+    /// Copy the selection to the system clipboard as RLE, and to the kill ring.
+    fn yank(&mut self) -> Result<()> {
+        let selection = self.selection();
+        self.kill_ring.push(&selection);
+        self.clipboard.set_text(selection.to_rle())?;
+        self.message
+            .show(format!("copied {} cells as RLE", selection.num_alive()));
+        Ok(())
+    }
+
+    /// Where a pattern placed at the cursor goes: the cursor, or with snapping on, the lattice
+    /// point at or below and to the left of it.
+    fn placement(&self) -> Position {
+        let (x, y) = self.cursor;
+        match self.snap {
+            Some(spacing) => (x - x.rem_euclid(spacing), y - y.rem_euclid(spacing)),
+            None => (x, y),
+        }
+    }
+
+    /// Move the cursor a cell, held to the axis lock.
+    fn step_cursor(&mut self, step: Position) {
+        let (dx, dy) = self.axis_lock.constrain(step);
+        self.cursor.0 += dx;
+        self.cursor.1 += dy;
+    }
+
+    /// Move the camera a cell, held to the axis lock.
+    fn step_camera(&mut self, step: Position) {
+        let (dx, dy) = self.axis_lock.constrain(step);
+        self.screen.camera.x += dx;
+        self.screen.camera.y += dy;
+    }
+
+    /// Clear the selection from the world, keeping it in the kill ring.
+    fn kill(&mut self) -> LifeWorld {
+        let selection = self.selection();
+        for (x, y) in selection
+            .cells()
+            .chain(selection.dying().map(|(pos, _)| pos))
+        {
+            self.sim.world.lower(x, y);
+        }
+        self.kill_ring.push(&selection);
+        self.count_clusters();
+        selection
+    }
+
+    /// Stamp the newest pattern in the kill ring at the cursor, or if `previous` is the paste just
+    /// made, replace it with the pattern before it in the ring.
+    fn paste_kill(&mut self, previous: Option<KillPaste>) -> Result<()> {
+        let generation = self.sim.world.generations;
+        let index = match previous.filter(|previous| previous.generation == generation) {
+            Some(previous) => {
+                for &(x, y) in &previous.raised {
+                    self.sim.world.lower(x, y);
+                }
+                (previous.index + 1) % self.kill_ring.len()
+            }
+            None => 0,
+        };
+        let pattern = self
+            .kill_ring
+            .get(index)
+            .ok_or_else(|| anyhow!("the kill ring is empty (cut with X or delete with D)"))?;
+        let (x0, y0) = self.placement();
+        let raised: Vec<Position> = pattern
+            .cells()
+            .map(|(x, y)| (x0 + x, y0 + y))
+            .filter(|&(x, y)| !self.sim.world.alive(x, y))
+            .collect();
+        for &(x, y) in &raised {
+            self.sim.world.raise(x, y);
+        }
+        self.count_clusters();
+        self.message.show(format!(
+            "pasted kill {} of {} at {},{} (Y again for an older one)",
+            index + 1,
+            self.kill_ring.len(),
+            x0,
+            y0
+        ));
+        self.kill_paste = Some(KillPaste {
+            index,
+            generation,
+            raised,
+        });
+        Ok(())
+    }
+
+    /// Stamp a pattern given as text, in RLE or plaintext as copied from LifeWiki, at the cursor.
+    fn paste_text(&mut self, text: &str) -> Result<()> {
+        let pattern = formats::read_pattern(text.trim().as_bytes())
+            .context("the clipboard does not hold an RLE or plaintext pattern")?;
+        if pattern.world.num_alive() == 0 {
+            return Err(anyhow!("the clipboard does not hold any cells"));
+        }
+        self.place(pattern, 1, self.placement(), "the clipboard")
+    }
+
+    /// Edit the rule, applying each change to the world as it is made.
+    fn handle_rule_editor_key(&mut self, code: KeyCode) {
+        let Some(editor) = self.rule_editor.as_mut() else {
+            return;
+        };
+        self.message.clear();
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => self.rule_editor = None,
+            KeyCode::Left | KeyCode::Char('h') => editor.left(),
+            KeyCode::Right | KeyCode::Char('l') => editor.right(),
+            KeyCode::Up | KeyCode::Down | KeyCode::Char('j' | 'k') => editor.switch_row(),
+            KeyCode::Char(' ') => match editor.toggle() {
+                Ok(()) => self.sim.world.set_rule(Arc::new(editor.rule)),
+                Err(e) => self.message.show(e.to_string()),
+            },
+            KeyCode::Char(c @ ('n' | 'N')) => match editor.cycle_preset(c == 'N') {
+                Ok(()) => self.sim.world.set_rule(Arc::new(editor.rule)),
+                Err(e) => self.message.show(e.to_string()),
+            },
+            _ => (),
+        }
+    }
+
+    fn handle_picker_key(&mut self, code: KeyCode) -> Result<()> {
+        let Some(picker) = self.picker.as_mut() else {
+            return Ok(());
+        };
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => self.picker = None,
+            KeyCode::Up | KeyCode::Char('k') => {
+                picker.up();
+                picker.load_previews(self.screen.picker_rows(), &mut self.previews);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                picker.down();
+                picker.load_previews(self.screen.picker_rows(), &mut self.previews);
+            }
+            KeyCode::Enter => {
+                let Some(entry) = self
+                    .picker
+                    .take()
+                    .and_then(|picker| picker.selected().cloned())
+                else {
+                    return Ok(());
+                };
+                match entry.load() {
+                    Ok(loaded) => self.place(loaded, 1, self.placement(), &entry.name)?,
+                    Err(e) => self.report(e),
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn handle_cmdline_key(&mut self, code: KeyCode) {
+        let Some(line) = self.cmdline.as_mut() else {
+            return;
+        };
+        self.completions.clear();
+        match code {
+            KeyCode::Esc => self.cmdline = None,
+            KeyCode::Backspace if line.is_empty() => self.cmdline = None,
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Enter => {
+                let line = self.cmdline.take().unwrap_or_default();
+                // Saving the history is ignored if it fails, like the recent files list.
+                let _ = self.ex_history.add(&line);
+                let result = line
+                    .parse::<ExCommand>()
+                    .and_then(|command| self.execute(command));
+                if let Err(e) = result {
+                    self.report(e);
+                }
+            }
+            KeyCode::Up => {
+                if let Some(previous) = self.ex_history.previous(line) {
+                    *line = previous.to_string();
+                }
+            }
+            KeyCode::Down => {
+                if let Some(next) = self.ex_history.next() {
+                    *line = next;
+                }
+            }
+            KeyCode::Tab => {
+                let catalog = library::catalog(self.library.as_ref(), None).unwrap_or_default();
+                let patterns: Vec<String> = catalog.into_iter().map(|entry| entry.name).collect();
+                (*line, self.completions) = completion::complete(line, &patterns);
+            }
+            KeyCode::Char(c) => line.push(c),
+            _ => (),
+        }
+        if self.cmdline.is_none() {
+            self.ex_history.stop();
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        self.sim.handle(Command::Start)?;
+        self.screen.clear()?;
+        if self.build_mode || self.rule_prompt.is_some() {
+            self.sim.handle(Command::Pause)?;
+        }
+        self.render()
+    }
+
+    fn run(&mut self) -> Result<()> {
+        self.start()?;
+        loop {
+            if self.job.is_some() {
+                self.handle_input(Duration::ZERO)?;
+                self.run_job()?;
+                self.render()?;
+                continue;
+            }
+            match self.sim.state() {
+                State::Done => break,
+                State::Setup => return Err(anyhow!("invalid state")),
+                State::Paused => {
+                    // Nothing changes while paused except in response to input, or a message
+                    // expiring.
+                    if self.handle_input(Duration::from_millis(50))?
+                        || self.message.expire(Instant::now())
+                        || self.reload_config(Instant::now())
+                    {
+                        self.render()?;
+                    }
+                }
+                State::Running => {
+                    self.message.expire(Instant::now());
+                    self.reload_config(Instant::now());
+                    // Wait for input until shortly before the next generation is due, then spin
+                    // for the rest, since poll timeouts are only accurate to a millisecond or so.
+                    let remaining = self.sim.remaining(Instant::now());
+                    if remaining > pacing::SPIN_MARGIN {
+                        if !self.handle_input(remaining - pacing::SPIN_MARGIN)? {
+                            continue;
+                        }
+                    } else {
+                        // Timesteps shorter than the margin never sleep, so check for input here
+                        // too.
+                        self.handle_input(Duration::ZERO)?;
+                        if self.sim.state() == State::Running {
+                            self.sim.step_when_due();
+                            self.after_step()?;
+                            let (_, draw_time) = self.render_stats();
+                            let timestep = self.sim.timestep();
+                            if !self.frame_skip.due(draw_time, timestep) {
+                                continue;
+                            }
+                        }
+                    }
+                    self.render()?;
+                }
+            }
+        }
+        match &mut self.renderer {
+            Some(renderer) => renderer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply the config file if it has changed, reporting a mistake in it in the status line
+    /// rather than the error panel, since it may only be a half-finished edit. Returns whether
+    /// anything changed.
+    fn reload_config(&mut self, now: Instant) -> bool {
+        let Some(watcher) = self.config_watcher.as_mut() else {
+            return false;
+        };
+        let Some(reloaded) = watcher.poll(now) else {
+            return false;
+        };
+        match reloaded {
+            Ok(config) => {
+                self.screen.glyphs = config.glyphs;
+                self.screen.theme = config.theme;
+                // Defaults only apply once they are changed, so a reload doesn't undo options
+                // given on the command line or changed with keys since.
+                if config.timestep != self.config.timestep
+                    && let Some(timestep) = config.timestep
+                    && let Err(e) = self.set_timestep(timestep)
+                {
+                    self.report(e);
+                }
+                if config.rulers != self.config.rulers
+                    && let Some(rulers) = config.rulers
+                {
+                    self.rulers = rulers;
+                }
+                self.config = config;
+                self.message.show("reloaded the config");
+            }
+            Err(e) => self.message.show(format!("error: {:#}", e)),
+        }
+        true
+    }
+
+    /// Run the current job for one slice, finishing it if there is no work left.
+    fn run_job(&mut self) -> Result<()> {
+        let Some(mut job) = self.job.take() else {
+            return Ok(());
+        };
+        let deadline = Instant::now() + job::SLICE;
+        let finished = loop {
+            match &mut job {
+                Job::FastForward { done, total } => {
+                    if done == total {
+                        break true;
+                    }
+                    self.sim.step();
+                    *done += 1;
+                    if self.after_step()? {
+                        // An alert that pauses the simulation stops the job too, as Esc would.
+                        return Ok(());
+                    }
+                }
+                Job::RunUntil { condition, done } => {
+                    if condition.holds(&self.sim.world) {
+                        break true;
+                    }
+                    self.sim.step();
+                    *done += 1;
+                    if self.after_step()? {
+                        return Ok(());
+                    }
+                }
+                Job::Load {
+                    session,
+                    world,
+                    loaded,
+                    ..
+                } => {
+                    let Some(&(x, y)) = session.cells.get(*loaded) else {
+                        break true;
+                    };
+                    world.raise(x, y);
+                    *loaded += 1;
+                }
+            }
+            if Instant::now() >= deadline {
+                break false;
+            }
+        };
+        if !finished {
+            self.job = Some(job);
+            return Ok(());
+        }
+        match job {
+            Job::FastForward { total, .. } => {
+                self.message
+                    .show(format!("fast-forwarded {} generations", total));
+            }
+            Job::RunUntil { condition, done } => {
+                self.sim.handle(Command::Pause)?;
+                self.message.show(format!(
+                    "{} after {} generations (generation {})",
+                    condition, done, self.sim.world.generations
+                ));
+            }
+            Job::Load {
+                path,
+                session,
+                mut world,
+                ..
+            } => {
+                session.add_dying_and_ants(&mut world);
+                self.restore_world(*world, *session);
+                self.remember(recent::Kind::Session, Path::new(&path));
+                self.message.show(format!("loaded session from {}", path));
+                self.open_session(Path::new(&path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand the current view to the render thread.
+    fn render(&mut self) -> Result<()> {
+        self.refresh_status(Instant::now());
+        let profile = self.profile.then(|| self.profile_lines());
+        let frame = self.screen.frame(self, profile);
+        match &mut self.renderer {
+            Some(renderer) => renderer.submit(frame),
+            None => Ok(()),
+        }
+    }
+
+    /// Remake the world status line unless the simulation is running and the line is recent.
+    /// Working out what it shows is wasted work at every generation when the line can't be read
+    /// that fast. While paused nothing changes between frames, which are only drawn after input
+    /// or a message expiring.
+    fn refresh_status(&mut self, now: Instant) {
+        let recent = |&(_, made): &(String, Instant)| now.duration_since(made) < STATUS_INTERVAL;
+        if self.sim.state() != State::Running || !self.status.as_ref().is_some_and(recent) {
+            self.status = Some((self.screen.world_status(self), now));
+        }
+    }
+
+    /// The number of frames dropped by the render thread and the average draw time.
+    fn render_stats(&self) -> (usize, Duration) {
+        self.renderer
+            .as_ref()
+            .map(Renderer::stats)
+            .unwrap_or_default()
+    }
+
+    /// The lines of the profiling overlay.
+    fn profile_lines(&self) -> Vec<String> {
+        let (dropped, draw_time) = self.render_stats();
+        let status = self.sim.status();
+        let requested = 1. / status.timestep.as_secs_f64();
+        vec![
+            format!("requested: {}", tickrate::format_hz(requested)),
+            format!("achieved: {}", tickrate::format_rate(status.rate)),
+            format!("step: {:.2}ms", status.step_time.as_secs_f64() * 1000.),
+            format!("render: {:.2}ms", draw_time.as_secs_f64() * 1000.),
+            format!("dropped frames: {}", dropped),
+        ]
+    }
+
+    /// Advance a number of generations at once, if paused.
+    fn step(&mut self, generations: usize) -> Result<()> {
+        if self.sim.handle(Command::Step(generations))?.is_some() {
+            self.after_step()?;
+        }
+        Ok(())
+    }
+
+    fn set_timestep(&mut self, timestep: Duration) -> Result<()> {
+        self.sim.handle(Command::SetTimestep(timestep))?;
+        self.frame_skip.reset();
+        self.message.show(format!("timestep: {:?}", timestep));
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for an input event and handle it. Returns whether an event was
+    /// handled.
+    fn handle_input(&mut self, timeout: Duration) -> Result<bool> {
+        if !event::poll(timeout)? {
+            return Ok(false);
+        }
+        match event::read()? {
+            Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) => self.handle_key(code, modifiers)?,
+            Event::Mouse(mouse) if self.job.is_none() => self.handle_mouse(mouse),
+            _ => (),
+        }
+        Ok(true)
+    }
+
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        // Keys may change what the status line shows, so it is remade for the next frame.
+        self.status = None;
+        if let Some(job) = &self.job {
+            // Other keys are ignored so the world is not edited halfway through a job.
+            if code == KeyCode::Esc {
+                self.message.show(format!("canceled {}", job));
+                self.job = None;
+            }
+            return Ok(());
+        }
+        if self.error.is_some() {
+            // Other keys are ignored so that dismissing the panel with Esc doesn't also quit.
+            if matches!(code, KeyCode::Esc | KeyCode::Enter) {
+                self.error = None;
+            }
+            return Ok(());
+        }
+        if self.cmdline.is_some() {
+            self.handle_cmdline_key(code);
+            return Ok(());
+        }
+        if self.picker.is_some() {
+            return self.handle_picker_key(code);
+        }
+        if self.rule_editor.is_some() {
+            self.handle_rule_editor_key(code);
+            return Ok(());
+        }
+        if self.rule_prompt.is_some() {
+            return self.handle_rule_prompt_key(code);
+        }
+        self.message.clear();
+        let kill_paste = self.kill_paste.take();
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        let code = if ctrl { code } else { self.config.key(code) };
+        let (columns, rows) = self.screen.view_size();
+        let cursor = self.cursor;
+        match code {
+            KeyCode::Char('u') if ctrl => {
+                self.screen.camera.y += (rows / 2).max(1);
+            }
+            KeyCode::Char('d') if ctrl => {
+                self.screen.camera.y -= (rows / 2).max(1);
+            }
+            KeyCode::PageUp => {
+                self.screen.camera.y += rows.max(1);
+            }
+            KeyCode::PageDown => {
+                self.screen.camera.y -= rows.max(1);
+            }
+            KeyCode::Home => {
+                self.screen.camera.x -= columns.max(1);
+            }
+            KeyCode::End => {
+                self.screen.camera.x += columns.max(1);
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.sim.handle(Command::Quit)?;
+            }
+            KeyCode::Char(' ') => {
+                self.sim.handle(Command::Toggle)?;
+            }
+            KeyCode::Char('.') => {
+                self.step(1)?;
+            }
+            KeyCode::Char(c @ '1'..='4') => {
+                // Jump 1, 10, 100 or 1000 generations.
+                self.step(10usize.pow(c as u32 - '1' as u32))?;
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                let timestep = (self.sim.timestep() / 2).max(MIN_TIMESTEP);
+                self.set_timestep(timestep)?;
+            }
+            KeyCode::Char('-') => {
+                let timestep = (self.sim.timestep() * 2).min(cmdline::MAX_DURATION);
+                self.set_timestep(timestep)?;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.step_camera((0, 1)),
+            KeyCode::Down | KeyCode::Char('j') => self.step_camera((0, -1)),
+            KeyCode::Left | KeyCode::Char('h') => self.step_camera((-1, 0)),
+            KeyCode::Right | KeyCode::Char('l') => self.step_camera((1, 0)),
+            KeyCode::Char('w') => self.step_cursor((0, 1)),
+            KeyCode::Char('s') => self.step_cursor((0, -1)),
+            KeyCode::Char('a') => self.step_cursor((-1, 0)),
+            KeyCode::Char('d') => self.step_cursor((1, 0)),
+            KeyCode::Char('/') => {
+                self.axis_lock = self.axis_lock.next();
+                self.message.show(format!("axis lock {}", self.axis_lock));
+            }
+            KeyCode::Char('c') => {
+                self.cursor = self.screen.center();
+            }
+            KeyCode::Char('C') => {
+                self.screen.center_on(self.cursor);
+            }
+            KeyCode::Char('S') => {
+                // A failed save is reported rather than ending the session.
+                match self.sim.save_snapshot() {
+                    Ok(path) => self.message.show(format!("saved {}", path.display())),
+                    Err(e) => self.report(e),
+                }
+            }
+            KeyCode::Char('I') => {
+                let region = self.screen.inner_region(0);
+                match self.sim.save_screenshot(region, &self.image_style) {
+                    Ok(path) => self.message.show(format!("saved {}", path.display())),
+                    Err(e) => self.report(e),
+                }
+            }
+            KeyCode::Char('v') if ctrl => {
+                if let Err(e) = self.paste() {
+                    self.report(e);
+                }
+            }
+            KeyCode::Char('v') => self.next_view(),
+            KeyCode::Char('y') => {
+                if let Err(e) = self.yank() {
+                    self.report(e);
+                }
+            }
+            KeyCode::Char('X') => {
+                let cut = self.kill();
+                self.message.show(format!("cut {} cells", cut.num_alive()));
+                if let Err(e) = self.clipboard.set_text(cut.to_rle()) {
+                    self.report(e);
+                }
+            }
+            KeyCode::Char('D') => {
+                let deleted = self.kill();
+                self.message.show(format!(
+                    "deleted {} cells (Y to paste)",
+                    deleted.num_alive()
+                ));
+            }
+            KeyCode::Char('Y') => {
+                if let Err(e) = self.paste_kill(kill_paste) {
+                    self.report(e);
+                }
+            }
+            KeyCode::Char('|') => self.screen.cycle_layout(),
+            KeyCode::Tab => self.screen.focus_next(),
+            KeyCode::Char('x') => {
+                let center = self.screen.center();
+                self.screen.center_on(self.cursor);
+                self.cursor = center;
+            }
+            KeyCode::Char('e') => {
+                self.brush.apply(&mut self.sim.world, self.cursor);
+                self.count_clusters();
+            }
+            KeyCode::Char('i') => {
+                let rule = Arc::clone(self.sim.world.rule());
+                self.brush.cycle_state(&*rule);
+                self.message
+                    .show(format!("painting {}", rule.state_name(self.brush.state)));
+            }
+            KeyCode::Char('A') => {
+                let (x, y) = self.cursor;
+                if self.sim.world.remove_ants(x, y) {
+                    self.message.show("picked up the ant");
+                } else {
+                    self.sim.world.add_ant(Ant::new(self.cursor));
+                    self.message.show(match self.sim.world.rule().turmite() {
+                        Some(_) => "put down an ant",
+                        None => "put down an ant (it only moves under a turmite rule)",
+                    });
+                }
+            }
+            KeyCode::Char('[') | KeyCode::Char(']') => {
+                let step = if code == KeyCode::Char(']') { 1 } else { -1 };
+                self.brush.radius = (self.brush.radius + step).clamp(1, brush::MAX_BRUSH_RADIUS);
+                self.message.show(self.brush.to_string());
+            }
+            KeyCode::Char('b') => {
+                self.spraying = !self.spraying;
+                if self.spraying {
+                    self.message.show(format!("spraying ({})", self.spray));
+                    self.spray();
+                } else {
+                    self.message.show("spraying off");
+                }
+            }
+            KeyCode::Char('o') => {
+                self.screen.camera.x = 0;
+                self.screen.camera.y = 0;
+            }
+            KeyCode::Char('r') => {
+                self.rulers = !self.rulers;
+            }
+            KeyCode::Char('H') => {
+                let enabled = self.sim.history().is_none();
+                self.sim.set_history(enabled);
+                self.message
+                    .show(format!("history {}", if enabled { "on" } else { "off" }));
+            }
+            KeyCode::Char('f') => {
+                self.auto_pan = !self.auto_pan;
+                self.message.show(format!(
+                    "auto-pan {}",
+                    if self.auto_pan { "on" } else { "off" }
+                ));
+            }
+            KeyCode::Char('m') => {
+                if self.marks.len() == 2 {
+                    self.marks.clear();
+                } else {
+                    self.marks.push(self.cursor);
+                }
+            }
+            KeyCode::Char('p') => {
+                self.profile = !self.profile;
+            }
+            KeyCode::Char('?') => {
+                self.help = !self.help;
+            }
+            KeyCode::Char(':') => {
+                self.cmdline = Some(String::new());
+            }
+            KeyCode::Char('R') => match RuleEditor::new(self.sim.world.rule().as_ref()) {
+                Ok(editor) => self.rule_editor = Some(editor),
+                Err(e) => self.message.show(e.to_string()),
+            },
+            KeyCode::Char('P') => match library::catalog(self.library.as_ref(), None) {
+                Ok(entries) => {
+                    let picker = Picker::new(entries);
+                    picker.load_previews(self.screen.picker_rows(), &mut self.previews);
+                    self.picker = Some(picker);
+                }
+                Err(e) => self.report(e),
+            },
+            KeyCode::Char('M') => {
+                let enabled = !self.screen.mouse_capture;
+                self.screen.set_mouse_capture(enabled)?;
+                self.message.show(if enabled {
+                    String::from("mouse capture on")
+                } else {
+                    String::from("mouse capture off (terminal text selection enabled)")
+                });
+            }
+            _ => (),
+        }
+        if self.spraying && self.cursor != cursor {
+            self.spray();
+        }
+        Ok(())
+    }
+
+    /// Raise cells at random around the cursor with the spray brush.
+    fn spray(&mut self) {
+        self.spray.apply(&mut self.sim.world, self.cursor);
+        self.count_clusters();
+    }
+
+    /// Left click moves the cursor (clicking the same spot again switches to the other cell in
+    /// that terminal row), right click toggles the cell under the cursor, and the wheel pans.
+    /// Clicking in a pane focuses it first.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        self.status = None;
+        self.kill_paste = None;
+        if matches!(mouse.kind, MouseEventKind::Down(_)) {
+            self.screen.focus_at(mouse.column, mouse.row);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some((x, y)) = self.screen.cell_at(mouse.column, mouse.row) {
+                    self.cursor = if self.cursor == (x, y) {
+                        (x, y + 1)
+                    } else {
+                        (x, y)
+                    };
+                    if self.spraying {
+                        self.spray();
+                    }
+                }
+            }
+            MouseEventKind::Down(MouseButton::Right) => {
+                self.sim.world.toggle(self.cursor.0, self.cursor.1);
+                self.count_clusters();
+            }
+            MouseEventKind::ScrollUp => self.screen.camera.y += 1,
+            MouseEventKind::ScrollDown => self.screen.camera.y -= 1,
+            MouseEventKind::ScrollLeft => self.screen.camera.x -= 1,
+            MouseEventKind::ScrollRight => self.screen.camera.x += 1,
+            _ => (),
+        }
+    }
+}
+
+type Position = (i32, i32);
+
+/// A rule declared by a loaded pattern, with what was loaded and whether to resume running once
+/// the prompt is answered.
+#[derive(Debug)]
+struct RulePrompt {
+    rule: Arc<dyn Rule>,
+    context: String,
+    resume: bool,
+}
+
+/// A paste from the kill ring: which pattern, in which generation, and the cells it raised.
+#[derive(Debug)]
+struct KillPaste {
+    index: usize,
+    generation: usize,
+    raised: Vec<Position>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::rule::WireWorld;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn throttles_the_status_line_while_running() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider"], 40, 20)?;
+        harness.ticks(3)?;
+        assert!(harness.status().starts_with("alive: 5, generations: 0"));
+        harness
+            .program
+            .refresh_status(Instant::now() + crate::STATUS_INTERVAL);
+        assert!(harness.status().starts_with("alive: 5, generations: 3"));
+        // Input and pausing bring it up to date straight away.
+        harness.tick()?.press(KeyCode::F(1))?;
+        assert!(harness.status().starts_with("alive: 5, generations: 4"));
+        harness.tick()?.program.sim.handle(crate::Command::Pause)?;
+        assert!(harness.status().starts_with("alive: 5, generations: 5"));
+        Ok(())
+    }
+
+    #[test]
+    fn tracks_the_envelope() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "blinker", "--build"], 40, 20)?;
+        harness.type_keys(":history reset\n")?;
+        assert!(harness.status().starts_with("error: history is off"));
+        harness.press(KeyCode::Esc)?.type_keys("H.")?;
+        assert!(harness.status().ends_with("envelope: 5 cells in 3x3"));
+        harness.type_keys(":history reset\n")?;
+        assert_eq!(harness.status(), "reset the history");
+        harness.press(KeyCode::F(1))?;
+        assert!(harness.status().ends_with("envelope: 3 cells in 3x1"));
+        Ok(())
+    }
+
+    #[test]
+    fn auto_pan_follows_gliders() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider"], 20, 10)?;
+        harness.type_keys("f")?.ticks(400)?;
+        let (min, max) = harness.program.screen.inner_region(0);
+        assert_eq!(harness.program.sim.world.cells_in(min, max).count(), 5);
+        assert!(harness.program.screen.camera.x > 50);
+        Ok(())
+    }
+
+    #[test]
+    fn moves_between_camera_and_cursor() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        harness.type_keys("ddddwwwllkk")?;
+        let center = harness.program.screen.center();
+        assert_eq!(harness.program.cursor, (4, 3));
+        harness.type_keys("x")?;
+        assert_eq!(harness.program.cursor, center);
+        assert_eq!(harness.program.screen.center(), (4, 2));
+        // The view can only center on even rows, so swapping back rounds the cursor down.
+        harness.type_keys("x")?;
+        assert_eq!(harness.program.cursor, (4, 2));
+        assert_eq!(harness.program.screen.center(), center);
+        harness.type_keys("wC")?;
+        assert_eq!(harness.program.screen.center(), (4, 2));
+        harness.type_keys("c")?;
+        assert_eq!(harness.program.cursor, (4, 2));
+        Ok(())
+    }
+
+    #[test]
+    fn switches_between_named_views() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        let camera = |harness: &Harness| {
+            let camera = &harness.program.screen.camera;
+            (camera.x, camera.y)
+        };
+        harness.type_keys("v")?;
+        assert!(harness.status().starts_with("no views"));
+        let start = camera(&harness);
+        harness.type_keys(":view save start\nlllll:view save east\n")?;
+        let east = camera(&harness);
+        assert_ne!(east, start);
+        harness.type_keys(":view start\n")?;
+        assert_eq!(camera(&harness), start);
+        harness.type_keys(":view\n")?;
+        assert_eq!(harness.status(), "views: east, start");
+        harness.type_keys("vv")?;
+        assert_eq!(harness.status(), "view start");
+        assert_eq!(camera(&harness), start);
+        harness.press(KeyCode::F(1))?.type_keys("v")?;
+        assert_eq!(harness.status(), "view east");
+        assert_eq!(camera(&harness), east);
+        harness.type_keys(":view west\n")?;
+        assert!(harness.status().starts_with("error: no view named west"));
+        Ok(())
+    }
+
+    #[test]
+    fn pages_through_the_world() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        let (columns, rows) = harness.program.screen.view_size();
+        let camera = |harness: &Harness| {
+            let camera = &harness.program.screen.camera;
+            (camera.x, camera.y)
+        };
+        harness.press(KeyCode::PageUp)?.press(KeyCode::End)?;
+        assert_eq!(camera(&harness), (columns, rows));
+        harness
+            .press_ctrl('d')?
+            .press_ctrl('d')?
+            .press(KeyCode::Home)?;
+        assert_eq!(camera(&harness), (0, 0));
+        harness.press_ctrl('u')?.press(KeyCode::PageDown)?;
+        assert_eq!(camera(&harness), (0, rows / 2 - rows));
+        // Without Ctrl, `d` still moves the cursor.
+        harness.type_keys("d")?;
+        assert_eq!(harness.program.cursor, (1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn saves_rle() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-save-{}", std::process::id()));
+        let args = [
+            "-p",
+            "glider",
+            "--build",
+            "--snapshot-dir",
+            dir.to_str().unwrap(),
+        ];
+        let mut harness = Harness::new(&args, 40, 20)?;
+        harness.type_keys("..S")?;
+        let path = dir.join("gen-00000002.rle");
+        assert_eq!(harness.status(), format!("saved {}", path.display()));
+        let saved = std::fs::read_to_string(path)?;
+        std::fs::remove_dir_all(&dir)?;
+        assert!(saved.ends_with(&harness.program.sim.world.to_rle()));
+        Ok(())
+    }
+
+    #[test]
+    fn names_patterns_by_apgcode() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "xs6_696", "--build"], 40, 20)?;
+        assert_eq!(harness.cells().len(), 6);
+        harness.type_keys(":apgcode\n")?;
+        assert_eq!(harness.status(), "apgcode: xs6_696");
+        harness.type_keys("e:apgcode\n")?;
+        assert!(harness
+            .status()
+            .starts_with("error: the pattern has not stabilized"));
+        Ok(())
+    }
+
+    #[test]
+    fn snaps_placed_patterns_to_a_lattice() -> Result<()> {
+        let mut harness = Harness::new(&["--build", "--snap", "8"], 40, 20)?;
+        harness.type_keys("dddwwwwwwwwwww")?;
+        harness.program.paste_text("2o!")?;
+        assert_eq!(harness.cells(), vec![(0, 8), (1, 8)]);
+        assert_eq!(harness.status(), "merged 2 cells from the clipboard at 0,8");
+        harness.type_keys("aaaassssssssssssss")?;
+        harness.program.paste_text("o!")?;
+        assert_eq!(harness.cells(), vec![(-8, -8), (0, 8), (1, 8)]);
+        harness.type_keys(":snap off\n")?;
+        harness.program.paste_text("o!")?;
+        assert_eq!(harness.cells(), vec![(-8, -8), (-1, -3), (0, 8), (1, 8)]);
+        Ok(())
+    }
+
+    #[test]
+    fn lays_down_wireworld_circuits() -> Result<()> {
+        let mut harness = Harness::new(&["--rule-name", "wireworld", "--build"], 40, 20)?;
+        harness.type_keys("ii")?;
+        assert_eq!(harness.status(), "painting wire");
+        harness.type_keys("ededede")?;
+        assert_eq!(harness.program.sim.world.dying().count(), 4);
+        // An electron head placed on the wire runs along it.
+        harness.type_keys("iaaae..")?;
+        assert_eq!(harness.cells(), vec![(2, 0)]);
+        assert_eq!(harness.program.sim.world.decay(1, 0), WireWorld::TAIL);
+        assert!(harness.status().contains("rule: WireWorld"));
+        let session = harness.program.session();
+        assert_eq!(session.dying, [((0, 0), 2), ((1, 0), 1), ((3, 0), 2)]);
+        assert!(harness.program.sim.world.to_rle().ends_with("CBAC!\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn continues_generation_numbering() -> Result<()> {
+        let args = ["-p", "glider", "--start-generation", "100", "--build"];
+        let mut harness = Harness::new(&args, 40, 20)?;
+        harness.type_keys(".")?;
+        assert!(harness.status().contains("generations: 101,"));
+        assert_eq!(harness.program.session().generations, 101);
+        Ok(())
+    }
+    #[test]
+    fn asks_before_switching_to_a_declared_rule() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("rust-life-rule-{}.rle", std::process::id()));
+        std::fs::write(&path, "x = 3, y = 1, rule = B36/S23\n3o!\n")?;
+        let file = path.to_str().unwrap();
+        let declined = Harness::new(&["-f", file], 40, 20);
+        let accepted = Harness::new(&["-f", file], 40, 20);
+        std::fs::remove_file(&path)?;
+
+        // Declined, the pattern runs under Life once the prompt is answered.
+        let mut harness = declined?;
+        assert_eq!(harness.state(), State::Paused);
+        assert_eq!(
+            harness.status(),
+            format!(
+                "loaded {}, which is for rule B36/S23: switch to it? (y/n)",
+                file
+            )
+        );
+        harness.type_keys(".")?;
+        assert_eq!(harness.generations(), 0);
+        harness.press(KeyCode::Esc)?;
+        assert_eq!(harness.status(), "kept rule B3/S23");
+        assert_eq!(harness.state(), State::Running);
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B3/S23");
+
+        // Accepted, a later paste under another rule pauses the world to ask again.
+        let mut harness = accepted?;
+        harness.type_keys("y")?;
+        assert_eq!(harness.status(), "rule: B36/S23");
+        assert_eq!(harness.state(), State::Running);
+        harness
+            .program
+            .paste_text("x = 1, y = 1, rule = B2/S\no!")?;
+        assert_eq!(harness.state(), State::Paused);
+        harness.type_keys("y")?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B2/S");
+        assert_eq!(harness.state(), State::Running);
+        Ok(())
+    }
+    #[test]
+    fn reloads_the_theme_keys_and_defaults() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("rust-life-reload-{}", std::process::id()));
+        std::fs::write(&path, "timestep 50ms\n")?;
+        let mut harness = Harness::new(&["--build", "--timestep", "200ms"], 40, 20)?;
+        harness.program.config = Config::load(Some(&path))?;
+        harness.program.config_watcher = Some(config::Watcher::new(Some(&path)));
+        std::fs::write(
+            &path,
+            "timestep 50ms\ncolor cursor red\nmap n d\nrulers on\n",
+        )?;
+        let later = Instant::now() + 2 * config::CHECK_INTERVAL;
+        let reloaded = harness.program.reload_config(later);
+        std::fs::remove_file(&path)?;
+
+        assert!(reloaded);
+        assert_eq!(harness.status(), "reloaded the config");
+        assert_eq!(
+            harness.program.screen.theme.cursor,
+            crossterm::style::Color::Red
+        );
+        assert!(harness.program.rulers);
+        // The timestep setting didn't change, so the one from the command line stays.
+        assert_eq!(harness.program.sim.timestep(), Duration::from_millis(200));
+        harness.type_keys("n")?;
+        assert_eq!(harness.program.cursor, (1, 0));
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+use crate::config::Config;
+use crate::formats::{self, Pattern};
+use crate::index::{Bounds, Index};
+use crate::{LifePattern, LifeWorld, PatternInfo};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The comment prefix for a pattern's tags, e.g. `#C tags: oscillator, p2`.
+const TAGS_PREFIX: &str = "tags:";
+
+/// The user's pattern library: directories of pattern files, each named after its pattern.
+/// Patterns are saved to the first directory, and earlier directories win when two hold a pattern
+/// of the same name.
+#[derive(Debug)]
+pub struct Library {
+    dirs: Vec<PathBuf>,
+}
+
+/// A `--pattern` argument: a built-in pattern, an apgcode, or the name of one in the library.
+#[derive(Clone, Debug)]
+pub enum PatternName {
+    BuiltIn(LifePattern),
+    Apgcode(String),
+    Library(String),
+}
+
+impl FromStr for PatternName {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<PatternName> {
+        Ok(match LifePattern::from_str(s, true) {
+            Ok(pattern) => PatternName::BuiltIn(pattern),
+            Err(_) if formats::is_apgcode(s) => PatternName::Apgcode(s.to_string()),
+            Err(_) => PatternName::Library(s.to_string()),
+        })
+    }
+}
+
+/// A pattern in a listing.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct Entry {
+    pub name: String,
+    pub tags: Vec<String>,
+    /// The pattern's bounding box, if known: it is recorded for library patterns that aren't
+    /// empty.
+    pub bounds: Option<Bounds>,
+    /// The file the pattern is stored in, or `None` for the built-in patterns.
+    pub path: Option<PathBuf>,
+}
+
+impl Entry {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// The rule, discoverer and period of a built-in pattern.
+    pub fn info(&self) -> Option<PatternInfo> {
+        match self.path {
+            Some(_) => None,
+            None => LifePattern::from_str(&self.name, true)
+                .ok()
+                .map(|pattern| pattern.info()),
+        }
+    }
+
+    pub fn load(&self) -> Result<Pattern> {
+        match &self.path {
+            Some(path) => formats::load_pattern(path),
+            None => {
+                let pattern = LifePattern::from_str(&self.name, true).map_err(|e| anyhow!(e))?;
+                Ok(Pattern::from(&pattern))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.tags.is_empty() {
+            write!(f, " [{}]", self.tags.join(", "))?;
+        }
+        if let Some(((x0, y0), (x1, y1))) = self.bounds {
+            write!(f, " {}x{}", x1 - x0 + 1, y1 - y0 + 1)?;
+        }
+        match &self.path {
+            Some(path) => write!(f, " ({})", path.display()),
+            None => write!(f, " (built in)"),
+        }
+    }
+}
+
+impl Library {
+    /// The library in `dir`, or if none is given, the `patterns` directories in the config and
+    /// data directories (`~/.config/rust-life/patterns` and `~/.local/share/rust-life/patterns`).
+    pub fn open(dir: Option<PathBuf>) -> Option<Library> {
+        let dirs: Vec<PathBuf> = match dir {
+            Some(dir) => vec![dir],
+            None => [Config::dir(), Config::data_dir()]
+                .into_iter()
+                .flatten()
+                .map(|dir| dir.join("patterns"))
+                .collect(),
+        };
+        (!dirs.is_empty()).then_some(Library { dirs })
+    }
+
+    /// Save a pattern as `<name>.rle`, with the name, tags and `comments` as metadata. An
+    /// existing pattern of the same name is never overwritten.
+    pub fn save(
+        &self,
+        name: &str,
+        world: &LifeWorld,
+        tags: &[String],
+        comments: &[String],
+    ) -> Result<PathBuf> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(anyhow!("invalid pattern name: {:?}", name));
+        }
+        let dir = &self.dirs[0];
+        let path = dir.join(format!("{}.rle", name));
+        if path.exists() {
+            return Err(anyhow!(
+                "{} is already in the library ({})",
+                name,
+                path.display()
+            ));
+        }
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let mut contents = format!("#N {}\n", name);
+        if !tags.is_empty() {
+            contents += &format!("#C {} {}\n", TAGS_PREFIX, tags.join(", "));
+        }
+        for comment in comments {
+            contents += &format!("#C {}\n", comment);
+        }
+        contents += &world.to_rle();
+        fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// The RLE and plaintext patterns in the library, sorted by name. Missing directories are
+    /// skipped.
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        let mut entries: Vec<Entry> = Vec::new();
+        for dir in &self.dirs {
+            for entry in scan(dir)? {
+                if !entries.iter().any(|e| e.name == entry.name) {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
+
+/// The RLE and plaintext patterns in a directory, as recorded in its index. Files changed since
+/// they were indexed are listed as they were and indexed again in the background.
+fn scan(dir: &Path) -> Result<Vec<Entry>> {
+    let (index, stale) = Index::update(dir)?;
+    let entries = index
+        .summaries()
+        .map(|(file, summary)| {
+            let path = dir.join(file);
+            Entry {
+                name: path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                tags: summary.tags.clone(),
+                bounds: summary.bounds,
+                path: Some(path),
+            }
+        })
+        .collect();
+    if !stale.is_empty() {
+        index.refresh_in_background(dir.to_path_buf(), stale);
+    }
+    Ok(entries)
+}
+
+/// The built-in patterns followed by those in the library, keeping only the ones tagged `tag` if
+/// one is given.
+pub fn catalog(library: Option<&Library>, tag: Option<&str>) -> Result<Vec<Entry>> {
+    let mut entries: Vec<Entry> = LifePattern::value_variants()
+        .iter()
+        .filter_map(|pattern| {
+            Some(Entry {
+                name: pattern.to_possible_value()?.get_name().to_string(),
+                tags: pattern.tags().iter().map(|tag| tag.to_string()).collect(),
+                bounds: None,
+                path: None,
+            })
+        })
+        .collect();
+    if let Some(library) = library {
+        entries.extend(library.entries()?);
+    }
+    entries.retain(|entry| tag.is_none_or(|tag| entry.has_tag(tag)));
+    Ok(entries)
+}
+
+/// The pattern called `name`, built in or in the library.
+pub fn find(library: Option<&Library>, name: &str) -> Result<Entry> {
+    catalog(library, None)?
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| anyhow!("no pattern called {} (see --list-patterns)", name))
+}
+
+/// The tags in an RLE (`#C tags: ...`) or plaintext (`!tags: ...`) comment.
+pub fn read_tags(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("#C").or_else(|| line.strip_prefix('!')))
+        .filter_map(|comment| comment.trim().strip_prefix(TAGS_PREFIX))
+        .flat_map(|tags| tags.split([',', ' ']))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats;
+    use crate::harness::Harness;
+
+    #[test]
+    fn saves_named_patterns() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-library-{}", std::process::id()));
+        let library = Library::open(Some(dir.clone())).unwrap();
+        let glider = LifeWorld::from(&LifePattern::Glider);
+        let tags = [String::from("spaceship"), String::from("c/4")];
+        let path = library.save("glider", &glider, &tags, &[String::from("generation 0")])?;
+        fs::write(dir.join("blinker.cells"), "!tags: oscillator\nOOO\n")?;
+        let contents = fs::read_to_string(&path)?;
+        let loaded = formats::load(&path)?;
+        let again = library.save("glider", &glider, &[], &[]);
+        let spaceships = catalog(Some(&library), Some("Spaceship"))?;
+        fs::remove_dir_all(&dir)?;
+
+        assert!(
+            contents.starts_with("#N glider\n#C tags: spaceship, c/4\n#C generation 0\nx = 3"),
+            "{}",
+            contents
+        );
+        assert_eq!(loaded.num_alive(), 5);
+        assert!(again.is_err());
+        assert!(library.save("../escape", &glider, &[], &[]).is_err());
+        assert!(library.save("", &glider, &[], &[]).is_err());
+        assert_eq!(
+            spaceships.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            vec![
+                String::from("glider [spaceship] (built in)"),
+                String::from("lwss [spaceship] (built in)"),
+                String::from("brain-ship [spaceship] (built in)"),
+                format!("glider [spaceship, c/4] 3x3 ({})", path.display()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reads_tags() {
+        assert_eq!(
+            read_tags("#N x\n#C tags: gun,  p30\nbo!"),
+            vec!["gun", "p30"]
+        );
+        assert_eq!(
+            read_tags("!Name: x\n!tags: oscillator\nOO"),
+            vec!["oscillator"]
+        );
+        assert!(read_tags("#C no tags here\n").is_empty());
+        assert!(catalog(None, Some("oscillator"))
+            .unwrap()
+            .iter()
+            .all(|e| e.path.is_none()));
+    }
+
+    #[test]
+    fn stores_the_selection_in_the_library() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-store-{}", std::process::id()));
+        let args = ["--build", "--library", dir.to_str().unwrap()];
+        let mut harness = Harness::new(&args, 40, 20)?;
+        // Two blocks of cells, with only the first one selected.
+        harness.type_keys("medeweaedm")?.type_keys("dddddese")?;
+        assert_eq!(harness.cells().len(), 6);
+        harness.type_keys(":store pair\n")?;
+        let path = dir.join("pair.rle");
+        assert_eq!(
+            harness.status(),
+            format!("stored 4 cells as pair in {}", path.display())
+        );
+        let stored = crate::formats::load(&path);
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(stored?.num_alive(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn switches_to_the_rule_of_built_in_patterns() -> Result<()> {
+        let harness = Harness::new(&["-p", "replicator"], 40, 20)?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B36/S23");
+        let harness = Harness::new(&["-p", "replicator", "--rule", "B3/S23"], 40, 20)?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B3/S23");
+        // Placed in a world, the replicator offers to bring its rule along.
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        harness
+            .type_keys("P")?
+            .type_keys(&"j".repeat(11))?
+            .type_keys("\n")?;
+        assert!(harness
+            .status()
+            .starts_with("merged 12 cells from replicator at 0,0, which is for rule B36/S23"));
+        harness.type_keys("y")?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B36/S23");
+        harness.type_keys("P")?.type_keys("j\n")?.type_keys("n")?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B36/S23");
+        Ok(())
+    }
+}
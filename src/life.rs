@@ -1,37 +1,278 @@
-use rand::random;
-use rustc_hash::FxHashMap;
+use crate::analysis;
+use crate::formats;
+use crate::grid::Grid;
+use crate::rule::{self, Elementary, Rule};
+use crate::topology::Topology;
+use crate::turmite::{Ant, Heading, Turmite};
+use clap::ValueEnum;
+use rand::rngs::StdRng;
+use rand::{random, Rng, SeedableRng};
+use std::fmt::Debug;
+use std::sync::{Arc, OnceLock};
+
+/// The largest factor the command line scales patterns up by, which keeps the copy
+/// [`LifeWorld::scaled`] makes within reach of memory.
+pub const MAX_SCALE: u32 = 256;
+
+/// The state stored in each cell of a [`LifeWorld`].
+///
+/// The default world uses `bool`, but any type implementing this trait can be used to attach
+/// extra data (a team, an age, an energy level) to cells and to define how it evolves.
+pub trait CellState: Clone + Debug + Default + PartialEq {
+    /// The state given to cells created by `raise` and `toggle`.
+    fn raised() -> Self;
+
+    /// Whether the cell counts as alive for neighbor counting and rendering.
+    fn is_alive(&self) -> bool;
+
+    /// Compute the state of the cell in the next generation.
+    fn next(&self, neighbors: &Neighbors<Self>) -> Self;
+}
+
+impl CellState for bool {
+    fn raised() -> bool {
+        true
+    }
+
+    fn is_alive(&self) -> bool {
+        *self
+    }
+
+    fn next(&self, neighbors: &Neighbors<bool>) -> bool {
+        let rule = neighbors.rule();
+        if rule.by_arrangement() {
+            rule.next_state_in(*self, neighbors.arrangement())
+        } else {
+            rule.next_state(*self, neighbors.count())
+        }
+    }
+}
+
+/// A view of the live cells surrounding a cell, handed to [`CellState::next`] along with the
+/// world's rule.
+///
+/// The count is cached so it is free, while the states of the neighbors are only looked up when
+/// iterated.
+pub struct Neighbors<'a, S> {
+    cells: &'a Grid<LifeCell<S>>,
+    rule: &'a dyn Rule,
+    topology: Topology,
+    x: i32,
+    y: i32,
+    count: u8,
+}
+
+impl<'a, S: CellState> Neighbors<'a, S> {
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    /// The rule of the world the cell is in.
+    pub fn rule(&self) -> &'a dyn Rule {
+        self.rule
+    }
+
+    /// Which of the eight surrounding cells are alive, as bits in the order described at
+    /// [`Rule::next_state_in`].
+    pub fn arrangement(&self) -> u8 {
+        const OFFSETS: [(i32, i32); 8] = [
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+            (-1, 0),
+            (1, 0),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+        ];
+        let mut arrangement = 0;
+        for (bit, (dx, dy)) in OFFSETS.iter().enumerate() {
+            let pos = self.wrap((self.x + dx, self.y + dy));
+            if pos
+                .and_then(|pos| self.cells.get(pos))
+                .is_some_and(|cell| cell.alive())
+            {
+                arrangement |= 1 << bit;
+            }
+        }
+        arrangement
+    }
+
+    /// The cell that a position next to this one is on the world's topology, if it is not off
+    /// the edge or, where edges meet at a corner of a cross-surface or sphere, this cell itself.
+    fn wrap(&self, pos: (i32, i32)) -> Option<(i32, i32)> {
+        self.topology
+            .wrap(pos)
+            .filter(|&pos| pos != (self.x, self.y))
+    }
+
+    /// The live cells in the rule's neighborhood, row by row from the lowest.
+    pub fn iter(&self) -> impl Iterator<Item = &'a S> + '_ {
+        let radius = i32::from(self.rule.radius());
+        (-radius..=radius)
+            .flat_map(move |dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|&offset| offset != (0, 0))
+            .filter_map(|(dx, dy)| {
+                self.wrap((self.x + dx, self.y + dy))
+                    .and_then(|pos| self.cells.get(pos))
+                    .map(|cell| &cell.state)
+                    .filter(|state| state.is_alive())
+            })
+    }
+}
 
 #[derive(PartialEq, Clone, Debug)]
-pub struct LifeCell {
-    pub alive: bool,
+pub struct LifeCell<S = bool> {
+    pub state: S,
     pub num_neighbors: u8,
+    /// How far a dead cell is through the dying states of a multi-state rule (see
+    /// [`Rule::states`]): 1 in the generation after it dies, counting up until it is fully dead
+    /// again at 0.
+    pub decay: u8,
 }
 
-impl LifeCell {
-    pub fn new(alive: bool) -> LifeCell {
+impl<S: CellState> LifeCell<S> {
+    pub fn new(state: S) -> LifeCell<S> {
         LifeCell {
-            alive,
+            state,
             num_neighbors: 0,
+            decay: 0,
+        }
+    }
+
+    pub fn alive(&self) -> bool {
+        self.state.is_alive()
+    }
+
+    /// Whether the cell is dead and has nothing to track, so it needn't be stored.
+    fn is_blank(&self) -> bool {
+        self.num_neighbors == 0 && self.decay == 0 && !self.alive()
+    }
+}
+
+/// The number of cells that came alive and died in a generation.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct Changes {
+    pub births: usize,
+    pub deaths: usize,
+}
+
+/// A summary of the living cells of a world, from [`LifeWorld::stats`].
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct WorldStats {
+    pub generation: usize,
+    pub population: usize,
+    /// The smallest rectangle containing every living cell, as inclusive `(min, max)` corners.
+    pub bounding_box: Option<((i32, i32), (i32, i32))>,
+    /// The share of the bounding box that is alive, from 0 to 1 (0 for an empty world).
+    pub density: f64,
+    /// The number of groups of touching living cells (see
+    /// [`analysis::clusters`](crate::analysis::clusters)).
+    pub clusters: usize,
+    pub largest_cluster: usize,
+}
+
+/// The parts of [`WorldStats`] that take a pass over the cells to work out.
+#[derive(Clone, Copy, Debug)]
+struct Shape {
+    bounding_box: Option<((i32, i32), (i32, i32))>,
+    clusters: usize,
+    largest_cluster: usize,
+}
+
+/// Something that happened to a world while it evolved.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum WorldEvent {
+    Birth(i32, i32),
+    Death(i32, i32),
+    /// A generation finished, with the total births and deaths that occurred during it.
+    Generation {
+        generation: usize,
+        changes: Changes,
+    },
+}
+
+/// Identifies a callback registered with [`LifeWorld::on_event`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ObserverId(usize);
+
+type Observer = Box<dyn FnMut(&WorldEvent) + Send>;
+
+/// The callbacks registered on a world. Callbacks belong to the world they were registered on,
+/// so cloning a world does not copy them.
+#[derive(Default)]
+struct Observers {
+    next_id: usize,
+    callbacks: Vec<(ObserverId, Observer)>,
+}
+
+impl Observers {
+    fn notify(&mut self, event: &WorldEvent) {
+        for (_, callback) in &mut self.callbacks {
+            callback(event);
         }
     }
 }
 
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Observers::default()
+    }
+}
+
+impl Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Observers({})", self.callbacks.len())
+    }
+}
+
 #[derive(Clone, Debug)]
-pub struct LifeWorld {
-    active_cells: FxHashMap<(i32, i32), LifeCell>,
+pub struct LifeWorld<S = bool> {
+    active_cells: Grid<LifeCell<S>>,
     pub generations: usize,
+    rule: Arc<dyn Rule>,
+    topology: Topology,
+    observers: Observers,
+    /// The number of living cells, kept up to date as they change.
+    population: usize,
+    /// The shape of the living cells, worked out when first asked for after they change.
+    shape: OnceLock<Shape>,
+    /// The ants walking over the world, which only move under a turmite rule.
+    ants: Vec<Ant>,
+    /// The row holding the current generation under an elementary rule, found from the lowest
+    /// live row when the world first evolves under one.
+    elementary_row: Option<i32>,
 }
 
-impl LifeWorld {
-    pub fn new() -> LifeWorld {
+impl<S: CellState> Default for LifeWorld<S> {
+    fn default() -> Self {
         LifeWorld {
-            active_cells: FxHashMap::default(),
+            active_cells: Grid::default(),
             generations: 0,
+            rule: rule::life(),
+            topology: Topology::Plane,
+            observers: Observers::default(),
+            population: 0,
+            shape: OnceLock::new(),
+            ants: Vec::new(),
+            elementary_row: None,
         }
     }
+}
+
+impl LifeWorld {
+    pub fn new() -> LifeWorld {
+        LifeWorld::default()
+    }
 
     pub fn from(pattern: &LifePattern) -> LifeWorld {
-        let mut world = LifeWorld::new();
+        LifeWorld::with_pattern(pattern)
+    }
+}
+
+impl<S: CellState> LifeWorld<S> {
+    pub fn with_pattern(pattern: &LifePattern) -> LifeWorld<S> {
+        let mut world = LifeWorld::default();
         match pattern {
             LifePattern::Blank => (),
             LifePattern::Glider => {
@@ -57,140 +298,743 @@ impl LifeWorld {
                 world.raise(3, 3);
             }
             LifePattern::Random(size) => {
-                let side = (*size as f64).sqrt().round() as i32;
-                for _ in 0..*size {
-                    let x = random::<i32>() % side;
-                    let y = random::<i32>() % side;
-                    world.raise(x, y);
+                world = LifeWorld::random(*size, random());
+            }
+            LifePattern::GosperGun
+            | LifePattern::Pulsar
+            | LifePattern::Lwss
+            | LifePattern::RPentomino
+            | LifePattern::Acorn
+            | LifePattern::Pentadecathlon
+            | LifePattern::Replicator
+            | LifePattern::BrainShip => {
+                let rle = pattern.rle().unwrap_or_default();
+                let cells = formats::rle_states(rle).expect("built-in patterns are valid RLE");
+                for ((x, y), state) in cells {
+                    match state {
+                        1 => world.raise(x, y),
+                        _ => world.set_dying(x, y, (state - 1) as u8),
+                    }
                 }
             }
         }
+        let rule = pattern.info().rule;
+        if rule != "B3/S23" {
+            world.set_rule(rule::parse(rule).expect("built-in patterns have valid rules"));
+        }
         world
     }
 
+    /// Build a random soup of `size` cells. The same seed always produces the same soup.
+    pub fn random(size: usize, seed: u64) -> LifeWorld<S> {
+        let mut world = LifeWorld::default();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let side = (size as f64).sqrt().round().max(1.) as i32;
+        for _ in 0..size {
+            let x = rng.r#gen::<i32>() % side;
+            let y = rng.r#gen::<i32>() % side;
+            world.raise(x, y);
+        }
+        world
+    }
+
+    /// The rule the world evolves by, Conway's Life unless changed with
+    /// [`set_rule`](LifeWorld::set_rule).
+    pub fn rule(&self) -> &Arc<dyn Rule> {
+        &self.rule
+    }
+
+    /// Change the rule the world evolves by from the next generation on.
+    pub fn set_rule(&mut self, rule: Arc<dyn Rule>) {
+        let recount = rule.radius() != self.rule.radius();
+        self.rule = rule;
+        self.elementary_row = None;
+        if recount {
+            // The neighbor counts cover the old neighborhood, so start again from the cells.
+            self.rebuild();
+        }
+    }
+
+    /// The row the current generation is in under an elementary rule, once the world has evolved
+    /// under it. Each generation after is written one row lower.
+    pub fn elementary_row(&self) -> Option<i32> {
+        self.elementary_row
+    }
+
+    /// The space the world's cells live in, the unbounded plane unless changed with
+    /// [`set_topology`](LifeWorld::set_topology).
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Change the space the world's cells live in. On a torus, cells outside its rectangle are
+    /// moved to the positions they wrap to, merging with any already there, and on a bounded
+    /// plane they are dropped, as are ants.
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.rebuild();
+        self.ants
+            .retain_mut(|ant| match topology.wrap(ant.position) {
+                Some(position) => {
+                    ant.position = position;
+                    true
+                }
+                None => false,
+            });
+    }
+
+    /// Add the live and dying cells again from scratch, recounting their neighbors.
+    fn rebuild(&mut self) {
+        let cells: Vec<_> = self
+            .active_cells
+            .iter()
+            .filter(|(_, cell)| cell.alive() || cell.decay > 0)
+            .map(|(pos, cell)| (pos, cell.state.clone(), cell.decay))
+            .collect();
+        self.active_cells = Grid::default();
+        self.population = 0;
+        self.shape.take();
+        for ((x, y), state, decay) in cells {
+            self.set_cell(x, y, state);
+            if let Some(pos) = self.topology.wrap((x, y)) {
+                self.set_decay(pos, decay);
+            }
+        }
+    }
+
     pub fn raise(&mut self, x: i32, y: i32) {
-        self.set_cell(x, y, true);
+        self.set_cell(x, y, S::raised());
     }
 
     pub fn lower(&mut self, x: i32, y: i32) {
-        self.set_cell(x, y, false);
+        self.set_cell(x, y, S::default());
     }
 
     pub fn toggle(&mut self, x: i32, y: i32) {
-        self.set_cell(x, y, !self.alive(x, y));
+        if self.alive(x, y) {
+            self.lower(x, y);
+        } else {
+            self.raise(x, y);
+        }
+    }
+
+    /// Set the cell at the given position to an arbitrary state.
+    pub fn set(&mut self, x: i32, y: i32, state: S) {
+        self.set_cell(x, y, state);
     }
 
     pub fn get(&self, x: i32, y: i32) -> Option<bool> {
-        self.active_cells.get(&(x, y)).map(|cell| cell.alive)
+        self.active_cells
+            .get(self.topology.wrap((x, y))?)
+            .map(|cell| cell.alive())
+    }
+
+    /// Get the full state of the cell at the given position, if it is being tracked.
+    pub fn state(&self, x: i32, y: i32) -> Option<&S> {
+        self.active_cells
+            .get(self.topology.wrap((x, y))?)
+            .map(|cell| &cell.state)
     }
 
     pub fn alive(&self, x: i32, y: i32) -> bool {
         self.get(x, y).unwrap_or(false)
     }
 
-    pub fn evolve(&mut self) {
-        let mut deltas = Vec::new();
-        for (pos, cell) in &self.active_cells {
-            match cell {
-                LifeCell {
-                    alive: true,
-                    num_neighbors: 2 | 3,
-                } => (),
-                LifeCell {
-                    alive: true,
-                    num_neighbors: _,
-                } => {
-                    deltas.push((false, *pos));
+    /// Copy the living and dying cells of `other` into this world, shifted by `offset`. Where
+    /// both worlds have a cell, the state from `other` wins.
+    pub fn merge(&mut self, other: &LifeWorld<S>, offset: (i32, i32)) {
+        let (dx, dy) = offset;
+        for (x, y) in other.cells() {
+            if let Some(state) = other.state(x, y) {
+                self.set_cell(x + dx, y + dy, state.clone());
+            }
+        }
+        for ((x, y), decay) in other.dying() {
+            self.set_dying(x + dx, y + dy, decay);
+        }
+    }
+
+    /// A copy of the world with each living and dying cell blown up into a `factor` by `factor`
+    /// block, e.g. for a zoomed "pixel art" version of a pattern. Cell `(x, y)` becomes the block
+    /// whose lower left corner is `(x * factor, y * factor)`, where its ants are put down too.
+    /// `None` if a block would fall outside the range of coordinates.
+    pub fn scaled(&self, factor: u32) -> Option<LifeWorld<S>> {
+        let factor = i32::try_from(factor.max(1)).ok()?;
+        let corner = |(x, y): (i32, i32)| {
+            let scale = |c: i32| {
+                c.checked_mul(factor)
+                    .filter(|c| c.checked_add(factor - 1).is_some())
+            };
+            Some((scale(x)?, scale(y)?))
+        };
+        let cells = self
+            .cells()
+            .filter_map(|(x, y)| Some(((x, y), self.state(x, y)?)))
+            .map(|(pos, state)| Some((corner(pos)?, state.clone())))
+            .collect::<Option<Vec<_>>>()?;
+        let dying = self
+            .dying()
+            .map(|(pos, decay)| Some((corner(pos)?, decay)))
+            .collect::<Option<Vec<_>>>()?;
+        let ants = self
+            .ants
+            .iter()
+            .map(|ant| {
+                Some(Ant {
+                    position: corner(ant.position)?,
+                    ..*ant
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let block = move |(x, y): (i32, i32)| {
+            (0..factor).flat_map(move |dy| (0..factor).map(move |dx| (x + dx, y + dy)))
+        };
+        // The copy lives in the same space under the same rule, which are set before any cells
+        // so that they wrap and count their neighbors as in this world.
+        let mut scaled = LifeWorld {
+            generations: self.generations,
+            rule: Arc::clone(&self.rule),
+            topology: self.topology,
+            ..LifeWorld::default()
+        };
+        for (corner, state) in cells {
+            for (x, y) in block(corner) {
+                scaled.set_cell(x, y, state.clone());
+            }
+        }
+        for (corner, decay) in dying {
+            for (x, y) in block(corner) {
+                scaled.set_dying(x, y, decay);
+            }
+        }
+        for ant in ants {
+            scaled.add_ant(ant);
+        }
+        Some(scaled)
+    }
+
+    /// Encode the living cells within the bounding box as RLE, with a header line, for sharing
+    /// with Golly and other simulators. See [`formats::to_rle`](crate::formats::to_rle).
+    pub fn to_rle(&self) -> String {
+        crate::formats::to_rle(self)
+    }
+
+    /// Draw the living cells within the bounding box as an SVG image, optionally with grid lines.
+    /// See [`formats::to_svg`](crate::formats::to_svg).
+    pub fn to_svg(&self, grid: bool) -> String {
+        crate::formats::to_svg(self, grid)
+    }
+
+    /// Keep only the living cells that are also alive in `other`, shifted by `offset`.
+    pub fn intersect(&mut self, other: &LifeWorld<S>, offset: (i32, i32)) {
+        let (dx, dy) = offset;
+        let outside: Vec<(i32, i32)> = self
+            .cells()
+            .filter(|&(x, y)| !other.alive(x - dx, y - dy))
+            .collect();
+        for (x, y) in outside {
+            self.lower(x, y);
+        }
+    }
+
+    /// Kill the cells that are alive in `other`, shifted by `offset`.
+    pub fn subtract(&mut self, other: &LifeWorld<S>, offset: (i32, i32)) {
+        let (dx, dy) = offset;
+        for (x, y) in other.cells() {
+            if self.alive(x + dx, y + dy) {
+                self.lower(x + dx, y + dy);
+            }
+        }
+    }
+
+    /// Iterate over the positions of all living cells.
+    pub fn cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.active_cells
+            .iter()
+            .filter(|(_, cell)| cell.alive())
+            .map(|(pos, _)| pos)
+    }
+
+    /// Iterate over the positions of the living cells in the inclusive region between `min` and
+    /// `max`, without visiting cells far outside of it. On a torus, the region may show several
+    /// copies of its rectangle, and the cells of each copy are included.
+    pub fn cells_in(
+        &self,
+        min: (i32, i32),
+        max: (i32, i32),
+    ) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.tiles_in(min, max)
+            .filter(|(_, cell)| cell.alive())
+            .map(|(pos, _)| pos)
+    }
+
+    /// The tracked cells in a region, with those of every copy of a torus's rectangle that
+    /// overlaps it at their positions in the copy.
+    fn tiles_in(
+        &self,
+        min: (i32, i32),
+        max: (i32, i32),
+    ) -> impl Iterator<Item = ((i32, i32), &LifeCell<S>)> + '_ {
+        self.topology
+            .tiles(min, max)
+            .into_iter()
+            .flat_map(|((dx, dy), (min, max))| {
+                self.active_cells
+                    .iter_region(min, max)
+                    .map(move |((x, y), cell)| ((x + dx, y + dy), cell))
+            })
+    }
+
+    /// Advance one generation: every cell changes by the rule, or under a turmite rule, every
+    /// ant takes a step, or under an elementary rule, the next row is written.
+    pub fn evolve(&mut self) -> Changes {
+        let rule = Arc::clone(&self.rule);
+        let changes = if let Some(turmite) = rule.turmite() {
+            self.move_ants(turmite)
+        } else if let Some(elementary) = rule.elementary() {
+            self.write_row(elementary)
+        } else {
+            self.apply_rule()
+        };
+        self.generations += 1;
+        self.observers.notify(&WorldEvent::Generation {
+            generation: self.generations,
+            changes,
+        });
+        changes
+    }
+
+    fn apply_rule(&mut self) -> Changes {
+        let mut changes = Changes::default();
+        for (state, decay, (x, y)) in self.deltas() {
+            let event = match (self.alive(x, y), state.is_alive()) {
+                (false, true) => {
+                    changes.births += 1;
+                    Some(WorldEvent::Birth(x, y))
                 }
-                LifeCell {
-                    alive: false,
-                    num_neighbors: 3,
-                } => {
-                    deltas.push((true, *pos));
+                (true, false) => {
+                    changes.deaths += 1;
+                    Some(WorldEvent::Death(x, y))
                 }
-                LifeCell {
-                    alive: false,
-                    num_neighbors: _,
-                } => (),
+                _ => None,
+            };
+            self.set_cell(x, y, state);
+            self.set_decay((x, y), decay);
+            if let Some(event) = event {
+                self.observers.notify(&event);
             }
         }
-        for (change, pos) in deltas {
-            let (x, y) = pos;
-            self.set_cell(x, y, change);
+        changes
+    }
+
+    /// Move each ant in turn: it turns, paints the cell it is on and the state it carries as
+    /// the turmite's table says, then steps forward. Ants that walk off a bounded plane are gone.
+    fn move_ants(&mut self, turmite: &Turmite) -> Changes {
+        let mut changes = Changes::default();
+        let mut ants = std::mem::take(&mut self.ants);
+        ants.retain_mut(|ant| {
+            let (x, y) = ant.position;
+            let color = self.color(x, y);
+            let transition = turmite.transition(ant.state, color);
+            ant.heading = ant.heading.turn(transition.turn);
+            ant.state = transition.state;
+            let event = match (color == 1, transition.color == 1) {
+                (false, true) => {
+                    changes.births += 1;
+                    Some(WorldEvent::Birth(x, y))
+                }
+                (true, false) => {
+                    changes.deaths += 1;
+                    Some(WorldEvent::Death(x, y))
+                }
+                _ => None,
+            };
+            self.paint(x, y, transition.color);
+            if let Some(event) = event {
+                self.observers.notify(&event);
+            }
+            let (dx, dy) = ant.heading.step();
+            let Some(next) = self.topology.wrap((x + dx, y + dy)) else {
+                return false;
+            };
+            // Across a twisted edge the ant comes back facing another way, which shows in where
+            // the cell after that lands.
+            if let Some((x2, y2)) = self.topology.wrap((x + 2 * dx, y + 2 * dy))
+                && let Some(heading) = Heading::from_step((x2 - next.0, y2 - next.1))
+            {
+                ant.heading = heading;
+            }
+            ant.position = next;
+            true
+        });
+        self.ants = ants;
+        changes
+    }
+
+    /// Work out the row below the current generation's from it by an elementary rule. Only the
+    /// cells next to live ones in the current row can be born, and any others already alive in
+    /// the next row die.
+    fn write_row(&mut self, elementary: &Elementary) -> Changes {
+        let mut changes = Changes::default();
+        // Rows are counted from wherever the pattern started, not from generation 0.
+        let lowest = || self.bounding_box().map(|((_, y), _)| y);
+        let Some(y) = self.elementary_row.or_else(lowest) else {
+            return changes;
+        };
+        self.elementary_row = Some(y.saturating_sub(1));
+        let row = |world: &Self, y: i32| -> Vec<i32> {
+            let Some((_, y)) = world.topology.wrap((0, y)) else {
+                return Vec::new();
+            };
+            let cells = world
+                .active_cells
+                .iter_row(y)
+                .filter(|(_, cell)| cell.alive());
+            cells.map(|((x, _), _)| x).collect()
+        };
+        let mut xs: Vec<i32> = row(self, y)
+            .iter()
+            .flat_map(|&x| [x - 1, x, x + 1])
+            .collect();
+        xs.extend(row(self, y - 1));
+        xs.sort_unstable();
+        xs.dedup();
+        for x in xs {
+            let (left, right) = (self.alive(x - 1, y), self.alive(x + 1, y));
+            let next = elementary.next(left, self.alive(x, y), right);
+            let event = match (self.alive(x, y - 1), next) {
+                (false, true) => {
+                    changes.births += 1;
+                    self.raise(x, y - 1);
+                    WorldEvent::Birth(x, y - 1)
+                }
+                (true, false) => {
+                    changes.deaths += 1;
+                    self.lower(x, y - 1);
+                    WorldEvent::Death(x, y - 1)
+                }
+                _ => continue,
+            };
+            self.observers.notify(&event);
+        }
+        changes
+    }
+
+    /// The state of the cell at a position as a turmite's cell color: 0 for dead, 1 for alive
+    /// and 2 onwards for the dying states.
+    fn color(&self, x: i32, y: i32) -> u8 {
+        match (self.alive(x, y), self.decay(x, y)) {
+            (true, _) => 1,
+            (false, 0) => 0,
+            (false, decay) => decay + 1,
+        }
+    }
+
+    /// Set the cell at a position to a turmite's cell color.
+    fn paint(&mut self, x: i32, y: i32, color: u8) {
+        match color {
+            1 => self.raise(x, y),
+            _ => self.set_dying(x, y, color.saturating_sub(1)),
+        }
+    }
+
+    /// The ants on the world, in the order they move.
+    pub fn ants(&self) -> &[Ant] {
+        &self.ants
+    }
+
+    /// Put an ant down, to move after those already there. Ants outside a bounded plane are
+    /// not added.
+    pub fn add_ant(&mut self, mut ant: Ant) {
+        if let Some(position) = self.topology.wrap(ant.position) {
+            ant.position = position;
+            self.ants.push(ant);
+        }
+    }
+
+    /// Take away the ants at a position, returning whether there were any.
+    pub fn remove_ants(&mut self, x: i32, y: i32) -> bool {
+        let before = self.ants.len();
+        let position = self.topology.wrap((x, y));
+        self.ants.retain(|ant| Some(ant.position) != position);
+        self.ants.len() != before
+    }
+
+    /// Advance `generations` generations, returning the total births and deaths.
+    pub fn evolve_n(&mut self, generations: usize) -> Changes {
+        let mut total = Changes::default();
+        for _ in 0..generations {
+            let changes = self.evolve();
+            total.births += changes.births;
+            total.deaths += changes.deaths;
+        }
+        total
+    }
+
+    /// Register a callback that is called with every birth and death as the world evolves, and
+    /// once more when each generation is complete. Births and deaths are reported in position
+    /// order; cells edited directly with `raise`, `lower`, `toggle` or `set` are not reported.
+    pub fn on_event<F>(&mut self, callback: F) -> ObserverId
+    where
+        F: FnMut(&WorldEvent) + Send + 'static,
+    {
+        let id = ObserverId(self.observers.next_id);
+        self.observers.next_id += 1;
+        self.observers.callbacks.push((id, Box::new(callback)));
+        id
+    }
+
+    /// Unregister a callback, returning whether it was registered.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        let before = self.observers.callbacks.len();
+        self.observers.callbacks.retain(|(other, _)| *other != id);
+        self.observers.callbacks.len() != before
+    }
+
+    /// Compute the cells that change in the next generation along with their new states and
+    /// decay.
+    ///
+    /// The changes are sorted by position so that they are applied in the same order regardless
+    /// of how the hashmap happens to be laid out, keeping runs reproducible.
+    fn deltas(&self) -> Vec<(S, u8, (i32, i32))> {
+        let dying_states = self.rule.states().saturating_sub(2);
+        let mut deltas = Vec::new();
+        for ((x, y), cell) in self.active_cells.iter() {
+            if cell.decay > 0 {
+                // Dying cells can't be born again until they have fully decayed, unless the rule
+                // revives them.
+                if self.rule.revives(cell.decay, cell.num_neighbors) {
+                    deltas.push((S::raised(), 0, (x, y)));
+                } else {
+                    let decay = self.rule.next_decay(cell.decay);
+                    if decay != cell.decay {
+                        deltas.push((cell.state.clone(), decay, (x, y)));
+                    }
+                }
+                continue;
+            }
+            let neighbors = Neighbors {
+                cells: &self.active_cells,
+                rule: &*self.rule,
+                topology: self.topology,
+                x,
+                y,
+                count: cell.num_neighbors,
+            };
+            let next = cell.state.next(&neighbors);
+            if next != cell.state {
+                let dies = cell.alive() && !next.is_alive();
+                let decay = if dies && dying_states > 0 { 1 } else { 0 };
+                deltas.push((next, decay, (x, y)));
+            }
+        }
+        deltas.sort_unstable_by_key(|&(_, _, pos)| pos);
+        deltas
+    }
+
+    /// Set how far the cell at `pos` is through the dying states.
+    fn set_decay(&mut self, pos: (i32, i32), decay: u8) {
+        if decay > 0 {
+            self.active_cells
+                .get_or_insert_with(pos, || LifeCell::new(S::default()))
+                .decay = decay;
+        } else if let Some(cell) = self.active_cells.get_mut(pos) {
+            cell.decay = 0;
+            if cell.is_blank() {
+                self.active_cells.remove(pos);
+            }
         }
-        self.generations += 1;
+    }
+
+    /// How far the cell at a position is through the dying states of a multi-state rule, or 0
+    /// if it is alive or fully dead.
+    pub fn decay(&self, x: i32, y: i32) -> u8 {
+        let cell = self
+            .topology
+            .wrap((x, y))
+            .and_then(|pos| self.active_cells.get(pos));
+        cell.map_or(0, |cell| cell.decay)
+    }
+
+    /// Put the cell at a position `decay` states into dying, as when drawing WireWorld wire,
+    /// killing it first if it is alive. A decay of 0 leaves it fully dead.
+    pub fn set_dying(&mut self, x: i32, y: i32, decay: u8) {
+        let Some(pos) = self.topology.wrap((x, y)) else {
+            return;
+        };
+        self.lower(x, y);
+        self.set_decay(pos, decay);
+    }
+
+    /// Iterate over the positions of the dying cells, with how far each is through the dying
+    /// states.
+    pub fn dying(&self) -> impl Iterator<Item = ((i32, i32), u8)> + '_ {
+        self.active_cells
+            .iter()
+            .filter(|(_, cell)| cell.decay > 0)
+            .map(|(pos, cell)| (pos, cell.decay))
+    }
+
+    /// Iterate over the dying cells in the inclusive region between `min` and `max`, with how far
+    /// each is through the dying states.
+    pub fn dying_in(
+        &self,
+        min: (i32, i32),
+        max: (i32, i32),
+    ) -> impl Iterator<Item = ((i32, i32), u8)> + '_ {
+        self.tiles_in(min, max)
+            .filter(|(_, cell)| cell.decay > 0)
+            .map(|(pos, cell)| (pos, cell.decay))
+    }
+
+    /// The smallest rectangle containing every living cell, as inclusive `(min, max)` corners.
+    pub fn bounding_box(&self) -> Option<((i32, i32), (i32, i32))> {
+        self.cells().fold(None, |bbox, (x, y)| match bbox {
+            None => Some(((x, y), (x, y))),
+            Some(((x0, y0), (x1, y1))) => Some(((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y)))),
+        })
+    }
+
+    /// The smallest rectangle containing every living and dying cell, which for a multi-state
+    /// rule like WireWorld can reach beyond the living cells.
+    pub fn extent(&self) -> Option<((i32, i32), (i32, i32))> {
+        let dying = self.dying().map(|(pos, _)| pos);
+        self.cells()
+            .chain(dying)
+            .fold(None, |bbox, (x, y)| match bbox {
+                None => Some(((x, y), (x, y))),
+                Some(((x0, y0), (x1, y1))) => {
+                    Some(((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y))))
+                }
+            })
     }
 
     pub fn num_alive(&self) -> i32 {
-        let mut count = 0;
-        for cell in self.active_cells.values() {
-            if cell.alive {
-                count += 1;
+        self.population as i32
+    }
+
+    /// The generation, population, bounding box, density and clusters of the world, for the
+    /// status line and stats streams to share. The population is kept up to date as cells
+    /// change, and the rest is worked out on the first call after a change and kept until the
+    /// next, so asking every generation costs one pass over the cells at most.
+    pub fn stats(&self) -> WorldStats {
+        let shape = *self.shape.get_or_init(|| {
+            let clusters = analysis::clusters(self);
+            let bounding_box = clusters.iter().map(|cluster| cluster.bounding_box).reduce(
+                |((x0, y0), (x1, y1)), ((u0, v0), (u1, v1))| {
+                    ((x0.min(u0), y0.min(v0)), (x1.max(u1), y1.max(v1)))
+                },
+            );
+            Shape {
+                bounding_box,
+                clusters: clusters.len(),
+                largest_cluster: clusters.first().map_or(0, analysis::Cluster::len),
             }
+        });
+        let area = shape.bounding_box.map_or(0, |((x0, y0), (x1, y1))| {
+            (i64::from(x1) - i64::from(x0) + 1) * (i64::from(y1) - i64::from(y0) + 1)
+        });
+        WorldStats {
+            generation: self.generations,
+            population: self.population,
+            bounding_box: shape.bounding_box,
+            density: if area > 0 {
+                self.population as f64 / area as f64
+            } else {
+                0.
+            },
+            clusters: shape.clusters,
+            largest_cluster: shape.largest_cluster,
         }
-        count
     }
 
-    fn set_cell(&mut self, x: i32, y: i32, alive: bool) {
+    fn set_cell(&mut self, x: i32, y: i32, state: S) {
+        let Some((x, y)) = self.topology.wrap((x, y)) else {
+            // Cells outside a bounded plane stay dead.
+            return;
+        };
+        let alive = state.is_alive();
         let dirty: bool;
         let mut new = false;
 
-        match self.active_cells.entry((x, y)) {
-            std::collections::hash_map::Entry::Occupied(mut occupied) => {
-                let cell = occupied.get_mut();
-                dirty = cell.alive != alive;
-                cell.alive = alive;
+        match self.active_cells.get_mut((x, y)) {
+            Some(cell) => {
+                dirty = cell.alive() != alive;
+                cell.state = state;
+                cell.decay = 0;
             }
-            std::collections::hash_map::Entry::Vacant(vacant) => {
-                vacant.insert(LifeCell::new(alive));
+            None => {
+                self.active_cells
+                    .get_or_insert_with((x, y), || LifeCell::new(state));
                 dirty = alive;
                 new = alive;
             }
         }
 
         if !dirty {
+            if self
+                .active_cells
+                .get((x, y))
+                .is_some_and(LifeCell::is_blank)
+            {
+                self.active_cells.remove((x, y));
+            }
             return;
         }
+        if alive {
+            self.population += 1;
+        } else {
+            self.population -= 1;
+        }
+        self.shape.take();
 
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-                if new && self.alive(x + dx, y + dy) {
-                    self.active_cells
-                        .entry((x, y))
-                        .and_modify(|cell| cell.num_neighbors += 1);
+        let mut live_neighbors = 0;
+        let mut update = |slot: &mut Option<LifeCell<S>>| {
+            if new && slot.as_ref().is_some_and(|cell| cell.alive()) {
+                live_neighbors += 1;
+            }
+            if alive {
+                slot.get_or_insert_with(|| LifeCell::new(S::default()))
+                    .num_neighbors += 1;
+            } else if let Some(cell) = slot {
+                cell.num_neighbors -= 1;
+                if cell.is_blank() {
+                    *slot = None;
                 }
-                if alive {
-                    let cell = self
-                        .active_cells
-                        .entry((x + dx, y + dy))
-                        .or_insert(LifeCell::new(false));
-                    cell.num_neighbors += 1;
-                } else {
-                    match self.active_cells.entry((x + dx, y + dy)) {
-                        std::collections::hash_map::Entry::Occupied(mut occupied) => {
-                            let cell = occupied.get_mut();
-                            cell.num_neighbors -= 1;
-                            if cell.num_neighbors == 0 && !cell.alive {
-                                occupied.remove();
-                            }
-                        }
-                        _ => (),
+            }
+        };
+        let radius = i32::from(self.rule.radius());
+        if self.topology.crosses_edge((x, y), radius) {
+            // Neighbors across the edge of a torus wrap around, and on a small one the same cell
+            // can be a neighbor more than once. Those off a bounded plane are never counted, nor
+            // is the cell itself where edges meet at a corner.
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let pos = self.topology.wrap((x + dx, y + dy));
+                    if let Some(pos) = pos.filter(|&pos| pos != (x, y)) {
+                        self.active_cells
+                            .update_block(pos, 0, alive, |_, slot| update(slot));
                     }
                 }
             }
+        } else {
+            self.active_cells
+                .update_block((x, y), radius, alive, |pos, slot| {
+                    if pos != (x, y) {
+                        update(slot);
+                    }
+                });
+        }
+        if let Some(cell) = self.active_cells.get_mut((x, y)) {
+            cell.num_neighbors += live_neighbors;
         }
 
-        match self.active_cells.entry((x, y)) {
-            std::collections::hash_map::Entry::Occupied(mut occupied) => {
-                let cell = occupied.get_mut();
-                if cell.num_neighbors == 0 && !cell.alive {
-                    occupied.remove();
-                }
-            }
-            _ => (),
+        if self
+            .active_cells
+            .get((x, y))
+            .is_some_and(LifeCell::is_blank)
+        {
+            self.active_cells.remove((x, y));
         }
     }
 }
@@ -202,11 +1046,129 @@ pub enum LifePattern {
     Blinker,
     Beacon,
     Random(usize),
+    GosperGun,
+    Pulsar,
+    /// The lightweight spaceship.
+    Lwss,
+    RPentomino,
+    Acorn,
+    Pentadecathlon,
+    /// The HighLife replicator, which copies itself every 12 generations.
+    Replicator,
+    /// The smallest Brian's Brain spaceship, with refractory cells behind its firing ones.
+    BrainShip,
+}
+
+/// What is known about a built-in pattern, shown in the pattern picker.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct PatternInfo {
+    /// The rule the pattern is meant for, which a world built from it runs as.
+    pub rule: &'static str,
+    /// Who found the pattern and in which year.
+    pub discovered: Option<(&'static str, u32)>,
+    /// The period of oscillators, spaceships and guns.
+    pub period: Option<usize>,
+}
+
+impl LifePattern {
+    /// The RLE source of the patterns in the built-in collection.
+    fn rle(&self) -> Option<&'static str> {
+        match self {
+            Self::GosperGun => Some(include_str!("../patterns/gosper-gun.rle")),
+            Self::Pulsar => Some(include_str!("../patterns/pulsar.rle")),
+            Self::Lwss => Some(include_str!("../patterns/lwss.rle")),
+            Self::RPentomino => Some(include_str!("../patterns/r-pentomino.rle")),
+            Self::Acorn => Some(include_str!("../patterns/acorn.rle")),
+            Self::Pentadecathlon => Some(include_str!("../patterns/pentadecathlon.rle")),
+            Self::Replicator => Some(include_str!("../patterns/replicator.rle")),
+            Self::BrainShip => Some(include_str!("../patterns/brain-ship.rle")),
+            _ => None,
+        }
+    }
+
+    /// Categories for finding the pattern in listings, such as `oscillator` or `spaceship`.
+    pub fn tags(&self) -> &'static [&'static str] {
+        match self {
+            Self::Blank => &[],
+            Self::Glider => &["spaceship"],
+            Self::Blinker | Self::Beacon | Self::Pulsar | Self::Pentadecathlon => &["oscillator"],
+            Self::Random(_) => &["soup"],
+            Self::GosperGun => &["gun"],
+            Self::Lwss | Self::BrainShip => &["spaceship"],
+            Self::RPentomino | Self::Acorn => &["methuselah"],
+            Self::Replicator => &["replicator"],
+        }
+    }
+
+    /// The pattern's rule, discoverer and period.
+    pub fn info(&self) -> PatternInfo {
+        let (rule, discovered, period) = match self {
+            Self::Blank | Self::Random(_) => ("B3/S23", None, None),
+            Self::Glider => ("B3/S23", Some(("Richard K. Guy", 1970)), Some(4)),
+            Self::Blinker => ("B3/S23", Some(("John Conway", 1970)), Some(2)),
+            Self::Beacon => ("B3/S23", Some(("John Conway", 1970)), Some(2)),
+            Self::GosperGun => ("B3/S23", Some(("Bill Gosper", 1970)), Some(30)),
+            Self::Pulsar => ("B3/S23", Some(("John Conway", 1970)), Some(3)),
+            Self::Lwss => ("B3/S23", Some(("John Conway", 1970)), Some(4)),
+            Self::RPentomino => ("B3/S23", Some(("John Conway", 1969)), None),
+            Self::Acorn => ("B3/S23", Some(("Charles Corderman", 1971)), None),
+            Self::Pentadecathlon => ("B3/S23", Some(("John Conway", 1970)), Some(15)),
+            Self::Replicator => ("B36/S23", Some(("Nathan Thompson", 1994)), None),
+            Self::BrainShip => ("B2/S/3", None, Some(1)),
+        };
+        PatternInfo {
+            rule,
+            discovered,
+            period,
+        }
+    }
+}
+
+impl ValueEnum for LifePattern {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Blank,
+            Self::Glider,
+            Self::Blinker,
+            Self::Beacon,
+            Self::Random(10000),
+            Self::GosperGun,
+            Self::Pulsar,
+            Self::Lwss,
+            Self::RPentomino,
+            Self::Acorn,
+            Self::Pentadecathlon,
+            Self::Replicator,
+            Self::BrainShip,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Blank => Some(clap::builder::PossibleValue::new("blank").alias("b")),
+            Self::Glider => Some(clap::builder::PossibleValue::new("glider").alias("g")),
+            Self::Blinker => Some(clap::builder::PossibleValue::new("blinker").alias("bl")),
+            Self::Beacon => Some(clap::builder::PossibleValue::new("beacon").alias("be")),
+            Self::Random(_) => Some(clap::builder::PossibleValue::new("random").alias("r")),
+            Self::GosperGun => Some(clap::builder::PossibleValue::new("gosper-gun").alias("gun")),
+            Self::Pulsar => Some(clap::builder::PossibleValue::new("pulsar")),
+            Self::Lwss => Some(clap::builder::PossibleValue::new("lwss")),
+            Self::RPentomino => Some(clap::builder::PossibleValue::new("r-pentomino")),
+            Self::Acorn => Some(clap::builder::PossibleValue::new("acorn")),
+            Self::Pentadecathlon => {
+                Some(clap::builder::PossibleValue::new("pentadecathlon").alias("pd"))
+            }
+            Self::Replicator => Some(clap::builder::PossibleValue::new("replicator")),
+            Self::BrainShip => Some(clap::builder::PossibleValue::new("brain-ship")),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rule::WireWorld;
+    use std::collections::HashSet;
 
     const POSITIONS: [[i32; 2]; 8] = [
         [0, 1],
@@ -224,7 +1186,7 @@ mod tests {
         for [x, y] in POSITIONS {
             let mut world = LifeWorld::new();
             world.raise(x, y);
-            assert_eq!(world.alive(x, y), true);
+            assert!(world.alive(x, y));
         }
     }
 
@@ -233,7 +1195,7 @@ mod tests {
         for [x, y] in POSITIONS {
             let mut world = LifeWorld::new();
             world.lower(x, y);
-            assert_eq!(world.alive(x, y), false);
+            assert!(!world.alive(x, y));
         }
     }
 
@@ -242,9 +1204,9 @@ mod tests {
         for [x, y] in POSITIONS {
             let mut world = LifeWorld::new();
             world.toggle(x, y);
-            assert_eq!(world.alive(x, y), true);
+            assert!(world.alive(x, y));
             world.toggle(x, y);
-            assert_eq!(world.alive(x, y), false);
+            assert!(!world.alive(x, y));
         }
     }
 
@@ -361,6 +1323,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn evolve_counts_births_and_deaths() {
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        let changes = world.evolve();
+        assert_eq!(
+            changes,
+            Changes {
+                births: 2,
+                deaths: 2
+            }
+        );
+    }
+
+    #[test]
+    fn evolves_several_generations() {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        let changes = world.evolve_n(4);
+        assert_eq!(world.generations, 4);
+        assert_eq!((changes.births, changes.deaths), (8, 8));
+        // A glider moves one cell diagonally every four generations.
+        let mut moved: Vec<_> = world.cells().collect();
+        let mut expected: Vec<_> = LifeWorld::from(&LifePattern::Glider)
+            .cells()
+            .map(|(x, y)| (x + 1, y - 1))
+            .collect();
+        moved.sort();
+        expected.sort();
+        assert_eq!(moved, expected);
+    }
+
+    #[test]
+    fn observers_receive_events() {
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        let sink = events.clone();
+        let id = world.on_event(move |event| sink.lock().unwrap().push(*event));
+        world.evolve();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                WorldEvent::Birth(-1, 1),
+                WorldEvent::Death(0, 0),
+                WorldEvent::Death(0, 2),
+                WorldEvent::Birth(1, 1),
+                WorldEvent::Generation {
+                    generation: 1,
+                    changes: Changes {
+                        births: 2,
+                        deaths: 2
+                    }
+                },
+            ]
+        );
+        assert!(world.remove_observer(id));
+        world.evolve();
+        assert_eq!(events.lock().unwrap().len(), 5);
+    }
+
     #[test]
     fn live_cell_with_n_living_neighbors() {
         for n in 0..=8 {
@@ -371,8 +1393,8 @@ mod tests {
             }
             world.evolve();
             match n {
-                2 | 3 => assert_eq!(world.alive(0, 0), true),
-                _ => assert_eq!(world.alive(0, 0), false),
+                2 | 3 => assert!(world.alive(0, 0)),
+                _ => assert!(!world.alive(0, 0)),
             }
         }
     }
@@ -389,9 +1411,543 @@ mod tests {
             }
             world.evolve();
             match n {
-                3 => assert_eq!(world.alive(0, 0), true),
-                _ => assert_eq!(world.alive(0, 0), false),
+                3 => assert!(world.alive(0, 0)),
+                _ => assert!(!world.alive(0, 0)),
+            }
+        }
+    }
+
+    #[test]
+    fn evolves_by_the_world_rule() -> anyhow::Result<()> {
+        // Six neighbors only cause a birth in HighLife.
+        let mut world = LifeWorld::new();
+        for [x, y] in &POSITIONS[0..6] {
+            world.raise(*x, *y);
+        }
+        let mut highlife = world.clone();
+        highlife.set_rule(rule::parse("B36/S23")?);
+        world.evolve();
+        highlife.evolve();
+        assert!(!world.alive(0, 0));
+        assert!(highlife.alive(0, 0));
+        // Seeds: every live cell dies, and cells with two neighbors are born.
+        let mut seeds = LifeWorld::from(&LifePattern::Blinker);
+        seeds.set_rule(rule::parse("B2/S")?);
+        seeds.evolve();
+        let mut cells: Vec<(i32, i32)> = seeds.cells().collect();
+        cells.sort();
+        assert_eq!(cells, [(-1, 0), (-1, 2), (1, 0), (1, 2)]);
+        Ok(())
+    }
+
+    /// A rule defined outside the rule module: cells are born next to exactly one live cell and
+    /// never die.
+    #[derive(Debug)]
+    struct Spread;
+
+    impl std::fmt::Display for Spread {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "spread")
+        }
+    }
+
+    impl Rule for Spread {
+        fn next_state(&self, alive: bool, neighbors: u8) -> bool {
+            alive || neighbors == 1
+        }
+    }
+
+    #[test]
+    fn swaps_rules_at_runtime() {
+        let mut world = LifeWorld::new();
+        world.raise(0, 0);
+        world.set_rule(Arc::new(Spread));
+        world.evolve();
+        assert_eq!(world.num_alive(), 9);
+        assert_eq!(world.rule().to_string(), "spread");
+        world.set_rule(rule::life());
+        world.evolve();
+        assert_eq!(world.num_alive(), 8);
+    }
+
+    #[test]
+    fn combines_worlds() {
+        let sorted = |world: &LifeWorld| {
+            let mut cells: Vec<(i32, i32)> = world.cells().collect();
+            cells.sort();
+            cells
+        };
+        let blinker = LifeWorld::from(&LifePattern::Blinker);
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        world.merge(&blinker, (1, 1));
+        assert_eq!(
+            sorted(&world),
+            [(0, 0), (0, 1), (0, 2), (1, 1), (1, 2), (1, 3)]
+        );
+        assert_eq!(world.num_alive(), 6);
+
+        let mut overlap = world.clone();
+        overlap.intersect(&blinker, (0, 1));
+        assert_eq!(sorted(&overlap), [(0, 1), (0, 2)]);
+
+        world.subtract(&blinker, (0, 0));
+        assert_eq!(sorted(&world), [(1, 1), (1, 2), (1, 3)]);
+        // The neighbor counts stay consistent, so the result evolves normally.
+        world.evolve();
+        assert_eq!(sorted(&world), [(0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn builds_the_built_in_collection() {
+        let populations = [
+            (LifePattern::GosperGun, 36),
+            (LifePattern::Pulsar, 48),
+            (LifePattern::Lwss, 9),
+            (LifePattern::RPentomino, 5),
+            (LifePattern::Acorn, 7),
+            (LifePattern::Pentadecathlon, 12),
+            (LifePattern::Replicator, 12),
+            (LifePattern::BrainShip, 2),
+        ];
+        for (pattern, population) in populations {
+            let world = LifeWorld::from(&pattern);
+            assert_eq!(world.num_alive(), population, "{:?}", pattern);
+            assert_eq!(world.extent().map(|(min, _)| min), Some((0, 0)));
+        }
+        // The oscillators return to their starting state after a period.
+        let sorted = |world: &LifeWorld| {
+            let mut cells: Vec<(i32, i32)> = world.cells().collect();
+            cells.sort();
+            cells
+        };
+        for pattern in [LifePattern::Pulsar, LifePattern::Pentadecathlon] {
+            let start = LifeWorld::from(&pattern);
+            let mut world = start.clone();
+            for _ in 0..pattern.info().period.unwrap() {
+                world.evolve();
             }
+            assert_eq!(sorted(&world), sorted(&start), "{:?}", pattern);
+        }
+        // Patterns for other rules run as them, so the replicator makes a copy of itself.
+        let mut replicator = LifeWorld::from(&LifePattern::Replicator);
+        assert_eq!(replicator.rule().to_string(), "B36/S23");
+        replicator.evolve_n(12);
+        assert_eq!(replicator.num_alive(), 24);
+        // The Brian's Brain ship keeps its refractory cells behind it as it moves.
+        let mut ship = LifeWorld::from(&LifePattern::BrainShip);
+        assert_eq!(ship.rule().to_string(), "B2/S/3");
+        ship.evolve_n(10);
+        assert_eq!(sorted(&ship), [(0, 11), (1, 11)]);
+        let dying: Vec<_> = ship.dying().collect();
+        assert_eq!(dying.len(), 2);
+        assert!(dying.iter().all(|&((_, y), decay)| y == 10 && decay == 1));
+    }
+
+    #[test]
+    fn scales_patterns() {
+        let world = LifeWorld::from(&LifePattern::Blinker).scaled(2).unwrap();
+        assert_eq!(world.num_alive(), 12);
+        assert_eq!(world.bounding_box(), Some(((0, 0), (1, 5))));
+        assert!(world.alive(1, 5) && !world.alive(2, 0));
+        assert_eq!(
+            LifeWorld::from(&LifePattern::Glider)
+                .scaled(1)
+                .unwrap()
+                .num_alive(),
+            5
+        );
+        // Blocks must fit in the range of coordinates.
+        let mut far = LifeWorld::new();
+        far.raise(i32::MAX / 4 + 1, 0);
+        assert!(far.scaled(4).is_none());
+        assert_eq!(far.scaled(3).unwrap().num_alive(), 9);
+    }
+
+    #[test]
+    fn scales_dying_cells_and_ants() -> anyhow::Result<()> {
+        let mut ship = LifeWorld::from(&LifePattern::BrainShip);
+        ship.evolve();
+        ship.add_ant(Ant::new((1, 2)));
+        // At factor 1 the copy is the same pattern, refractory cells and all.
+        let copy = ship.scaled(1).unwrap();
+        assert_eq!(copy.to_rle(), ship.to_rle());
+        let read = formats::read_pattern(copy.to_rle().as_bytes())?;
+        assert_eq!(read.world.to_rle(), ship.to_rle());
+        assert_eq!(copy.ants(), ship.ants());
+        let scaled = ship.scaled(2).unwrap();
+        assert_eq!(scaled.num_alive(), 4 * ship.num_alive());
+        assert_eq!(scaled.dying().count(), 4 * ship.dying().count());
+        assert!(ship
+            .dying()
+            .all(|((x, y), decay)| scaled.decay(2 * x + 1, 2 * y + 1) == decay));
+        assert_eq!(scaled.ants()[0].position, (2, 4));
+        Ok(())
+    }
+
+    #[test]
+    fn scales_within_the_topology() -> anyhow::Result<()> {
+        let mut glider = LifeWorld::from(&LifePattern::Glider);
+        glider.set_topology(Topology::torus("8x8")?);
+        let copy = glider.scaled(1).unwrap();
+        assert_eq!(copy.topology(), glider.topology());
+        assert_eq!(copy.to_rle(), glider.to_rle());
+        // Blocks past the edge of the torus wrap around it.
+        let scaled = glider.scaled(3).unwrap();
+        assert_eq!(scaled.topology(), glider.topology());
+        assert!(scaled
+            .cells()
+            .all(|(x, y)| (-4..4).contains(&x) && (-4..4).contains(&y)));
+        Ok(())
+    }
+
+    #[test]
+    fn random_soups_are_reproducible() {
+        let a: LifeWorld = LifeWorld::random(500, 42);
+        let b: LifeWorld = LifeWorld::random(500, 42);
+        let mut a = a.cells().collect::<Vec<_>>();
+        let mut b = b.cells().collect::<Vec<_>>();
+        a.sort();
+        b.sort();
+        assert!(!a.is_empty());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn bounding_box_covers_living_cells() {
+        let mut world = LifeWorld::new();
+        assert_eq!(world.bounding_box(), None);
+        world.raise(-2, 3);
+        world.raise(4, -1);
+        world.raise(0, 0);
+        assert_eq!(world.bounding_box(), Some(((-2, -1), (4, 3))));
+    }
+
+    #[test]
+    fn deltas_are_independent_of_insertion_order() {
+        let cells = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (2, 1),
+            (1, 2),
+            (-5, -5),
+            (-5, -4),
+            (-5, -3),
+        ];
+        let mut forward = LifeWorld::new();
+        let mut backward = LifeWorld::new();
+        for &(x, y) in &cells {
+            forward.raise(x, y);
+        }
+        for &(x, y) in cells.iter().rev() {
+            backward.raise(x, y);
         }
+        for _ in 0..10 {
+            let deltas = forward.deltas();
+            assert!(deltas.is_sorted_by_key(|&(_, _, pos)| pos));
+            assert_eq!(deltas, backward.deltas());
+            forward.evolve();
+            backward.evolve();
+        }
+    }
+
+    #[test]
+    fn dying_cells_decay() -> anyhow::Result<()> {
+        let mut world = LifeWorld::new();
+        world.set_rule(rule::parse("B2/S/4")?);
+        world.raise(0, 0);
+        world.raise(1, 0);
+        world.evolve();
+        assert_eq!(world.decay(0, 0), 1);
+        assert!(!world.alive(0, 0));
+        assert_eq!(world.num_alive(), 4);
+        let dying: Vec<_> = world.dying_in((-5, -5), (5, 5)).collect();
+        assert_eq!(dying, [((0, 0), 1), ((1, 0), 1)]);
+        world.evolve();
+        // Dying cells are neither counted as neighbors nor born again.
+        assert_eq!(world.decay(0, 0), 2);
+        assert!(!world.alive(0, 0));
+        world.evolve();
+        assert_eq!(world.decay(0, 0), 0);
+        world.raise(9, 9);
+        world.raise(9, 10);
+        world.evolve();
+        world.raise(9, 9);
+        assert_eq!(world.decay(9, 9), 0);
+        assert!(world.alive(9, 9));
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_stats_up_to_date() {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        world.raise(10, 10);
+        let stats = world.stats();
+        assert_eq!(stats.population, 6);
+        assert_eq!(stats.bounding_box, Some(((0, 0), (10, 10))));
+        assert_eq!((stats.clusters, stats.largest_cluster), (2, 5));
+        assert_eq!(stats.density, 6. / 121.);
+        world.lower(10, 10);
+        world.evolve_n(4);
+        let stats = world.stats();
+        assert_eq!(stats.generation, 4);
+        assert_eq!(stats.population, world.cells().count());
+        assert_eq!(stats.bounding_box, world.bounding_box());
+        assert_eq!((stats.clusters, stats.density), (1, 5. / 9.));
+        world.set_topology(Topology::torus("8x8").unwrap());
+        assert_eq!(world.stats().population, 5);
+        assert_eq!(LifeWorld::new().stats().density, 0.);
+    }
+
+    #[test]
+    fn runs_electrons_along_wires() -> anyhow::Result<()> {
+        let mut world = LifeWorld::new();
+        world.set_rule(rule::parse("WireWorld")?);
+        for x in 0..10 {
+            world.set_dying(x, 0, WireWorld::WIRE);
+        }
+        world.set_dying(0, 0, WireWorld::TAIL);
+        world.raise(1, 0);
+        let changes = world.evolve_n(3);
+        assert_eq!(world.cells().collect::<Vec<_>>(), [(4, 0)]);
+        assert_eq!(world.decay(3, 0), WireWorld::TAIL);
+        assert_eq!(world.decay(0, 0), WireWorld::WIRE);
+        assert_eq!(world.dying().count(), 9);
+        assert_eq!(
+            changes,
+            Changes {
+                births: 3,
+                deaths: 3
+            }
+        );
+        // The electron runs off the end of the wire, leaving it as it was.
+        world.evolve_n(7);
+        assert_eq!(world.num_alive(), 0);
+        assert!(world.dying().all(|(_, decay)| decay == WireWorld::WIRE));
+        assert_eq!(world.dying().count(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn walks_langtons_ant() -> anyhow::Result<()> {
+        let mut world = LifeWorld::new();
+        world.set_rule(rule::parse("langtons-ant")?);
+        world.add_ant(Ant::new((0, 0)));
+        // Right around a square of dead cells, painting them, then left off the first one.
+        let changes = world.evolve_n(5);
+        let mut cells: Vec<_> = world.cells().collect();
+        cells.sort();
+        assert_eq!(cells, [(0, -1), (1, -1), (1, 0)]);
+        assert_eq!(
+            changes,
+            Changes {
+                births: 4,
+                deaths: 1
+            }
+        );
+        let ant = world.ants()[0];
+        assert_eq!((ant.position, ant.heading), ((-1, 0), Heading::West));
+
+        // An ant that never turns walks off the edge of a bounded plane.
+        world.set_rule(rule::parse("NN")?);
+        world.set_topology(Topology::bounded("8x8")?);
+        world.evolve_n(4);
+        assert!(world.ants().is_empty());
+        assert!(!world.remove_ants(-1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn writes_elementary_rows() -> anyhow::Result<()> {
+        let mut world = LifeWorld::new();
+        world.set_rule(rule::parse("rule-110")?);
+        world.raise(0, 0);
+        world.evolve();
+        assert_eq!(world.elementary_row(), Some(-1));
+        // A stray cell in the way is overwritten.
+        world.raise(5, -2);
+        let changes = world.evolve();
+        let mut cells: Vec<_> = world.cells().collect();
+        cells.sort();
+        assert_eq!(
+            cells,
+            [(-2, -2), (-1, -2), (-1, -1), (0, -2), (0, -1), (0, 0)]
+        );
+        assert_eq!(
+            changes,
+            Changes {
+                births: 3,
+                deaths: 1
+            }
+        );
+        // Rows start from the lowest row of the pattern, whatever generation it starts in.
+        let mut glider = LifeWorld::from(&LifePattern::Glider);
+        glider.set_rule(rule::parse("W110")?);
+        glider.generations = 50;
+        glider.evolve_n(5);
+        assert_eq!(glider.elementary_row(), Some(-5));
+        assert_eq!(glider.bounding_box(), Some(((-5, -5), (2, 2))));
+        Ok(())
+    }
+
+    #[test]
+    fn counts_larger_neighborhoods() -> anyhow::Result<()> {
+        let mut world = LifeWorld::new();
+        world.raise(0, 0);
+        world.raise(40, 40);
+        // Switching to a wider neighborhood counts the neighbors again.
+        world.set_rule(rule::parse("R2,C0,M0,S0..24,B1,NM")?);
+        world.evolve();
+        assert_eq!(world.num_alive(), 50);
+        assert_eq!(world.bounding_box(), Some(((-2, -2), (42, 42))));
+        let mut life = LifeWorld::from(&LifePattern::Glider);
+        let mut ltl = life.clone();
+        ltl.set_rule(rule::parse("R1,C0,M0,S2..3,B3,NM")?);
+        life.evolve_n(8);
+        ltl.evolve_n(8);
+        assert_eq!(ltl.cells().collect::<Vec<_>>().len(), 5);
+        assert!(ltl.cells().all(|(x, y)| life.alive(x, y)));
+        Ok(())
+    }
+
+    #[test]
+    fn wraps_around_a_torus() -> anyhow::Result<()> {
+        let torus = Topology::torus("8x8")?;
+        // A blinker across the edge still oscillates.
+        let mut world = LifeWorld::new();
+        world.set_topology(torus);
+        for x in 3..=5 {
+            world.raise(x, 0);
+        }
+        assert!(world.alive(-4, 0) && world.alive(12, 8));
+        world.evolve();
+        let mut cells: Vec<_> = world.cells().collect();
+        cells.sort();
+        assert_eq!(cells, [(-4, -1), (-4, 0), (-4, 1)]);
+        // A glider comes back to where it started after crossing the torus.
+        let mut glider = LifeWorld::from(&LifePattern::Glider);
+        let start = glider.clone();
+        glider.set_topology(torus);
+        glider.evolve_n(32);
+        assert_eq!(glider.num_alive(), 5);
+        assert!(start.cells().all(|(x, y)| glider.alive(x, y)));
+        // Every copy of the torus shows its cells.
+        assert_eq!(glider.cells_in((-8, -8), (15, 15)).count(), 5 * 9);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_cells_off_a_bounded_plane_dead() -> anyhow::Result<()> {
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        world.raise(20, 20);
+        world.set_topology(Topology::bounded("5x3")?);
+        assert_eq!(world.num_alive(), 2);
+        world.raise(3, 0);
+        assert!(!world.alive(3, 0));
+        // A blinker across the edge loses its end, and the remaining domino dies out.
+        world.evolve();
+        assert_eq!(world.cells().collect::<Vec<_>>(), []);
+        // A glider turns into a block at the edge.
+        let mut glider = LifeWorld::from(&LifePattern::Glider);
+        glider.set_topology(Topology::bounded("8x8")?);
+        glider.evolve_n(40);
+        let mut cells: Vec<_> = glider.cells().collect();
+        cells.sort();
+        assert_eq!(cells, [(2, -2), (2, -1), (3, -2), (3, -1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn evolves_on_twisted_surfaces() -> anyhow::Result<()> {
+        // Compare with counting each cell's neighbors through `Topology::wrap` directly.
+        let naive = |topology: Topology, cells: &HashSet<(i32, i32)>| {
+            let ((x0, y0), (x1, y1)) = topology.bounds().unwrap();
+            let mut next = HashSet::new();
+            for (x, y) in (y0..=y1).flat_map(|y| (x0..=x1).map(move |x| (x, y))) {
+                let count = POSITIONS
+                    .iter()
+                    .filter_map(|&[dx, dy]| topology.wrap((x + dx, y + dy)))
+                    .filter(|&pos| pos != (x, y) && cells.contains(&pos))
+                    .count();
+                if count == 3 || count == 2 && cells.contains(&(x, y)) {
+                    next.insert((x, y));
+                }
+            }
+            next
+        };
+        for topology in ["K10*,9", "K10,9*", "C10,9", "S9"] {
+            let topology: Topology = topology.parse()?;
+            let mut world = LifeWorld::from(&LifePattern::RPentomino);
+            world.set_topology(topology);
+            let mut cells: HashSet<_> = world.cells().collect();
+            for generation in 0..60 {
+                world.evolve();
+                cells = naive(topology, &cells);
+                let actual: HashSet<_> = world.cells().collect();
+                assert_eq!(
+                    actual,
+                    cells,
+                    "{} at generation {}",
+                    topology,
+                    generation + 1
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn evolves_by_neighbor_arrangement() -> anyhow::Result<()> {
+        // Cells survive with two neighbors next to each other, but not with two opposite ones.
+        let mut world = LifeWorld::new();
+        world.set_rule(rule::parse("B3/S2a")?);
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (10, 0), (11, 0), (12, 0)] {
+            world.raise(x, y);
+        }
+        world.evolve();
+        assert!(world.alive(1, 0) && !world.alive(11, 0));
+        // With every arrangement of each count it is the same as the totalistic rule.
+        let mut life = LifeWorld::from(&LifePattern::RPentomino);
+        let mut isotropic = life.clone();
+        isotropic.set_rule(Arc::new("B3/S23".parse::<rule::Isotropic>()?));
+        life.evolve_n(50);
+        isotropic.evolve_n(50);
+        assert_eq!(isotropic.num_alive(), life.num_alive());
+        assert!(isotropic.cells().all(|(x, y)| life.alive(x, y)));
+        Ok(())
+    }
+
+    #[derive(PartialEq, Clone, Debug, Default)]
+    struct Age(Option<u32>);
+
+    impl CellState for Age {
+        fn raised() -> Age {
+            Age(Some(0))
+        }
+
+        fn is_alive(&self) -> bool {
+            self.0.is_some()
+        }
+
+        fn next(&self, neighbors: &Neighbors<Age>) -> Age {
+            match (self.0, neighbors.count()) {
+                (Some(age), 2 | 3) => Age(Some(age + 1)),
+                (None, 3) => Age(Some(
+                    neighbors.iter().filter_map(|n| n.0).min().unwrap_or(0),
+                )),
+                _ => Age(None),
+            }
+        }
+    }
+
+    #[test]
+    fn custom_state_carries_payload() {
+        let mut world: LifeWorld<Age> = LifeWorld::with_pattern(&LifePattern::Blinker);
+        world.set(0, 0, Age(Some(5)));
+        world.evolve();
+        assert_eq!(world.state(0, 1), Some(&Age(Some(1))));
+        assert_eq!(world.state(-1, 1), Some(&Age(Some(0))));
+        assert_eq!(world.state(0, 0), Some(&Age(None)));
+        assert_eq!(world.num_alive(), 3);
     }
 }
@@ -0,0 +1,115 @@
+use crate::Position;
+use std::fmt;
+
+/// The offset between two marked points, as used for lining up glider lanes and gun spacings.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Measurement {
+    pub from: Position,
+    pub to: Position,
+}
+
+impl Measurement {
+    pub fn new(from: Position, to: Position) -> Measurement {
+        Measurement { from, to }
+    }
+
+    pub fn dx(&self) -> i32 {
+        self.to.0 - self.from.0
+    }
+
+    pub fn dy(&self) -> i32 {
+        self.to.1 - self.from.1
+    }
+
+    pub fn euclidean(&self) -> f64 {
+        f64::from(self.dx()).hypot(f64::from(self.dy()))
+    }
+
+    pub fn chebyshev(&self) -> i32 {
+        self.dx().abs().max(self.dy().abs())
+    }
+
+    /// Describe the lattice direction of the offset, e.g. `orthogonal E`, `diagonal NW` or
+    /// `oblique 2:-1` for knight-like slopes (reduced to lowest terms).
+    pub fn direction(&self) -> String {
+        let (dx, dy) = (self.dx(), self.dy());
+        let compass = format!(
+            "{}{}",
+            match dy.signum() {
+                1 => "N",
+                -1 => "S",
+                _ => "",
+            },
+            match dx.signum() {
+                1 => "E",
+                -1 => "W",
+                _ => "",
+            }
+        );
+        if dx == 0 && dy == 0 {
+            String::from("none")
+        } else if dx == 0 || dy == 0 {
+            format!("orthogonal {}", compass)
+        } else if dx.abs() == dy.abs() {
+            format!("diagonal {}", compass)
+        } else {
+            let d = gcd(dx.unsigned_abs(), dy.unsigned_abs()) as i32;
+            format!("oblique {}:{}", dx / d, dy / d)
+        }
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({},{})->({},{}) dx: {}, dy: {}, euclidean: {:.2}, chebyshev: {}, direction: {}",
+            self.from.0,
+            self.from.1,
+            self.to.0,
+            self.to.1,
+            self.dx(),
+            self.dy(),
+            self.euclidean(),
+            self.chebyshev(),
+            self.direction(),
+        )
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_distances() {
+        let m = Measurement::new((1, 2), (4, 6));
+        assert_eq!(m.dx(), 3);
+        assert_eq!(m.dy(), 4);
+        assert_eq!(m.euclidean(), 5.);
+        assert_eq!(m.chebyshev(), 4);
+    }
+
+    #[test]
+    fn classifies_directions() {
+        assert_eq!(Measurement::new((0, 0), (0, 0)).direction(), "none");
+        assert_eq!(Measurement::new((0, 0), (5, 0)).direction(), "orthogonal E");
+        assert_eq!(
+            Measurement::new((0, 0), (0, -3)).direction(),
+            "orthogonal S"
+        );
+        assert_eq!(Measurement::new((0, 0), (-4, 4)).direction(), "diagonal NW");
+        assert_eq!(
+            Measurement::new((0, 0), (4, -2)).direction(),
+            "oblique 2:-1"
+        );
+    }
+}
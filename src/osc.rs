@@ -0,0 +1,124 @@
+use crate::{Changes, LifeWorld, WorldEvent};
+use anyhow::{anyhow, Context, Result};
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+/// Sends per-generation statistics, and optionally every birth and death, as OSC messages over
+/// UDP for live-coding environments such as SuperCollider or TidalCycles:
+///
+/// - `/life/generation <generation> <population> <births> <deaths>`
+/// - `/life/birth <x> <y>` and `/life/death <x> <y>`
+#[derive(Debug)]
+pub struct OscSender {
+    socket: UdpSocket,
+    cells: bool,
+}
+
+impl OscSender {
+    /// Send to `target` (`host:port`), including cell events if `cells` is set.
+    pub fn connect(target: &str, cells: bool) -> Result<OscSender> {
+        let addr = target
+            .to_socket_addrs()
+            .with_context(|| format!("invalid OSC address {}", target))?
+            .next()
+            .ok_or_else(|| anyhow!("invalid OSC address {}", target))?;
+        let local = if addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket
+            .connect(addr)
+            .with_context(|| format!("failed to connect to {}", target))?;
+        Ok(OscSender { socket, cells })
+    }
+
+    pub fn record(&self, world: &LifeWorld, changes: Changes) -> Result<()> {
+        let packet = message(
+            "/life/generation",
+            &[
+                world.generations as i32,
+                world.num_alive(),
+                changes.births as i32,
+                changes.deaths as i32,
+            ],
+        );
+        send(&self.socket, &packet)
+    }
+
+    /// Register an observer on the world that sends births and deaths, if cell events were
+    /// requested. This has to be repeated whenever the world is replaced.
+    pub fn observe(&self, world: &mut LifeWorld) -> Result<()> {
+        if !self.cells {
+            return Ok(());
+        }
+        let socket = self.socket.try_clone()?;
+        world.on_event(move |event| {
+            let packet = match event {
+                WorldEvent::Birth(x, y) => message("/life/birth", &[*x, *y]),
+                WorldEvent::Death(x, y) => message("/life/death", &[*x, *y]),
+                WorldEvent::Generation { .. } => return,
+            };
+            // Cell events are best effort; generation messages report errors.
+            let _ = send(&socket, &packet);
+        });
+        Ok(())
+    }
+}
+
+/// Send a packet, ignoring the refusals reported when nothing is listening yet, which is normal
+/// while a live-coding session is being set up.
+fn send(socket: &UdpSocket, packet: &[u8]) -> Result<()> {
+    match socket.send(packet) {
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => Ok(()),
+        result => result.map(|_| ()).context("failed to send OSC message"),
+    }
+}
+
+/// Encode an OSC message with 32-bit integer arguments.
+fn message(address: &str, args: &[i32]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    push_string(&mut packet, address);
+    push_string(&mut packet, &format!(",{}", "i".repeat(args.len())));
+    for arg in args {
+        packet.extend_from_slice(&arg.to_be_bytes());
+    }
+    packet
+}
+
+/// OSC strings are null terminated and padded with nulls to a multiple of four bytes.
+fn push_string(packet: &mut Vec<u8>, s: &str) {
+    packet.extend_from_slice(s.as_bytes());
+    let padding = 4 - s.len() % 4;
+    packet.extend(std::iter::repeat_n(0, padding));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_messages() {
+        assert_eq!(message("/a", &[1]), b"/a\0\0,i\0\0\0\0\0\x01");
+        assert_eq!(
+            message("/life/birth", &[-1, 2]),
+            b"/life/birth\0,ii\0\xff\xff\xff\xff\0\0\0\x02"
+        );
+    }
+
+    #[test]
+    fn sends_generation_stats() -> Result<()> {
+        let receiver = UdpSocket::bind("127.0.0.1:0")?;
+        let sender = OscSender::connect(&receiver.local_addr()?.to_string(), false)?;
+        let world = LifeWorld::new();
+        sender.record(&world, Changes::default())?;
+        let mut buf = [0; 64];
+        let len = receiver.recv(&mut buf)?;
+        assert_eq!(
+            &buf[..len],
+            message("/life/generation", &[0, 0, 0, 0]).as_slice()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+/// How long before a deadline to stop sleeping and start spinning. Sleeps (including the input
+/// poll timeout) can overshoot by about a millisecond on Linux and macOS and by a whole timer
+/// tick, about 15ms, on Windows.
+#[cfg(windows)]
+pub const SPIN_MARGIN: Duration = Duration::from_millis(16);
+#[cfg(not(windows))]
+pub const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+/// Schedules generations at fixed intervals. Deadlines advance by exactly one interval each
+/// generation, so time spent rendering and handling input does not accumulate as drift.
+#[derive(Debug)]
+pub struct Pacer {
+    interval: Duration,
+    next: Instant,
+}
+
+impl Pacer {
+    pub fn new(interval: Duration, now: Instant) -> Pacer {
+        Pacer {
+            interval,
+            next: now + interval,
+        }
+    }
+
+    /// Time left until the next generation is due, or zero if it is overdue.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.next.saturating_duration_since(now)
+    }
+
+    /// Busy-wait until the next generation is due. Only meant for the last `SPIN_MARGIN`.
+    pub fn spin(&self) {
+        while Instant::now() < self.next {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Schedule the generation after the one that just ran. If the simulation has fallen behind,
+    /// the schedule restarts from `now` instead of trying to catch up in a burst.
+    pub fn advance(&mut self, now: Instant) {
+        self.next += self.interval;
+        if self.next < now {
+            self.next = now;
+        }
+    }
+
+    /// Restart the schedule, e.g. after resuming, so the next generation is one interval away.
+    pub fn reset(&mut self, now: Instant) {
+        self.next = now + self.interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advances_by_interval_without_drift() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(Duration::from_millis(5), start);
+        assert_eq!(pacer.remaining(start), Duration::from_millis(5));
+        // The generation ran a little late; the next deadline is still on the original grid.
+        pacer.advance(start + Duration::from_millis(6));
+        assert_eq!(
+            pacer.remaining(start + Duration::from_millis(6)),
+            Duration::from_millis(4)
+        );
+    }
+
+    #[test]
+    fn restarts_when_behind() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(Duration::from_millis(5), start);
+        let late = start + Duration::from_millis(50);
+        pacer.advance(late);
+        assert_eq!(pacer.remaining(late), Duration::ZERO);
+        pacer.advance(late);
+        assert_eq!(pacer.remaining(late), Duration::from_millis(5));
+        pacer.reset(late + Duration::from_secs(1));
+        assert_eq!(
+            pacer.remaining(late + Duration::from_secs(1)),
+            Duration::from_millis(5)
+        );
+    }
+}
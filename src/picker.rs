@@ -0,0 +1,307 @@
+use crate::formats::Pattern;
+use crate::library::Entry;
+use crate::{analysis, LifeWorld};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// The most terminal columns and rows a thumbnail takes up.
+const THUMBNAIL_SIZE: (usize, usize) = (16, 4);
+/// How many generations a pattern is run for to find its period, if its tags don't give one.
+const PERIOD_LIMIT: usize = 64;
+
+/// The size, period and a thumbnail of a pattern, shown in the picker, along with the rule it
+/// declares and who discovered it.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Preview {
+    pub size: (i32, i32),
+    pub period: Option<usize>,
+    /// Lines of block characters, each character covering two equal blocks of cells.
+    pub thumbnail: Vec<String>,
+    pub rule: Option<String>,
+    /// Who found the pattern and in which year, known for built-in patterns.
+    pub discovered: Option<(&'static str, u32)>,
+}
+
+/// Previews of the patterns shown so far, or `None` for patterns that failed to load. They are
+/// made the first time a pattern is listed and kept for the rest of the session.
+pub type Previews = HashMap<Entry, Option<Preview>>;
+
+impl Preview {
+    /// Load a pattern and make its preview. The period comes from the built-in pattern's
+    /// metadata or a `pN` tag, or failing those from running the pattern for a short while.
+    pub fn of(entry: &Entry) -> Result<Preview> {
+        let Pattern { world, rule } = entry.load()?;
+        let info = entry.info();
+        let discovered = info.and_then(|info| info.discovered);
+        let Some(bounds @ ((x0, y0), (x1, y1))) = world.extent() else {
+            return Ok(Preview {
+                size: (0, 0),
+                period: None,
+                thumbnail: Vec::new(),
+                rule,
+                discovered,
+            });
+        };
+        let period = info
+            .and_then(|info| info.period)
+            .or_else(|| {
+                entry
+                    .tags
+                    .iter()
+                    .find_map(|tag| tag.strip_prefix('p')?.parse().ok())
+            })
+            .or_else(|| {
+                let outcome = analysis::classify(&mut world.clone(), PERIOD_LIMIT);
+                outcome.classification.period()
+            });
+        Ok(Preview {
+            size: (x1 - x0 + 1, y1 - y0 + 1),
+            period,
+            thumbnail: thumbnail(&world, bounds),
+            rule,
+            discovered,
+        })
+    }
+
+    /// The size and period, e.g. `3x3 p4`.
+    fn caption(&self) -> String {
+        let mut caption = format!("{}x{}", self.size.0, self.size.1);
+        if let Some(period) = self.period {
+            caption += &format!(" p{}", period);
+        }
+        caption
+    }
+
+    /// The rule and discoverer, e.g. `rule B36/S23, found by Nathan Thompson in 1994`.
+    fn details(&self) -> Option<String> {
+        let rule = self.rule.as_ref().map(|rule| format!("rule {}", rule));
+        let discovered = self
+            .discovered
+            .map(|(discoverer, year)| format!("found by {} in {}", discoverer, year));
+        let details: Vec<String> = rule.into_iter().chain(discovered).collect();
+        (!details.is_empty()).then(|| details.join(", "))
+    }
+}
+
+/// Draw the bounding box of a world scaled down to fit in [`THUMBNAIL_SIZE`], with each half of a
+/// character showing whether any cell in its block is alive.
+fn thumbnail(world: &LifeWorld, ((x0, y0), (x1, y1)): ((i32, i32), (i32, i32))) -> Vec<String> {
+    let (columns, rows) = THUMBNAIL_SIZE;
+    let (width, height) = ((x1 - x0 + 1) as usize, (y1 - y0 + 1) as usize);
+    let scale = width.div_ceil(columns).max(height.div_ceil(2 * rows));
+    // The block `row` blocks down from the top and `column` blocks across.
+    let alive = |column: usize, row: usize| {
+        let (scale, column, row) = (scale as i32, column as i32, row as i32);
+        let min = (x0 + column * scale, y1 - (row + 1) * scale + 1);
+        let max = (min.0 + scale - 1, y1 - row * scale);
+        world.cells_in(min, max).next().is_some()
+    };
+    (0..height.div_ceil(scale).div_ceil(2))
+        .map(|line| {
+            (0..width.div_ceil(scale))
+                .map(
+                    |column| match (alive(column, 2 * line), alive(column, 2 * line + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                )
+                .collect()
+        })
+        .collect()
+}
+
+/// A list of patterns to choose one from, shown over the grid.
+#[derive(Debug)]
+pub struct Picker {
+    pub entries: Vec<Entry>,
+    selected: usize,
+}
+
+impl Picker {
+    pub fn new(entries: Vec<Entry>) -> Picker {
+        Picker {
+            entries,
+            selected: 0,
+        }
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    /// The entries listed in `rows` lines, scrolled to keep the selection in view.
+    fn visible(&self, rows: usize) -> Range<usize> {
+        let rows = rows.max(1);
+        let first = (self.selected + 1).saturating_sub(rows);
+        first..(first + rows).min(self.entries.len())
+    }
+
+    /// Make the previews of the entries that `lines` would list that haven't been made yet.
+    pub fn load_previews(&self, rows: usize, previews: &mut Previews) {
+        for entry in &self.entries[self.visible(rows)] {
+            if !previews.contains_key(entry) {
+                previews.insert(entry.clone(), Preview::of(entry).ok());
+            }
+        }
+    }
+
+    /// At most `rows` lines listing the entries, scrolled to keep the selection in view, with the
+    /// selection marked, followed by the selected pattern's thumbnail and details if there is
+    /// room. Entries with previews are captioned with their size and period.
+    pub fn lines(&self, rows: usize, previews: &Previews) -> Vec<String> {
+        let preview = |entry: &Entry| previews.get(entry).and_then(Option::as_ref);
+        let mut panel = Vec::new();
+        if let Some(selected) = self.selected().and_then(preview) {
+            panel.extend(selected.thumbnail.iter().map(|line| format!("  {}", line)));
+            panel.extend(selected.details().map(|details| format!("  {}", details)));
+        }
+        let room = !panel.is_empty() && rows > panel.len() + 2;
+        let list_rows = if room { rows - panel.len() - 1 } else { rows };
+        let mut lines: Vec<String> = self
+            .visible(list_rows)
+            .map(|i| {
+                let entry = &self.entries[i];
+                let marker = if i == self.selected { '>' } else { ' ' };
+                let mut line = format!("{} {}", marker, entry.name);
+                if !entry.tags.is_empty() {
+                    line += &format!(" [{}]", entry.tags.join(", "));
+                }
+                if let Some(preview) = preview(entry) {
+                    line += &format!("  {}", preview.caption());
+                }
+                line
+            })
+            .collect();
+        if room {
+            lines.push(String::new());
+            lines.extend(panel);
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn scrolls_to_the_selection() {
+        let entries = ["a", "b", "c", "d"]
+            .map(|name| Entry {
+                name: name.to_string(),
+                tags: Vec::new(),
+                bounds: None,
+                path: None,
+            })
+            .to_vec();
+        let mut picker = Picker::new(entries);
+        let previews = Previews::new();
+        assert_eq!(picker.lines(2, &previews), vec!["> a", "  b"]);
+        for _ in 0..5 {
+            picker.down();
+        }
+        assert_eq!(picker.selected().unwrap().name, "d");
+        assert_eq!(picker.lines(2, &previews), vec!["  c", "> d"]);
+        picker.up();
+        assert_eq!(picker.lines(2, &previews), vec!["  b", "> c"]);
+    }
+
+    #[test]
+    fn previews_patterns() -> Result<()> {
+        let entry = |name: &str, tags: &[&str]| Entry {
+            name: name.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            bounds: None,
+            path: None,
+        };
+        let mut picker = Picker::new(vec![
+            entry("glider", &["spaceship"]),
+            entry("pulsar", &["p3"]),
+        ]);
+        let mut previews = Previews::new();
+        picker.load_previews(1, &mut previews);
+        assert_eq!(previews.len(), 1);
+        assert_eq!(
+            picker.lines(6, &previews),
+            [
+                "> glider [spaceship]  3x3 p4",
+                "  pulsar [p3]",
+                "",
+                "   ▀▄",
+                "  ▀▀▀",
+                "  rule B3/S23, found by Richard K. Guy in 1970",
+            ]
+        );
+        picker.down();
+        picker.load_previews(5, &mut previews);
+        assert_eq!(previews.len(), 2);
+        // Too big to show at full size, so scaled down.
+        let pulsar = Preview::of(&entry("pulsar", &["p3"]))?;
+        assert_eq!((pulsar.size, pulsar.period), ((13, 13), Some(3)));
+        assert_eq!(pulsar.thumbnail.len(), 4);
+        assert_eq!(
+            picker.lines(2, &previews),
+            ["  glider [spaceship]  3x3 p4", "> pulsar [p3]  13x13 p3"]
+        );
+        let replicator = Preview::of(&entry("replicator", &[]))?;
+        assert_eq!(
+            replicator.details().as_deref(),
+            Some("rule B36/S23, found by Nathan Thompson in 1994")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn picks_library_patterns() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-picker-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("domino.cells"), "!tags: test\nOO\n")?;
+        let library = dir.to_str().unwrap();
+        let from_library = Harness::new(&["-p", "domino", "--library", library], 40, 20);
+        let mut harness = Harness::new(&["--build", "--library", library], 40, 20)?;
+        // The built-in patterns come first.
+        harness
+            .type_keys("P")?
+            .type_keys(&"j".repeat(20))?
+            .type_keys("k")?;
+        let lines = harness
+            .program
+            .picker
+            .as_ref()
+            .unwrap()
+            .lines(3, &harness.program.previews);
+        harness.type_keys("j\n")?;
+        std::fs::remove_dir_all(&dir)?;
+
+        assert_eq!(from_library?.cells(), vec![(0, 0), (1, 0)]);
+        assert_eq!(
+            lines,
+            [
+                "  pentadecathlon [oscillator]  10x3 p15",
+                "  replicator [replicator]  5x5",
+                "> brain-ship [spaceship]  2x2 p1",
+            ]
+        );
+        assert!(harness.program.picker.is_none());
+        assert_eq!(harness.cells(), vec![(0, 0), (1, 0)]);
+        assert!(harness
+            .status()
+            .starts_with("merged 2 cells from domino at 0,0"));
+        assert!(Harness::new(&["-p", "nonesuch", "--library", library], 40, 20).is_err());
+        Ok(())
+    }
+}
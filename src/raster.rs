@@ -0,0 +1,196 @@
+use crate::life::{CellState, LifeWorld};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// A rectangular region of the world, as inclusive `(min, max)` corners.
+pub type Region = ((i32, i32), (i32, i32));
+
+/// An RGB color, written `#rrggbb`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| anyhow!("invalid color: {} (expected #rrggbb)", s))
+        };
+        if hex.len() != 6 {
+            return Err(anyhow!("invalid color: {} (expected #rrggbb)", s));
+        }
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+/// The colors live and dead cells are drawn in.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Palette {
+    pub live: Rgb,
+    pub dead: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            live: Rgb::WHITE,
+            dead: Rgb::BLACK,
+        }
+    }
+}
+
+/// The most pixels a raster may have, about 8192 by 8192, so a large region or scale fails with
+/// an error rather than exhausting memory.
+pub const MAX_PIXELS: u64 = 1 << 26;
+
+/// A black and white image of a region of the world, with each cell drawn as a square block of
+/// pixels.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Raster {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<bool>,
+}
+
+impl Raster {
+    /// Rasterize a region of the world at `scale` pixels per cell. As on screen, higher y
+    /// coordinates are drawn towards the top of the image. Fails if the image would have more than
+    /// [`MAX_PIXELS`].
+    pub fn render<S: CellState>(
+        world: &LifeWorld<S>,
+        region: Region,
+        scale: u32,
+    ) -> Result<Raster> {
+        let ((x0, y0), (x1, y1)) = region;
+        let cells =
+            |lo: i32, hi: i32| u64::try_from(i64::from(hi) - i64::from(lo) + 1).unwrap_or(0);
+        let (columns, rows) = (cells(x0, x1), cells(y0, y1));
+        let too_large = || {
+            anyhow!(
+                "image too large: {}x{} cells at {} pixels per cell",
+                columns,
+                rows,
+                scale
+            )
+        };
+        let pixel_size = |cells: u64| {
+            cells
+                .checked_mul(u64::from(scale))
+                .and_then(|pixels| u32::try_from(pixels).ok())
+                .ok_or_else(too_large)
+        };
+        let (width, height) = (pixel_size(columns)?, pixel_size(rows)?);
+        let count = u64::from(width) * u64::from(height);
+        if count > MAX_PIXELS {
+            return Err(too_large());
+        }
+        // The pixel count is capped, so offsets into the pixels fit in u32.
+        let mut pixels = vec![false; count as usize];
+        for (x, y) in world.cells() {
+            if x < x0 || x > x1 || y < y0 || y > y1 {
+                continue;
+            }
+            let column = (x - x0) as u32;
+            let row = (y1 - y) as u32;
+            for py in row * scale..(row + 1) * scale {
+                let start = (py * width + column * scale) as usize;
+                pixels[start..start + scale as usize].fill(true);
+            }
+        }
+        Ok(Raster {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Whether each pixel is a live cell, row by row from the top left.
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    /// Encode the raster as an 8-bit RGB PNG in the colors of `palette`.
+    pub fn write_png<W: Write>(&self, writer: W, palette: &Palette) -> Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        let data = self
+            .pixels
+            .iter()
+            .flat_map(|&alive| {
+                let Rgb(r, g, b) = if alive { palette.live } else { palette.dead };
+                [r, g, b]
+            })
+            .collect::<Vec<u8>>();
+        writer.write_image_data(&data)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::LifePattern;
+
+    #[test]
+    fn renders_scaled_cells() -> Result<()> {
+        let world = LifeWorld::from(&LifePattern::Glider);
+        let raster = Raster::render(&world, ((0, 0), (2, 2)), 2)?;
+        assert_eq!((raster.width, raster.height), (6, 6));
+        // The top row of the glider is `.O.`
+        assert!(!raster.get(0, 0));
+        assert!(raster.get(2, 0));
+        assert!(raster.get(3, 1));
+        // The bottom row is `OOO`
+        assert!(raster.get(0, 5));
+        assert!(raster.get(5, 4));
+        // Images too large to hold are refused rather than overflowing.
+        let everything = ((i32::MIN, 0), (i32::MAX, 0));
+        assert!(Raster::render(&world, everything, 1).is_err());
+        assert!(Raster::render(&world, ((0, 0), (2, 2)), u32::MAX).is_err());
+        assert!(Raster::render(&world, ((0, 0), (9999, 9999)), 1).is_err());
+        assert_eq!(Raster::render(&world, ((1, 1), (0, 0)), 4)?.pixels(), []);
+        Ok(())
+    }
+
+    #[test]
+    fn writes_png() -> Result<()> {
+        let world = LifeWorld::from(&LifePattern::Blinker);
+        let raster = Raster::render(&world, ((-1, -1), (1, 3)), 4)?;
+        let mut buf = Vec::new();
+        raster.write_png(&mut buf, &Palette::default())?;
+        assert_eq!(&buf[..8], b"\x89PNG\r\n\x1a\n");
+        Ok(())
+    }
+
+    #[test]
+    fn parses_colors() -> Result<()> {
+        assert_eq!("#ff8000".parse::<Rgb>()?, Rgb(255, 128, 0));
+        assert_eq!("0A0b0c".parse::<Rgb>()?, Rgb(10, 11, 12));
+        assert_eq!(Rgb(10, 11, 12).to_string(), "#0a0b0c");
+        assert!("#fff".parse::<Rgb>().is_err());
+        assert!("#gg0000".parse::<Rgb>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use crate::config::Config;
+use anyhow::{anyhow, Context, Result};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How many recently loaded files are remembered.
+const MAX_RECENT: usize = 20;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Kind {
+    Pattern,
+    Session,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Pattern => "pattern",
+            Kind::Session => "session",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Item {
+    pub kind: Kind,
+    pub path: PathBuf,
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<8} {}", self.kind.name(), self.path.display())
+    }
+}
+
+/// The recently loaded pattern files and sessions, most recent first, kept in a file in the
+/// data directory with one `<kind> <path>` line per item.
+#[derive(PartialEq, Debug)]
+pub struct Recent {
+    pub items: Vec<Item>,
+}
+
+impl Recent {
+    /// `$XDG_DATA_HOME/rust-life/recent`, or `~/.local/share/rust-life/recent`.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::data_dir()?.join("recent"))
+    }
+
+    /// Read the list, with a missing file being an empty list.
+    pub fn load(path: &Path) -> Result<Recent> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Recent::parse(&contents)
+                .with_context(|| format!("invalid recent files list {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Recent { items: Vec::new() }),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Recent> {
+        let mut items = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let (kind, path) = line.split_once(' ').unwrap_or((line, ""));
+            let kind = match kind {
+                "pattern" => Kind::Pattern,
+                "session" => Kind::Session,
+                _ => return Err(anyhow!("unknown kind: {}", kind)),
+            };
+            items.push(Item {
+                kind,
+                path: PathBuf::from(path),
+            });
+        }
+        Ok(Recent { items })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let contents: String = self
+            .items
+            .iter()
+            .map(|item| format!("{} {}\n", item.kind.name(), item.path.display()))
+            .collect();
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Move a file to the front of the list, adding it if it is new. Paths are made absolute so
+    /// they still work from another directory.
+    pub fn add(&mut self, kind: Kind, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.items.retain(|item| item.path != path);
+        self.items.insert(0, Item { kind, path });
+        self.items.truncate(MAX_RECENT);
+    }
+}
+
+/// Add a file to the list stored at `list`.
+pub fn remember(list: &Path, kind: Kind, path: &Path) -> Result<()> {
+    let mut recent = Recent::load(list)?;
+    recent.add(kind, path);
+    recent.save(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_most_recent_first() -> Result<()> {
+        let mut recent = Recent::parse("pattern /a.rle\nsession /b.session\n")?;
+        recent.add(Kind::Pattern, Path::new("/c.rle"));
+        recent.add(Kind::Session, Path::new("/b.session"));
+        let paths: Vec<_> = recent.items.iter().map(|item| item.path.clone()).collect();
+        assert_eq!(paths, ["/b.session", "/c.rle", "/a.rle"].map(PathBuf::from));
+        assert_eq!(recent.items[0].to_string(), "session  /b.session");
+        for i in 0..MAX_RECENT {
+            recent.add(Kind::Pattern, Path::new(&format!("/{}.rle", i)));
+        }
+        assert_eq!(recent.items.len(), MAX_RECENT);
+        assert!(Recent::parse("movie /a.gif\n").is_err());
+        Ok(())
+    }
+}
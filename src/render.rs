@@ -0,0 +1,227 @@
+use crate::cast::Cast;
+use crate::ui::Frame;
+use anyhow::{anyhow, Result};
+use std::io::{self, Write};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Weight of the latest draw in the running average of draw times.
+const DRAW_TIME_SMOOTHING: f64 = 0.2;
+/// The most generations `FrameSkip` lets pass between frames.
+const MAX_SKIP: usize = 100;
+
+#[derive(Debug, Default)]
+struct Mailbox {
+    /// The next frame to draw. A newer frame replaces it if the renderer has not picked it up yet.
+    frame: Option<Frame>,
+    closed: bool,
+    dropped: usize,
+    /// A running average, so a single slow draw does not change the frame rate.
+    draw_time: Duration,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    mailbox: Mutex<Mailbox>,
+    ready: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> Result<MutexGuard<'_, Mailbox>> {
+        self.mailbox
+            .lock()
+            .map_err(|_| anyhow!("render mailbox poisoned"))
+    }
+}
+
+/// Draws frames on a separate thread so a slow terminal (e.g. over SSH) drops frames instead of
+/// stalling the simulation. Only the most recent frame is kept, so the screen always catches up
+/// to the latest state once the terminal does.
+#[derive(Debug)]
+pub struct Renderer {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl Renderer {
+    /// Start the render thread, also recording every frame to `cast` if there is one.
+    pub fn spawn(cast: Option<Cast>) -> Result<Renderer> {
+        let shared = Arc::new(Shared::default());
+        let worker = Arc::clone(&shared);
+        let handle = thread::Builder::new()
+            .name("render".into())
+            .spawn(move || Renderer::draw_frames(&worker, cast))?;
+        Ok(Renderer {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    fn draw_frames(shared: &Shared, mut cast: Option<Cast>) -> Result<()> {
+        // Frames are drawn into a buffer first so they reach the terminal in one write.
+        let mut buf = Vec::new();
+        loop {
+            let frame = {
+                let mut mailbox = shared.lock()?;
+                loop {
+                    if let Some(frame) = mailbox.frame.take() {
+                        break frame;
+                    }
+                    if mailbox.closed {
+                        return cast.as_mut().map_or(Ok(()), Cast::finish);
+                    }
+                    mailbox = shared
+                        .ready
+                        .wait(mailbox)
+                        .map_err(|_| anyhow!("render mailbox poisoned"))?;
+                }
+            };
+            let start = Instant::now();
+            buf.clear();
+            frame.draw(&mut buf)?;
+            let mut stdout = io::stdout();
+            stdout.write_all(&buf)?;
+            stdout.flush()?;
+            let elapsed = start.elapsed();
+            if let Some(cast) = &mut cast {
+                cast.record(&buf)?;
+            }
+            let mut mailbox = shared.lock()?;
+            mailbox.draw_time = if mailbox.draw_time.is_zero() {
+                elapsed
+            } else {
+                mailbox.draw_time.mul_f64(1. - DRAW_TIME_SMOOTHING)
+                    + elapsed.mul_f64(DRAW_TIME_SMOOTHING)
+            };
+        }
+    }
+
+    /// Queue a frame to be drawn, replacing any frame that is still waiting. Fails if the render
+    /// thread has stopped because drawing failed.
+    pub fn submit(&mut self, frame: Frame) -> Result<()> {
+        if self
+            .handle
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished())
+        {
+            self.join()?;
+        }
+        if self.handle.is_none() {
+            return Err(anyhow!("render thread is not running"));
+        }
+        let mut mailbox = self.shared.lock()?;
+        if mailbox.frame.replace(frame).is_some() {
+            mailbox.dropped += 1;
+        }
+        self.shared.ready.notify_one();
+        Ok(())
+    }
+
+    /// The number of frames replaced before they were drawn, and how long drawing takes on
+    /// average.
+    pub fn stats(&self) -> (usize, Duration) {
+        match self.shared.lock() {
+            Ok(mailbox) => (mailbox.dropped, mailbox.draw_time),
+            Err(_) => (0, Duration::ZERO),
+        }
+    }
+
+    /// Draw any pending frame, then stop the render thread.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Ok(mut mailbox) = self.shared.lock() {
+            mailbox.closed = true;
+        }
+        self.shared.ready.notify_one();
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .map_err(|_| anyhow!("render thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            eprintln!("Error stopping render thread: {}", e);
+        }
+    }
+}
+
+/// Decides which generations get a frame. While drawing keeps up with the timestep every
+/// generation is shown; when it takes longer, frames are only captured every few generations so
+/// the simulation keeps its speed instead of spending time on frames the terminal cannot show.
+#[derive(Debug)]
+pub struct FrameSkip {
+    every: usize,
+    since: usize,
+}
+
+impl FrameSkip {
+    pub fn new() -> FrameSkip {
+        FrameSkip { every: 1, since: 0 }
+    }
+
+    /// Count a generation and return whether it should be drawn, adjusting the interval to how
+    /// long drawing takes.
+    pub fn due(&mut self, draw_time: Duration, timestep: Duration) -> bool {
+        self.since += 1;
+        if self.since < self.every {
+            return false;
+        }
+        self.since = 0;
+        let needed = if timestep.is_zero() {
+            MAX_SKIP
+        } else {
+            (draw_time.as_secs_f64() / timestep.as_secs_f64()).ceil() as usize
+        };
+        self.every = needed.clamp(1, MAX_SKIP);
+        true
+    }
+
+    /// Draw every generation again, e.g. after the timestep changed.
+    pub fn reset(&mut self) {
+        self.every = 1;
+        self.since = 0;
+    }
+
+    /// Generations per frame; 1 while drawing keeps up.
+    pub fn every(&self) -> usize {
+        self.every
+    }
+}
+
+impl Default for FrameSkip {
+    fn default() -> Self {
+        FrameSkip::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_frames_while_drawing_is_slow() {
+        let timestep = Duration::from_millis(10);
+        let mut skip = FrameSkip::new();
+        assert!(skip.due(Duration::from_millis(5), timestep));
+        assert_eq!(skip.every(), 1);
+        assert!(skip.due(Duration::from_millis(25), timestep));
+        assert_eq!(skip.every(), 3);
+        let drawn: Vec<bool> = (0..6)
+            .map(|_| skip.due(Duration::from_millis(25), timestep))
+            .collect();
+        assert_eq!(drawn, [false, false, true, false, false, true]);
+        // Drawing sped up again, so every generation is shown from the next frame on.
+        skip.since = 2;
+        assert!(skip.due(Duration::from_millis(2), timestep));
+        assert_eq!(skip.every(), 1);
+    }
+}
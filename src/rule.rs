@@ -0,0 +1,947 @@
+use crate::turmite::Turmite;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How cells evolve: whether a cell is alive in the next generation given whether it is alive
+/// now and how many of its neighbors are.
+///
+/// A [`LifeWorld`](crate::LifeWorld) holds its rule behind an `Arc`, so rules can be swapped
+/// while it runs. Rules display as the rulestring they are parsed from, and rules with the same
+/// rulestring are equal.
+pub trait Rule: fmt::Debug + fmt::Display + Send + Sync {
+    fn next_state(&self, alive: bool, neighbors: u8) -> bool;
+
+    /// The number of states a cell can be in: alive, dead, and any dying states in between that
+    /// a cell passes through, one per generation, after it dies. Dying cells don't count as
+    /// neighbors and can't be born again until they are fully dead, unless the rule
+    /// [`revives`](Rule::revives) them.
+    fn states(&self) -> u8 {
+        2
+    }
+
+    /// How far the neighborhood reaches in each direction: the eight surrounding cells for a
+    /// radius of 1, or the (2r+1)² - 1 cells around a cell in general. At most
+    /// [`MAX_RADIUS`], so that neighbor counts fit in a `u8`.
+    fn radius(&self) -> u8 {
+        1
+    }
+
+    /// Whether the next state depends on where the live neighbors are, not just how many there
+    /// are, in which case [`next_state_in`](Rule::next_state_in) is used instead of
+    /// `next_state`.
+    fn by_arrangement(&self) -> bool {
+        false
+    }
+
+    /// The next state given which of the eight neighbors are alive, as bits for the neighbors
+    /// to the NW, N, NE, W, E, SW, S and SE from the lowest.
+    fn next_state_in(&self, alive: bool, arrangement: u8) -> bool {
+        self.next_state(alive, arrangement.count_ones() as u8)
+    }
+
+    /// How far a dying cell is through the dying states in the next generation, given how far it
+    /// is now (1 just after it died). By default it moves on one state each generation and is
+    /// fully dead (0) after the last.
+    fn next_decay(&self, decay: u8) -> u8 {
+        if decay < self.states().saturating_sub(2) {
+            decay + 1
+        } else {
+            0
+        }
+    }
+
+    /// Whether a dying cell comes back to life with `neighbors` live neighbors instead of
+    /// decaying further. Never, by default.
+    fn revives(&self, _decay: u8, _neighbors: u8) -> bool {
+        false
+    }
+
+    /// The name of a state for the editing palette, numbered as in multi-state RLE: 1 for alive
+    /// and 2 onwards for the dying states in order.
+    fn state_name(&self, state: u8) -> String {
+        match state {
+            0 => String::from("dead"),
+            1 => String::from("alive"),
+            _ => format!("dying state {}", state - 1),
+        }
+    }
+
+    /// The turmite table, if the rule moves ants around the world instead of changing cells by
+    /// their neighbors. Then only [`states`](Rule::states) matters of the rest.
+    fn turmite(&self) -> Option<&Turmite> {
+        None
+    }
+
+    /// The elementary rule, if the rule grows a single row down the world one generation at a
+    /// time instead of changing cells by their neighbors.
+    fn elementary(&self) -> Option<&Elementary> {
+        None
+    }
+}
+
+/// The largest neighborhood radius a rule can have.
+pub const MAX_RADIUS: u8 = 7;
+
+impl PartialEq for dyn Rule + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+/// Conway's Game of Life, the rule worlds start with.
+pub fn life() -> Arc<dyn Rule> {
+    Arc::new(Totalistic::LIFE)
+}
+
+/// Well-known rules, by name and rulestring (as rules display).
+pub const PRESETS: &[(&str, &str)] = &[
+    ("life", "B3/S23"),
+    ("highlife", "B36/S23"),
+    ("day-and-night", "B3678/S34678"),
+    ("seeds", "B2/S"),
+    ("life-without-death", "B3/S012345678"),
+    ("maze", "B3/S12345"),
+    ("2x2", "B36/S125"),
+    ("brians-brain", "B2/S/3"),
+    ("star-wars", "B2/S345/4"),
+    ("bosco", "R5,C0,M1,S34..58,B34..45,NM"),
+    ("majority", "R4,C0,M1,S41..81,B41..81,NM"),
+    ("wireworld", "WireWorld"),
+    ("langtons-ant", "RL"),
+    ("rule-30", "W30"),
+    ("rule-90", "W90"),
+    ("rule-110", "W110"),
+];
+
+/// Parse a rulestring, or the name of a preset, into a rule.
+pub fn parse(s: &str) -> Result<Arc<dyn Rule>> {
+    if let Ok(rule) = preset(s) {
+        return Ok(rule);
+    }
+    parse_rulestring(s)
+}
+
+/// Parse a rulestring: a Larger than Life rule if it gives a radius, a Generations rule if it
+/// has a state count, an isotropic rule if it has Hensel letters, a turmite if it is a table
+/// in braces or only turns, an elementary rule if it is `W` and a number, otherwise a B/S
+/// rule. WireWorld has no rulestring and goes by its name.
+fn parse_rulestring(s: &str) -> Result<Arc<dyn Rule>> {
+    if s.trim().eq_ignore_ascii_case("wireworld") {
+        return Ok(Arc::new(WireWorld));
+    }
+    if let Some(code) = s.trim().strip_prefix(['W', 'w'])
+        && !code.is_empty()
+        && code.chars().all(|c| c.is_ascii_digit())
+    {
+        return Ok(Arc::new(s.parse::<Elementary>()?));
+    }
+    let turns = s
+        .trim()
+        .chars()
+        .all(|c| "LRNU".contains(c.to_ascii_uppercase()));
+    if s.trim_start().starts_with('{') || (turns && !s.trim().is_empty()) {
+        return Ok(Arc::new(s.parse::<Turmite>()?));
+    }
+    let letters = s.as_bytes().windows(2).any(|pair| {
+        let letter = pair[1].is_ascii_alphabetic() && !b"Ss".contains(&pair[1]);
+        pair[0].is_ascii_digit() && (pair[1] == b'-' || letter)
+    });
+    if s.trim_start().starts_with(['R', 'r']) && s.contains(',') {
+        Ok(Arc::new(s.parse::<LargerThanLife>()?))
+    } else if s.matches('/').count() == 2 {
+        Ok(Arc::new(s.parse::<Generations>()?))
+    } else if letters {
+        Ok(Arc::new(s.parse::<Isotropic>()?))
+    } else {
+        Ok(Arc::new(s.parse::<Totalistic>()?))
+    }
+}
+
+/// The preset rule with a name, ignoring case.
+pub fn preset(name: &str) -> Result<Arc<dyn Rule>> {
+    let (_, rulestring) = PRESETS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+        .ok_or_else(|| {
+            let names: Vec<&str> = PRESETS.iter().map(|(name, _)| *name).collect();
+            anyhow!(
+                "unknown rule preset: {} (expected {})",
+                name,
+                names.join(", ")
+            )
+        })?;
+    parse_rulestring(rulestring)
+}
+
+/// The name of the preset a rule is, if any.
+pub fn preset_name(rule: &dyn Rule) -> Option<&'static str> {
+    let rulestring = rule.to_string();
+    PRESETS
+        .iter()
+        .find(|(_, preset)| *preset == rulestring)
+        .map(|(name, _)| *name)
+}
+
+/// An outer totalistic rule for two-state automata, given by the neighbor counts at which dead
+/// cells are born and live cells survive. Written in B/S notation, e.g. `B3/S23` for Conway's
+/// Life or `B36/S23` for HighLife.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Totalistic {
+    birth: [bool; 9],
+    survival: [bool; 9],
+}
+
+impl Totalistic {
+    /// Conway's Game of Life, `B3/S23`.
+    pub const LIFE: Totalistic = Totalistic {
+        birth: [false, false, false, true, false, false, false, false, false],
+        survival: [false, false, true, true, false, false, false, false, false],
+    };
+
+    pub fn births(&self, neighbors: usize) -> bool {
+        self.birth.get(neighbors).copied().unwrap_or(false)
+    }
+
+    pub fn survives(&self, neighbors: usize) -> bool {
+        self.survival.get(neighbors).copied().unwrap_or(false)
+    }
+
+    /// Turn birth at a neighbor count on or off. Fails for counts above 8 and for `B0`, which
+    /// isn't supported.
+    pub fn toggle_birth(&mut self, neighbors: usize) -> Result<()> {
+        if neighbors == 0 {
+            return Err(anyhow!("unsupported rule: B0 rules fill empty space"));
+        }
+        toggle(&mut self.birth, neighbors)
+    }
+
+    /// Turn survival at a neighbor count on or off. Fails for counts above 8.
+    pub fn toggle_survival(&mut self, neighbors: usize) -> Result<()> {
+        toggle(&mut self.survival, neighbors)
+    }
+}
+
+fn toggle(table: &mut [bool; 9], neighbors: usize) -> Result<()> {
+    let count = table
+        .get_mut(neighbors)
+        .ok_or_else(|| anyhow!("invalid neighbor count: {}", neighbors))?;
+    *count = !*count;
+    Ok(())
+}
+
+impl Rule for Totalistic {
+    fn next_state(&self, alive: bool, neighbors: u8) -> bool {
+        if alive {
+            self.survives(usize::from(neighbors))
+        } else {
+            self.births(usize::from(neighbors))
+        }
+    }
+}
+
+impl Default for Totalistic {
+    fn default() -> Self {
+        Totalistic::LIFE
+    }
+}
+
+impl fmt::Display for Totalistic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |table: &[bool; 9]| -> String {
+            (0..9)
+                .filter(|&n| table[n])
+                .map(|n| n.to_string())
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+impl FromStr for Totalistic {
+    type Err = anyhow::Error;
+
+    /// Parse B/S notation (`B36/S23`, case insensitive, with or without the slash) or the older
+    /// S/B notation used by Life 1.05 files (`23/36`).
+    fn from_str(s: &str) -> Result<Totalistic> {
+        let s = s.trim().to_ascii_uppercase();
+        let (birth, survival) = if let Some(rest) = s.strip_prefix('B') {
+            let (birth, survival) = rest
+                .split_once(['/', 'S'])
+                .ok_or_else(|| anyhow!("invalid rule: {} (expected e.g. B3/S23)", s))?;
+            (birth, survival.strip_prefix('S').unwrap_or(survival))
+        } else {
+            let (survival, birth) = s
+                .split_once('/')
+                .ok_or_else(|| anyhow!("invalid rule: {} (expected e.g. B3/S23)", s))?;
+            (birth, survival)
+        };
+        let rule = Totalistic {
+            birth: counts(birth).ok_or_else(|| anyhow!("invalid rule: {}", s))?,
+            survival: counts(survival).ok_or_else(|| anyhow!("invalid rule: {}", s))?,
+        };
+        // Only cells near live ones are visited, so births in empty space would be missed.
+        if rule.births(0) {
+            return Err(anyhow!(
+                "unsupported rule: {} (B0 rules fill empty space)",
+                s
+            ));
+        }
+        Ok(rule)
+    }
+}
+
+/// A Generations rule: a B/S rule where cells that die take a number of generations to decay,
+/// passing through dying states that neither count as neighbors nor can be born. Written as the
+/// B/S rule followed by the total number of states, e.g. `B2/S/3` for Brian's Brain.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Generations {
+    table: Totalistic,
+    states: u8,
+}
+
+impl Rule for Generations {
+    fn next_state(&self, alive: bool, neighbors: u8) -> bool {
+        self.table.next_state(alive, neighbors)
+    }
+
+    fn states(&self) -> u8 {
+        self.states
+    }
+}
+
+impl fmt::Display for Generations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.table, self.states)
+    }
+}
+
+impl FromStr for Generations {
+    type Err = anyhow::Error;
+
+    /// Parse a B/S rule in either notation followed by the number of states, optionally prefixed
+    /// with `C` (`B2/S/3`, `B2/S/C3` or `/2/3`).
+    fn from_str(s: &str) -> Result<Generations> {
+        let (table, states) = s
+            .trim()
+            .rsplit_once('/')
+            .ok_or_else(|| anyhow!("invalid rule: {} (expected e.g. B2/S/3)", s))?;
+        let states = states.strip_prefix(['C', 'c']).unwrap_or(states);
+        let states: u8 = match states.parse() {
+            Ok(states @ 3..) => states,
+            _ => return Err(anyhow!("invalid rule: {} (expected 3 to 255 states)", s)),
+        };
+        Ok(Generations {
+            table: table.parse()?,
+            states,
+        })
+    }
+}
+
+/// WireWorld, for simulating electronic circuits: electrons run along wires as an electron head
+/// followed by a tail. Heads become tails, tails become wire again, and wire becomes a head next
+/// to one or two heads. Heads are the live cells and tails and wire the two dying states, with
+/// wire decaying no further, so nothing is ever born in empty space.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct WireWorld;
+
+impl WireWorld {
+    /// How far electron tails are through the dying states.
+    pub const TAIL: u8 = 1;
+    /// How far wire is through the dying states.
+    pub const WIRE: u8 = 2;
+}
+
+impl Rule for WireWorld {
+    fn next_state(&self, _alive: bool, _neighbors: u8) -> bool {
+        false
+    }
+
+    fn states(&self) -> u8 {
+        4
+    }
+
+    fn next_decay(&self, _decay: u8) -> u8 {
+        WireWorld::WIRE
+    }
+
+    fn revives(&self, decay: u8, neighbors: u8) -> bool {
+        decay == WireWorld::WIRE && (1..=2).contains(&neighbors)
+    }
+
+    fn state_name(&self, state: u8) -> String {
+        let name = match state {
+            0 => "empty",
+            1 => "electron head",
+            2 => "electron tail",
+            _ => "wire",
+        };
+        String::from(name)
+    }
+}
+
+impl fmt::Display for WireWorld {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WireWorld")
+    }
+}
+
+/// An elementary cellular automaton: a single row of cells, each of which is alive in the next
+/// generation depending on it and its two neighbors, numbered as Wolfram does: bit `n` of the
+/// rule number says whether a cell is alive next when the cell to its left, itself and the one
+/// to its right spell out `n` in binary. Written as `W` and the number, e.g. `W110` for Rule
+/// 110.
+///
+/// Each generation is written as a new row one below the last, starting from the lowest row of the
+/// pattern, so the world keeps the whole history with `y` as time. Odd numbers, which would fill
+/// the empty space, are not supported.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Elementary {
+    number: u8,
+}
+
+impl Elementary {
+    /// Whether a cell is alive in the next generation given whether it and its neighbors are.
+    pub fn next(&self, left: bool, center: bool, right: bool) -> bool {
+        let index = u8::from(left) << 2 | u8::from(center) << 1 | u8::from(right);
+        self.number >> index & 1 == 1
+    }
+}
+
+impl Rule for Elementary {
+    fn next_state(&self, _alive: bool, _neighbors: u8) -> bool {
+        false
+    }
+
+    fn elementary(&self) -> Option<&Elementary> {
+        Some(self)
+    }
+}
+
+impl fmt::Display for Elementary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "W{}", self.number)
+    }
+}
+
+impl FromStr for Elementary {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Elementary> {
+        let s = s.trim();
+        let number: u8 = s
+            .strip_prefix(['W', 'w'])
+            .and_then(|number| number.parse().ok())
+            .ok_or_else(|| anyhow!("invalid rule: {} (expected e.g. W110)", s))?;
+        if number % 2 == 1 {
+            return Err(anyhow!(
+                "unsupported rule: {} (odd rules fill empty space)",
+                s
+            ));
+        }
+        Ok(Elementary { number })
+    }
+}
+
+/// An isotropic non-totalistic rule: births and survival depend on the arrangement of the live
+/// neighbors up to rotation and reflection, not just on how many there are. Written in Hensel
+/// notation, where letters after a count pick out arrangements with that many neighbors and a
+/// `-` excludes them instead: in `B2-a/S12`, cells are born with two neighbors unless they are
+/// next to each other (`a`).
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Isotropic {
+    /// Indexed by arrangement, as for [`Rule::next_state_in`].
+    birth: Box<[bool; 256]>,
+    survival: Box<[bool; 256]>,
+}
+
+/// The Hensel letters for each neighbor count up to 4 and an arrangement of each, as Golly
+/// numbers them: bits for NW, N, NE, W, the cell itself, E, SW, S and SE from the lowest. The
+/// arrangements for higher counts are the complements of those for lower ones, with the same
+/// letters.
+const HENSEL: [(&str, &[u16]); 5] = [
+    ("", &[0]),
+    ("ce", &[1, 2]),
+    ("ceaikn", &[5, 10, 3, 40, 33, 68]),
+    ("ceaiknjqry", &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97]),
+    (
+        "ceaiknjqrtwyz",
+        &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108],
+    ),
+];
+
+/// The letters for a neighbor count.
+fn hensel_letters(count: usize) -> &'static str {
+    HENSEL[count.min(8 - count)].0
+}
+
+/// The Hensel letter of every arrangement of neighbors (`' '` for counts 0 and 8).
+fn hensel_table() -> [char; 256] {
+    // The positions of the neighbors in the order of their bits.
+    const POSITIONS: [(i32, i32); 8] = [
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ];
+    let mut table = [' '; 256];
+    for (count, (letters, arrangements)) in HENSEL.iter().enumerate() {
+        for (letter, &arrangement) in letters.chars().zip(arrangements.iter()) {
+            let arrangement = (arrangement & 0xf) | (arrangement >> 1 & 0xf0);
+            // Rotate and reflect the arrangement every way.
+            for turns in 0..4 {
+                for flip in [false, true] {
+                    let mut image = 0;
+                    for (bit, &(mut x, mut y)) in POSITIONS.iter().enumerate() {
+                        if arrangement & 1 << bit == 0 {
+                            continue;
+                        }
+                        for _ in 0..turns {
+                            (x, y) = (-y, x);
+                        }
+                        if flip {
+                            x = -x;
+                        }
+                        image |= 1 << POSITIONS.iter().position(|&p| p == (x, y)).unwrap_or(0);
+                    }
+                    table[image] = letter;
+                    if count < 4 {
+                        table[255 - image] = letter;
+                    }
+                }
+            }
+        }
+    }
+    table
+}
+
+impl Rule for Isotropic {
+    fn next_state(&self, alive: bool, neighbors: u8) -> bool {
+        // Only used if asked directly: whether any arrangement of that many neighbors counts.
+        let table = if alive { &self.survival } else { &self.birth };
+        (0..=255u8).any(|a| a.count_ones() == u32::from(neighbors) && table[usize::from(a)])
+    }
+
+    fn by_arrangement(&self) -> bool {
+        true
+    }
+
+    fn next_state_in(&self, alive: bool, arrangement: u8) -> bool {
+        let table = if alive { &self.survival } else { &self.birth };
+        table[usize::from(arrangement)]
+    }
+}
+
+impl fmt::Display for Isotropic {
+    /// Each count is written alone if all of its arrangements are included, and otherwise
+    /// followed by the letters included or, if that is shorter, by `-` and the letters excluded.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letters = hensel_table();
+        let part = |table: &[bool; 256]| -> String {
+            let mut out = String::new();
+            for count in 0..=8 {
+                let arrangements = (0..256).filter(|&a: &usize| a.count_ones() as usize == count);
+                let includes = |letter| {
+                    arrangements
+                        .clone()
+                        .any(|a| letters[a] == letter && table[a])
+                };
+                let (included, excluded): (String, String) = hensel_letters(count)
+                    .chars()
+                    .partition(|&letter| includes(letter));
+                if !arrangements.clone().any(|a| table[a]) {
+                    continue;
+                }
+                out += &count.to_string();
+                if excluded.is_empty() {
+                    continue;
+                }
+                if included.len() <= excluded.len() {
+                    out += &included;
+                } else {
+                    out += &format!("-{}", excluded);
+                }
+            }
+            out
+        };
+        write!(f, "B{}/S{}", part(&self.birth), part(&self.survival))
+    }
+}
+
+impl FromStr for Isotropic {
+    type Err = anyhow::Error;
+
+    /// Parse Hensel notation, e.g. `B2-a/S12` or `B3/S2-i34q`.
+    fn from_str(s: &str) -> Result<Isotropic> {
+        let invalid = || anyhow!("invalid rule: {} (expected e.g. B2-a/S12)", s);
+        let (birth, survival) = s
+            .trim()
+            .strip_prefix(['B', 'b'])
+            .and_then(|rest| rest.split_once('/'))
+            .ok_or_else(invalid)?;
+        let survival = survival.strip_prefix(['S', 's']).ok_or_else(invalid)?;
+        let letters = hensel_table();
+        let rule = Isotropic {
+            birth: arrangements(birth, &letters).ok_or_else(invalid)?,
+            survival: arrangements(survival, &letters).ok_or_else(invalid)?,
+        };
+        // Only cells near live ones are visited, so births in empty space would be missed.
+        if rule.birth[0] {
+            return Err(anyhow!(
+                "unsupported rule: {} (B0 rules fill empty space)",
+                s
+            ));
+        }
+        Ok(rule)
+    }
+}
+
+/// A table of the arrangements picked out by the counts and letters of one half of a Hensel
+/// rulestring.
+fn arrangements(part: &str, letters: &[char; 256]) -> Option<Box<[bool; 256]>> {
+    let mut table = Box::new([false; 256]);
+    let mut chars = part.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = c.to_digit(10).filter(|&count| count <= 8)? as usize;
+        let exclude = chars.next_if_eq(&'-').is_some();
+        let mut chosen = String::new();
+        while let Some(letter) = chars.next_if(char::is_ascii_alphabetic) {
+            let letter = letter.to_ascii_lowercase();
+            if !hensel_letters(count).contains(letter) {
+                return None;
+            }
+            chosen.push(letter);
+        }
+        if exclude && chosen.is_empty() {
+            return None;
+        }
+        for (arrangement, slot) in table.iter_mut().enumerate() {
+            if arrangement.count_ones() as usize == count
+                && (chosen.is_empty() || chosen.contains(letters[arrangement]) != exclude)
+            {
+                *slot = true;
+            }
+        }
+    }
+    Some(table)
+}
+
+/// A Larger than Life rule: births and survival each happen over a range of neighbor counts,
+/// in a square neighborhood of a given radius. Written in Golly's notation, e.g.
+/// `R5,C0,M1,S34..58,B34..45,NM` for Bosco's Rule: radius 5, two states (`C0`), the middle
+/// cell counted as its own neighbor (`M1`), and the Moore neighborhood (`NM`). More than two
+/// states give dying states, as in a [`Generations`] rule.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LargerThanLife {
+    radius: u8,
+    states: u8,
+    middle: bool,
+    survival: RangeInclusive<u8>,
+    birth: RangeInclusive<u8>,
+}
+
+impl Rule for LargerThanLife {
+    fn next_state(&self, alive: bool, neighbors: u8) -> bool {
+        if alive {
+            self.survival.contains(&(neighbors + u8::from(self.middle)))
+        } else {
+            self.birth.contains(&neighbors)
+        }
+    }
+
+    fn states(&self) -> u8 {
+        self.states
+    }
+
+    fn radius(&self) -> u8 {
+        self.radius
+    }
+}
+
+impl fmt::Display for LargerThanLife {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},NM",
+            self.radius,
+            if self.states == 2 { 0 } else { self.states },
+            u8::from(self.middle),
+            self.survival.start(),
+            self.survival.end(),
+            self.birth.start(),
+            self.birth.end(),
+        )
+    }
+}
+
+impl FromStr for LargerThanLife {
+    type Err = anyhow::Error;
+
+    /// Parse Golly's notation. `C`, `M` and `N` may be left out, defaulting to two states, the
+    /// middle cell not counted, and the Moore neighborhood, which is the only one supported.
+    /// A range can be a single count, e.g. `B3`.
+    fn from_str(s: &str) -> Result<LargerThanLife> {
+        let invalid = || {
+            anyhow!(
+                "invalid rule: {} (expected e.g. R5,C0,M1,S34..58,B34..45,NM)",
+                s
+            )
+        };
+        let (mut radius, mut states, mut middle) = (0, 2, false);
+        let (mut survival, mut birth) = (None, None);
+        for field in s.trim().to_ascii_uppercase().split(',') {
+            let (key, value) = field.split_at_checked(1).ok_or_else(invalid)?;
+            let number = || value.parse::<u8>().map_err(|_| invalid());
+            match key {
+                "R" => radius = number()?,
+                "C" => states = number()?.max(2),
+                "M" => middle = number()? == 1,
+                "S" => survival = Some(range(value).ok_or_else(invalid)?),
+                "B" => birth = Some(range(value).ok_or_else(invalid)?),
+                "N" if value == "M" => (),
+                "N" => return Err(anyhow!("unsupported rule: {} (only NM is supported)", s)),
+                _ => return Err(invalid()),
+            }
+        }
+        if !(1..=MAX_RADIUS).contains(&radius) {
+            return Err(anyhow!(
+                "unsupported rule: {} (the radius must be 1 to {})",
+                s,
+                MAX_RADIUS
+            ));
+        }
+        let (survival, birth) = survival.zip(birth).ok_or_else(invalid)?;
+        // Only cells near live ones are visited, so births in empty space would be missed.
+        if *birth.start() == 0 {
+            return Err(anyhow!(
+                "unsupported rule: {} (births at 0 fill empty space)",
+                s
+            ));
+        }
+        Ok(LargerThanLife {
+            radius,
+            states,
+            middle,
+            survival,
+            birth,
+        })
+    }
+}
+
+/// A range of neighbor counts, `3..5` or just `3`.
+fn range(s: &str) -> Option<RangeInclusive<u8>> {
+    match s.split_once("..") {
+        Some((start, end)) => Some(start.parse().ok()?..=end.parse().ok()?),
+        None => {
+            let count = s.parse().ok()?;
+            Some(count..=count)
+        }
+    }
+}
+
+/// A table of which neighbor counts appear in a string of digits.
+fn counts(digits: &str) -> Option<[bool; 9]> {
+    let mut table = [false; 9];
+    for c in digits.chars() {
+        *table.get_mut(c.to_digit(10)? as usize)? = true;
+    }
+    Some(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn applies_the_rule_table() -> Result<()> {
+        let seeds = parse("B2/S")?;
+        assert!(seeds.next_state(false, 2));
+        assert!(!seeds.next_state(true, 2));
+        let life = life();
+        assert!(life.next_state(true, 2) && life.next_state(false, 3));
+        assert!(!life.next_state(false, 2) && !life.next_state(true, 4));
+        assert!(*parse("b3s23")? == *life);
+        assert!(*seeds != *life);
+        Ok(())
+    }
+
+    #[test]
+    fn names_presets() -> Result<()> {
+        assert_eq!(preset("HighLife")?.to_string(), "B36/S23");
+        assert_eq!(parse("day-and-night")?.to_string(), "B3678/S34678");
+        assert!(preset("B3/S23").is_err());
+        assert_eq!(preset_name(&*parse("B2/S")?), Some("seeds"));
+        assert_eq!(
+            preset_name(&*parse("B3/S012345678")?),
+            Some("life-without-death")
+        );
+        assert_eq!(preset_name(&*parse("B34/S34")?), None);
+        for (name, rulestring) in PRESETS {
+            assert_eq!(preset(name)?.to_string(), *rulestring);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parses_rulestrings() -> Result<()> {
+        assert_eq!("B3/S23".parse::<Totalistic>()?, Totalistic::LIFE);
+        assert_eq!("b3s23".parse::<Totalistic>()?, Totalistic::LIFE);
+        assert_eq!("23/3".parse::<Totalistic>()?, Totalistic::LIFE);
+        let highlife: Totalistic = "B36/S23".parse()?;
+        assert!(highlife.births(6) && !highlife.survives(6));
+        assert_eq!(highlife.to_string(), "B36/S23");
+        assert_eq!("B2/S".parse::<Totalistic>()?.to_string(), "B2/S");
+        assert!("B9/S23".parse::<Totalistic>().is_err());
+        assert!("B03/S23".parse::<Totalistic>().is_err());
+        assert!("Life".parse::<Totalistic>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_generations_rules() -> Result<()> {
+        let brain = parse("B2/S/3")?;
+        assert_eq!(brain.states(), 3);
+        assert_eq!(brain.to_string(), "B2/S/3");
+        assert!(*parse("/2/C3")? == *brain);
+        assert_eq!(preset_name(&*brain), Some("brians-brain"));
+        assert_eq!(preset("star-wars")?.states(), 4);
+        assert_eq!(life().states(), 2);
+        assert!(parse("B2/S/2").is_err());
+        assert!(parse("B2/S/256").is_err());
+        assert!(parse("B0/S/3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_wireworld() -> Result<()> {
+        let wireworld = parse("wireworld")?;
+        assert_eq!(wireworld.to_string(), "WireWorld");
+        assert!(*parse("WireWorld")? == *wireworld);
+        assert_eq!(wireworld.states(), 4);
+        assert_eq!(wireworld.next_decay(WireWorld::TAIL), WireWorld::WIRE);
+        assert_eq!(wireworld.next_decay(WireWorld::WIRE), WireWorld::WIRE);
+        let revives: Vec<u8> = (0..=8)
+            .filter(|&n| wireworld.revives(WireWorld::WIRE, n))
+            .collect();
+        assert_eq!(revives, [1, 2]);
+        assert!(!wireworld.revives(WireWorld::TAIL, 1));
+        assert_eq!(parse("B2/S/3")?.next_decay(1), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_turmites() -> Result<()> {
+        let ant = parse("{{{1,2,0},{0,8,0}}}")?;
+        assert_eq!(ant.to_string(), "RL");
+        assert_eq!(preset_name(&*ant), Some("langtons-ant"));
+        assert!(ant.turmite().is_some());
+        assert_eq!(parse("LLRR")?.states(), 4);
+        assert!(life().turmite().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_elementary_rules() -> Result<()> {
+        let rule = parse("w110")?;
+        assert_eq!(rule.to_string(), "W110");
+        assert_eq!(preset_name(&*rule), Some("rule-110"));
+        let elementary = rule.elementary().unwrap();
+        // 110 is 01101110 in binary.
+        assert!(elementary.next(false, false, true));
+        assert!(!elementary.next(true, false, false));
+        assert!(!elementary.next(true, true, true));
+        assert!(parse("W31").is_err());
+        assert!(parse("W256").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_hensel_notation() -> Result<()> {
+        for rulestring in ["B2-a/S12", "B3/S2-i34q", "B2ce3/S23", "B35y/S1e2-ak"] {
+            assert_eq!(parse(rulestring)?.to_string(), rulestring);
+        }
+        assert_eq!(parse("b2ceaikn/s2-CE")?.to_string(), "B2/S2-ce");
+        let rule = parse("B2-a/S12")?;
+        assert!(rule.by_arrangement());
+        // NW and N are an `a` pair, N and S an `i` pair.
+        assert!(!rule.next_state_in(false, 0b11) && rule.next_state_in(false, 0b100_0010));
+        for bad in ["B2x/S", "B0c/S", "B1c/S9", "B2-/S", "B8c/S"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+        // Every arrangement has a letter of its count, and every letter an arrangement.
+        let table = hensel_table();
+        for count in 1..8 {
+            let letters: String = (0..256usize)
+                .filter(|a| a.count_ones() as usize == count)
+                .map(|a| table[a])
+                .collect();
+            assert!(letters
+                .chars()
+                .all(|letter| hensel_letters(count).contains(letter)));
+            assert!(hensel_letters(count)
+                .chars()
+                .all(|letter| letters.contains(letter)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parses_larger_than_life_rules() -> Result<()> {
+        let bosco = parse("bosco")?;
+        assert_eq!((bosco.radius(), bosco.states()), (5, 2));
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        // The middle cell counts towards survival.
+        assert!(bosco.next_state(true, 33) && !bosco.next_state(true, 58));
+        assert!(bosco.next_state(false, 34) && !bosco.next_state(false, 46));
+        let short = parse("r2,b3,s2..3")?;
+        assert_eq!(short.to_string(), "R2,C0,M0,S2..3,B3..3,NM");
+        assert_eq!(parse("R1,C3,M0,S2..3,B3,NM")?.states(), 3);
+        assert!(parse("R8,C0,M0,S2..3,B3,NM").is_err());
+        assert!(parse("R2,C0,M0,S2..3,B0..3,NM").is_err());
+        assert!(parse("R2,C0,M0,S2..3,B3,NN").is_err());
+        assert!(parse("R2,C0,M0,S2..3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn scrolls_with_elementary_rows() -> Result<()> {
+        let mut harness = Harness::new(&["--rule-name", "rule-90", "--build"], 20, 10)?;
+        harness.type_keys("e2")?;
+        // Row n of Sierpinski's triangle has 2 to the number of ones in n cells.
+        let rows: u32 = (0..=10u32).map(|n| 1 << n.count_ones()).sum();
+        assert_eq!(harness.cells().len(), rows as usize);
+        let (min, max) = harness.program.screen.inner_region(0);
+        assert!(harness.program.screen.camera.y < 0);
+        assert!((min.1..=max.1).contains(&-10));
+        Ok(())
+    }
+
+    #[test]
+    fn runs_other_rules() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "blinker", "--rule", "B2/S", "--build"], 40, 20)?;
+        harness.type_keys(".")?;
+        assert_eq!(harness.cells(), vec![(-1, 0), (-1, 2), (1, 0), (1, 2)]);
+        assert!(harness.status().contains(", rule: B2/S,"));
+        harness
+            .program
+            .paste_text("x = 3, y = 1, rule = B36/S23\n3o!")?;
+        assert_eq!(
+            harness.status(),
+            "merged 3 cells from the clipboard at 0,0, which is for rule B36/S23: \
+             switch to it? (y/n)"
+        );
+        harness.type_keys("n")?;
+        assert_eq!(harness.status(), "kept rule B2/S");
+        harness.type_keys(":rule B36/S23\n")?;
+        assert_eq!(harness.status(), "rule: B36/S23");
+        assert_eq!(harness.program.session().rule.to_string(), "B36/S23");
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use crate::rule::{self, Rule, Totalistic};
+use anyhow::{anyhow, Result};
+
+/// A grid of the birth and survival counts of a B/S rule, shown over the grid, with one count
+/// selected for toggling.
+#[derive(Debug)]
+pub struct RuleEditor {
+    pub rule: Totalistic,
+    /// Editing the survival row rather than the birth row.
+    survival: bool,
+    neighbors: usize,
+}
+
+impl RuleEditor {
+    /// Start editing a rule, which has to be a B/S rule.
+    pub fn new(rule: &dyn Rule) -> Result<RuleEditor> {
+        let rule = rule
+            .to_string()
+            .parse()
+            .map_err(|_| anyhow!("the rule editor only edits B/S rules, not {}", rule))?;
+        Ok(RuleEditor {
+            rule,
+            survival: false,
+            neighbors: 3,
+        })
+    }
+
+    pub fn left(&mut self) {
+        self.neighbors = self.neighbors.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.neighbors = (self.neighbors + 1).min(8);
+    }
+
+    /// Switch between the birth and survival rows.
+    pub fn switch_row(&mut self) {
+        self.survival = !self.survival;
+    }
+
+    /// Switch to the B/S preset after the current rule (or before, with `back`), in the order
+    /// they are listed.
+    pub fn cycle_preset(&mut self, back: bool) -> Result<()> {
+        let presets: Vec<Totalistic> = rule::PRESETS
+            .iter()
+            .filter_map(|(_, rulestring)| rulestring.parse().ok())
+            .collect();
+        let count = presets.len();
+        let current = presets.iter().position(|preset| *preset == self.rule);
+        let next = match current {
+            Some(current) if back => current + count - 1,
+            Some(current) => current + 1,
+            None if back => count - 1,
+            None => 0,
+        };
+        self.rule = presets[next % count];
+        Ok(())
+    }
+
+    /// Toggle the selected count.
+    pub fn toggle(&mut self) -> Result<()> {
+        if self.survival {
+            self.rule.toggle_survival(self.neighbors)
+        } else {
+            self.rule.toggle_birth(self.neighbors)
+        }
+    }
+
+    /// The rule above a row of the counts 0 to 8 and a row each for birth and survival, with the
+    /// selected count in brackets.
+    pub fn lines(&self) -> Vec<String> {
+        let row = |survival: bool, label: char| {
+            let mut line = format!("{} ", label);
+            for neighbors in 0..=8 {
+                let on = if survival {
+                    self.rule.survives(neighbors)
+                } else {
+                    self.rule.births(neighbors)
+                };
+                let mark = if on { '#' } else { '.' };
+                if survival == self.survival && neighbors == self.neighbors {
+                    line += &format!("[{}]", mark);
+                } else {
+                    line += &format!(" {} ", mark);
+                }
+            }
+            line
+        };
+        let name = rule::preset_name(&self.rule).map(|name| format!(" ({})", name));
+        vec![
+            format!("rule: {}{}", self.rule, name.unwrap_or_default()),
+            String::from("   0  1  2  3  4  5  6  7  8"),
+            row(false, 'B'),
+            row(true, 'S'),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::rule;
+    use crate::simulation::State;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn toggles_counts() -> Result<()> {
+        let mut editor = RuleEditor::new(&*rule::life())?;
+        assert_eq!(
+            editor.lines(),
+            [
+                "rule: B3/S23 (life)",
+                "   0  1  2  3  4  5  6  7  8",
+                "B  .  .  . [#] .  .  .  .  . ",
+                "S  .  .  #  #  .  .  .  .  . ",
+            ]
+        );
+        editor.right();
+        editor.right();
+        editor.right();
+        editor.toggle()?;
+        assert_eq!(editor.rule.to_string(), "B36/S23");
+        editor.switch_row();
+        editor.left();
+        editor.left();
+        editor.left();
+        editor.toggle()?;
+        assert_eq!(editor.rule.to_string(), "B36/S2");
+        editor.switch_row();
+        for _ in 0..5 {
+            editor.left();
+        }
+        assert!(editor.toggle().is_err());
+        assert_eq!(editor.rule.to_string(), "B36/S2");
+        editor.cycle_preset(false)?;
+        assert_eq!(editor.lines()[0], "rule: B3/S23 (life)");
+        editor.cycle_preset(false)?;
+        assert_eq!(editor.lines()[0], "rule: B36/S23 (highlife)");
+        editor.cycle_preset(true)?;
+        editor.cycle_preset(true)?;
+        assert_eq!(editor.lines()[0], "rule: B36/S125 (2x2)");
+        Ok(())
+    }
+
+    #[test]
+    fn edits_the_rule_live() -> Result<()> {
+        let mut harness = Harness::new(&["--build"], 40, 20)?;
+        harness.type_keys("Rlll ")?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B36/S23");
+        assert!(harness.status().starts_with("edit the rule:"));
+        harness.type_keys("hhhhhh ")?;
+        assert_eq!(
+            harness.status(),
+            "unsupported rule: B0 rules fill empty space"
+        );
+        harness.press(KeyCode::Esc)?;
+        assert!(harness.program.rule_editor.is_none());
+        assert_eq!(harness.state(), State::Paused);
+        assert!(harness.status().contains("rule: B36/S23"));
+        Ok(())
+    }
+
+    #[test]
+    fn picks_preset_rules() -> Result<()> {
+        let mut harness = Harness::new(&["--rule-name", "seeds", "--build"], 40, 20)?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B2/S");
+        harness.type_keys("Rn")?;
+        assert_eq!(
+            harness.program.sim.world.rule().to_string(),
+            "B3/S012345678"
+        );
+        harness.type_keys("NN")?;
+        assert_eq!(harness.program.sim.world.rule().to_string(), "B3678/S34678");
+        assert!(Harness::new(&["--rule-name", "B3/S23"], 40, 20).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use crate::interrupt;
+use crate::{analysis, formats, LifeWorld};
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::{random, Rng, SeedableRng};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug)]
+pub enum Fitness {
+    /// Generations before the population settles.
+    Lifespan,
+    /// Population once settled (or at the generation limit).
+    Population,
+    /// Area of the final bounding box relative to the starting area.
+    Growth,
+}
+
+impl fmt::Display for Fitness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        write!(f, "{}", value.get_name())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    #[arg(short = 'f', long = "fitness", value_enum, default_value_t = Fitness::Lifespan)]
+    fitness: Fitness,
+    /// Width of the area candidates are drawn in.
+    #[arg(long = "width", default_value = "16")]
+    width: usize,
+    /// Height of the area candidates are drawn in.
+    #[arg(long = "height", default_value = "16")]
+    height: usize,
+    /// Number of rounds of mutation and selection.
+    #[arg(short = 'n', long = "iterations", default_value = "200")]
+    iterations: usize,
+    /// Mutants tried per round.
+    #[arg(long = "offspring", default_value = "8")]
+    offspring: usize,
+    /// Cells flipped in each mutant.
+    #[arg(long = "mutations", default_value = "2")]
+    mutations: usize,
+    #[arg(long = "max-generations", default_value = "5000")]
+    max_generations: usize,
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+    /// RLE file the best pattern is written to whenever it improves.
+    #[arg(short = 'o', long = "output", default_value = "best.rle")]
+    output: PathBuf,
+}
+
+/// A starting pattern: a `width` by `height` grid of cells.
+#[derive(PartialEq, Clone, Debug)]
+struct Candidate {
+    width: usize,
+    cells: Vec<bool>,
+}
+
+impl Candidate {
+    fn random(width: usize, height: usize, rng: &mut StdRng) -> Candidate {
+        Candidate {
+            width,
+            cells: (0..width * height).map(|_| rng.gen_bool(0.5)).collect(),
+        }
+    }
+
+    /// A copy with `count` cells flipped.
+    fn mutate(&self, count: usize, rng: &mut StdRng) -> Candidate {
+        let mut mutant = self.clone();
+        for _ in 0..count {
+            let i = rng.gen_range(0..mutant.cells.len());
+            mutant.cells[i] = !mutant.cells[i];
+        }
+        mutant
+    }
+
+    fn world(&self) -> LifeWorld {
+        let mut world = LifeWorld::new();
+        for (i, &alive) in self.cells.iter().enumerate() {
+            if alive {
+                world.raise((i % self.width) as i32, (i / self.width) as i32);
+            }
+        }
+        world
+    }
+
+    fn score(&self, fitness: Fitness, max_generations: usize) -> usize {
+        let mut world = self.world();
+        let start_area = area(&world);
+        let longevity = analysis::longevity(&mut world, max_generations);
+        match fitness {
+            Fitness::Lifespan => longevity.lifespan,
+            Fitness::Population => world.num_alive() as usize,
+            Fitness::Growth => area(&world) / start_area.max(1),
+        }
+    }
+}
+
+fn area(world: &LifeWorld) -> usize {
+    match world.bounding_box() {
+        Some(((x0, y0), (x1, y1))) => ((x1 - x0 + 1) as usize) * ((y1 - y0 + 1) as usize),
+        None => 0,
+    }
+}
+
+/// Hill-climb from a random pattern: each round, keep the best of several mutants if it scores
+/// at least as well as the current pattern. Accepting ties lets the search drift across
+/// plateaus instead of getting stuck on them. Ctrl+C stops the search after the current round,
+/// with the best pattern so far saved.
+pub fn run(args: &SearchArgs) -> Result<()> {
+    let seed = args.seed.unwrap_or_else(random);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut best = Candidate::random(args.width.max(1), args.height.max(1), &mut rng);
+    let mut best_score = best.score(args.fitness, args.max_generations);
+    save(args, seed, &best, best_score)?;
+    eprintln!("round 0: {} {}", args.fitness, best_score);
+
+    for round in 1..=args.iterations {
+        if interrupt::requested() {
+            eprintln!(
+                "interrupted after {} of {} rounds",
+                round - 1,
+                args.iterations
+            );
+            break;
+        }
+        let Some((mutant, score)) = (0..args.offspring)
+            .map(|_| {
+                let mutant = best.mutate(args.mutations, &mut rng);
+                let score = mutant.score(args.fitness, args.max_generations);
+                (mutant, score)
+            })
+            .max_by_key(|(_, score)| *score)
+        else {
+            break;
+        };
+        if score >= best_score {
+            if score > best_score {
+                eprintln!("round {}: {} {}", round, args.fitness, score);
+                save(args, seed, &mutant, score)?;
+            }
+            best = mutant;
+            best_score = score;
+        }
+    }
+    save(args, seed, &best, best_score)?;
+    println!(
+        "best {}: {} (written to {})",
+        args.fitness,
+        best_score,
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn save(args: &SearchArgs, seed: u64, candidate: &Candidate, score: usize) -> Result<()> {
+    let contents = format!(
+        "#C evolve-search seed {}, fitness {} {}\n{}",
+        seed,
+        args.fitness,
+        score,
+        formats::to_rle(&candidate.world())
+    );
+    fs::write(&args.output, contents)
+        .with_context(|| format!("failed to write {}", args.output.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutants_differ_in_few_cells() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let parent = Candidate::random(8, 8, &mut rng);
+        let mutant = parent.mutate(3, &mut rng);
+        let flipped = parent
+            .cells
+            .iter()
+            .zip(&mutant.cells)
+            .filter(|(a, b)| a != b)
+            .count();
+        assert!((1..=3).contains(&flipped));
+    }
+
+    #[test]
+    fn scores_candidates() {
+        // The R-pentomino, drawn in a 3x3 box.
+        let candidate = Candidate {
+            width: 3,
+            cells: vec![
+                false, true, false, //
+                true, true, false, //
+                false, true, true,
+            ],
+        };
+        assert_eq!(candidate.score(Fitness::Lifespan, 2000), 1103);
+        assert!(candidate.score(Fitness::Growth, 2000) > 1);
+    }
+}
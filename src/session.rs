@@ -0,0 +1,255 @@
+use crate::rule::{self, Rule};
+use crate::topology::Topology;
+use crate::turmite::Ant;
+use crate::LifeWorld;
+use crate::Position;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Everything needed to pick up work where it was left off.
+///
+/// Sessions are stored as plain text, one record per line, so they can be inspected and edited by
+/// hand:
+///
+/// ```text
+/// #rust-life session
+/// generation 42
+/// rule B36/S23
+/// topology T200,100
+/// seed 7
+/// camera 0 0
+/// cursor 3 -4
+/// label 10 20 gun A
+/// view 120 -40 east-gun
+/// cell 1 2
+/// dying 1 3 2
+/// ant 0 0 N 0
+/// ```
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub generations: usize,
+    pub rule: Arc<dyn Rule>,
+    pub topology: Topology,
+    /// The seed of the random soup the session started from, if any.
+    pub seed: Option<u64>,
+    pub camera: Position,
+    pub cursor: Position,
+    pub labels: BTreeMap<Position, String>,
+    /// Named camera positions.
+    pub views: BTreeMap<String, Position>,
+    pub cells: Vec<Position>,
+    /// The cells in the dying states of a multi-state rule, with how far each is through them.
+    pub dying: Vec<(Position, u8)>,
+    /// The ants of a turmite rule, in the order they move.
+    pub ants: Vec<Ant>,
+}
+
+const HEADER: &str = "#rust-life session";
+
+// Derived equality can't compare the rule, which is a trait object.
+impl PartialEq for Session {
+    fn eq(&self, other: &Session) -> bool {
+        self.generations == other.generations
+            && *self.rule == *other.rule
+            && self.topology == other.topology
+            && self.seed == other.seed
+            && self.camera == other.camera
+            && self.cursor == other.cursor
+            && self.labels == other.labels
+            && self.views == other.views
+            && self.cells == other.cells
+            && self.dying == other.dying
+            && self.ants == other.ants
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            generations: 0,
+            rule: rule::life(),
+            topology: Topology::Plane,
+            seed: None,
+            camera: (0, 0),
+            cursor: (0, 0),
+            labels: BTreeMap::new(),
+            views: BTreeMap::new(),
+            cells: Vec::new(),
+            dying: Vec::new(),
+            ants: Vec::new(),
+        }
+    }
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Session> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read session {}", path.display()))?;
+        Session::parse(&contents)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.serialize())
+            .with_context(|| format!("failed to write session {}", path.display()))
+    }
+
+    /// Build a world containing the session's cells.
+    pub fn world(&self) -> LifeWorld {
+        let mut world = self.empty_world();
+        for &(x, y) in &self.cells {
+            world.raise(x, y);
+        }
+        self.add_dying_and_ants(&mut world);
+        world
+    }
+
+    /// Put the session's dying cells and ants into `world`.
+    pub fn add_dying_and_ants(&self, world: &mut LifeWorld) {
+        for &((x, y), decay) in &self.dying {
+            world.set_dying(x, y, decay);
+        }
+        for &ant in &self.ants {
+            world.add_ant(ant);
+        }
+    }
+
+    /// A world at the session's generation and with its rule and topology, but none of its
+    /// cells yet.
+    pub fn empty_world(&self) -> LifeWorld {
+        let mut world = LifeWorld::new();
+        world.generations = self.generations;
+        world.set_rule(Arc::clone(&self.rule));
+        world.set_topology(self.topology);
+        world
+    }
+
+    pub fn parse(contents: &str) -> Result<Session> {
+        let mut lines = contents.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(anyhow!("not a rust-life session")),
+        }
+        let mut session = Session::default();
+        for (i, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            session
+                .parse_record(line)
+                .with_context(|| format!("invalid session record on line {}", i + 1))?;
+        }
+        Ok(session)
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = format!("{}\n", HEADER);
+        out += &format!("generation {}\n", self.generations);
+        out += &format!("rule {}\n", self.rule);
+        if self.topology != Topology::Plane {
+            out += &format!("topology {}\n", self.topology);
+        }
+        if let Some(seed) = self.seed {
+            out += &format!("seed {}\n", seed);
+        }
+        out += &format!("camera {} {}\n", self.camera.0, self.camera.1);
+        out += &format!("cursor {} {}\n", self.cursor.0, self.cursor.1);
+        for ((x, y), text) in &self.labels {
+            out += &format!("label {} {} {}\n", x, y, text);
+        }
+        for (name, (x, y)) in &self.views {
+            out += &format!("view {} {} {}\n", x, y, name);
+        }
+        for (x, y) in &self.cells {
+            out += &format!("cell {} {}\n", x, y);
+        }
+        for ((x, y), decay) in &self.dying {
+            out += &format!("dying {} {} {}\n", x, y, decay);
+        }
+        for ant in &self.ants {
+            let (x, y) = ant.position;
+            out += &format!("ant {} {} {} {}\n", x, y, ant.heading, ant.state);
+        }
+        out
+    }
+
+    fn parse_record(&mut self, line: &str) -> Result<()> {
+        let mut fields = line.splitn(4, ' ');
+        let kind = fields.next().unwrap_or_default();
+        let mut next = || fields.next().ok_or_else(|| anyhow!("missing field"));
+        match kind {
+            "generation" => self.generations = next()?.parse()?,
+            "rule" => self.rule = rule::parse(next()?)?,
+            "topology" => self.topology = next()?.parse()?,
+            "seed" => self.seed = Some(next()?.parse()?),
+            "camera" => self.camera = (next()?.parse()?, next()?.parse()?),
+            "cursor" => self.cursor = (next()?.parse()?, next()?.parse()?),
+            "label" => {
+                let pos = (next()?.parse()?, next()?.parse()?);
+                self.labels.insert(pos, next()?.to_string());
+            }
+            "view" => {
+                let pos = (next()?.parse()?, next()?.parse()?);
+                self.views.insert(next()?.to_string(), pos);
+            }
+            "cell" => self.cells.push((next()?.parse()?, next()?.parse()?)),
+            "dying" => {
+                let pos = (next()?.parse()?, next()?.parse()?);
+                self.dying.push((pos, next()?.parse()?));
+            }
+            "ant" => {
+                let position = (next()?.parse()?, next()?.parse()?);
+                let (heading, state) = next()?
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow!("missing field"))?;
+                self.ants.push(Ant {
+                    position,
+                    heading: heading.parse()?,
+                    state: state.parse()?,
+                });
+            }
+            _ => return Err(anyhow!("unknown record: {}", kind)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() -> Result<()> {
+        let mut session = Session {
+            generations: 42,
+            rule: rule::parse("B36/S23")?,
+            topology: Topology::torus("200x100")?,
+            seed: Some(7),
+            camera: (-5, 7),
+            cursor: (3, -4),
+            cells: vec![(0, 0), (1, 2)],
+            dying: vec![((1, 3), 2)],
+            ants: vec![Ant::new((0, 0))],
+            ..Default::default()
+        };
+        session.labels.insert((10, 20), String::from("gun A"));
+        session.views.insert(String::from("east-gun"), (120, -40));
+        assert_eq!(Session::parse(&session.serialize())?, session);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        let err = Session::parse("cell 0 0\n").unwrap_err();
+        assert_eq!(err.to_string(), "not a rust-life session");
+    }
+
+    #[test]
+    fn reports_bad_records() {
+        let err = Session::parse("#rust-life session\ncell 0\n").unwrap_err();
+        assert_eq!(err.to_string(), "invalid session record on line 2");
+    }
+}
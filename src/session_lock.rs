@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Context, Result};
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// An advisory lock on a session file, held while the session is open so that another instance
+/// of the program opening it is warned, and one saving over it is stopped.
+///
+/// The lock is taken on a `.lock` file next to the session, which holds the process id of the
+/// instance that has it. The operating system releases it if the process exits without
+/// cleaning up, so a leftover lock file doesn't keep the session locked.
+#[derive(Debug)]
+pub struct SessionLock {
+    /// The lock file, canonicalized so that the lock can be matched to a session however its
+    /// path is written.
+    path: PathBuf,
+    file: File,
+}
+
+/// Another instance has the session open.
+#[derive(PartialEq, Debug)]
+pub struct Busy {
+    pub session: PathBuf,
+    /// The process id of the instance with the lock, if it could be read.
+    pub pid: Option<u32>,
+}
+
+impl fmt::Display for Busy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is open in another instance", self.session.display())?;
+        if let Some(pid) = self.pid {
+            write!(f, " (pid {})", pid)?;
+        }
+        Ok(())
+    }
+}
+
+impl SessionLock {
+    /// Lock the session at `session`, or find out who has it locked. The outer error is for
+    /// failing to create the lock file, in a read-only directory for example.
+    pub fn acquire(session: &Path) -> Result<Result<SessionLock, Busy>> {
+        let path = lock_path(session);
+        let mut file = loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            match file.try_lock() {
+                // The instance that had the lock may have removed the file between our opening
+                // and locking it, so the lock only counts if the file is still the lock file.
+                Ok(()) if is_linked(&file, &path) => break file,
+                Ok(()) => continue,
+                Err(TryLockError::WouldBlock) => {
+                    let mut contents = String::new();
+                    let _ = file.read_to_string(&mut contents);
+                    let pid = contents.trim().parse().ok();
+                    return Ok(Err(Busy {
+                        session: session.to_path_buf(),
+                        pid,
+                    }));
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(anyhow!(e).context(format!("failed to lock {}", path.display())));
+                }
+            }
+        };
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+        let path = fs::canonicalize(&path)?;
+        Ok(Ok(SessionLock { path, file }))
+    }
+
+    /// Whether this is the lock on the session at `session`.
+    pub fn covers(&self, session: &Path) -> bool {
+        fs::canonicalize(lock_path(session)).is_ok_and(|path| path == self.path)
+    }
+}
+
+/// Remove the lock file before the lock is released by closing it, so the lock file is only
+/// there while the session is open. An instance that opened the file before it was removed and
+/// locks it after finds it gone, and tries again with a new one.
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Whether `path` still names the open `file`, rather than having been removed by the instance
+/// that held the lock on it.
+#[cfg(unix)]
+fn is_linked(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(named)) => open.dev() == named.dev() && open.ino() == named.ino(),
+        _ => false,
+    }
+}
+
+/// Elsewhere an open file can't be removed, so the lock file is the one that was opened.
+#[cfg(not(unix))]
+fn is_linked(_file: &File, _path: &Path) -> bool {
+    true
+}
+
+/// `<session>.lock`.
+fn lock_path(session: &Path) -> PathBuf {
+    let mut path = session.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn locks_sessions_once() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let session = dir.join("a.session");
+        let lock = SessionLock::acquire(&session)?;
+        let again = SessionLock::acquire(&session)?;
+        let roundabout = dir
+            .join("..")
+            .join(dir.file_name().unwrap())
+            .join("a.session");
+        let covers = lock.as_ref().is_ok_and(|lock| lock.covers(&roundabout));
+        drop(lock);
+        let released = !lock_path(&session).exists();
+        let after = SessionLock::acquire(&session)?.is_ok();
+        let other = SessionLock::acquire(&dir.join("b.session"))?.is_ok();
+        fs::remove_dir_all(&dir)?;
+
+        let pid = std::process::id();
+        let busy = again.unwrap_err();
+        assert_eq!(busy.pid, Some(pid));
+        assert_eq!(
+            busy.to_string(),
+            format!(
+                "{} is open in another instance (pid {})",
+                session.display(),
+                pid
+            )
+        );
+        assert!(covers);
+        assert!(released && after && other);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn notices_removed_lock_files() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("rust-life-{}.lock", std::process::id()));
+        let file = File::create(&path)?;
+        let linked = is_linked(&file, &path);
+        fs::remove_file(&path)?;
+        assert!(linked && !is_linked(&file, &path));
+        // A new lock file in its place is a different file.
+        let _new = File::create(&path)?;
+        let replaced = is_linked(&file, &path);
+        fs::remove_file(&path)?;
+        assert!(!replaced);
+        Ok(())
+    }
+
+    #[test]
+    fn locks_open_sessions() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-locks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("shared.session");
+        let path = path.to_str().unwrap();
+        let mut first = Harness::new(&["-p", "glider", "--build"], 40, 20)?;
+        first.type_keys(&format!(":save {}\n", path))?;
+        let mut second = Harness::new(&["--session", path, "--build"], 40, 20)?;
+        let warning = second.status();
+        second.type_keys(&format!(":save {}\n", path))?;
+        let refused = second.program.error.take().map(|e| e.to_string());
+        // Once the first instance is done with the session, the second can save over it.
+        drop(first);
+        second.type_keys(&format!(":save {}\n", path))?;
+        let saved = second.status();
+        drop(second);
+        let leftovers = std::fs::read_dir(&dir)?.count();
+        std::fs::remove_dir_all(&dir)?;
+
+        let busy = format!(
+            "{} is open in another instance (pid {})",
+            path,
+            std::process::id()
+        );
+        assert_eq!(
+            warning,
+            format!("warning: {}, so saving over it is refused", busy)
+        );
+        assert_eq!(refused, Some(format!("{}, so it was not saved over", busy)));
+        assert_eq!(saved, format!("saved session to {}", path));
+        // Only the session is left, without a lock file.
+        assert_eq!(leftovers, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,403 @@
+#[cfg(feature = "audio")]
+use crate::audio::Sonifier;
+use crate::export::{self, ImageStyle};
+use crate::history::History;
+use crate::osc::OscSender;
+use crate::pacing::Pacer;
+use crate::raster::Region;
+use crate::stats::StatsStream;
+use crate::tickrate::TickRate;
+use crate::{Changes, LifeWorld, ObserverId};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Commands accepted by a [`Simulation`].
+#[derive(Debug)]
+pub enum Command {
+    Start,
+    Pause,
+    Resume,
+    /// Pause if running, resume if paused.
+    Toggle,
+    /// Advance a number of generations while paused, writing the outputs for each of them.
+    Step(usize),
+    SetTimestep(Duration),
+    Quit,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum State {
+    Setup,
+    Running,
+    Paused,
+    Done,
+}
+
+impl State {
+    pub fn handle_command(&mut self, command: &Command) -> Result<&State> {
+        match (*self, command) {
+            (Self::Setup, Command::Start) | (Self::Paused, Command::Resume) => {
+                *self = Self::Running;
+                Ok(self)
+            }
+            (Self::Running, Command::Pause) => {
+                *self = Self::Paused;
+                Ok(self)
+            }
+            (Self::Running, Command::Resume) | (Self::Paused, Command::Pause) => Ok(self),
+            (_, Command::Quit) => {
+                *self = Self::Done;
+                Ok(self)
+            }
+            _ => Err(anyhow!(
+                "Invalid command {:?} for state {:?}",
+                command,
+                self
+            )),
+        }
+    }
+}
+
+/// A snapshot of a simulation's progress.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Status {
+    pub state: State,
+    pub generations: usize,
+    pub population: i32,
+    pub timestep: Duration,
+    /// The measured rate in generations per second, if running.
+    pub rate: Option<f64>,
+    /// How long the last generation took to compute.
+    pub step_time: Duration,
+}
+
+/// Runs a world: the run/pause state machine, pacing generations to the timestep, and the
+/// per-generation outputs (stats stream, OSC, snapshots, sound). Front ends send it commands and
+/// read back its status, so the TUI and headless mode share one implementation.
+#[derive(Debug)]
+pub struct Simulation {
+    pub world: LifeWorld,
+    /// The seed the world was generated from, if it started as a random soup. It is recorded in
+    /// the stats stream and snapshots so the run can be reproduced.
+    pub seed: Option<u64>,
+    state: State,
+    timestep: Duration,
+    pacer: Pacer,
+    tickrate: TickRate,
+    step_time: Duration,
+    stats: Option<StatsStream>,
+    osc: Option<OscSender>,
+    snapshot_every: Option<usize>,
+    snapshot_dir: PathBuf,
+    #[cfg(feature = "audio")]
+    audio: Option<Sonifier>,
+    /// The cells that have been alive since history recording was turned on, and the observer
+    /// recording them.
+    history: Option<(History, ObserverId)>,
+    /// The first output error since the last call to `take_error`.
+    error: Option<anyhow::Error>,
+}
+
+impl Simulation {
+    pub fn new(world: LifeWorld, timestep: Duration) -> Simulation {
+        Simulation {
+            world,
+            seed: None,
+            state: State::Setup,
+            timestep,
+            pacer: Pacer::new(timestep, Instant::now()),
+            tickrate: TickRate::new(),
+            step_time: Duration::ZERO,
+            stats: None,
+            osc: None,
+            snapshot_every: None,
+            snapshot_dir: PathBuf::from("."),
+            #[cfg(feature = "audio")]
+            audio: None,
+            history: None,
+            error: None,
+        }
+    }
+
+    pub fn set_stats(&mut self, stream: StatsStream) {
+        self.stats = Some(stream);
+    }
+
+    pub fn set_osc(&mut self, osc: OscSender) -> Result<()> {
+        osc.observe(&mut self.world)?;
+        self.osc = Some(osc);
+        Ok(())
+    }
+
+    /// Start or stop recording the cells that have ever been alive.
+    pub fn set_history(&mut self, enabled: bool) {
+        match (self.history.take(), enabled) {
+            (Some((_, id)), false) => {
+                self.world.remove_observer(id);
+            }
+            (Some(history), true) => self.history = Some(history),
+            (None, true) => self.history = Some(History::record(&mut self.world)),
+            (None, false) => (),
+        }
+    }
+
+    /// The cells that have ever been alive, if they are being recorded.
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref().map(|(history, _)| history)
+    }
+
+    /// Write an RLE snapshot into `dir` every `every` generations (0 disables snapshots).
+    pub fn set_snapshots(&mut self, every: Option<usize>, dir: &Path) -> Result<()> {
+        self.snapshot_every = every.filter(|&n| n > 0);
+        self.snapshot_dir = dir.to_path_buf();
+        if self.snapshot_every.is_some() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Write an RLE snapshot of the current generation into the snapshot directory now.
+    pub fn save_snapshot(&self) -> Result<PathBuf> {
+        fs::create_dir_all(&self.snapshot_dir)?;
+        export::write_snapshot(&self.world, &self.snapshot_dir, self.seed)
+    }
+
+    /// Write a PNG of a region of the current generation into the snapshot directory.
+    pub fn save_screenshot(&self, region: Region, style: &ImageStyle) -> Result<PathBuf> {
+        fs::create_dir_all(&self.snapshot_dir)?;
+        export::write_screenshot(&self.world, &self.snapshot_dir, region, style)
+    }
+
+    #[cfg(feature = "audio")]
+    pub fn set_audio(&mut self, audio: Sonifier) {
+        self.audio = Some(audio);
+    }
+
+    /// Replace the world, e.g. after loading a session, keeping the outputs attached.
+    pub fn set_world(&mut self, world: LifeWorld) -> Result<()> {
+        self.world = world;
+        // The history belongs to the old world, so start a new one.
+        if self.history.is_some() {
+            self.history = Some(History::record(&mut self.world));
+        }
+        if let Some(osc) = &self.osc {
+            osc.observe(&mut self.world)?;
+        }
+        Ok(())
+    }
+
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            state: self.state,
+            generations: self.world.generations,
+            population: self.world.num_alive(),
+            timestep: self.timestep,
+            rate: self.tickrate.hz(),
+            step_time: self.step_time,
+        }
+    }
+
+    /// Apply a command. Returns the changes if the command advanced the world.
+    pub fn handle(&mut self, command: Command) -> Result<Option<Changes>> {
+        match command {
+            Command::Toggle => match self.state {
+                State::Running => return self.handle(Command::Pause),
+                State::Paused => return self.handle(Command::Resume),
+                _ => (),
+            },
+            Command::Step(generations) => {
+                if self.state == State::Paused {
+                    let mut total = Changes::default();
+                    for _ in 0..generations {
+                        let changes = self.step();
+                        total.births += changes.births;
+                        total.deaths += changes.deaths;
+                    }
+                    return Ok(Some(total));
+                }
+            }
+            Command::SetTimestep(timestep) => {
+                self.timestep = timestep;
+                self.pacer = Pacer::new(timestep, Instant::now());
+                self.tickrate.reset();
+            }
+            command => {
+                self.state.handle_command(&command)?;
+                // Start measuring afresh so a pause does not count as a slow generation.
+                self.tickrate.reset();
+                self.pacer.reset(Instant::now());
+            }
+        }
+        Ok(None)
+    }
+
+    /// Time left until the next generation is due while running.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.pacer.remaining(now)
+    }
+
+    /// Wait out the last moments until the next generation is due, then advance the world. Meant
+    /// to be called once `remaining` is within `pacing::SPIN_MARGIN`.
+    pub fn step_when_due(&mut self) -> Changes {
+        self.pacer.spin();
+        let start = Instant::now();
+        let changes = self.step();
+        self.step_time = start.elapsed();
+        self.tickrate.tick(start);
+        self.pacer.advance(Instant::now());
+        changes
+    }
+
+    /// Advance the world by one generation and write the outputs for it.
+    pub fn step(&mut self) -> Changes {
+        let changes = self.world.evolve();
+        self.record(changes);
+        changes
+    }
+
+    /// Write the outputs for the current generation. Errors are kept for `take_error`, so one
+    /// failing output does not hold up the others or the simulation.
+    pub fn record(&mut self, changes: Changes) {
+        let mut result = Ok(());
+        if let Some(stream) = &mut self.stats {
+            let history = self.history.as_ref().map(|(history, _)| history);
+            result = result.and(stream.record(&self.world, changes, self.seed, history));
+        }
+        if let Some(osc) = &self.osc {
+            result = result.and(osc.record(&self.world, changes));
+        }
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &self.audio {
+            audio.play(self.world.num_alive(), changes);
+        }
+        if let Some(every) = self.snapshot_every
+            && self.world.generations.is_multiple_of(every)
+        {
+            let snapshot = export::write_snapshot(&self.world, &self.snapshot_dir, self.seed);
+            result = result.and(snapshot.map(|_| ()));
+        }
+        if let Err(e) = result {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    /// The first output error since the last call, if any.
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        self.error.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::simulation::State;
+    use crate::LifePattern;
+
+    #[test]
+    fn starts_from_setup() -> Result<()> {
+        let mut state = State::Setup;
+        state.handle_command(&Command::Start)?;
+        assert_eq!(state, State::Running);
+        Ok(())
+    }
+
+    #[test]
+    fn resumes_from_pause() -> Result<()> {
+        let mut state = State::Paused;
+        state.handle_command(&Command::Resume)?;
+        assert_eq!(state, State::Running);
+        Ok(())
+    }
+
+    #[test]
+    fn quits_from_running() -> Result<()> {
+        let mut state = State::Running;
+        state.handle_command(&Command::Quit)?;
+        assert_eq!(state, State::Done);
+        Ok(())
+    }
+
+    #[test]
+    fn pauses_from_running() -> Result<()> {
+        let mut state = State::Running;
+        state.handle_command(&Command::Pause)?;
+        assert_eq!(state, State::Paused);
+        Ok(())
+    }
+
+    #[test]
+    fn toggles_and_steps() -> Result<()> {
+        let world = LifeWorld::from(&LifePattern::Blinker);
+        let mut sim = Simulation::new(world, Duration::from_millis(10));
+        sim.handle(Command::Start)?;
+        // Stepping is only for single-stepping while paused.
+        assert_eq!(sim.handle(Command::Step(1))?, None);
+        sim.handle(Command::Toggle)?;
+        assert_eq!(sim.state(), State::Paused);
+        let changes = sim.handle(Command::Step(1))?;
+        assert_eq!(changes.map(|c| (c.births, c.deaths)), Some((2, 2)));
+        let status = sim.status();
+        assert_eq!((status.generations, status.population), (1, 3));
+        sim.handle(Command::Toggle)?;
+        assert_eq!(sim.state(), State::Running);
+        Ok(())
+    }
+
+    #[test]
+    fn records_every_generation_stepped_over() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust-life-step-{}", std::process::id()));
+        let mut sim = Simulation::new(LifeWorld::from(&LifePattern::Glider), Duration::ZERO);
+        sim.set_snapshots(Some(10), &dir)?;
+        sim.handle(Command::Start)?;
+        sim.handle(Command::Pause)?;
+        let changes = sim.handle(Command::Step(25))?;
+        let snapshots = fs::read_dir(&dir)?.count();
+        fs::remove_dir_all(&dir)?;
+        assert_eq!(changes.map(|c| (c.births, c.deaths)), Some((50, 50)));
+        assert_eq!(snapshots, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn changes_timestep() -> Result<()> {
+        let mut sim = Simulation::new(LifeWorld::new(), Duration::from_millis(100));
+        sim.handle(Command::SetTimestep(Duration::from_millis(5)))?;
+        assert_eq!(sim.status().timestep, Duration::from_millis(5));
+        assert!(sim.remaining(Instant::now()) <= Duration::from_millis(5));
+        Ok(())
+    }
+
+    #[test]
+    fn steps_while_paused() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "blinker"], 40, 20)?;
+        harness.type_keys(" ..")?;
+        assert_eq!(harness.state(), State::Paused);
+        assert_eq!(harness.program.sim.world.generations, 2);
+        harness.type_keys("-")?;
+        assert_eq!(harness.status(), "timestep: 200ms");
+        Ok(())
+    }
+
+    #[test]
+    fn jumps_by_powers_of_ten() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider", "--build"], 40, 20)?;
+        harness.type_keys("1234")?;
+        assert_eq!(harness.program.sim.world.generations, 1111);
+        assert_eq!(harness.cells().len(), 5);
+        // Jumps only apply while paused.
+        harness.type_keys(" 4")?;
+        assert_eq!(harness.program.sim.world.generations, 1111);
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use crate::history::History;
+use crate::raster::Region;
+use crate::{Changes, LifeWorld};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// The columns of CSV stats streams.
+const CSV_HEADER: &str = "generation,population,births,deaths,width,height";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    /// One JSON object per line.
+    Json,
+    /// Comma-separated values with a header row, for spreadsheets and plotting tools.
+    Csv,
+}
+
+impl StatsFormat {
+    /// The format a file name asks for: CSV for `.csv` files, otherwise JSON.
+    pub fn for_path(path: &Path) -> StatsFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => StatsFormat::Csv,
+            _ => StatsFormat::Json,
+        }
+    }
+}
+
+/// Writes a line per generation so external tools can follow a run as it happens.
+pub struct StatsStream {
+    writer: Box<dyn Write>,
+    format: StatsFormat,
+}
+
+impl StatsStream {
+    /// Open a stream to a file, or to stdout if the path is `-`.
+    pub fn open(path: &Path, format: StatsFormat) -> Result<StatsStream> {
+        let writer: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout())
+        } else {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create stats stream {}", path.display()))?;
+            Box::new(BufWriter::new(file))
+        };
+        let mut stream = StatsStream { writer, format };
+        if format == StatsFormat::Csv {
+            writeln!(stream.writer, "{}", CSV_HEADER)?;
+        }
+        Ok(stream)
+    }
+
+    /// Write a line for the current generation. JSON lines also include the seed of the starting
+    /// soup if it was random and the envelope if history is being recorded.
+    pub fn record(
+        &mut self,
+        world: &LifeWorld,
+        changes: Changes,
+        seed: Option<u64>,
+        history: Option<&History>,
+    ) -> Result<()> {
+        let line = match self.format {
+            StatsFormat::Json => stats_line(world, changes, seed, history),
+            StatsFormat::Csv => csv_line(world, changes),
+        };
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for StatsStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("StatsStream").finish_non_exhaustive()
+    }
+}
+
+fn stats_line(
+    world: &LifeWorld,
+    changes: Changes,
+    seed: Option<u64>,
+    history: Option<&History>,
+) -> String {
+    let seed = seed
+        .map(|seed| format!(r#","seed":{}"#, seed))
+        .unwrap_or_default();
+    let envelope = history
+        .map(|history| {
+            format!(
+                r#","envelope":{{"cells":{},"bbox":{}}}"#,
+                history.len(),
+                bbox_json(history.bounding_box())
+            )
+        })
+        .unwrap_or_default();
+    let stats = world.stats();
+    format!(
+        concat!(
+            r#"{{"gen":{},"population":{},"births":{},"deaths":{},"bbox":{},"#,
+            r#""density":{:.4},"clusters":{}{}{}}}"#
+        ),
+        stats.generation,
+        stats.population,
+        changes.births,
+        changes.deaths,
+        bbox_json(stats.bounding_box),
+        stats.density,
+        stats.clusters,
+        seed,
+        envelope
+    )
+}
+
+/// A CSV row, with the size of the bounding box rather than its corners.
+fn csv_line(world: &LifeWorld, changes: Changes) -> String {
+    let stats = world.stats();
+    let (width, height) = match stats.bounding_box {
+        Some(((x0, y0), (x1, y1))) => (x1 - x0 + 1, y1 - y0 + 1),
+        None => (0, 0),
+    };
+    format!(
+        "{},{},{},{},{},{}",
+        stats.generation, stats.population, changes.births, changes.deaths, width, height
+    )
+}
+
+fn bbox_json(bbox: Option<Region>) -> String {
+    match bbox {
+        Some(((x0, y0), (x1, y1))) => format!("[{},{},{},{}]", x0, y0, x1, y1),
+        None => String::from("null"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LifePattern;
+
+    #[test]
+    fn formats_json_lines() {
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        let changes = world.evolve();
+        assert_eq!(
+            stats_line(&world, changes, None, None),
+            concat!(
+                r#"{"gen":1,"population":3,"births":2,"deaths":2,"bbox":[-1,1,1,1],"#,
+                r#""density":1.0000,"clusters":1}"#
+            )
+        );
+    }
+
+    #[test]
+    fn includes_the_envelope() {
+        let mut world = LifeWorld::from(&LifePattern::Blinker);
+        let (history, _) = History::record(&mut world);
+        let changes = world.evolve();
+        assert!(stats_line(&world, changes, None, Some(&history))
+            .ends_with(r#""envelope":{"cells":5,"bbox":[-1,0,1,2]}}"#));
+    }
+
+    #[test]
+    fn formats_csv_rows() {
+        let mut world = LifeWorld::from(&LifePattern::Glider);
+        let changes = world.evolve();
+        assert_eq!(csv_line(&world, changes), "1,5,2,2,3,3");
+        assert_eq!(
+            csv_line(&LifeWorld::new(), Changes::default()),
+            "0,0,0,0,0,0"
+        );
+        assert_eq!(
+            StatsFormat::for_path(Path::new("run.CSV")),
+            StatsFormat::Csv
+        );
+        assert_eq!(StatsFormat::for_path(Path::new("-")), StatsFormat::Json);
+    }
+
+    #[test]
+    fn formats_empty_world() {
+        let world = LifeWorld::new();
+        assert_eq!(
+            stats_line(&world, Changes::default(), Some(42), None),
+            concat!(
+                r#"{"gen":0,"population":0,"births":0,"deaths":0,"bbox":null,"#,
+                r#""density":0.0000,"clusters":0,"seed":42}"#
+            )
+        );
+    }
+}
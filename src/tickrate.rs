@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Number of recent generation intervals averaged into the reported rate.
+const WINDOW: usize = 32;
+
+/// Measures how many generations are actually simulated per second, averaged over the last few
+/// generations so the display does not jump around from one frame to the next.
+#[derive(Debug, Default)]
+pub struct TickRate {
+    last: Option<Instant>,
+    intervals: VecDeque<Duration>,
+    total: Duration,
+}
+
+impl TickRate {
+    pub fn new() -> TickRate {
+        TickRate::default()
+    }
+
+    /// Record that a generation was completed at `now`.
+    pub fn tick(&mut self, now: Instant) {
+        if let Some(last) = self.last {
+            let interval = now.duration_since(last);
+            self.intervals.push_back(interval);
+            self.total += interval;
+            if self.intervals.len() > WINDOW
+                && let Some(oldest) = self.intervals.pop_front()
+            {
+                self.total -= oldest;
+            }
+        }
+        self.last = Some(now);
+    }
+
+    /// Forget all measurements, e.g. after pausing, so the pause does not count as a slow
+    /// generation.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.intervals.clear();
+        self.total = Duration::ZERO;
+    }
+
+    /// The average rate over the window, in generations per second.
+    pub fn hz(&self) -> Option<f64> {
+        if self.intervals.is_empty() || self.total.is_zero() {
+            return None;
+        }
+        Some(self.intervals.len() as f64 / self.total.as_secs_f64())
+    }
+}
+
+impl fmt::Display for TickRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_rate(self.hz()))
+    }
+}
+
+/// Format a measured rate, or `-` if there is none yet.
+pub fn format_rate(hz: Option<f64>) -> String {
+    hz.map_or_else(|| String::from("-"), format_hz)
+}
+
+/// Format a rate with about three significant digits.
+pub fn format_hz(hz: f64) -> String {
+    if hz >= 100. {
+        format!("{:.0}Hz", hz)
+    } else if hz >= 10. {
+        format!("{:.1}Hz", hz)
+    } else {
+        format!("{:.2}Hz", hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_intervals() {
+        let start = Instant::now();
+        let mut rate = TickRate::new();
+        assert_eq!(rate.hz(), None);
+        rate.tick(start);
+        rate.tick(start + Duration::from_millis(90));
+        rate.tick(start + Duration::from_millis(200));
+        assert_eq!(rate.hz(), Some(10.));
+        assert_eq!(rate.to_string(), "10.0Hz");
+    }
+
+    #[test]
+    fn forgets_old_intervals() {
+        let start = Instant::now();
+        let mut rate = TickRate::new();
+        rate.tick(start);
+        rate.tick(start + Duration::from_secs(10));
+        for i in 1..=WINDOW as u64 {
+            rate.tick(start + Duration::from_secs(10) + Duration::from_millis(5 * i));
+        }
+        assert_eq!(rate.to_string(), "200Hz");
+    }
+
+    #[test]
+    fn resets() {
+        let start = Instant::now();
+        let mut rate = TickRate::new();
+        rate.tick(start);
+        rate.tick(start + Duration::from_millis(500));
+        rate.reset();
+        assert_eq!(rate.to_string(), "-");
+        rate.tick(start + Duration::from_secs(60));
+        rate.tick(start + Duration::from_secs(61));
+        assert_eq!(rate.to_string(), "1.00Hz");
+    }
+}
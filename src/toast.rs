@@ -0,0 +1,53 @@
+use std::time::{Duration, Instant};
+
+/// How long a message stays in the status line.
+pub const DURATION: Duration = Duration::from_secs(4);
+
+/// A transient message, such as a saved file's path or an error, shown in place of the normal
+/// status line until it expires or the next key is pressed.
+#[derive(Debug, Default)]
+pub struct Toast {
+    message: Option<(String, Instant)>,
+}
+
+impl Toast {
+    pub fn show(&mut self, text: impl Into<String>) {
+        self.message = Some((text.into(), Instant::now()));
+    }
+
+    pub fn clear(&mut self) {
+        self.message = None;
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.message.as_ref().map(|(text, _)| text.as_str())
+    }
+
+    /// Clear the message once it has been shown for [`DURATION`]. Returns whether it was
+    /// cleared, so the normal status line can be redrawn.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        match &self.message {
+            Some((_, shown)) if now.duration_since(*shown) >= DURATION => {
+                self.message = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expires_after_a_while() {
+        let mut toast = Toast::default();
+        assert!(!toast.expire(Instant::now()));
+        toast.show("saved");
+        assert!(!toast.expire(Instant::now()));
+        assert_eq!(toast.text(), Some("saved"));
+        assert!(toast.expire(Instant::now() + DURATION));
+        assert_eq!(toast.text(), None);
+    }
+}
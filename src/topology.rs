@@ -0,0 +1,406 @@
+use crate::raster::Region;
+use crate::rule::MAX_RADIUS;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// The shortest side a closed surface such as a torus can have. On a narrower one a cell would be
+/// in its own neighborhood under rules with the largest radius.
+pub const MIN_SIDE: i32 = MAX_RADIUS as i32 + 1;
+
+/// The space the cells of a [`LifeWorld`](crate::LifeWorld) live in.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Topology {
+    /// The unbounded plane.
+    #[default]
+    Plane,
+    /// A `width` by `height` rectangle centered on the origin with its opposite edges joined,
+    /// so a pattern leaving one side comes back on the other, as in simulators with a fixed-size
+    /// grid. Every position is the same cell as one inside the rectangle.
+    Torus { width: i32, height: i32 },
+    /// A `width` by `height` rectangle centered on the origin, outside of which cells are
+    /// always dead, as in simulators with a fixed-size grid and dead edges.
+    Bounded { width: i32, height: i32 },
+    /// A torus with one pair of edges joined with a twist, so a pattern leaving one side comes
+    /// back on the other mirrored. With `flip_x`, crossing the top or bottom edge flips the x
+    /// coordinate, otherwise crossing the left or right edge flips the y coordinate.
+    Klein {
+        width: i32,
+        height: i32,
+        flip_x: bool,
+    },
+    /// A rectangle with both pairs of opposite edges joined with a twist, the real projective
+    /// plane.
+    CrossSurface { width: i32, height: i32 },
+    /// A square with each edge joined to the one next to it, the bottom to the left and the top
+    /// to the right, so a pattern leaving the bottom comes back on the left, turned.
+    Sphere { size: i32 },
+}
+
+impl Topology {
+    /// A torus of the size given as `WxH`, e.g. `200x100`.
+    pub fn torus(size: &str) -> Result<Topology> {
+        let (width, height) = parse_size(size)?;
+        Topology::sized('T', width, height)
+    }
+
+    /// A bounded plane of the size given as `WxH`, e.g. `200x100`.
+    pub fn bounded(size: &str) -> Result<Topology> {
+        let (width, height) = parse_size(size)?;
+        Topology::sized('P', width, height)
+    }
+
+    /// A finite topology by its letter in Golly's notation.
+    fn sized(kind: char, width: i32, height: i32) -> Result<Topology> {
+        match kind {
+            'P' if width < 1 || height < 1 => Err(anyhow!("a bounded plane must be at least 1x1")),
+            'P' => Ok(Topology::Bounded { width, height }),
+            _ if width < MIN_SIDE || height < MIN_SIDE => Err(anyhow!(
+                "a closed surface must be at least {0}x{0}",
+                MIN_SIDE
+            )),
+            'K' => Ok(Topology::Klein {
+                width,
+                height,
+                flip_x: true,
+            }),
+            'C' => Ok(Topology::CrossSurface { width, height }),
+            'S' => Ok(Topology::Sphere { size: width }),
+            _ => Ok(Topology::Torus { width, height }),
+        }
+    }
+
+    /// The width and height of the rectangle, if the topology is finite.
+    fn size(&self) -> Option<(i32, i32)> {
+        match *self {
+            Topology::Plane => None,
+            Topology::Torus { width, height }
+            | Topology::Bounded { width, height }
+            | Topology::Klein { width, height, .. }
+            | Topology::CrossSurface { width, height } => Some((width, height)),
+            Topology::Sphere { size } => Some((size, size)),
+        }
+    }
+
+    /// The inclusive corners of the rectangle, if the topology is finite.
+    pub fn bounds(&self) -> Option<Region> {
+        let (width, height) = self.size()?;
+        let (x0, y0) = (-(width / 2), -(height / 2));
+        Some(((x0, y0), (x0 + width - 1, y0 + height - 1)))
+    }
+
+    /// The position inside the rectangle that is the same cell as `pos`, or `None` if `pos` is
+    /// outside a bounded plane, where cells are always dead, or beyond the corners of a sphere.
+    pub fn wrap(&self, (x, y): (i32, i32)) -> Option<(i32, i32)> {
+        let Some(((x0, y0), _)) = self.bounds() else {
+            return Some((x, y));
+        };
+        let (u, v) = self.fold((x - x0, y - y0))?;
+        Some((u + x0, v + y0))
+    }
+
+    /// [`wrap`](Topology::wrap) for positions relative to the bottom left corner of the
+    /// rectangle.
+    fn fold(&self, (u, v): (i32, i32)) -> Option<(i32, i32)> {
+        let (width, height) = self.size()?;
+        // Whether crossing the top or bottom edge flips u, and the left or right edge flips v.
+        let (flip_u, flip_v) = match *self {
+            Topology::Bounded { .. } => {
+                let inside = (0..width).contains(&u) && (0..height).contains(&v);
+                return inside.then_some((u, v));
+            }
+            Topology::Sphere { size } => return fold_sphere(size, (u, v)),
+            Topology::Klein { flip_x, .. } => (flip_x, !flip_x),
+            Topology::CrossSurface { .. } => (true, true),
+            _ => (false, false),
+        };
+        let (across, up) = (u.div_euclid(width), v.div_euclid(height));
+        let (mut u, mut v) = (u.rem_euclid(width), v.rem_euclid(height));
+        if flip_u && up % 2 != 0 {
+            u = width - 1 - u;
+        }
+        if flip_v && across % 2 != 0 {
+            v = height - 1 - v;
+        }
+        Some((u, v))
+    }
+
+    /// Whether `pos` is inside the rectangle, or anywhere on the plane.
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        match self.bounds() {
+            None => true,
+            Some(((x0, y0), (x1, y1))) => (x0..=x1).contains(&x) && (y0..=y1).contains(&y),
+        }
+    }
+
+    /// Whether the square reaching `radius` cells around `pos` crosses an edge, so that the
+    /// positions in it have to be wrapped one by one.
+    pub fn crosses_edge(&self, (x, y): (i32, i32), radius: i32) -> bool {
+        match self.bounds() {
+            None => false,
+            Some(((x0, y0), (x1, y1))) => {
+                x - radius < x0 || x + radius > x1 || y - radius < y0 || y + radius > y1
+            }
+        }
+    }
+
+    /// The copies of the rectangle that overlap the inclusive region between `min` and `max`,
+    /// each as the offset of the copy and the part of the rectangle (in its own coordinates)
+    /// that shows through the region. The whole region for the plane, and the part of it inside
+    /// the rectangle for a bounded plane.
+    /// For other surfaces, whose copies would be mirrored or turned, just the rectangle itself.
+    pub fn tiles(&self, min: (i32, i32), max: (i32, i32)) -> Vec<((i32, i32), Region)> {
+        let Some(((x0, y0), (x1, y1))) = self.bounds() else {
+            return vec![((0, 0), (min, max))];
+        };
+        let Topology::Torus { width, height } = *self else {
+            let (lo, hi) = (
+                (min.0.max(x0), min.1.max(y0)),
+                (max.0.min(x1), max.1.min(y1)),
+            );
+            return if lo.0 <= hi.0 && lo.1 <= hi.1 {
+                vec![((0, 0), (lo, hi))]
+            } else {
+                Vec::new()
+            };
+        };
+        let tiles = |lo: i32, hi: i32, start: i32, side: i32| {
+            ((lo - start).div_euclid(side)..=(hi - start).div_euclid(side)).map(move |k| k * side)
+        };
+        let mut copies = Vec::new();
+        for dy in tiles(min.1, max.1, y0, height) {
+            for dx in tiles(min.0, max.0, x0, width) {
+                let lo = ((min.0 - dx).max(x0), (min.1 - dy).max(y0));
+                let hi = ((max.0 - dx).min(x1), (max.1 - dy).min(y1));
+                copies.push(((dx, dy), (lo, hi)));
+            }
+        }
+        copies
+    }
+}
+
+/// [`Topology::fold`] for a sphere: a position past the bottom edge is the one as far past the
+/// left edge, turned, and likewise for the top and right edges. Positions past a corner or more
+/// than a side away are off the sphere.
+fn fold_sphere(size: i32, (u, v): (i32, i32)) -> Option<(i32, i32)> {
+    let inside = |n: i32| (0..size).contains(&n);
+    match (inside(u), inside(v)) {
+        (true, true) => Some((u, v)),
+        (true, false) if (-size..0).contains(&v) => Some((-1 - v, u)),
+        (true, false) if (size..2 * size).contains(&v) => Some((2 * size - 1 - v, u)),
+        (false, true) if (-size..0).contains(&u) => Some((v, -1 - u)),
+        (false, true) if (size..2 * size).contains(&u) => Some((v, 2 * size - 1 - u)),
+        _ => None,
+    }
+}
+
+/// Parse a size given as `WxH`.
+fn parse_size(size: &str) -> Result<(i32, i32)> {
+    let invalid = || anyhow!("invalid size: {} (expected e.g. 200x100)", size);
+    let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width = width.trim().parse().map_err(|_| invalid())?;
+    let height = height.trim().parse().map_err(|_| invalid())?;
+    Ok((width, height))
+}
+
+/// Topologies display in Golly's notation, as it follows the rule in RLE headers: `T200,100`
+/// for a 200 by 100 torus, `P200,100` for a bounded plane, `K200*,100` for a Klein bottle with
+/// its top and bottom edges twisted (`K200,100*` for the left and right), `C200,100` for a
+/// cross-surface and `S200` for a sphere.
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Topology::Plane => write!(f, "plane"),
+            Topology::Torus { width, height } => write!(f, "T{},{}", width, height),
+            Topology::Bounded { width, height } => write!(f, "P{},{}", width, height),
+            Topology::Klein {
+                width,
+                height,
+                flip_x: true,
+            } => write!(f, "K{}*,{}", width, height),
+            Topology::Klein { width, height, .. } => write!(f, "K{},{}*", width, height),
+            Topology::CrossSurface { width, height } => write!(f, "C{},{}", width, height),
+            Topology::Sphere { size } => write!(f, "S{}", size),
+        }
+    }
+}
+
+impl FromStr for Topology {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Topology> {
+        if s == "plane" {
+            return Ok(Topology::Plane);
+        }
+        let invalid = || anyhow!("invalid topology: {} (expected e.g. T200,100)", s);
+        let kinds = ['T', 'P', 'K', 'C', 'S'];
+        let kind = s
+            .chars()
+            .next()
+            .filter(|kind| kinds.contains(kind))
+            .ok_or_else(invalid)?;
+        let number = |n: &str| n.parse::<i32>().map_err(|_| invalid());
+        if kind == 'S' {
+            let size = number(&s[1..])?;
+            return Topology::sized(kind, size, size);
+        }
+        let (width, height) = s[1..].split_once(',').ok_or_else(invalid)?;
+        match (kind, width.strip_suffix('*'), height.strip_suffix('*')) {
+            ('K', Some(width), None) => Topology::sized(kind, number(width)?, number(height)?),
+            ('K', None, Some(height)) => {
+                let (width, height) = (number(width)?, number(height)?);
+                // Checks the size.
+                Topology::sized(kind, width, height)?;
+                Ok(Topology::Klein {
+                    width,
+                    height,
+                    flip_x: false,
+                })
+            }
+            (_, None, None) if kind != 'K' => {
+                Topology::sized(kind, number(width)?, number(height)?)
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::topology::Topology;
+
+    #[test]
+    fn parses_tori() -> Result<()> {
+        let torus = Topology::Torus {
+            width: 200,
+            height: 100,
+        };
+        assert_eq!(Topology::torus("200x100")?, torus);
+        assert_eq!("T200,100".parse::<Topology>()?, torus);
+        assert_eq!(torus.to_string(), "T200,100");
+        assert_eq!("plane".parse::<Topology>()?, Topology::Plane);
+        assert!(Topology::torus("200").is_err());
+        assert!(Topology::torus("4x4").is_err());
+        assert!("T200".parse::<Topology>().is_err());
+        assert!("Q200,100".parse::<Topology>().is_err());
+        let bounded = Topology::Bounded {
+            width: 4,
+            height: 3,
+        };
+        assert_eq!(Topology::bounded("4x3")?, bounded);
+        assert_eq!("P4,3".parse::<Topology>()?, bounded);
+        assert_eq!(bounded.to_string(), "P4,3");
+        assert!(Topology::bounded("0x3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_twisted_surfaces() -> Result<()> {
+        for notation in ["K20*,10", "K20,10*", "C20,10", "S20"] {
+            assert_eq!(notation.parse::<Topology>()?.to_string(), notation);
+        }
+        let klein = Topology::Klein {
+            width: 20,
+            height: 10,
+            flip_x: false,
+        };
+        assert_eq!("K20,10*".parse::<Topology>()?, klein);
+        assert_eq!(
+            "S20".parse::<Topology>()?.bounds(),
+            Some(((-10, -10), (9, 9)))
+        );
+        for invalid in ["K20,10", "K20*,10*", "T20*,10", "S4", "S20,10"] {
+            assert!(invalid.parse::<Topology>().is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn wraps_positions_into_the_rectangle() -> Result<()> {
+        let torus = Topology::torus("10x9")?;
+        assert_eq!(torus.bounds(), Some(((-5, -4), (4, 4))));
+        assert_eq!(torus.wrap((4, 4)), Some((4, 4)));
+        assert_eq!(torus.wrap((5, -5)), Some((-5, 4)));
+        assert_eq!(torus.wrap((-26, 13)), Some((4, 4)));
+        assert_eq!(Topology::Plane.wrap((-26, 13)), Some((-26, 13)));
+        let bounded = Topology::bounded("10x9")?;
+        assert_eq!(bounded.wrap((4, 4)), Some((4, 4)));
+        assert_eq!(bounded.wrap((5, 4)), None);
+        assert!(!torus.crosses_edge((3, 0), 1));
+        assert!(torus.crosses_edge((4, 0), 1));
+        // Crossing the top or bottom of this Klein bottle mirrors x, and its sides don't.
+        let klein: Topology = "K10*,9".parse()?;
+        assert_eq!(klein.wrap((-3, 5)), Some((2, -4)));
+        assert_eq!(klein.wrap((-3, 14)), Some((-3, -4)));
+        assert_eq!(klein.wrap((5, 4)), Some((-5, 4)));
+        // The bottom of a sphere is its left side.
+        let sphere: Topology = "S10".parse()?;
+        assert_eq!(sphere.wrap((2, -6)), Some((-5, 2)));
+        assert_eq!(sphere.wrap((-6, 2)), Some((2, -5)));
+        assert_eq!(sphere.wrap((-6, -6)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn tiles_regions() -> Result<()> {
+        let torus = Topology::torus("10x10")?;
+        assert_eq!(torus.tiles((0, 0), (3, 3)), [((0, 0), ((0, 0), (3, 3)))]);
+        assert_eq!(
+            torus.tiles((3, 0), (7, 0)),
+            [((0, 0), ((3, 0), (4, 0))), ((10, 0), ((-5, 0), (-3, 0)))]
+        );
+        assert_eq!(torus.tiles((-30, -30), (30, 30)).len(), 49);
+        let bounded = Topology::bounded("10x10")?;
+        assert_eq!(
+            bounded.tiles((-30, 3), (30, 30)),
+            [((0, 0), ((-5, 3), (4, 4)))]
+        );
+        assert!(bounded.tiles((5, 0), (30, 30)).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn runs_on_a_torus() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider", "--wrap", "8x8", "--build"], 40, 20)?;
+        assert!(harness.status().contains(", topology: T8,8"));
+        let start = harness.cells();
+        // After 32 generations the glider has crossed the torus and is back where it started.
+        harness.type_keys("22211")?;
+        assert_eq!(harness.program.sim.world.generations, 32);
+        assert_eq!(harness.cells(), start);
+        // The view shows copies of the torus side by side.
+        let (min, max) = harness.program.screen.inner_region(0);
+        assert_eq!(
+            harness.program.sim.world.cells_in((-8, -8), (7, 7)).count(),
+            4 * 5
+        );
+        assert!(harness.program.sim.world.cells_in(min, max).count() > 4 * 5);
+        let session = harness.program.session();
+        assert_eq!(session.topology, Topology::torus("8x8")?);
+        Ok(())
+    }
+
+    #[test]
+    fn runs_on_a_bounded_plane() -> Result<()> {
+        let mut harness = Harness::new(&["--bounded", "10x10", "--build"], 40, 20)?;
+        assert!(harness.status().contains(", topology: P10,10"));
+        harness.type_keys("deddddde")?;
+        assert_eq!(harness.cells(), vec![(1, 0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn runs_on_a_klein_bottle() -> Result<()> {
+        let args = ["--topology", "K10*,10", "--build"];
+        let mut harness = Harness::new(&args, 40, 20)?;
+        assert!(harness.status().contains(", topology: K10*,10"));
+        // Toggling a cell past the top edge toggles the mirrored cell at the bottom.
+        harness.type_keys("ddwwwwwe")?;
+        assert_eq!(harness.cells(), vec![(-3, -5)]);
+        assert_eq!(harness.program.session().topology.to_string(), "K10*,10");
+        assert!(Harness::new(&["--topology", "K10,10", "--build"], 40, 20).is_err());
+        Ok(())
+    }
+}
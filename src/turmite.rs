@@ -0,0 +1,334 @@
+use crate::rule::Rule;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Which way an ant faces, with north towards increasing `y`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Heading {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    const ALL: [Heading; 4] = [Heading::North, Heading::East, Heading::South, Heading::West];
+
+    /// The offset of the cell in front.
+    pub fn step(self) -> (i32, i32) {
+        match self {
+            Heading::North => (0, 1),
+            Heading::East => (1, 0),
+            Heading::South => (0, -1),
+            Heading::West => (-1, 0),
+        }
+    }
+
+    /// The heading that moves by `step`, if it is one cell in one of the four directions.
+    pub fn from_step(step: (i32, i32)) -> Option<Heading> {
+        Heading::ALL
+            .into_iter()
+            .find(|heading| heading.step() == step)
+    }
+
+    pub fn turn(self, turn: Turn) -> Heading {
+        let quarters = match turn {
+            Turn::None => 0,
+            Turn::Right => 1,
+            Turn::UTurn => 2,
+            Turn::Left => 3,
+        };
+        Heading::ALL[(self as usize + quarters) % 4]
+    }
+}
+
+impl fmt::Display for Heading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = match self {
+            Heading::North => "N",
+            Heading::East => "E",
+            Heading::South => "S",
+            Heading::West => "W",
+        };
+        write!(f, "{}", letter)
+    }
+}
+
+impl FromStr for Heading {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Heading> {
+        Heading::ALL
+            .into_iter()
+            .find(|heading| heading.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| anyhow!("invalid heading: {} (expected N, E, S or W)", s))
+    }
+}
+
+/// Which way an ant turns before it moves on, with the codes Golly's turmite rules use for them.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Turn {
+    None = 1,
+    Right = 2,
+    UTurn = 4,
+    Left = 8,
+}
+
+impl Turn {
+    fn from_code(code: u8) -> Option<Turn> {
+        [Turn::None, Turn::Right, Turn::UTurn, Turn::Left]
+            .into_iter()
+            .find(|&turn| turn as u8 == code)
+    }
+
+    /// The letter for the turn in the rulestring of a Langton's Ant generalization.
+    fn letter(self) -> char {
+        match self {
+            Turn::None => 'N',
+            Turn::Right => 'R',
+            Turn::UTurn => 'U',
+            Turn::Left => 'L',
+        }
+    }
+}
+
+/// An ant walking over the cells of a turmite world. It carries a state of its own, which
+/// together with the color of the cell it is on decides what it does next.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Ant {
+    pub position: (i32, i32),
+    pub heading: Heading,
+    pub state: u8,
+}
+
+impl Ant {
+    /// An ant facing north in its first state.
+    pub fn new(position: (i32, i32)) -> Ant {
+        Ant {
+            position,
+            heading: Heading::North,
+            state: 0,
+        }
+    }
+}
+
+/// What an ant does on a cell: the color it paints the cell, how it turns, and the state it
+/// moves on in.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Transition {
+    pub color: u8,
+    pub turn: Turn,
+    pub state: u8,
+}
+
+/// A turmite rule: instead of every cell changing with its neighbors, ants move around the
+/// world, one cell per generation, each recoloring the cell it leaves. Cells are colored by
+/// their state: 0 for dead, 1 for alive and 2 onwards for the dying states, which don't decay.
+///
+/// Written either as a Langton's Ant generalization, with the way the ant turns on each color
+/// as `L`, `R`, `N` (no turn) or `U` (U-turn) and each color painted over with the next, e.g.
+/// `RL` for Langton's Ant itself, or in Golly's notation, a table of `{color,turn,state}`
+/// triples for each ant state and cell color, e.g. `{{{1,2,0},{0,8,0}}}`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Turmite {
+    /// Indexed by ant state and then by cell color.
+    table: Vec<Vec<Transition>>,
+}
+
+impl Turmite {
+    /// What an ant in `state` does on a cell of `color`. States and colors the table doesn't
+    /// cover wrap around into it.
+    pub fn transition(&self, state: u8, color: u8) -> Transition {
+        let row = &self.table[usize::from(state) % self.table.len()];
+        row[usize::from(color) % row.len()]
+    }
+
+    /// The turns of a Langton's Ant generalization, if this is one: a single ant state, with
+    /// each color painted over with the next.
+    fn ant_turns(&self) -> Option<String> {
+        let [row] = &self.table[..] else {
+            return None;
+        };
+        let colors = row.len();
+        row.iter()
+            .enumerate()
+            .map(|(color, transition)| {
+                let next = usize::from(transition.color) == (color + 1) % colors;
+                (next && transition.state == 0).then(|| transition.turn.letter())
+            })
+            .collect()
+    }
+}
+
+impl Rule for Turmite {
+    fn next_state(&self, _alive: bool, _neighbors: u8) -> bool {
+        false
+    }
+
+    fn states(&self) -> u8 {
+        self.table[0].len() as u8
+    }
+
+    fn state_name(&self, state: u8) -> String {
+        format!("color {}", state)
+    }
+
+    fn turmite(&self) -> Option<&Turmite> {
+        Some(self)
+    }
+}
+
+impl fmt::Display for Turmite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(turns) = self.ant_turns() {
+            return write!(f, "{}", turns);
+        }
+        let states: Vec<String> = self
+            .table
+            .iter()
+            .map(|row| {
+                let triples: Vec<String> = row
+                    .iter()
+                    .map(|t| format!("{{{},{},{}}}", t.color, t.turn as u8, t.state))
+                    .collect();
+                format!("{{{}}}", triples.join(","))
+            })
+            .collect();
+        write!(f, "{{{}}}", states.join(","))
+    }
+}
+
+impl FromStr for Turmite {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Turmite> {
+        let s = s.trim();
+        let invalid = || anyhow!("invalid turmite: {} (expected e.g. RL)", s);
+        let table = if s.starts_with('{') {
+            golly_table(s).ok_or_else(invalid)?
+        } else {
+            let colors = s.len();
+            let row = s
+                .chars()
+                .enumerate()
+                .map(|(color, letter)| {
+                    let turn = [Turn::None, Turn::Right, Turn::UTurn, Turn::Left]
+                        .into_iter()
+                        .find(|turn| turn.letter() == letter.to_ascii_uppercase())?;
+                    let color = ((color + 1) % colors) as u8;
+                    Some(Transition {
+                        color,
+                        turn,
+                        state: 0,
+                    })
+                })
+                .collect::<Option<_>>()
+                .ok_or_else(invalid)?;
+            vec![row]
+        };
+        let colors = table[0].len();
+        let fits =
+            |t: &Transition| usize::from(t.color) < colors && usize::from(t.state) < table.len();
+        if !(2..=usize::from(u8::MAX)).contains(&colors)
+            || table
+                .iter()
+                .any(|row| row.len() != colors || !row.iter().all(fits))
+        {
+            return Err(invalid());
+        }
+        Ok(Turmite { table })
+    }
+}
+
+/// The table of a turmite in Golly's notation, with spaces allowed anywhere.
+fn golly_table(s: &str) -> Option<Vec<Vec<Transition>>> {
+    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    let inner = s.strip_prefix("{{{")?.strip_suffix("}}}")?;
+    inner
+        .split("}},{{")
+        .map(|row| {
+            row.split("},{")
+                .map(|triple| {
+                    let fields: Vec<u8> = triple
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .ok()?;
+                    let &[color, turn, state] = &fields[..] else {
+                        return None;
+                    };
+                    Some(Transition {
+                        color,
+                        turn: Turn::from_code(turn)?,
+                        state,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn parses_turmites() -> Result<()> {
+        let langton: Turmite = "RL".parse()?;
+        assert_eq!(
+            langton.transition(0, 1),
+            Transition {
+                color: 0,
+                turn: Turn::Left,
+                state: 0
+            }
+        );
+        assert_eq!(langton.states(), 2);
+        assert_eq!("{{{1,2,0},{0,8,0}}}".parse::<Turmite>()?, langton);
+        assert_eq!(" llrr ".parse::<Turmite>()?.to_string(), "LLRR");
+
+        let fibonacci = "{{{1,8,1},{1,8,1}},{{1,2,1},{0,1,0}}}";
+        let turmite: Turmite = fibonacci.parse()?;
+        assert_eq!(turmite.to_string(), fibonacci);
+        assert_eq!(turmite.transition(1, 0).state, 1);
+
+        for invalid in [
+            "R",
+            "RX",
+            "{{{1,2,0}}}",
+            "{{{1,2,0},{0,3,0}}}",
+            "{{{1,2,1},{0,8,0}}}",
+        ] {
+            assert!(invalid.parse::<Turmite>().is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn turns_headings() {
+        assert_eq!(Heading::North.turn(Turn::Right), Heading::East);
+        assert_eq!(Heading::North.turn(Turn::Left), Heading::West);
+        assert_eq!(Heading::East.turn(Turn::UTurn), Heading::West);
+        assert_eq!(Heading::from_step((0, -1)), Some(Heading::South));
+        assert_eq!(Heading::from_step((2, 0)), None);
+    }
+
+    #[test]
+    fn walks_ants_around() -> Result<()> {
+        let mut harness = Harness::new(&["--rule", "RL", "--build"], 40, 20)?;
+        harness.type_keys("A")?;
+        assert_eq!(harness.status(), "put down an ant");
+        harness.type_keys(".....")?;
+        assert_eq!(harness.cells(), vec![(0, -1), (1, -1), (1, 0)]);
+        assert!(harness.status().contains("rule: RL, ants: 1"));
+        let session = harness.program.session();
+        assert!(session.serialize().ends_with("ant -1 0 W 0\n"));
+        harness.type_keys("aA")?;
+        assert_eq!(harness.status(), "picked up the ant");
+        assert!(harness.program.sim.world.ants().is_empty());
+        Ok(())
+    }
+}
@@ -1,21 +1,56 @@
-use crate::Program;
+use crate::brush::Brush;
+use crate::config::{Glyphs, Theme};
+use crate::history::History;
+use crate::raster::Region;
+use crate::rule;
+use crate::rule_editor::RuleEditor;
+use crate::tickrate;
+use crate::topology::Topology;
+use crate::{Position, Program};
 use anyhow::{anyhow, Result};
 use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
     LeaveAlternateScreen,
 };
-use crossterm::ExecutableCommand;
+use crossterm::{ExecutableCommand, QueueableCommand};
+use lazy_static::lazy_static;
 use std::io::{self, Write};
+use std::mem;
 use std::sync::atomic::{AtomicBool, Ordering};
-use lazy_static::lazy_static;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 lazy_static! {
     static ref TERMINAL_ACQUIRED: AtomicBool = AtomicBool::new(false);
 }
 
-#[derive(Debug)]
+/// Spacing of the vertical grid lines, in cells.
+const GRID_X: i32 = 8;
+/// Spacing of the horizontal grid lines, in terminal rows (two cells each).
+const GRID_Y: i32 = 4;
+/// The colors of WireWorld's electron tails and wire.
+const TAIL_COLOR: Color = Color::Rgb {
+    r: 255,
+    g: 80,
+    b: 0,
+};
+const WIRE_COLOR: Color = Color::Rgb {
+    r: 200,
+    g: 160,
+    b: 0,
+};
+/// The color of Brian's Brain's refractory cells, which have just fired.
+const REFRACTORY_COLOR: Color = Color::Rgb {
+    r: 60,
+    g: 110,
+    b: 255,
+};
+/// The color of the cells with a turmite's ants on them.
+const ANT_COLOR: Color = Color::Red;
+
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub x: i32,
     pub y: i32,
@@ -27,11 +62,52 @@ impl Camera {
     }
 }
 
+/// How the grid is divided into panes, each showing the world through its own camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Single,
+    /// Two panes side by side.
+    Split,
+    /// Four panes in a 2x2 grid.
+    Quad,
+}
+
+impl Layout {
+    pub fn panes(self) -> usize {
+        match self {
+            Layout::Single => 1,
+            Layout::Split => 2,
+            Layout::Quad => 4,
+        }
+    }
+
+    fn next(self) -> Layout {
+        match self {
+            Layout::Single => Layout::Split,
+            Layout::Split => Layout::Quad,
+            Layout::Quad => Layout::Single,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Screen {
     pub width: u16,
     pub height: u16,
+    /// The camera of the focused pane, which keys and the mouse move.
     pub camera: Camera,
+    layout: Layout,
+    /// The cameras of the other panes, by pane. The focused pane's slot is unused while it has
+    /// focus.
+    panes: Vec<Camera>,
+    focus: usize,
+    /// Whether mouse events are reported to us rather than handled by the terminal (e.g. for
+    /// text selection).
+    pub mouse_capture: bool,
+    pub glyphs: Glyphs,
+    pub theme: Theme,
+    /// Whether this screen owns the real terminal. Fake screens used in tests do not.
+    terminal: bool,
 }
 
 impl Screen {
@@ -43,18 +119,45 @@ impl Screen {
             width: w,
             height: h,
             camera,
+            layout: Layout::Single,
+            panes: vec![Camera::new()],
+            focus: 0,
+            mouse_capture: true,
+            glyphs: Glyphs::default(),
+            theme: Theme::default(),
+            terminal: true,
         })
     }
 
+    /// A screen of a fixed size that never touches the real terminal, for driving the program
+    /// in tests.
+    #[cfg(any(test, feature = "test-harness"))]
+    pub fn fake(width: u16, height: u16) -> Screen {
+        Screen {
+            width,
+            height,
+            camera: Camera::new(),
+            layout: Layout::Single,
+            panes: vec![Camera::new()],
+            focus: 0,
+            mouse_capture: true,
+            glyphs: Glyphs::default(),
+            theme: Theme::default(),
+            terminal: false,
+        }
+    }
+
     pub fn acquire_terminal() -> Result<()> {
-        if TERMINAL_ACQUIRED.compare_exchange(
-            false, true, Ordering::SeqCst, Ordering::SeqCst
-        ).is_err() {
+        if TERMINAL_ACQUIRED
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
             return Err(anyhow!("terminal already in use"));
         }
         let mut stdout = io::stdout();
         stdout.execute(EnterAlternateScreen)?;
         stdout.execute(Hide)?;
+        stdout.execute(EnableMouseCapture)?;
         enable_raw_mode()?;
         Ok(())
     }
@@ -63,6 +166,7 @@ impl Screen {
         let mut stdout = io::stdout();
         if TERMINAL_ACQUIRED.load(Ordering::SeqCst) {
             disable_raw_mode()?;
+            stdout.execute(DisableMouseCapture)?;
             stdout.execute(Show)?;
             stdout.execute(LeaveAlternateScreen)?;
             TERMINAL_ACQUIRED.store(false, Ordering::SeqCst);
@@ -71,87 +175,827 @@ impl Screen {
     }
 
     pub fn clear(&self) -> Result<()> {
-        let mut stdout = io::stdout();
-        stdout.execute(Clear(ClearType::All))?;
+        if self.terminal {
+            io::stdout().execute(Clear(ClearType::All))?;
+        }
         Ok(())
     }
 
-    pub fn reset_cursor(&self) -> Result<()> {
+    pub fn set_mouse_capture(&mut self, enabled: bool) -> Result<()> {
         let mut stdout = io::stdout();
-        stdout.execute(MoveTo(0, 0))?;
+        match (self.terminal, enabled) {
+            (false, _) => (),
+            (true, true) => {
+                stdout.execute(EnableMouseCapture)?;
+            }
+            (true, false) => {
+                stdout.execute(DisableMouseCapture)?;
+            }
+        }
+        self.mouse_capture = enabled;
+        Ok(())
+    }
+
+    /// Switch to the next layout: one pane, two side by side, then four. New panes start at the
+    /// focused pane's camera, and going back to one pane keeps the focused pane.
+    pub fn cycle_layout(&mut self) {
+        self.layout = self.layout.next();
+        let count = self.layout.panes();
+        if count < self.panes.len() {
+            self.panes.truncate(count);
+            self.focus = 0;
+        }
+        while self.panes.len() < count {
+            self.panes.push(self.camera.clone());
+        }
+    }
+
+    /// The focused pane and the number of panes.
+    #[cfg(test)]
+    pub fn focus(&self) -> (usize, usize) {
+        (self.focus, self.layout.panes())
+    }
+
+    /// Move the focus to the next pane.
+    pub fn focus_next(&mut self) {
+        self.set_focus((self.focus + 1) % self.layout.panes());
+    }
+
+    /// Focus the pane containing a terminal position, if there is one.
+    pub fn focus_at(&mut self, column: u16, row: u16) {
+        let pane = (0..self.layout.panes()).find(|&pane| {
+            let (col, top, width, rows) = self.rect(pane);
+            (col..col + width).contains(&column) && (top..top + rows).contains(&row)
+        });
+        if let Some(pane) = pane {
+            self.set_focus(pane);
+        }
+    }
+
+    fn set_focus(&mut self, pane: usize) {
+        if pane != self.focus {
+            mem::swap(&mut self.camera, &mut self.panes[self.focus]);
+            mem::swap(&mut self.camera, &mut self.panes[pane]);
+            self.focus = pane;
+        }
+    }
+
+    fn camera_of(&self, pane: usize) -> &Camera {
+        if pane == self.focus {
+            &self.camera
+        } else {
+            &self.panes[pane]
+        }
+    }
+
+    /// The terminal column and row a pane starts at, and its size in columns and rows. Panes
+    /// share the space above the separator, with a one character divider between them.
+    fn rect(&self, pane: usize) -> (u16, u16, u16, u16) {
+        let rows = self.height.saturating_sub(2);
+        let halve = |size: u16| {
+            let first = size.saturating_sub(1) / 2;
+            [(0, first), (first + 1, size.saturating_sub(first + 1))]
+        };
+        let (columns, rows) = match self.layout {
+            Layout::Single => ((0, self.width), (0, rows)),
+            Layout::Split => (halve(self.width)[pane], (0, rows)),
+            Layout::Quad => (halve(self.width)[pane % 2], halve(rows)[pane / 2]),
+        };
+        (columns.0, rows.0, columns.1, rows.1)
+    }
+
+    /// The area a pane shows in cell columns and terminal rows, with the maximum exclusive.
+    fn pane_viewport(&self, pane: usize) -> ((i32, i32), (i32, i32)) {
+        let camera = self.camera_of(pane);
+        let (_, _, width, rows) = self.rect(pane);
+        let x0 = camera.x - i32::from(width) / 2;
+        let y0 = camera.y - i32::from(rows) / 2;
+        ((x0, y0), (x0 + i32::from(width), y0 + i32::from(rows)))
+    }
+
+    /// The area the focused pane shows.
+    fn viewport(&self) -> ((i32, i32), (i32, i32)) {
+        self.pane_viewport(self.focus)
+    }
+
+    /// The size of the view in cell columns and terminal rows.
+    pub fn view_size(&self) -> (i32, i32) {
+        let ((x0, y0), (x1, y1)) = self.viewport();
+        (x1 - x0, y1 - y0)
+    }
+
+    /// The cell at the center of the view.
+    pub fn center(&self) -> Position {
+        // The camera moves in terminal rows, which hold two cells each.
+        (self.camera.x, 2 * self.camera.y)
+    }
+
+    /// Move the camera so that `position` is at the center of the view.
+    pub fn center_on(&mut self, (x, y): Position) {
+        self.camera.x = x;
+        self.camera.y = y.div_euclid(2);
+    }
+
+    /// The inclusive region of cells at least `margin` cells inside the edges of the view.
+    pub fn inner_region(&self, margin: i32) -> ((i32, i32), (i32, i32)) {
+        let ((x0, y0), (x1, y1)) = self.viewport();
+        let (min, max) = ((x0, 2 * y0), (x1 - 1, 2 * y1 - 1));
+        // Keep at least the middle of the view if it is too small for the margin.
+        let mx = margin.min((max.0 - min.0) / 2).max(0);
+        let my = margin.min((max.1 - min.1) / 2).max(0);
+        ((min.0 + mx, min.1 + my), (max.0 - mx, max.1 - my))
+    }
+
+    /// The cell drawn in the lower half of a terminal position, if it is part of the focused
+    /// pane.
+    pub fn cell_at(&self, column: u16, row: u16) -> Option<Position> {
+        let ((x0, y0), (x1, y1)) = self.viewport();
+        let (col, top, _, _) = self.rect(self.focus);
+        let x = x0 + i32::from(column) - i32::from(col);
+        let y = y1 - 1 - (i32::from(row) - i32::from(top));
+        if (x0..x1).contains(&x) && (y0..y1).contains(&y) {
+            Some((x, 2 * y))
+        } else {
+            None
+        }
+    }
+
+    /// The number of lines the pattern picker can take up.
+    pub fn picker_rows(&self) -> usize {
+        usize::from(self.height).saturating_sub(2)
+    }
+
+    /// Capture everything needed to draw the current view, so it can be drawn on another thread
+    /// while the simulation continues.
+    pub fn frame(&self, program: &Program, profile: Option<Vec<String>>) -> Frame {
+        let panes = (0..self.layout.panes())
+            .map(|pane| self.pane_view(program, pane))
+            .collect();
+
+        let status = if let Some(job) = &program.job {
+            format!("{} (Esc to cancel)", job)
+        } else {
+            match (
+                &program.cmdline,
+                program.message.text(),
+                program.measurement(),
+            ) {
+                (Some(line), _, _) if program.completions.is_empty() => format!(":{}", line),
+                (Some(line), _, _) => format!(":{}  ({})", line, program.completions.join(" ")),
+                _ if let Some(error) = &program.error => {
+                    format!("error: {} (Enter or Esc to dismiss)", error)
+                }
+                _ if let Some(prompt) = &program.rule_prompt => format!(
+                    "{}, which is for rule {}: switch to it? (y/n)",
+                    prompt.context, prompt.rule
+                ),
+                _ if program.picker.is_some() => String::from(PICKER_HELP),
+                (None, Some(message), _) => message.to_string(),
+                _ if program.rule_editor.is_some() => String::from(RULE_EDITOR_HELP),
+                (None, None, Some(measurement)) => measurement.to_string(),
+                (None, None, None) => match &program.status {
+                    Some((status, _)) => status.clone(),
+                    None => self.world_status(program),
+                },
+            }
+        };
+
+        Frame {
+            width: self.width,
+            height: self.height,
+            panes,
+            cursor: program.cursor,
+            brush: (program.brush.radius > 1).then_some(program.brush),
+            marks: program.marks.clone(),
+            status,
+            rulers: program.rulers,
+            profile,
+            help: program
+                .help
+                .then(|| HELP.iter().map(|line| line.to_string()).collect()),
+            picker: program
+                .picker
+                .as_ref()
+                .map(|picker| picker.lines(self.picker_rows(), &program.previews)),
+            rule_editor: program.rule_editor.as_ref().map(RuleEditor::lines),
+            error: program
+                .error
+                .as_ref()
+                .map(|error| error_lines(error, usize::from(self.width).saturating_sub(2))),
+            glyphs: self.glyphs,
+            theme: self.theme,
+        }
+    }
+
+    /// The status line when nothing else is shown: the population, generation and rate, and
+    /// whatever else is being tracked.
+    pub fn world_status(&self, program: &Program) -> String {
+        let sim = program.sim.status();
+        let mut status = format!(
+            "alive: {}, generations: {}, tickrate: {}",
+            sim.population,
+            sim.generations,
+            tickrate::format_rate(sim.rate),
+        );
+        let rule = program.sim.world.rule();
+        if *rule != rule::life() {
+            status += &format!(", rule: {}", rule);
+        }
+        if (2..rule.states()).contains(&program.brush.state) {
+            status += &format!(", painting: {}", rule.state_name(program.brush.state));
+        }
+        let topology = program.sim.world.topology();
+        if topology != Topology::Plane {
+            status += &format!(", topology: {}", topology);
+        }
+        let ants = program.sim.world.ants().len();
+        if ants > 0 {
+            status += &format!(", ants: {}", ants);
+        }
+        if let Some(stats) = program.sampled_stats {
+            status += &format!(
+                ", clusters: {} (largest: {})",
+                stats.clusters, stats.largest_cluster
+            );
+        }
+        if let Some(history) = program.sim.history() {
+            status += &format!(", envelope: {}", envelope(history));
+        }
+        if self.layout.panes() > 1 {
+            status += &format!(", pane: {}/{}", self.focus + 1, self.layout.panes());
+        }
+        if program.frame_skip.every() > 1 {
+            status += &format!(", showing 1 in {} generations", program.frame_skip.every());
+        }
+        status
+    }
+
+    fn pane_view(&self, program: &Program, pane: usize) -> PaneView {
+        let (col, row, _, _) = self.rect(pane);
+        let ((x0, y0), (x1, y1)) = self.pane_viewport(pane);
+
+        // Gather the visible cells with one region query rather than looking up every position.
+        let columns = (x1 - x0) as usize;
+        let mut visible = vec![false; columns * 2 * (y1 - y0).max(0) as usize];
+        for (x, y) in program
+            .sim
+            .world
+            .cells_in((x0, 2 * y0), (x1 - 1, 2 * y1 - 1))
+        {
+            visible[(y - 2 * y0) as usize * columns + (x - x0) as usize] = true;
+        }
+        let history = program.sim.history().map(|history| {
+            let mut cells = vec![false; visible.len()];
+            for (x, y) in history.cells_in((x0, 2 * y0), (x1 - 1, 2 * y1 - 1)) {
+                cells[(y - 2 * y0) as usize * columns + (x - x0) as usize] = true;
+            }
+            cells
+        });
+        let world = &program.sim.world;
+        let decay = (world.rule().states() > 2).then(|| {
+            let mut cells = vec![0; visible.len()];
+            for ((x, y), decay) in world.dying_in((x0, 2 * y0), (x1 - 1, 2 * y1 - 1)) {
+                cells[(y - 2 * y0) as usize * columns + (x - x0) as usize] = decay;
+            }
+            (cells, dying_colors(&**world.rule()))
+        });
+        // Only a torus is drawn repeating; around other finite topologies there is nothing.
+        let bounds = match world.topology() {
+            Topology::Torus { .. } => None,
+            topology => topology.bounds(),
+        };
+
+        let ants = world
+            .ants()
+            .iter()
+            .map(|ant| ant.position)
+            .filter(|&(x, y)| x >= x0 && x < x1 && y >= 2 * y0 && y < 2 * y1)
+            .collect();
+
+        let labels = program
+            .labels
+            .iter()
+            .filter(|&(&(x, y), _)| {
+                let row = y.div_euclid(2);
+                x >= x0 && x < x1 && row >= y0 && row < y1
+            })
+            .map(|(&pos, text)| (pos, text.clone()))
+            .collect();
+
+        PaneView {
+            origin: (col, row),
+            min: (x0, y0),
+            max: (x1, y1),
+            visible,
+            history,
+            decay,
+            bounds,
+            ants,
+            labels,
+        }
+    }
+}
+
+/// The colors of the dying states of a rule, in order: WireWorld's tails and wire, Brian's
+/// Brain's refractory cells, or for other rules, fading as cells die.
+fn dying_colors(rule: &dyn rule::Rule) -> Vec<Color> {
+    match rule::preset_name(rule) {
+        Some("wireworld") => return vec![TAIL_COLOR, WIRE_COLOR],
+        Some("brians-brain") => return vec![REFRACTORY_COLOR],
+        _ => (),
+    }
+    let states = rule.states();
+    (1..states.saturating_sub(1))
+        .map(|decay| dying_color(decay, states))
+        .collect()
+}
+
+/// The color of a cell `decay` generations into dying under a rule with `states` states, from
+/// orange just after it dies to dark red just before it is fully dead.
+fn dying_color(decay: u8, states: u8) -> Color {
+    let steps = u16::from(states.saturating_sub(3)).max(1);
+    let t = u16::from(decay - 1).min(steps);
+    let mix = |from: u16, to: u16| ((from * (steps - t) + to * t) / steps) as u8;
+    Color::Rgb {
+        r: mix(255, 80),
+        g: mix(140, 0),
+        b: 0,
+    }
+}
+
+/// The lines of the error panel: the error and each of its causes, wrapped to `width`.
+fn error_lines(error: &anyhow::Error, width: usize) -> Vec<String> {
+    let mut lines = vec![String::from("error:")];
+    for (i, cause) in error.chain().enumerate() {
+        let text = if i == 0 {
+            format!("  {}", cause)
+        } else {
+            format!("  caused by: {}", cause)
+        };
+        lines.extend(wrap(&text, width));
+    }
+    lines
+}
+
+/// Text with control characters, which would move the terminal's cursor, replaced by spaces.
+fn printable(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Split text into lines of at most `width` terminal columns (but at least one character).
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut used = 0;
+    for c in printable(text).chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width && used > 0 {
+            lines.push(String::new());
+            used = 0;
+        }
+        lines.last_mut().expect("there is always a line").push(c);
+        used += w;
+    }
+    lines
+}
+
+/// Cut text down to at most `width` terminal columns, ending it with an ellipsis if anything was
+/// left off. Widths are in columns rather than characters, so wide characters such as emoji and
+/// CJK count double.
+fn truncate(text: &str, width: usize) -> String {
+    let text = printable(text);
+    if text.width() <= width {
+        return text;
+    }
+    // Leave a column for the ellipsis.
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+/// Text truncated or padded with spaces to exactly `width` terminal columns.
+fn fit(text: &str, width: usize) -> String {
+    let mut out = truncate(text, width);
+    out += &" ".repeat(width.saturating_sub(out.width()));
+    out
+}
+
+/// The size of the envelope of cells that have ever been alive, and its bounding box.
+pub fn envelope(history: &History) -> String {
+    match history.bounding_box() {
+        Some(((x0, y0), (x1, y1))) => {
+            format!("{} cells in {}x{}", history.len(), x1 - x0 + 1, y1 - y0 + 1)
+        }
+        None => String::from("0 cells"),
+    }
+}
+
+/// The key help overlay, toggled with `?`.
+const HELP: &[&str] = &[
+    "q/Esc       quit",
+    "Space       play/pause",
+    ".           step one generation (paused)",
+    "1/2/3/4     step 1/10/100/1000 generations (paused)",
+    "+/-         halve/double the timestep",
+    "hjkl        move the view",
+    "v           cycle through saved views",
+    "|/Tab       split the screen/focus the next pane",
+    "wasd        move the cursor",
+    "/           cycle the axis lock (one-cell moves)",
+    "e           fill/erase under the brush",
+    "i           cycle the brush state (multi-state rules)",
+    "A           put down/pick up an ant (turmite rules)",
+    "[/]         shrink/grow the brush",
+    "b           toggle the spray brush",
+    "m           mark for measuring",
+    "f           toggle auto-pan",
+    "r           toggle rulers",
+    "H           toggle history",
+    "p           toggle the profiling overlay",
+    "P           place a pattern",
+    "R           edit the rule",
+    "y/Ctrl+V    copy the selection/paste a pattern (clipboard)",
+    "X/D         cut/delete the selection (into the kill ring)",
+    "Y           paste from the kill ring (again for older ones)",
+    "S/I         save RLE/PNG",
+    ":           command line",
+    "?           toggle this help",
+];
+
+/// The status line while the pattern picker is open.
+const PICKER_HELP: &str =
+    "place a pattern: j/k to choose, Enter to place at the cursor, Esc to cancel";
+
+/// The status line while the rule editor is open.
+const RULE_EDITOR_HELP: &str =
+    "edit the rule: h/l/j/k to choose a count, Space to toggle, n/N for presets, Esc to close";
+
+/// Where an overlay is drawn.
+enum Corner {
+    TopLeft,
+    TopRight,
+}
+
+/// What one pane shows.
+#[derive(Debug)]
+struct PaneView {
+    /// The terminal column and row of the pane's top left corner.
+    origin: (u16, u16),
+    /// The viewport in cell columns and terminal rows, with `max` exclusive.
+    min: (i32, i32),
+    max: (i32, i32),
+    visible: Vec<bool>,
+    /// The cells that have ever been alive, laid out like `visible`, if history is shown.
+    history: Option<Vec<bool>>,
+    /// How far each cell is through the dying states, laid out like `visible`, if the rule has
+    /// any, along with the color of each dying state.
+    decay: Option<(Vec<u8>, Vec<Color>)>,
+    /// The rectangle of a finite topology other than a torus, outside of which is shaded.
+    bounds: Option<Region>,
+    /// The positions of the ants in view.
+    ants: Vec<Position>,
+    labels: Vec<(Position, String)>,
+}
+
+impl PaneView {
+    fn width(&self) -> u16 {
+        (self.max.0 - self.min.0) as u16
+    }
+
+    fn rows(&self) -> u16 {
+        (self.max.1 - self.min.1) as u16
+    }
+}
+
+/// A snapshot of one screenful: the visible cells and everything drawn on top of them.
+#[derive(Debug)]
+pub struct Frame {
+    width: u16,
+    height: u16,
+    panes: Vec<PaneView>,
+    cursor: Position,
+    /// The brush around the cursor, if it covers more than the cursor cell.
+    brush: Option<Brush>,
+    marks: Vec<Position>,
+    status: String,
+    rulers: bool,
+    profile: Option<Vec<String>>,
+    help: Option<Vec<String>>,
+    picker: Option<Vec<String>>,
+    rule_editor: Option<Vec<String>>,
+    error: Option<Vec<String>>,
+    glyphs: Glyphs,
+    theme: Theme,
+}
+
+impl Frame {
+    #[cfg(any(test, feature = "test-harness"))]
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    #[cfg(test)]
+    pub fn error(&self) -> Option<&[String]> {
+        self.error.as_deref()
+    }
+
+    /// Write the escape sequences that draw the frame to `out`.
+    pub fn draw<W: Write>(&self, out: &mut W) -> Result<()> {
+        for pane in &self.panes {
+            self.draw_pane(out, pane)?;
+        }
+        self.draw_dividers(out)?;
+
+        // The separator runs under the bottom panes, with ticks under their vertical grid lines.
+        let rows = self.height.saturating_sub(2);
+        let glyphs = &self.glyphs;
+        out.queue(MoveTo(0, rows))?;
+        for column in 0..self.width {
+            let below = self.panes.iter().find(|pane| {
+                let (col, row) = pane.origin;
+                row + pane.rows() == rows && (col..col + pane.width()).contains(&column)
+            });
+            match below {
+                Some(pane) if (pane.min.0 + i32::from(column - pane.origin.0)) % GRID_X == 0 => {
+                    write!(out, "{}", glyphs.separator_tick)?
+                }
+                Some(_) => write!(out, "{}", glyphs.separator)?,
+                None => write!(out, "{}", glyphs.separator_tick)?,
+            }
+        }
+
+        out.queue(MoveTo(0, rows + 1))?;
+        write!(out, "{}", fit(&self.status, usize::from(self.width)))?;
+        for pane in &self.panes {
+            if self.rulers {
+                self.draw_rulers(out, pane)?;
+            }
+            self.draw_labels(out, pane)?;
+        }
+        if let Some(lines) = &self.profile {
+            self.draw_overlay(out, lines, Corner::TopRight, Color::Cyan)?;
+        }
+        if let Some(lines) = &self.help {
+            self.draw_overlay(out, lines, Corner::TopLeft, Color::White)?;
+        }
+        if let Some(lines) = &self.picker {
+            self.draw_overlay(out, lines, Corner::TopLeft, Color::White)?;
+        }
+        if let Some(lines) = &self.rule_editor {
+            self.draw_overlay(out, lines, Corner::TopLeft, Color::White)?;
+        }
+        if let Some(lines) = &self.error {
+            self.draw_overlay(out, lines, Corner::TopLeft, Color::Red)?;
+        }
+
         Ok(())
     }
 
-    pub fn render(&self, program: &Program) -> Result<()> {
-        self.reset_cursor()?;
-        let x0 = self.camera.x - (self.width as i32 / 2);
-        let y0 = self.camera.y - (self.height as i32 / 2) + 1;
-        let x1 = self.camera.x + (self.width as i32 / 2) + (self.width as i32 % 2);
-        let y1 = self.camera.y + (self.height as i32 / 2) + (self.height as i32 % 2) - 1;
+    fn draw_pane<W: Write>(&self, out: &mut W, pane: &PaneView) -> Result<()> {
+        let (x0, y0) = pane.min;
+        let (x1, y1) = pane.max;
+        let columns = (x1 - x0) as usize;
+        let alive =
+            |x: i32, y: i32| pane.visible[(y - 2 * y0) as usize * columns + (x - x0) as usize];
+        // The color of a dead cell: dying cells fade from orange to dark red (or show as
+        // WireWorld's tails and wire), cells that were alive but no longer are show with history
+        // on, and the space outside a bounded plane is shaded. Ants show over any cell.
+        let ant = |x: i32, y: i32| pane.ants.contains(&(x, y));
+        let shade = |x: i32, y: i32| {
+            if ant(x, y) {
+                return Some(ANT_COLOR);
+            }
+            let index = (y - 2 * y0) as usize * columns + (x - x0) as usize;
+            if let Some((decay, colors)) = &pane.decay
+                && let Some(i) = usize::from(decay[index]).checked_sub(1)
+                && let Some(&color) = colors.get(i).or(colors.last())
+            {
+                return Some(color);
+            }
+            if let Some(((bx0, by0), (bx1, by1))) = pane.bounds
+                && !((bx0..=bx1).contains(&x) && (by0..=by1).contains(&y))
+            {
+                return Some(self.theme.outside);
+            }
+            pane.history
+                .as_ref()
+                .is_some_and(|history| history[index] && !pane.visible[index])
+                .then_some(self.theme.history)
+        };
+        let glyphs = &self.glyphs;
 
         for y in (y0..y1).rev() {
+            out.queue(MoveTo(pane.origin.0, pane.origin.1 + (y1 - 1 - y) as u16))?;
             for x in x0..x1 {
-                let (cx, cy) = program.cursor;
-                let a = program.world.alive(x, 2 * y);
-                let b = program.world.alive(x, 2 * y + 1);
-                let mut stdout = io::stdout();
-
-                if x == cx && 2 * y == cy  {
-                    stdout.execute(SetForegroundColor(Color::Green))?;
-                    if b {
-                        stdout.execute(SetBackgroundColor(Color::Grey))?;
+                let a = alive(x, 2 * y);
+                let b = alive(x, 2 * y + 1);
+
+                if let Some(color) = self.highlight((x, 2 * y), a) {
+                    out.queue(SetForegroundColor(color))?;
+                    if let Some(top) = self.highlight((x, 2 * y + 1), b) {
+                        out.queue(SetBackgroundColor(top))?;
+                    } else if b {
+                        out.queue(SetBackgroundColor(Color::Grey))?;
                     }
-                    print!("▄");
-                    stdout.execute(ResetColor)?;
-                } else if x == cx && 2 * y + 1 == cy {
-                    stdout.execute(SetForegroundColor(Color::Green))?;
+                    write!(out, "▄")?;
+                    out.queue(ResetColor)?;
+                } else if let Some(color) = self.highlight((x, 2 * y + 1), b) {
+                    out.queue(SetForegroundColor(color))?;
                     if a {
-                        stdout.execute(SetBackgroundColor(Color::Grey))?;
+                        out.queue(SetBackgroundColor(Color::Grey))?;
+                    }
+                    write!(out, "▀")?;
+                    out.queue(ResetColor)?;
+                } else if shade(x, 2 * y).is_some() || shade(x, 2 * y + 1).is_some() {
+                    // The block characters can only draw one color, so draw both halves
+                    // explicitly.
+                    let color = |alive: bool, y: i32| {
+                        if alive && !ant(x, y) {
+                            Some(Color::White)
+                        } else {
+                            shade(x, y)
+                        }
+                    };
+                    let bottom = color(a, 2 * y);
+                    let top = color(b, 2 * y + 1);
+                    match (top, bottom) {
+                        (Some(top), bottom) => {
+                            out.queue(SetForegroundColor(top))?;
+                            if let Some(bottom) = bottom {
+                                out.queue(SetBackgroundColor(bottom))?;
+                            }
+                            write!(out, "▀")?;
+                        }
+                        (None, Some(bottom)) => {
+                            out.queue(SetForegroundColor(bottom))?;
+                            write!(out, "▄")?;
+                        }
+                        (None, None) => write!(out, " ")?,
                     }
-                    print!("▀");
-                    stdout.execute(ResetColor)?;
+                    out.queue(ResetColor)?;
                 } else {
-                    match (x, y, a, b) {
-                        (_, _, true, true)  => print!("█"),
-                        (_, _, false, true) => print!("▀"),
-                        (_, _, true, false) => print!("▄"),
-                        (x, y, false, false) if x == 0 && y == 0 => print!("●"),
-                        (x, y, false, false) if x % 4 == 0 && y % 2 == 0 => print!("┼"),
-                        (x, _, false, false) if x % 8 == 0 => print!("│"),
-                        (_, y, false, false) if y % 4 == 0 => print!("─"),
-                        _ => print!(" "),
+                    let glyph = match (x, y, a, b) {
+                        (_, _, true, true) => '█',
+                        (_, _, false, true) => '▀',
+                        (_, _, true, false) => '▄',
+                        (x, y, false, false) if x == 0 && y == 0 => glyphs.origin,
+                        (x, y, false, false) if x % (GRID_X / 2) == 0 && y % (GRID_Y / 2) == 0 => {
+                            glyphs.cross
+                        }
+                        (x, _, false, false) if x % GRID_X == 0 => glyphs.vertical,
+                        (_, y, false, false) if y % GRID_Y == 0 => glyphs.horizontal,
+                        _ => ' ',
+                    };
+                    write!(out, "{}", glyph)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw the lines between panes.
+    fn draw_dividers<W: Write>(&self, out: &mut W) -> Result<()> {
+        let rows = self.height.saturating_sub(2);
+        let glyphs = &self.glyphs;
+        out.queue(SetForegroundColor(self.theme.divider))?;
+        // The second pane starts just right of the vertical divider, and the third just below the
+        // horizontal one.
+        let column = self.panes.get(1).map(|pane| pane.origin.0 - 1);
+        let row = self.panes.get(2).map(|pane| pane.origin.1 - 1);
+        if let Some(column) = column {
+            for r in 0..rows {
+                out.queue(MoveTo(column, r))?;
+                write!(
+                    out,
+                    "{}",
+                    if Some(r) == row {
+                        glyphs.cross
+                    } else {
+                        glyphs.vertical
                     }
+                )?;
+            }
+        }
+        if let Some(row) = row {
+            for c in 0..self.width {
+                if Some(c) != column {
+                    out.queue(MoveTo(c, row))?;
+                    write!(out, "{}", glyphs.horizontal)?;
                 }
             }
         }
+        out.queue(ResetColor)?;
+        Ok(())
+    }
+
+    /// The color to draw a position in if it is the cursor, a measurement mark, or a dead cell
+    /// under the brush.
+    fn highlight(&self, pos: Position, alive: bool) -> Option<Color> {
+        if pos == self.cursor {
+            Some(self.theme.cursor)
+        } else if self.marks.contains(&pos) {
+            Some(self.theme.mark)
+        } else if !alive
+            && self
+                .brush
+                .is_some_and(|brush| brush.covers(self.cursor, pos))
+        {
+            Some(self.theme.brush)
+        } else {
+            None
+        }
+    }
 
+    /// Draw the coordinates of the grid lines along the top and left edges of a pane.
+    fn draw_rulers<W: Write>(&self, out: &mut W, pane: &PaneView) -> Result<()> {
+        let (x0, y0) = pane.min;
+        let (x1, y1) = pane.max;
+        let (col, row) = pane.origin;
+        out.queue(SetForegroundColor(self.theme.rulers))?;
         for x in x0..x1 {
-            if x % 8 == 0 {
-                print!("┷");
-            } else {
-                print!("━");
+            if x % GRID_X == 0 {
+                let text = x.to_string();
+                let room = usize::from(pane.width()).saturating_sub((x - x0) as usize);
+                out.queue(MoveTo(col + (x - x0) as u16, row))?;
+                write!(out, "{}", &text[..text.len().min(room)])?;
+            }
+        }
+        for y in y0..y1 {
+            if y % GRID_Y == 0 && y != y1 - 1 {
+                let text = (2 * y).to_string();
+                out.queue(MoveTo(col, row + (y1 - 1 - y) as u16))?;
+                write!(
+                    out,
+                    "{}",
+                    &text[..text.len().min(usize::from(pane.width()))]
+                )?;
             }
         }
+        out.queue(ResetColor)?;
+        Ok(())
+    }
 
-        let status = format!(
-            "alive: {}, generations: {}, tickrate: {:.2}Hz",
-            program.world.num_alive(),
-            program.world.generations,
-            program.tickrate,
-        );
-        let pad = std::iter::repeat(" ")
-            .take(usize::from(self.width) - status.len())
-            .collect::<String>();
-        print!("{}{}", status, pad);
-        io::stdout().flush()?;
+    /// Draw labels just to the right of the cells they are attached to, on top of the grid.
+    fn draw_labels<W: Write>(&self, out: &mut W, pane: &PaneView) -> Result<()> {
+        let (x0, _) = pane.min;
+        let (_, y1) = pane.max;
+        for ((x, y), text) in &pane.labels {
+            let row = y.div_euclid(2);
+            let col = x - x0 + 1;
+            let room = usize::from(pane.width()).saturating_sub(col as usize);
+            let text = truncate(text, room);
+            let (left, top) = pane.origin;
+            out.queue(MoveTo(left + col as u16, top + (y1 - 1 - row) as u16))?;
+            out.queue(SetForegroundColor(self.theme.labels))?;
+            write!(out, "{}", text)?;
+            out.queue(ResetColor)?;
+        }
+        Ok(())
+    }
 
+    /// Draw lines of text in a box over the grid, such as the profiling overlay or error panel.
+    fn draw_overlay<W: Write>(
+        &self,
+        out: &mut W,
+        lines: &[String],
+        corner: Corner,
+        color: Color,
+    ) -> Result<()> {
+        // A column of padding on the left, and the lines cut short if the screen is too narrow.
+        let width = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+        let width = width.min(usize::from(self.width).saturating_sub(1));
+        let col = match corner {
+            Corner::TopLeft => 0,
+            Corner::TopRight => usize::from(self.width).saturating_sub(width + 1),
+        };
+        out.queue(SetForegroundColor(color))?;
+        for (row, line) in lines.iter().enumerate().take(usize::from(self.height)) {
+            out.queue(MoveTo(col as u16, row as u16))?;
+            write!(out, " {}", fit(line, width))?;
+        }
+        out.queue(ResetColor)?;
         Ok(())
     }
 }
 
 impl Drop for Screen {
     fn drop(&mut self) {
-        if let Err(e) = Screen::release_terminal() {
+        if self.terminal
+            && let Err(e) = Screen::release_terminal()
+        {
             eprintln!("Error releasing terminal: {}", e);
         }
     }
@@ -160,9 +1004,26 @@ impl Drop for Screen {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::harness::Harness;
+    use crossterm::event::KeyCode;
+    use crossterm::event::MouseButton;
     use crossterm::terminal::is_raw_mode_enabled;
     use serial_test::serial;
 
+    #[test]
+    fn fits_text_to_the_width() {
+        assert_eq!(fit("alive: 5", 10), "alive: 5  ");
+        assert_eq!(fit("alive: 5", 5), "aliv…");
+        assert_eq!(fit("alive: 5", 1), "…");
+        assert_eq!(fit("alive: 5", 0), "");
+        // Wide characters take two columns, so one that doesn't fit whole is left off.
+        assert_eq!(fit("🦀🦀🦀", 5), "🦀🦀…");
+        assert_eq!(fit("🦀🦀🦀", 4), "🦀… ");
+        assert_eq!(fit("a\nb", 4), "a b ");
+        assert_eq!(wrap("🦀🦀🦀", 4), ["🦀🦀", "🦀"]);
+        assert_eq!(wrap("🦀", 1), ["🦀"]);
+    }
+
     #[test]
     #[serial]
     fn acquires_and_releases_terminal() -> Result<()> {
@@ -185,6 +1046,16 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn maps_terminal_positions_to_cells() {
+        let screen = Screen::fake(40, 20);
+        let ((x0, _), (_, y1)) = screen.viewport();
+        assert_eq!(screen.cell_at(0, 0), Some((x0, 2 * (y1 - 1))));
+        assert_eq!(screen.cell_at(3, 2), Some((x0 + 3, 2 * (y1 - 3))));
+        // The bottom border and status line are not part of the grid.
+        assert_eq!(screen.cell_at(0, screen.height - 1), None);
+    }
+
     #[test]
     #[serial]
     fn lock_prevents_concurrent_access() -> Result<()> {
@@ -197,4 +1068,40 @@ mod tests {
         assert!(screen.camera.y == 0);
         Ok(())
     }
+
+    #[test]
+    fn splits_the_screen_into_panes() -> Result<()> {
+        let mut harness = Harness::new(&["-p", "glider"], 41, 22)?;
+        let camera = |harness: &Harness| {
+            let camera = &harness.program.screen.camera;
+            (camera.x, camera.y)
+        };
+        harness.type_keys("|lll")?;
+        assert_eq!(harness.program.screen.focus(), (0, 2));
+        assert!(harness.status().ends_with("pane: 1/2"));
+        let moved = camera(&harness);
+        harness.press(KeyCode::Tab)?;
+        assert_eq!(harness.program.screen.focus(), (1, 2));
+        assert_eq!(camera(&harness), (0, 0));
+        // Clicking in the left pane focuses it and maps the click through its camera.
+        harness.click(MouseButton::Left, 10, 10)?;
+        assert_eq!(harness.program.screen.focus(), (0, 2));
+        assert_eq!(camera(&harness), moved);
+        assert_eq!(
+            Some(harness.program.cursor),
+            harness.program.screen.cell_at(10, 10)
+        );
+        assert_eq!(harness.program.screen.cell_at(30, 10), None);
+        harness
+            .type_keys("|")?
+            .press(KeyCode::Tab)?
+            .press(KeyCode::Tab)?;
+        assert_eq!(harness.program.screen.focus(), (2, 4));
+        // New panes start where the focused pane was, and one pane keeps the focused camera.
+        assert_eq!(camera(&harness), moved);
+        harness.type_keys("hh|")?;
+        assert_eq!(harness.program.screen.focus(), (0, 1));
+        assert_eq!(camera(&harness), (moved.0 - 2, moved.1));
+        Ok(())
+    }
 }
@@ -0,0 +1,100 @@
+use crate::life::{CellState, LifeWorld};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui::widgets::Widget;
+
+/// Draws a world into a ratatui buffer, so other TUI apps can embed a Game of Life panel.
+///
+/// As in the `rust-life` TUI, each terminal cell shows two cells stacked vertically using half
+/// block characters, and higher y coordinates are drawn towards the top. Terminal cells with no
+/// living cells are left untouched, so the widget can be drawn over a background or a `Block`.
+#[derive(Clone, Debug)]
+pub struct LifeWidget<'a, S: CellState = bool> {
+    world: &'a LifeWorld<S>,
+    center: (i32, i32),
+    style: Style,
+}
+
+impl<'a, S: CellState> LifeWidget<'a, S> {
+    pub fn new(world: &'a LifeWorld<S>) -> Self {
+        LifeWidget {
+            world,
+            center: (0, 0),
+            style: Style::default(),
+        }
+    }
+
+    /// Center the view on a position instead of the origin.
+    pub fn center(mut self, x: i32, y: i32) -> Self {
+        self.center = (x, y);
+        self
+    }
+
+    /// The style living cells are drawn with.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl<S: CellState> Widget for LifeWidget<'_, S> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = area.intersection(buf.area);
+        if area.is_empty() {
+            return;
+        }
+        let (width, height) = (i32::from(area.width), i32::from(area.height));
+        let x0 = self.center.0 - width / 2;
+        let y_max = self.center.1 + height - 1;
+        let y_min = self.center.1 - height;
+
+        // Bit 0 marks the upper cell of a terminal cell and bit 1 the lower one.
+        let mut halves = vec![0u8; area.width as usize * area.height as usize];
+        for (x, y) in self.world.cells_in((x0, y_min), (x0 + width - 1, y_max)) {
+            let row = (y_max - y) / 2;
+            let bit = if (y_max - y) % 2 == 0 { 1 } else { 2 };
+            halves[(row * width + x - x0) as usize] |= bit;
+        }
+
+        for (i, &half) in halves.iter().enumerate() {
+            let symbol = match half {
+                1 => "▀",
+                2 => "▄",
+                3 => "█",
+                _ => continue,
+            };
+            let column = area.x + (i % area.width as usize) as u16;
+            let row = area.y + (i / area.width as usize) as u16;
+            if let Some(cell) = buf.cell_mut((column, row)) {
+                cell.set_symbol(symbol).set_style(self.style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::LifePattern;
+
+    #[test]
+    fn renders_half_blocks() {
+        let world = LifeWorld::from(&LifePattern::Glider);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 3, 2));
+        LifeWidget::new(&world)
+            .center(1, 1)
+            .render(buf.area, &mut buf);
+        assert_eq!(buf, Buffer::with_lines([" ▀▄", "▀▀▀"]));
+    }
+
+    #[test]
+    fn renders_into_offset_area() {
+        let world = LifeWorld::from(&LifePattern::Glider);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 3));
+        LifeWidget::new(&world)
+            .center(1, 1)
+            .render(Rect::new(1, 1, 3, 2), &mut buf);
+        assert_eq!(buf, Buffer::with_lines(["     ", "  ▀▄ ", " ▀▀▀ "]));
+    }
+}
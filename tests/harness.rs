@@ -0,0 +1,19 @@
+use anyhow::Result;
+use crossterm::event::KeyCode;
+use rust_life::harness::{Harness, State};
+
+#[test]
+fn drives_keybindings_without_a_terminal() -> Result<()> {
+    let mut harness = Harness::new(&["-p", "blinker", "--build"], 40, 20)?;
+    assert_eq!(harness.state(), State::Paused);
+    assert_eq!(harness.cells(), vec![(0, 0), (0, 1), (0, 2)]);
+    harness.type_keys("..")?;
+    assert_eq!(harness.generations(), 2);
+    harness.type_keys(" ")?.ticks(3)?;
+    assert_eq!(harness.state(), State::Running);
+    assert_eq!(harness.generations(), 5);
+    assert_eq!(harness.cells(), vec![(-1, 1), (0, 1), (1, 1)]);
+    harness.press(KeyCode::Char('q'))?;
+    assert_eq!(harness.state(), State::Done);
+    Ok(())
+}